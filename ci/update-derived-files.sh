@@ -44,11 +44,15 @@ for cmd in \
     list-clients \
     move-pane-to-new-tab \
     rename-workspace \
+    screenshot \
+    send-key \
     send-text \
     set-tab-title \
     set-window-title \
     spawn \
     split-pane \
+    subscribe \
+    wait-for \
     zoom-pane \
     ; do
   fname="docs/examples/cmd-synopsis-wezterm-cli-${cmd}--help.txt"
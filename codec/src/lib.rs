@@ -34,7 +34,10 @@ use termwiz::image::{ImageData, TextureCoordinate};
 use termwiz::surface::{Line, SequenceNo};
 use thiserror::Error;
 use wezterm_term::color::ColorPalette;
-use wezterm_term::{Alert, ClipboardSelection, StableRowIndex, TerminalSize};
+use wezterm_term::{
+    Alert, ClipboardSelection, CommandExitStatus, CommandHistoryEntry, SemanticZone,
+    StableRowIndex, TerminalSize,
+};
 
 #[derive(Error, Debug)]
 #[error("Corrupt Response")]
@@ -431,7 +434,7 @@ macro_rules! pdu {
 /// The overall version of the codec.
 /// This must be bumped when backwards incompatible changes
 /// are made to the types and protocol.
-pub const CODEC_VERSION: usize = 42;
+pub const CODEC_VERSION: usize = 53;
 
 // Defines the Pdu enum.
 // Each struct has an explicit identifying number.
@@ -492,6 +495,14 @@ pdu! {
     GetPaneDirection: 60,
     GetPaneDirectionResponse: 61,
     AdjustPaneSize: 62,
+    SubscribeToMuxEvents: 63,
+    MuxEventNotification: 64,
+    GetSemanticZones: 65,
+    GetSemanticZonesResponse: 66,
+    GetPalette: 67,
+    GetPaletteResponse: 68,
+    GetCommandHistory: 69,
+    GetCommandHistoryResponse: 70,
 }
 
 impl Pdu {
@@ -629,8 +640,12 @@ pub struct GetTlsCredsResponse {
     pub client_cert_pem: String,
 }
 
-#[derive(Deserialize, Serialize, PartialEq, Debug)]
-pub struct ListPanes {}
+#[derive(Deserialize, Serialize, PartialEq, Debug, Default)]
+pub struct ListPanes {
+    /// Whether to report the resources used by the processes
+    /// in each pane, which is relatively expensive to compute
+    pub include_resources: bool,
+}
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct ListPanesResponse {
@@ -865,11 +880,182 @@ pub struct AdjustPaneSize {
     pub amount: usize,
 }
 
+/// Requests that the server send a `MuxEventNotification` to this
+/// client for each subsequent mux notification.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SubscribeToMuxEvents {
+    /// Whether to include `MuxEvent::PaneOutput`, which is generated
+    /// very frequently by busy panes
+    pub include_pane_output: bool,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct MuxEventNotification {
+    #[serde(with = "WireMuxEvent")]
+    pub event: MuxEvent,
+}
+
+/// A serializable form of `mux::MuxNotification`, sent to clients that
+/// have subscribed via `SubscribeToMuxEvents`.
+/// The tab and window ids of pane events are resolved at the time that
+/// the event is sent, and are `None` if the pane is not in a tab.
+/// The derived serialization is a flat object whose `event` field names
+/// the variant in snake case, which is what `wezterm cli subscribe` prints.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum MuxEvent {
+    PaneOutput {
+        pane_id: PaneId,
+        tab_id: Option<TabId>,
+        window_id: Option<WindowId>,
+    },
+    PaneAdded {
+        pane_id: PaneId,
+        tab_id: Option<TabId>,
+        window_id: Option<WindowId>,
+    },
+    PaneRemoved {
+        pane_id: PaneId,
+    },
+    PaneFocused {
+        pane_id: PaneId,
+        tab_id: Option<TabId>,
+        window_id: Option<WindowId>,
+    },
+    Alert {
+        pane_id: PaneId,
+        tab_id: Option<TabId>,
+        window_id: Option<WindowId>,
+        alert: Alert,
+    },
+    WindowCreated {
+        window_id: WindowId,
+    },
+    WindowRemoved {
+        window_id: WindowId,
+    },
+    WindowWorkspaceChanged {
+        window_id: WindowId,
+        workspace: String,
+    },
+    WindowTitleChanged {
+        window_id: WindowId,
+        title: String,
+    },
+    TabAddedToWindow {
+        tab_id: TabId,
+        window_id: WindowId,
+    },
+    TabResized {
+        tab_id: TabId,
+    },
+    TabTitleChanged {
+        tab_id: TabId,
+        title: String,
+    },
+    WorkspaceRenamed {
+        old_workspace: String,
+        new_workspace: String,
+    },
+}
+
+/// The PDU encoding of `MuxEvent`.  varbincode isn't self-describing
+/// and cannot decode internally tagged enums, so the wire form uses
+/// the default externally tagged representation.
+#[derive(Deserialize, Serialize)]
+#[serde(remote = "MuxEvent")]
+enum WireMuxEvent {
+    PaneOutput {
+        pane_id: PaneId,
+        tab_id: Option<TabId>,
+        window_id: Option<WindowId>,
+    },
+    PaneAdded {
+        pane_id: PaneId,
+        tab_id: Option<TabId>,
+        window_id: Option<WindowId>,
+    },
+    PaneRemoved {
+        pane_id: PaneId,
+    },
+    PaneFocused {
+        pane_id: PaneId,
+        tab_id: Option<TabId>,
+        window_id: Option<WindowId>,
+    },
+    Alert {
+        pane_id: PaneId,
+        tab_id: Option<TabId>,
+        window_id: Option<WindowId>,
+        alert: Alert,
+    },
+    WindowCreated {
+        window_id: WindowId,
+    },
+    WindowRemoved {
+        window_id: WindowId,
+    },
+    WindowWorkspaceChanged {
+        window_id: WindowId,
+        workspace: String,
+    },
+    WindowTitleChanged {
+        window_id: WindowId,
+        title: String,
+    },
+    TabAddedToWindow {
+        tab_id: TabId,
+        window_id: WindowId,
+    },
+    TabResized {
+        tab_id: TabId,
+    },
+    TabTitleChanged {
+        tab_id: TabId,
+        title: String,
+    },
+    WorkspaceRenamed {
+        old_workspace: String,
+        new_workspace: String,
+    },
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct GetPaneDirectionResponse {
     pub pane_id: Option<PaneId>,
 }
 
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetSemanticZones {
+    pub pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetSemanticZonesResponse {
+    pub zones: Vec<SemanticZone>,
+    pub exit_statuses: Vec<CommandExitStatus>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetCommandHistory {
+    pub pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetCommandHistoryResponse {
+    pub history: Vec<CommandHistoryEntry>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetPalette {
+    pub pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetPaletteResponse {
+    pub palette: ColorPalette,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct ActivatePaneDirection {
     pub pane_id: PaneId,
@@ -903,6 +1089,7 @@ pub struct LivenessResponse {
 pub struct GetPaneRenderChangesResponse {
     pub pane_id: PaneId,
     pub mouse_grabbed: bool,
+    pub alt_screen_active: bool,
     pub cursor_position: StableCursorPosition,
     pub dimensions: RenderableDimensions,
     pub dirty_lines: Vec<Range<StableRowIndex>>,
@@ -1225,6 +1412,28 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_pdu_mux_event() {
+        let event = MuxEvent::PaneAdded {
+            pane_id: 1,
+            tab_id: Some(2),
+            window_id: None,
+        };
+        let mut encoded = Vec::new();
+        Pdu::MuxEventNotification(MuxEventNotification {
+            event: event.clone(),
+        })
+        .encode(&mut encoded, 0x43)
+        .unwrap();
+        assert_eq!(
+            DecodedPdu {
+                serial: 0x43,
+                pdu: Pdu::MuxEventNotification(MuxEventNotification { event })
+            },
+            Pdu::decode(encoded.as_slice()).unwrap()
+        );
+    }
+
     #[test]
     fn test_pdu_pong() {
         let mut encoded = Vec::new();
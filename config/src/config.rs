@@ -17,6 +17,7 @@ use crate::keys::{Key, LeaderKey, Mouse};
 use crate::lua::make_lua_context;
 use crate::ssh::{SshBackend, SshDomain};
 use crate::tls::{TlsDomainClient, TlsDomainServer};
+use crate::trigger::Trigger;
 use crate::units::Dimension;
 use crate::unix::UnixDomain;
 use crate::wsl::WslDomain;
@@ -37,6 +38,7 @@ use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::Ordering;
 use std::time::Duration;
+use termwiz::highlight;
 use termwiz::hyperlink;
 use termwiz::surface::CursorShape;
 use wezterm_bidi::ParagraphDirectionHint;
@@ -216,6 +218,21 @@ pub struct Config {
     #[dynamic(default)]
     pub exit_behavior_messaging: ExitBehaviorMessaging,
 
+    /// When exit_behavior is Restart or RestartOnFailure, how long
+    /// to wait before restarting the command, in milliseconds.
+    /// The delay doubles with each consecutive restart, up to
+    /// exit_behavior_restart_max_delay.
+    #[dynamic(default = "default_exit_behavior_restart_delay")]
+    pub exit_behavior_restart_delay: u64,
+
+    #[dynamic(default = "default_exit_behavior_restart_max_delay")]
+    pub exit_behavior_restart_max_delay: u64,
+
+    /// The maximum number of consecutive restarts before giving up
+    /// and holding the pane open. None means no limit.
+    #[dynamic(default)]
+    pub exit_behavior_restart_max_attempts: Option<u32>,
+
     #[dynamic(default = "default_clean_exits")]
     pub clean_exit_codes: Vec<u32>,
 
@@ -252,6 +269,15 @@ pub struct Config {
     #[dynamic(default = "default_hyperlink_rules")]
     pub hyperlink_rules: Vec<hyperlink::Rule>,
 
+    /// Rules that change the colors or underline of text
+    /// that matches a regex
+    #[dynamic(default)]
+    pub highlight_rules: Vec<highlight::Rule>,
+
+    /// Actions to perform when lines of output match a regex
+    #[dynamic(default)]
+    pub triggers: Vec<Trigger>,
+
     /// What to set the TERM variable to
     #[dynamic(default = "default_term")]
     pub term: String,
@@ -370,6 +396,13 @@ pub struct Config {
     #[dynamic(default = "default_ratelimit_line_prefetches_per_second")]
     pub ratelimit_mux_line_prefetches_per_second: u32,
 
+    /// Constrains the number of lines of output per second that are
+    /// matched against the triggers of a pane.  Lines that exceed
+    /// this rate are not matched, so that noisy output cannot stall
+    /// the pane.
+    #[dynamic(default = "default_ratelimit_trigger_lines_per_second")]
+    pub ratelimit_trigger_lines_per_second: u32,
+
     /// The buffer size used by parse_buffered_data in the mux module.
     /// This should not be too large, otherwise the processing cost
     /// of applying a batch of actions to the terminal will be too
@@ -671,6 +704,13 @@ pub struct Config {
     #[dynamic(default)]
     pub notification_handling: NotificationHandling,
 
+    /// When a command, as delimited by the OSC 133 shell integration
+    /// escapes, runs for at least this many milliseconds while its
+    /// pane is not focused, a notification is shown when it finishes.
+    /// 0 disables the notification.
+    #[dynamic(default)]
+    pub command_finished_notification_threshold: u64,
+
     #[dynamic(default = "default_true")]
     pub use_dead_keys: bool,
 
@@ -1607,6 +1647,10 @@ fn default_ratelimit_line_prefetches_per_second() -> u32 {
     50
 }
 
+fn default_ratelimit_trigger_lines_per_second() -> u32 {
+    1000
+}
+
 fn default_cursor_blink_rate() -> u64 {
     800
 }
@@ -1789,6 +1833,14 @@ fn default_clean_exits() -> Vec<u32> {
     vec![]
 }
 
+fn default_exit_behavior_restart_delay() -> u64 {
+    1000
+}
+
+fn default_exit_behavior_restart_max_delay() -> u64 {
+    30_000
+}
+
 fn default_inactive_pane_hsb() -> HsbTransform {
     HsbTransform {
         brightness: 0.8,
@@ -1906,6 +1958,11 @@ pub enum ExitBehavior {
     CloseOnCleanExit,
     /// Hold the pane until it is explicitly closed
     Hold,
+    /// Restart the program in the same pane
+    Restart,
+    /// Restart the program in the same pane if it was not successful,
+    /// otherwise close the pane
+    RestartOnFailure,
 }
 
 #[derive(Debug, FromDynamic, ToDynamic, Clone, Copy, PartialEq, Eq, Default)]
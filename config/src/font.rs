@@ -3,6 +3,7 @@ use crate::*;
 use bitflags::*;
 use enum_display_derive::Display;
 use luahelper::impl_lua_conversion_dynamic;
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::fmt::Display;
 use wezterm_dynamic::{FromDynamic, FromDynamicOptions, ToDynamic, Value};
@@ -379,6 +380,10 @@ pub struct FontAttributes {
     pub scale: Option<NotNan<f64>>,
     #[dynamic(default)]
     pub assume_emoji_presentation: Option<bool>,
+    /// Design coordinates for the axes of a variable font,
+    /// keyed by axis tag, such as `wght` or `CASL`
+    #[dynamic(default)]
+    pub variations: Option<BTreeMap<String, NotNan<f64>>>,
 }
 impl_lua_conversion_dynamic!(FontAttributes);
 
@@ -407,6 +412,7 @@ impl FontAttributes {
             freetype_load_flags: None,
             scale: None,
             assume_emoji_presentation: None,
+            variations: None,
         }
     }
 
@@ -424,6 +430,7 @@ impl FontAttributes {
             freetype_load_flags: None,
             scale: None,
             assume_emoji_presentation: None,
+            variations: None,
         }
     }
 }
@@ -443,6 +450,7 @@ impl Default for FontAttributes {
             freetype_load_flags: None,
             scale: None,
             assume_emoji_presentation: None,
+            variations: None,
         }
     }
 }
@@ -691,6 +699,7 @@ pub enum FontRasterizerSelection {
     #[default]
     FreeType,
     Harfbuzz,
+    AbGlyph,
 }
 
 #[derive(Debug, Clone, Copy, FromDynamic, ToDynamic, Default)]
@@ -698,6 +707,7 @@ pub enum FontShaperSelection {
     Allsorts,
     #[default]
     Harfbuzz,
+    Rustybuzz,
 }
 
 #[cfg(test)]
@@ -111,6 +111,124 @@ impl TryFrom<&str> for DeferredKeyCode {
     }
 }
 
+/// Maps a `KeyCode` to the equivalent termwiz key code, as used when
+/// encoding key presses for a pane.
+/// Physical key codes are mapped via their default key code, and a
+/// composed key code is only mapped if it consists of a single char.
+/// `swap_backspace_and_delete` has the same meaning as the config
+/// option of that name.
+pub fn key_code_to_termwiz(
+    key: &KeyCode,
+    swap_backspace_and_delete: bool,
+) -> Option<termwiz::input::KeyCode> {
+    use termwiz::input::KeyCode as TK;
+
+    Some(match key {
+        // TODO: consider eliminating these codes from termwiz::input::KeyCode
+        KeyCode::Char('\r') => TK::Enter,
+        KeyCode::Char('\t') => TK::Tab,
+        KeyCode::Char('\u{08}') if swap_backspace_and_delete => TK::Delete,
+        KeyCode::Char('\u{08}') => TK::Backspace,
+        KeyCode::Char('\u{7f}') if swap_backspace_and_delete => TK::Backspace,
+        KeyCode::Char('\u{7f}') => TK::Delete,
+        KeyCode::Char('\u{1b}') => TK::Escape,
+        KeyCode::Char(c) => TK::Char(*c),
+        KeyCode::Composed(s) => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => {
+                    return key_code_to_termwiz(&KeyCode::Char(c), swap_backspace_and_delete)
+                }
+                _ => return None,
+            }
+        }
+        KeyCode::Physical(phys) => {
+            return key_code_to_termwiz(&phys.to_key_code(), swap_backspace_and_delete)
+        }
+        KeyCode::RawCode(_) | KeyCode::VoidSymbol => return None,
+        KeyCode::Function(f) => TK::Function(*f),
+        KeyCode::LeftArrow => TK::LeftArrow,
+        KeyCode::RightArrow => TK::RightArrow,
+        KeyCode::UpArrow => TK::UpArrow,
+        KeyCode::DownArrow => TK::DownArrow,
+        KeyCode::Home => TK::Home,
+        KeyCode::End => TK::End,
+        KeyCode::PageUp => TK::PageUp,
+        KeyCode::PageDown => TK::PageDown,
+        KeyCode::Insert => TK::Insert,
+        KeyCode::Hyper => TK::Hyper,
+        KeyCode::Super => TK::Super,
+        KeyCode::Meta => TK::Meta,
+        KeyCode::Cancel => TK::Cancel,
+        KeyCode::Clear => TK::Clear,
+        KeyCode::Shift => TK::Shift,
+        KeyCode::LeftShift => TK::LeftShift,
+        KeyCode::RightShift => TK::RightShift,
+        KeyCode::Control => TK::Control,
+        KeyCode::LeftControl => TK::LeftControl,
+        KeyCode::RightControl => TK::RightControl,
+        KeyCode::Alt => TK::Alt,
+        KeyCode::LeftAlt => TK::LeftAlt,
+        KeyCode::RightAlt => TK::RightAlt,
+        KeyCode::Pause => TK::Pause,
+        KeyCode::CapsLock => TK::CapsLock,
+        KeyCode::Select => TK::Select,
+        KeyCode::Print => TK::Print,
+        KeyCode::Execute => TK::Execute,
+        KeyCode::PrintScreen => TK::PrintScreen,
+        KeyCode::Help => TK::Help,
+        KeyCode::LeftWindows => TK::LeftWindows,
+        KeyCode::RightWindows => TK::RightWindows,
+        KeyCode::Sleep => TK::Sleep,
+        KeyCode::Multiply => TK::Multiply,
+        KeyCode::Applications => TK::Applications,
+        KeyCode::Add => TK::Add,
+        KeyCode::Numpad(0) => TK::Numpad0,
+        KeyCode::Numpad(1) => TK::Numpad1,
+        KeyCode::Numpad(2) => TK::Numpad2,
+        KeyCode::Numpad(3) => TK::Numpad3,
+        KeyCode::Numpad(4) => TK::Numpad4,
+        KeyCode::Numpad(5) => TK::Numpad5,
+        KeyCode::Numpad(6) => TK::Numpad6,
+        KeyCode::Numpad(7) => TK::Numpad7,
+        KeyCode::Numpad(8) => TK::Numpad8,
+        KeyCode::Numpad(9) => TK::Numpad9,
+        KeyCode::Numpad(_) => return None,
+        KeyCode::Separator => TK::Separator,
+        KeyCode::Subtract => TK::Subtract,
+        KeyCode::Decimal => TK::Decimal,
+        KeyCode::Divide => TK::Divide,
+        KeyCode::NumLock => TK::NumLock,
+        KeyCode::ScrollLock => TK::ScrollLock,
+        KeyCode::Copy => TK::Copy,
+        KeyCode::Cut => TK::Cut,
+        KeyCode::Paste => TK::Paste,
+        KeyCode::BrowserBack => TK::BrowserBack,
+        KeyCode::BrowserForward => TK::BrowserForward,
+        KeyCode::BrowserRefresh => TK::BrowserRefresh,
+        KeyCode::BrowserStop => TK::BrowserStop,
+        KeyCode::BrowserSearch => TK::BrowserSearch,
+        KeyCode::BrowserFavorites => TK::BrowserFavorites,
+        KeyCode::BrowserHome => TK::BrowserHome,
+        KeyCode::VolumeMute => TK::VolumeMute,
+        KeyCode::VolumeDown => TK::VolumeDown,
+        KeyCode::VolumeUp => TK::VolumeUp,
+        KeyCode::MediaNextTrack => TK::MediaNextTrack,
+        KeyCode::MediaPrevTrack => TK::MediaPrevTrack,
+        KeyCode::MediaStop => TK::MediaStop,
+        KeyCode::MediaPlayPause => TK::MediaPlayPause,
+        KeyCode::ApplicationLeftArrow => TK::ApplicationLeftArrow,
+        KeyCode::ApplicationRightArrow => TK::ApplicationRightArrow,
+        KeyCode::ApplicationUpArrow => TK::ApplicationUpArrow,
+        KeyCode::ApplicationDownArrow => TK::ApplicationDownArrow,
+        KeyCode::KeyPadHome => TK::KeyPadHome,
+        KeyCode::KeyPadEnd => TK::KeyPadEnd,
+        KeyCode::KeyPadBegin => TK::KeyPadBegin,
+        KeyCode::KeyPadPageUp => TK::KeyPadPageUp,
+        KeyCode::KeyPadPageDown => TK::KeyPadPageDown,
+    })
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
 pub struct KeyNoAction {
     pub key: DeferredKeyCode,
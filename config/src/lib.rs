@@ -36,6 +36,7 @@ mod serial;
 mod ssh;
 mod terminal;
 mod tls;
+mod trigger;
 mod units;
 mod unix;
 mod version;
@@ -55,6 +56,7 @@ pub use serial::*;
 pub use ssh::*;
 pub use terminal::*;
 pub use tls::*;
+pub use trigger::*;
 pub use units::*;
 pub use unix::*;
 pub use version::*;
@@ -9,6 +9,7 @@ use luahelper::{from_lua_value_dynamic, lua_value_to_dynamic, to_lua};
 use mlua::{FromLua, IntoLuaMulti, Lua, Table, Value, Variadic};
 use ordered_float::NotNan;
 use portable_pty::CommandBuilder;
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::path::Path;
 use std::sync::Mutex;
@@ -498,6 +499,8 @@ struct LuaFontAttributes {
     pub scale: Option<NotNan<f64>>,
     #[dynamic(default)]
     pub assume_emoji_presentation: Option<bool>,
+    #[dynamic(default)]
+    pub variations: Option<BTreeMap<String, NotNan<f64>>>,
 }
 impl<'lua> FromLua<'lua> for LuaFontAttributes {
     fn from_lua(value: Value<'lua>, _lua: &'lua Lua) -> Result<Self, mlua::Error> {
@@ -585,6 +588,7 @@ fn font<'lua>(
             },
             scale: attrs.scale,
             assume_emoji_presentation: attrs.assume_emoji_presentation,
+            variations: attrs.variations,
         }));
 
     Ok(text_style)
@@ -634,6 +638,7 @@ fn font_with_fallback<'lua>(
                 },
                 scale: attrs.scale,
                 assume_emoji_presentation: attrs.assume_emoji_presentation,
+                variations: attrs.variations,
             }));
     }
 
@@ -744,6 +749,14 @@ pub fn register_event<'lua>(
     }
 }
 
+/// Returns true if at least one handler has been registered
+/// for the named event via `wezterm.on`
+pub fn has_event_handler(lua: &Lua, name: &str) -> mlua::Result<bool> {
+    let decorated_name = format!("wezterm-event-{}", name);
+    let tbl: mlua::Value = lua.named_registry_value(&decorated_name)?;
+    Ok(matches!(tbl, mlua::Value::Table(tbl) if tbl.raw_len() > 0))
+}
+
 const IS_EVENT: &str = "wezterm-is-event-emission";
 
 /// Returns true if the current lua context is being called as part
@@ -2,6 +2,7 @@
 
 use crate::{configuration, ConfigHandle, NewlineCanon};
 use std::sync::Mutex;
+use std::time::Duration;
 use termwiz::cell::UnicodeVersion;
 use wezterm_term::color::ColorPalette;
 use wezterm_term::config::BidiMode;
@@ -120,6 +121,13 @@ impl wezterm_term::TerminalConfiguration for TermConfig {
         self.configuration().normalize_output_to_unicode_nfc
     }
 
+    fn command_finished_notification_threshold(&self) -> Option<Duration> {
+        match self.configuration().command_finished_notification_threshold {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        }
+    }
+
     fn bidi_mode(&self) -> BidiMode {
         let config = self.configuration();
         BidiMode {
@@ -0,0 +1,69 @@
+use crate::keyassignment::KeyAssignment;
+use crate::RgbaColor;
+use wezterm_dynamic::{FromDynamic, ToDynamic};
+use wezterm_term::SemanticType;
+
+/// A trigger performs an action when a line of output from
+/// a pane matches a regular expression
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
+pub struct Trigger {
+    /// The regular expression to match against each line of output
+    pub regex: String,
+    /// If set, only match text that is part of this kind of
+    /// semantic zone, as defined by OSC 133
+    #[dynamic(default)]
+    pub semantic_zone: Option<SemanticType>,
+    pub action: TriggerAction,
+}
+
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
+pub enum TriggerAction {
+    /// Change the colors of the matched text
+    Highlight {
+        #[dynamic(default)]
+        foreground: Option<RgbaColor>,
+        #[dynamic(default)]
+        background: Option<RgbaColor>,
+    },
+    /// Show a toast notification.
+    /// `$N` in the title and body is replaced by capture N
+    Notify {
+        #[dynamic(default)]
+        title: Option<String>,
+        body: String,
+    },
+    /// Set a user var on the pane, as if by OSC 1337.
+    /// `$N` in the value is replaced by capture N
+    SetUserVar { name: String, value: String },
+    /// Perform a key assignment in the window that contains the pane
+    PerformAction(KeyAssignment),
+    /// Emit a lua event, passing the window, pane and captures
+    EmitEvent(String),
+}
+
+/// Replaces instances of `$N` in `format` with the capture
+/// number N from `captures`.
+/// The replacements are carried out in reverse order, so that
+/// `$11` is not confused with `$1`.
+pub fn expand_trigger_captures(format: &str, captures: &[String]) -> String {
+    let mut result = format.to_string();
+    for (n, capture) in captures.iter().enumerate().rev() {
+        result = result.replace(&format!("${}", n), capture);
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn expand_captures() {
+        let captures: Vec<String> = (0..12).map(|n| format!("c{}", n)).collect();
+        assert_eq!(
+            expand_trigger_captures("$0: $1 $11 $2$", &captures),
+            "c0: c1 c11 c2$"
+        );
+        assert_eq!(expand_trigger_captures("$1 $3", &captures[0..2]), "c1 $3");
+    }
+}
@@ -172,6 +172,18 @@ impl UserData for MuxPane {
             Ok(pane.get_foreground_process_info(CachePolicy::AllowStale))
         });
 
+        methods.add_method("get_process_resources", |_, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            Ok(pane.get_process_resources(CachePolicy::AllowStale))
+        });
+
+        methods.add_method("get_listening_sockets", |_, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            Ok(pane.get_listening_sockets(CachePolicy::AllowStale))
+        });
+
         methods.add_method("get_cursor_position", |_, this, _: ()| {
             let mux = get_mux()?;
             let pane = this.resolve(&mux)?;
@@ -312,6 +324,15 @@ impl UserData for MuxPane {
             Ok(zones)
         });
 
+        methods.add_method("get_command_history", |lua, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            let history = pane
+                .get_command_history()
+                .map_err(|e| mlua::Error::external(format!("{:#}", e)))?;
+            to_lua(lua, history)
+        });
+
         methods.add_method(
             "get_semantic_zone_at",
             |lua, this, (x, y): (usize, StableRowIndex)| {
@@ -1,6 +1,6 @@
 use config::lua::get_or_create_sub_module;
 use config::lua::mlua::Lua;
-use procinfo::LocalProcessInfo;
+use procinfo::{LocalProcessDetails, LocalProcessInfo};
 
 pub fn register(lua: &Lua) -> anyhow::Result<()> {
     let proc_mod = get_or_create_sub_module(lua, "procinfo")?;
@@ -10,7 +10,15 @@ pub fn register(lua: &Lua) -> anyhow::Result<()> {
     )?;
     proc_mod.set(
         "get_info_for_pid",
-        lua.create_function(|_, pid: u32| Ok(LocalProcessInfo::with_root_pid(pid)))?,
+        lua.create_function(|_, pid: u32| {
+            Ok(LocalProcessInfo::with_root_pid_and_details(
+                pid,
+                LocalProcessDetails {
+                    resources: true,
+                    listening_sockets: true,
+                },
+            ))
+        })?,
     )?;
     proc_mod.set(
         "current_working_dir_for_pid",
@@ -4,7 +4,8 @@ use crossbeam::channel::{unbounded, Receiver, Sender};
 use finl_unicode::grapheme_clusters::Graphemes;
 use promise::spawn::block_on;
 use promise::Promise;
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use termwiz::cell::{unicode_column_width, CellAttributes};
 use termwiz::lineedit::*;
@@ -204,6 +205,7 @@ impl ConnectionUIImpl {
 
 struct HeadlessImpl {
     rx: Receiver<UIRequest>,
+    input_requested: Arc<AtomicBool>,
 }
 
 impl HeadlessImpl {
@@ -215,6 +217,7 @@ impl HeadlessImpl {
                     log::trace!("Output: {:?}", changes);
                 }
                 Ok(UIRequest::Input { mut respond, .. }) => {
+                    self.input_requested.store(true, Ordering::SeqCst);
                     respond.result(Err(anyhow!("Input requested from headless context")));
                 }
                 Ok(UIRequest::Sleep {
@@ -245,6 +248,7 @@ pub struct ConnectionUIParams {
 #[derive(Clone)]
 pub struct ConnectionUI {
     tx: Sender<UIRequest>,
+    input_requested: Arc<AtomicBool>,
 }
 
 impl ConnectionUI {
@@ -276,7 +280,10 @@ impl ConnectionUI {
             None,
         ))
         .detach();
-        Self { tx }
+        Self {
+            tx,
+            input_requested: Arc::new(AtomicBool::new(false)),
+        }
     }
 
     pub fn new_with_no_close_delay() -> Self {
@@ -288,11 +295,28 @@ impl ConnectionUI {
 
     pub fn new_headless() -> Self {
         let (tx, rx) = unbounded();
-        std::thread::spawn(move || {
-            let mut ui = HeadlessImpl { rx };
-            ui.run()
+        let input_requested = Arc::new(AtomicBool::new(false));
+        std::thread::spawn({
+            let input_requested = Arc::clone(&input_requested);
+            move || {
+                let mut ui = HeadlessImpl {
+                    rx,
+                    input_requested,
+                };
+                ui.run()
+            }
         });
-        Self { tx }
+        Self {
+            tx,
+            input_requested,
+        }
+    }
+
+    /// Returns true if this is a headless UI and something tried to
+    /// prompt for input through it.  Such prompts always fail, so the
+    /// caller may want to retry using an interactive UI.
+    pub fn headless_input_requested(&self) -> bool {
+        self.input_requested.load(Ordering::SeqCst)
     }
 
     pub fn run_and_log_error<T, F>(&self, f: F) -> anyhow::Result<T>
@@ -16,7 +16,9 @@ use config::keyassignment::{SpawnCommand, SpawnTabDomain};
 use config::{configuration, ExecDomain, SerialDomain, ValueOrFunc, WslDomain};
 use downcast_rs::{impl_downcast, Downcast};
 use parking_lot::Mutex;
-use portable_pty::{native_pty_system, CommandBuilder, ExitStatus, MasterPty, PtySize, PtySystem};
+use portable_pty::{
+    native_pty_system, Child, CommandBuilder, ExitStatus, MasterPty, PtySize, PtySystem,
+};
 use std::collections::HashMap;
 use std::ffi::OsString;
 use std::io::Write;
@@ -243,6 +245,18 @@ impl LocalDomain {
         Ok(Self::with_pty_system(&serial_domain.name, pty_system))
     }
 
+    /// Spawns `cmd` in a newly allocated pty.
+    /// This is used to restart the command of an existing pane.
+    pub(crate) fn spawn_command_in_new_pty(
+        &self,
+        cmd: CommandBuilder,
+        size: PtySize,
+    ) -> anyhow::Result<(Box<dyn Child + Send + Sync>, Box<dyn MasterPty>)> {
+        let pair = self.pty_system.lock().openpty(size)?;
+        let child = pair.slave.spawn_command(cmd)?;
+        Ok((child, pair.master))
+    }
+
     #[cfg(unix)]
     fn is_conpty(&self) -> bool {
         false
@@ -489,6 +503,11 @@ impl WriterWrapper {
             writer: Arc::new(Mutex::new(writer)),
         }
     }
+
+    /// Replaces the underlying writer for all clones of this wrapper
+    pub fn replace(&self, writer: Box<dyn Write + Send>) {
+        *self.writer.lock() = writer;
+    }
 }
 
 impl std::io::Write for WriterWrapper {
@@ -600,6 +619,7 @@ impl Domain for LocalDomain {
             },
             self.name
         );
+        let restart_cmd = cmd.clone();
         let child_result = pair.slave.spawn_command(cmd);
         let mut writer = WriterWrapper::new(pair.master.take_writer()?);
 
@@ -615,15 +635,18 @@ impl Domain for LocalDomain {
         }
 
         let pane: Arc<dyn Pane> = match child_result {
-            Ok(child) => Arc::new(LocalPane::new(
-                pane_id,
-                terminal,
-                child,
-                pair.master,
-                Box::new(writer),
-                self.id,
-                command_description,
-            )),
+            Ok(child) => Arc::new(
+                LocalPane::new(
+                    pane_id,
+                    terminal,
+                    child,
+                    pair.master,
+                    Box::new(writer.clone()),
+                    self.id,
+                    command_description,
+                )
+                .with_restart_command(restart_cmd, writer),
+            ),
             Err(err) => {
                 // Show the error to the user in the new pane
                 write!(writer, "{err:#}").ok();
@@ -43,6 +43,7 @@ pub mod termwiztermtab;
 pub mod tmux;
 pub mod tmux_commands;
 mod tmux_pty;
+mod trigger;
 pub mod window;
 
 use crate::activity::Activity;
@@ -91,6 +92,18 @@ pub enum MuxNotification {
         old_workspace: String,
         new_workspace: String,
     },
+    ListeningSocketsChanged {
+        pane_id: PaneId,
+        sockets: Vec<procinfo::LocalListeningSocket>,
+    },
+    /// A trigger whose action must be performed by the gui
+    /// matched the output of a pane
+    TriggerMatched {
+        pane_id: PaneId,
+        action: config::TriggerAction,
+        /// The matched text, followed by the text of each capture
+        captures: Vec<String>,
+    },
 }
 
 static SUB_ID: AtomicUsize = AtomicUsize::new(0);
@@ -108,6 +121,7 @@ pub struct Mux {
     identity: RwLock<Option<Arc<ClientId>>>,
     num_panes_by_workspace: RwLock<HashMap<String, usize>>,
     main_thread_id: std::thread::ThreadId,
+    listening_sockets_wanted: AtomicBool,
 }
 
 const BUFSIZE: usize = 1024 * 1024;
@@ -334,7 +348,10 @@ fn read_from_pane_pty(
     }
 
     match exit_behavior.unwrap_or_else(|| configuration().exit_behavior) {
-        ExitBehavior::Hold | ExitBehavior::CloseOnCleanExit => {
+        ExitBehavior::Hold
+        | ExitBehavior::CloseOnCleanExit
+        | ExitBehavior::Restart
+        | ExitBehavior::RestartOnFailure => {
             // We don't know if we can unilaterally close
             // this pane right now, so don't!
             promise::spawn::spawn_into_main_thread(async move {
@@ -434,6 +451,7 @@ impl Mux {
             identity: RwLock::new(None),
             num_panes_by_workspace: RwLock::new(HashMap::new()),
             main_thread_id: std::thread::current().id(),
+            listening_sockets_wanted: AtomicBool::new(false),
         }
     }
 
@@ -1087,6 +1105,18 @@ impl Mux {
         *self.banner.write() = banner;
     }
 
+    /// Panes only watch for changes to the TCP sockets that their
+    /// processes are listening on, which is relatively expensive,
+    /// when something wants MuxNotification::ListeningSocketsChanged
+    pub fn set_listening_sockets_wanted(&self, wanted: bool) {
+        self.listening_sockets_wanted
+            .store(wanted, Ordering::Relaxed);
+    }
+
+    pub fn listening_sockets_wanted(&self) -> bool {
+        self.listening_sockets_wanted.load(Ordering::Relaxed)
+    }
+
     pub fn resolve_spawn_tab_domain(
         &self,
         // TODO: disambiguate with TabId
@@ -1,10 +1,11 @@
-use crate::domain::DomainId;
+use crate::domain::{DomainId, LocalDomain, WriterWrapper};
 use crate::pane::{
     CachePolicy, CloseReason, ForEachPaneLogicalLine, LogicalLine, Pane, PaneId, Pattern,
     SearchResult, WithPaneLines,
 };
 use crate::renderable::*;
 use crate::tmux::{TmuxDomain, TmuxDomainState};
+use crate::trigger::TriggerState;
 use crate::{Domain, Mux, MuxNotification};
 use anyhow::Error;
 use async_trait::async_trait;
@@ -12,8 +13,8 @@ use config::keyassignment::ScrollbackEraseMode;
 use config::{configuration, ExitBehavior, ExitBehaviorMessaging};
 use fancy_regex::Regex;
 use parking_lot::{MappedMutexGuard, Mutex, MutexGuard};
-use portable_pty::{Child, ChildKiller, ExitStatus, MasterPty, PtySize};
-use procinfo::LocalProcessInfo;
+use portable_pty::{Child, ChildKiller, CommandBuilder, ExitStatus, MasterPty, PtySize};
+use procinfo::{LocalListeningSocket, LocalProcessInfo, LocalProcessResources};
 use rangeset::RangeSet;
 use smol::channel::{bounded, Receiver, TryRecvError};
 use std::borrow::Cow;
@@ -28,14 +29,19 @@ use termwiz::escape::{Action, DeviceControlMode};
 use termwiz::input::KeyboardEncoding;
 use termwiz::surface::{Line, SequenceNo};
 use url::Url;
-use wezterm_dynamic::Value;
+use wezterm_dynamic::{ToDynamic, Value};
 use wezterm_term::color::ColorPalette;
 use wezterm_term::{
-    Alert, AlertHandler, Clipboard, DownloadHandler, KeyCode, KeyModifiers, MouseEvent,
-    SemanticZone, StableRowIndex, Terminal, TerminalConfiguration, TerminalSize,
+    Alert, AlertHandler, Clipboard, CommandExitStatus, CommandHistoryEntry, DownloadHandler,
+    KeyCode, KeyModifiers, MouseEvent, PointerShape, Progress, SemanticZone, StableRowIndex,
+    Terminal, TerminalConfiguration, TerminalSize,
 };
 
 const PROC_INFO_CACHE_TTL: Duration = Duration::from_millis(300);
+const LISTENING_SOCKETS_CHECK_DELAY: Duration = Duration::from_secs(1);
+/// A restarted command that runs for at least this long resets
+/// the restart backoff and attempt count
+const RESTART_ATTEMPTS_RESET_AFTER: Duration = Duration::from_secs(60);
 
 #[derive(Debug)]
 enum ProcessState {
@@ -49,9 +55,46 @@ enum ProcessState {
     DeadPendingClose {
         killed: bool,
     },
+    /// Waiting for the command to be restarted, as a result
+    /// of ExitBehavior::Restart or RestartOnFailure
+    DeadPendingRestart {
+        killed: bool,
+    },
     Dead,
 }
 
+/// Tracks what is needed to restart the command of a pane
+struct RestartState {
+    command: CommandBuilder,
+    /// The writer shared with the terminal, so that it can be
+    /// redirected to the pty of the restarted command
+    writer: WriterWrapper,
+    attempts: u32,
+    started: Instant,
+}
+
+/// Computes the delay before restarting a command that has already
+/// been restarted `attempts` times in a row.  The delay starts at
+/// `delay_ms` and doubles with each attempt, up to `max_delay_ms`.
+/// Returns None if `max_attempts` has been reached.
+fn restart_delay(
+    attempts: u32,
+    delay_ms: u64,
+    max_delay_ms: u64,
+    max_attempts: Option<u32>,
+) -> Option<Duration> {
+    if let Some(max_attempts) = max_attempts {
+        if attempts >= max_attempts {
+            return None;
+        }
+    }
+
+    let delay = delay_ms
+        .saturating_mul(1u64 << attempts.min(16))
+        .min(max_delay_ms.max(delay_ms));
+    Some(Duration::from_millis(delay))
+}
+
 struct CachedProcInfo {
     root: LocalProcessInfo,
     updated: Instant,
@@ -115,6 +158,55 @@ impl CachedLeaderInfo {
     }
 }
 
+/// The most recently observed set of listening sockets for a pane,
+/// used to generate MuxNotification::ListeningSocketsChanged
+#[derive(Default)]
+struct ListeningSockets {
+    sockets: Vec<LocalListeningSocket>,
+    updated: Option<Instant>,
+}
+
+fn update_listening_sockets(
+    pane_id: PaneId,
+    state: &Mutex<ListeningSockets>,
+    sockets: Vec<LocalListeningSocket>,
+) {
+    {
+        let mut state = state.lock();
+        state.updated = Some(Instant::now());
+        if state.sockets == sockets {
+            return;
+        }
+        state.sockets = sockets.clone();
+    }
+    Mux::notify_from_any_thread(MuxNotification::ListeningSocketsChanged { pane_id, sockets });
+}
+
+fn scan_listening_sockets(pid: u32) -> Vec<LocalListeningSocket> {
+    LocalProcessInfo::with_root_pid_and_listening_sockets(pid)
+        .map(|info| info.all_listening_sockets())
+        .unwrap_or_default()
+}
+
+/// Runs for the life of a pane once its listening sockets are being
+/// watched, re-scanning them when asked to by the pane.  Requests that
+/// arrive while waiting to scan are coalesced, so that a burst of output
+/// results in a single scan.
+/// Exits when the pane, which holds the sender, is dropped.
+fn watch_listening_sockets(
+    pane_id: PaneId,
+    state: Arc<Mutex<ListeningSockets>>,
+    requests: crossbeam::channel::Receiver<u32>,
+) {
+    while let Ok(mut pid) = requests.recv() {
+        std::thread::sleep(LISTENING_SOCKETS_CHECK_DELAY);
+        while let Ok(latest) = requests.try_recv() {
+            pid = latest;
+        }
+        update_listening_sockets(pane_id, &state, scan_listening_sockets(pid));
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum LocalPaneConnectionState {
     Connecting,
@@ -133,6 +225,10 @@ pub struct LocalPane {
     #[cfg(unix)]
     leader: Arc<Mutex<Option<CachedLeaderInfo>>>,
     command_description: String,
+    listening_sockets: Arc<Mutex<ListeningSockets>>,
+    listening_sockets_watcher: Mutex<Option<crossbeam::channel::Sender<u32>>>,
+    restart: Mutex<Option<RestartState>>,
+    triggers: Mutex<TriggerState>,
 }
 
 #[async_trait(?Send)]
@@ -160,6 +256,11 @@ impl Pane for LocalPane {
             );
         }
 
+        map.insert(
+            Value::String("progress".to_string()),
+            self.terminal.lock().get_progress().to_dynamic(),
+        );
+
         Value::Object(map.into())
     }
 
@@ -251,7 +352,8 @@ impl Pane for LocalPane {
                 let _ = signaller.kill();
                 *killed = true;
             }
-            ProcessState::DeadPendingClose { killed } => {
+            ProcessState::DeadPendingClose { killed }
+            | ProcessState::DeadPendingRestart { killed } => {
                 *killed = true;
             }
             _ => {}
@@ -291,12 +393,19 @@ impl Pane for LocalPane {
                             .contains(&status.exit_code()),
                     };
 
-                    match (
-                        self.exit_behavior()
-                            .unwrap_or_else(|| configuration().exit_behavior),
-                        success,
-                        killed,
-                    ) {
+                    let mut exit_behavior = self
+                        .exit_behavior()
+                        .unwrap_or_else(|| configuration().exit_behavior);
+                    if matches!(
+                        exit_behavior,
+                        ExitBehavior::Restart | ExitBehavior::RestartOnFailure
+                    ) && self.restart.lock().is_none()
+                    {
+                        // We don't know how to restart this command
+                        exit_behavior = ExitBehavior::Hold;
+                    }
+
+                    match (exit_behavior, success, killed) {
                         (ExitBehavior::Close, _, _) => *proc = ProcessState::Dead,
                         (ExitBehavior::CloseOnCleanExit, false, _) => {
                             brief = format!("⚠️  Process {cmd} didn't exit cleanly");
@@ -319,11 +428,51 @@ impl Pane for LocalPane {
                             *proc = ProcessState::DeadPendingClose { killed: false }
                         }
                         (ExitBehavior::Hold, _, true) => *proc = ProcessState::Dead,
+                        (ExitBehavior::RestartOnFailure, true, _) => *proc = ProcessState::Dead,
+                        (ExitBehavior::Restart | ExitBehavior::RestartOnFailure, _, true) => {
+                            *proc = ProcessState::Dead
+                        }
+                        (
+                            ExitBehavior::Restart | ExitBehavior::RestartOnFailure,
+                            success,
+                            false,
+                        ) => {
+                            let outcome = if success {
+                                "completed"
+                            } else {
+                                "didn't exit cleanly"
+                            };
+                            let name = match exit_behavior {
+                                ExitBehavior::Restart => "Restart",
+                                _ => "RestartOnFailure",
+                            };
+                            trailer = format!("{EXIT_BEHAVIOR}=\"{name}\"");
+
+                            match self.schedule_restart() {
+                                Some(delay) => {
+                                    brief = format!(
+                                        "🔁 Process {cmd} {outcome}, restarting in {:.1}s",
+                                        delay.as_secs_f32()
+                                    );
+                                    terse = format!("{status}, restarting");
+                                    *proc = ProcessState::DeadPendingRestart { killed: false }
+                                }
+                                None => {
+                                    brief = format!(
+                                        "⚠️  Process {cmd} {outcome}, \
+                                         giving up after too many restarts"
+                                    );
+                                    terse = format!("{status}");
+                                    *proc = ProcessState::DeadPendingClose { killed: false }
+                                }
+                            }
+                        }
                     }
                     log::debug!("child terminated, new state is {:?}", proc);
                 }
             }
-            ProcessState::DeadPendingClose { killed } => {
+            ProcessState::DeadPendingClose { killed }
+            | ProcessState::DeadPendingRestart { killed } => {
                 if *killed {
                     *proc = ProcessState::Dead;
                     log::debug!("child state -> {:?}", proc);
@@ -363,6 +512,7 @@ impl Pane for LocalPane {
         match &*proc {
             ProcessState::Running { .. } => false,
             ProcessState::DeadPendingClose { .. } => false,
+            ProcessState::DeadPendingRestart { .. } => false,
             ProcessState::Dead => true,
         }
     }
@@ -384,7 +534,12 @@ impl Pane for LocalPane {
     }
 
     fn perform_actions(&self, actions: Vec<termwiz::escape::Action>) {
-        self.terminal.lock().perform_actions(actions)
+        {
+            let mut terminal = self.terminal.lock();
+            terminal.perform_actions(actions);
+            self.triggers.lock().process(self.pane_id, &mut terminal);
+        }
+        self.schedule_listening_sockets_check();
     }
 
     fn mouse_event(&self, event: MouseEvent) -> Result<(), Error> {
@@ -485,6 +640,14 @@ impl Pane for LocalPane {
         self.terminal.lock().has_unseen_output()
     }
 
+    fn get_progress(&self) -> Progress {
+        self.terminal.lock().get_progress()
+    }
+
+    fn get_pointer_shape(&self) -> Option<PointerShape> {
+        self.terminal.lock().get_pointer_shape()
+    }
+
     fn is_mouse_grabbed(&self) -> bool {
         if self.tmux_domain.lock().is_some() {
             false
@@ -531,6 +694,32 @@ impl Pane for LocalPane {
         self.divine_foreground_process(policy)
     }
 
+    fn get_process_resources(&self, _policy: CachePolicy) -> Option<LocalProcessResources> {
+        // Not cached, as the CPU usage is measured since the previous
+        // sample, and sampling is cheap relative to how often this is used
+        let pid = match &*self.process.lock() {
+            ProcessState::Running { pid: Some(pid), .. } => *pid,
+            _ => return None,
+        };
+        LocalProcessInfo::with_root_pid_and_resources(pid).and_then(|info| info.total_resources())
+    }
+
+    fn get_listening_sockets(&self, policy: CachePolicy) -> Vec<LocalListeningSocket> {
+        let pid = match &*self.process.lock() {
+            ProcessState::Running { pid: Some(pid), .. } => *pid,
+            _ => return vec![],
+        };
+        if policy == CachePolicy::AllowStale {
+            let state = self.listening_sockets.lock();
+            if matches!(state.updated, Some(updated) if updated.elapsed() < PROC_INFO_CACHE_TTL) {
+                return state.sockets.clone();
+            }
+        }
+        let sockets = scan_listening_sockets(pid);
+        update_listening_sockets(self.pane_id, &self.listening_sockets, sockets.clone());
+        sockets
+    }
+
     fn get_foreground_process_name(&self, policy: CachePolicy) -> Option<String> {
         #[cfg(unix)]
         {
@@ -635,6 +824,14 @@ impl Pane for LocalPane {
         term.get_semantic_zones()
     }
 
+    fn get_command_exit_statuses(&self) -> anyhow::Result<Vec<CommandExitStatus>> {
+        Ok(self.terminal.lock().get_command_exit_statuses())
+    }
+
+    fn get_command_history(&self) -> anyhow::Result<Vec<CommandHistoryEntry>> {
+        Ok(self.terminal.lock().get_command_history())
+    }
+
     async fn search(
         &self,
         pattern: Pattern,
@@ -1011,9 +1208,139 @@ impl LocalPane {
             #[cfg(unix)]
             leader: Arc::new(Mutex::new(None)),
             command_description,
+            listening_sockets: Arc::new(Mutex::new(ListeningSockets::default())),
+            listening_sockets_watcher: Mutex::new(None),
+            restart: Mutex::new(None),
+            triggers: Mutex::new(TriggerState::new()),
         }
     }
 
+    /// Enables ExitBehavior::Restart and RestartOnFailure for this pane.
+    /// `command` is respawned when the process exits, and `writer`
+    /// must be the writer that is shared with the terminal.
+    pub(crate) fn with_restart_command(
+        self,
+        command: CommandBuilder,
+        writer: WriterWrapper,
+    ) -> Self {
+        self.restart.lock().replace(RestartState {
+            command,
+            writer,
+            attempts: 0,
+            started: Instant::now(),
+        });
+        self
+    }
+
+    /// Arranges for the command to be restarted after a delay that
+    /// grows with the number of consecutive restarts.
+    /// Returns the delay, or None if the maximum number of attempts
+    /// has been reached.
+    fn schedule_restart(&self) -> Option<Duration> {
+        let config = configuration();
+        let mut restart = self.restart.lock();
+        let restart = restart.as_mut()?;
+
+        if restart.started.elapsed() >= RESTART_ATTEMPTS_RESET_AFTER {
+            restart.attempts = 0;
+        }
+        let delay = restart_delay(
+            restart.attempts,
+            config.exit_behavior_restart_delay,
+            config.exit_behavior_restart_max_delay,
+            config.exit_behavior_restart_max_attempts,
+        )?;
+        restart.attempts += 1;
+
+        let pane_id = self.pane_id;
+        promise::spawn::spawn_into_main_thread(async move {
+            smol::Timer::after(delay).await;
+            let mux = Mux::get();
+            if let Some(pane) = mux.get_pane(pane_id) {
+                if let Some(local_pane) = pane.downcast_ref::<LocalPane>() {
+                    if let Err(err) = local_pane.restart_process(&pane) {
+                        log::error!("Failed to restart command in pane {pane_id}: {err:#}");
+                        emit_output_for_pane(
+                            pane_id,
+                            &format!("\r\n⚠️  Failed to restart command: {err:#}"),
+                        );
+                        let mut proc = local_pane.process.lock();
+                        if let ProcessState::DeadPendingRestart { killed } = *proc {
+                            *proc = ProcessState::DeadPendingClose { killed };
+                        }
+                    }
+                }
+            }
+        })
+        .detach();
+
+        Some(delay)
+    }
+
+    /// Respawns the command in a new pty, keeping the pane and its
+    /// terminal state, and separating the output of the new process
+    /// from that of the old one with a line
+    fn restart_process(&self, pane: &Arc<dyn Pane>) -> anyhow::Result<()> {
+        if !matches!(
+            &*self.process.lock(),
+            ProcessState::DeadPendingRestart { killed: false }
+        ) {
+            // The pane was closed while we were waiting
+            return Ok(());
+        }
+        let command = match self.restart.lock().as_ref() {
+            Some(restart) => restart.command.clone(),
+            None => return Ok(()),
+        };
+
+        let mux = Mux::get();
+        let domain = mux
+            .get_domain(self.domain_id)
+            .ok_or_else(|| anyhow::anyhow!("domain {} not found", self.domain_id))?;
+        let domain = domain.downcast_ref::<LocalDomain>().ok_or_else(|| {
+            anyhow::anyhow!(
+                "domain {} doesn't support restarting commands",
+                domain.domain_name()
+            )
+        })?;
+
+        let size = self.pty.lock().get_size()?;
+        let (child, pty) = domain.spawn_command_in_new_pty(command, size)?;
+        let writer = pty.take_writer()?;
+        let reader = pty.try_clone_reader()?;
+
+        let cols = self.terminal.lock().get_size().cols;
+        let mut parser = termwiz::escape::parser::Parser::new();
+        let mut actions = vec![Action::CSI(CSI::Sgr(Sgr::Reset))];
+        parser.parse(
+            format!("\r\n{}\r\n", "─".repeat(cols)).as_bytes(),
+            |action| actions.push(action),
+        );
+        self.perform_actions(actions);
+
+        if let Some(restart) = self.restart.lock().as_mut() {
+            restart.writer.replace(writer);
+            restart.started = Instant::now();
+        }
+        *self.pty.lock() = pty;
+        #[cfg(unix)]
+        self.leader.lock().take();
+        self.proc_list.lock().take();
+
+        let (child_waiter, signaller, pid) = split_child(child);
+        *self.process.lock() = ProcessState::Running {
+            child_waiter,
+            pid,
+            signaller,
+            killed: false,
+        };
+
+        let pane = Arc::downgrade(pane);
+        std::thread::spawn(move || crate::read_from_pane_pty(pane, None, reader));
+        mux.notify(MuxNotification::PaneOutput(self.pane_id));
+        Ok(())
+    }
+
     #[cfg(unix)]
     fn get_leader(&self, policy: CachePolicy) -> CachedLeaderInfo {
         let mut leader = self.leader.lock();
@@ -1062,6 +1389,33 @@ impl LocalPane {
         None
     }
 
+    /// Arranges for the listening sockets of the process tree to be
+    /// re-scanned shortly after output has been received, as that is
+    /// typically when a server announces that it is ready.
+    /// This is a no-op unless something wants to know about changes to
+    /// the sockets, or on systems where procinfo cannot report them.
+    fn schedule_listening_sockets_check(&self) {
+        let wanted = Mux::try_get().map(|mux| mux.listening_sockets_wanted());
+        if !cfg!(target_os = "linux") || wanted != Some(true) {
+            return;
+        }
+        let pid = match &*self.process.lock() {
+            ProcessState::Running { pid: Some(pid), .. } => *pid,
+            _ => return,
+        };
+
+        let mut watcher = self.listening_sockets_watcher.lock();
+        let watcher = watcher.get_or_insert_with(|| {
+            let (tx, rx) = crossbeam::channel::bounded(1);
+            let pane_id = self.pane_id;
+            let state = Arc::clone(&self.listening_sockets);
+            std::thread::spawn(move || watch_listening_sockets(pane_id, state, rx));
+            tx
+        });
+        // If a check is already pending then it will cover this output too
+        watcher.try_send(pid).ok();
+    }
+
     fn divine_process_list(&self, policy: CachePolicy) -> Option<MappedMutexGuard<CachedProcInfo>> {
         if let ProcessState::Running { pid: Some(pid), .. } = &*self.process.lock() {
             let mut proc_list = self.proc_list.lock();
@@ -1135,3 +1489,53 @@ impl Drop for LocalPane {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn delays(count: u32, delay_ms: u64, max_delay_ms: u64) -> Vec<u64> {
+        (0..count)
+            .map(|attempts| {
+                restart_delay(attempts, delay_ms, max_delay_ms, None)
+                    .unwrap()
+                    .as_millis() as u64
+            })
+            .collect()
+    }
+
+    #[test]
+    fn restart_delay_backoff() {
+        assert_eq!(delays(6, 100, 2000), vec![100, 200, 400, 800, 1600, 2000]);
+        // A max delay that is smaller than the initial delay
+        // doesn't shorten the initial delay
+        assert_eq!(delays(3, 500, 100), vec![500, 500, 500]);
+        assert_eq!(delays(3, 0, 1000), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn restart_delay_does_not_overflow() {
+        assert_eq!(
+            restart_delay(u32::MAX, 1000, u64::MAX, None),
+            Some(Duration::from_millis(1000 << 16))
+        );
+        assert_eq!(
+            restart_delay(20, u64::MAX / 2, u64::MAX, None),
+            Some(Duration::from_millis(u64::MAX))
+        );
+    }
+
+    #[test]
+    fn restart_delay_max_attempts() {
+        assert_eq!(
+            restart_delay(0, 100, 1000, Some(2)),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            restart_delay(1, 100, 1000, Some(2)),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(restart_delay(2, 100, 1000, Some(2)), None);
+        assert_eq!(restart_delay(0, 100, 1000, Some(0)), None);
+    }
+}
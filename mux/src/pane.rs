@@ -10,6 +10,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::ops::Range;
 use std::sync::Arc;
+use termwiz::highlight::Rule as HighlightRule;
 use termwiz::hyperlink::Rule;
 use termwiz::input::KeyboardEncoding;
 use termwiz::surface::{Line, SequenceNo};
@@ -17,8 +18,9 @@ use url::Url;
 use wezterm_dynamic::Value;
 use wezterm_term::color::ColorPalette;
 use wezterm_term::{
-    Clipboard, DownloadHandler, KeyCode, KeyModifiers, MouseEvent, SemanticZone, StableRowIndex,
-    TerminalConfiguration, TerminalSize,
+    Clipboard, CommandExitStatus, CommandHistoryEntry, DownloadHandler, KeyCode, KeyModifiers,
+    MouseEvent, PointerShape, Progress, SemanticZone, StableRowIndex, TerminalConfiguration,
+    TerminalSize,
 };
 
 static PANE_ID: ::std::sync::atomic::AtomicUsize = ::std::sync::atomic::AtomicUsize::new(0);
@@ -211,6 +213,55 @@ pub trait Pane: Downcast + Send + Sync {
         self.for_each_logical_line_in_stable_range_mut(lines, &mut ApplyHyperLinks { rules });
     }
 
+    fn apply_highlights(&self, lines: Range<StableRowIndex>, rules: &[HighlightRule]) {
+        struct ApplyHighlights<'a> {
+            rules: &'a [HighlightRule],
+        }
+        impl<'a> ForEachPaneLogicalLine for ApplyHighlights<'a> {
+            fn with_logical_line_mut(
+                &mut self,
+                _: Range<StableRowIndex>,
+                lines: &mut [&mut Line],
+            ) -> bool {
+                Line::apply_highlight_rules(self.rules, lines);
+
+                true
+            }
+        }
+
+        if rules.is_empty() {
+            return;
+        }
+        self.for_each_logical_line_in_stable_range_mut(lines, &mut ApplyHighlights { rules });
+    }
+
+    /// Discards the highlights applied by `apply_highlights`, so that
+    /// they are computed again from the current rules.
+    /// This should be called when the highlight rules are changed.
+    fn clear_highlights(&self) {
+        struct ClearHighlights;
+        impl ForEachPaneLogicalLine for ClearHighlights {
+            fn with_logical_line_mut(
+                &mut self,
+                _: Range<StableRowIndex>,
+                lines: &mut [&mut Line],
+            ) -> bool {
+                for line in lines.iter_mut() {
+                    line.clear_highlights();
+                }
+
+                true
+            }
+        }
+
+        let dims = self.get_dimensions();
+        let end = dims.physical_top + dims.viewport_rows as StableRowIndex;
+        self.for_each_logical_line_in_stable_range_mut(
+            dims.scrollback_top..end,
+            &mut ClearHighlights,
+        );
+    }
+
     /// Returns render related dimensions
     fn get_dimensions(&self) -> RenderableDimensions;
 
@@ -255,6 +306,18 @@ pub trait Pane: Downcast + Send + Sync {
         false
     }
 
+    /// Returns the progress most recently reported by the
+    /// application running in the pane, via OSC 9;4
+    fn get_progress(&self) -> Progress {
+        Progress::None
+    }
+
+    /// Returns the shape of the mouse pointer that was requested by
+    /// the application running in the pane, via OSC 22
+    fn get_pointer_shape(&self) -> Option<PointerShape> {
+        None
+    }
+
     /// Certain panes are OK to be closed with impunity (no prompts)
     fn can_close_without_prompting(&self, _reason: CloseReason) -> bool {
         false
@@ -284,6 +347,18 @@ pub trait Pane: Downcast + Send + Sync {
         Ok(vec![])
     }
 
+    /// Retrieve the exit statuses that the shell reported for
+    /// the commands in the semantic zones
+    fn get_command_exit_statuses(&self) -> anyhow::Result<Vec<CommandExitStatus>> {
+        Ok(vec![])
+    }
+
+    /// Retrieve the commands that the shell has reported via OSC 133
+    /// whose output is still present in the scrollback, oldest first
+    fn get_command_history(&self) -> anyhow::Result<Vec<CommandHistoryEntry>> {
+        Ok(vec![])
+    }
+
     /// Returns true if the terminal has grabbed the mouse and wants to
     /// give the embedded application a chance to process events.
     /// In practice this controls whether the gui will perform local
@@ -309,6 +384,21 @@ pub trait Pane: Downcast + Send + Sync {
         None
     }
 
+    /// Returns the resources used by all of the processes
+    /// that are running in the pane
+    fn get_process_resources(
+        &self,
+        _policy: CachePolicy,
+    ) -> Option<procinfo::LocalProcessResources> {
+        None
+    }
+
+    /// Returns the TCP sockets that the processes running in
+    /// the pane are listening on
+    fn get_listening_sockets(&self, _policy: CachePolicy) -> Vec<procinfo::LocalListeningSocket> {
+        vec![]
+    }
+
     fn tty_name(&self) -> Option<String> {
         None
     }
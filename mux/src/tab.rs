@@ -222,6 +222,7 @@ fn pane_tree(
     workspace: &str,
     left_col: usize,
     top_row: usize,
+    include_resources: bool,
 ) -> PaneNode {
     match tree {
         Tree::Empty => PaneNode::Empty,
@@ -229,7 +230,15 @@ fn pane_tree(
             let data = data.unwrap();
             PaneNode::Split {
                 left: Box::new(pane_tree(
-                    &*left, tab_id, window_id, active, zoomed, workspace, left_col, top_row,
+                    &*left,
+                    tab_id,
+                    window_id,
+                    active,
+                    zoomed,
+                    workspace,
+                    left_col,
+                    top_row,
+                    include_resources,
                 )),
                 right: Box::new(pane_tree(
                     &*right,
@@ -248,6 +257,7 @@ fn pane_tree(
                     } else {
                         top_row + data.top_of_second()
                     },
+                    include_resources,
                 )),
                 node: data,
             }
@@ -278,6 +288,11 @@ fn pane_tree(
                 left_col,
                 top_row,
                 tty_name: pane.tty_name(),
+                process_resources: if include_resources {
+                    pane.get_process_resources(CachePolicy::AllowStale)
+                } else {
+                    None
+                },
             })
         }
     }
@@ -551,8 +566,8 @@ impl Tab {
         self.inner.lock().sync_with_pane_tree(size, root, make_pane)
     }
 
-    pub fn codec_pane_tree(&self) -> PaneNode {
-        self.inner.lock().codec_pane_tree()
+    pub fn codec_pane_tree(&self, include_resources: bool) -> PaneNode {
+        self.inner.lock().codec_pane_tree(include_resources)
     }
 
     /// Returns a count of how many panes are in this tab
@@ -818,7 +833,7 @@ impl TabInner {
         assert!(self.pane.is_some());
     }
 
-    fn codec_pane_tree(&mut self) -> PaneNode {
+    fn codec_pane_tree(&mut self, include_resources: bool) -> PaneNode {
         let mux = Mux::get();
         let tab_id = self.id;
         let window_id = match mux.window_containing_tab(tab_id) {
@@ -852,6 +867,7 @@ impl TabInner {
                 &workspace,
                 0,
                 0,
+                include_resources,
             )
         } else {
             PaneNode::Empty
@@ -2149,6 +2165,8 @@ pub struct PaneEntry {
     pub top_row: usize,
     pub left_col: usize,
     pub tty_name: Option<String>,
+    /// The resources used by the processes running in the pane
+    pub process_resources: Option<procinfo::LocalProcessResources>,
 }
 
 #[derive(Deserialize, Clone, Serialize, PartialEq, Debug)]
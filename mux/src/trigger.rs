@@ -0,0 +1,315 @@
+//! Matches the output of local panes against the configured triggers
+use crate::pane::PaneId;
+use crate::{Mux, MuxNotification};
+use config::{configuration, expand_trigger_captures, Trigger, TriggerAction};
+use fancy_regex::Regex;
+use ratelim::RateLimiter;
+use termwiz::color::ColorAttribute;
+use termwiz::escape::osc::{ITermProprietary, OperatingSystemCommand};
+use termwiz::escape::Action;
+use termwiz::surface::line::LogicalLineText;
+use termwiz::surface::{Line, SequenceNo};
+use wezterm_term::{Alert, SemanticType, StableRowIndex, Terminal};
+
+struct CompiledTrigger {
+    regex: Regex,
+    trigger: Trigger,
+}
+
+struct TriggerMatch {
+    action: TriggerAction,
+    captures: Vec<String>,
+}
+
+pub(crate) struct TriggerState {
+    /// The first row that has not yet been matched against the triggers
+    next_row: StableRowIndex,
+    generation: Option<usize>,
+    triggers: Vec<CompiledTrigger>,
+    limiter: RateLimiter,
+}
+
+impl TriggerState {
+    pub fn new() -> Self {
+        Self {
+            next_row: 0,
+            generation: None,
+            triggers: vec![],
+            limiter: RateLimiter::new(|config| config.ratelimit_trigger_lines_per_second.max(1)),
+        }
+    }
+
+    fn update_config(&mut self) {
+        let config = configuration();
+        if self.generation == Some(config.generation()) {
+            return;
+        }
+        self.generation = Some(config.generation());
+        self.triggers = config
+            .triggers
+            .iter()
+            .filter_map(|trigger| match Regex::new(&trigger.regex) {
+                Ok(regex) => Some(CompiledTrigger {
+                    regex,
+                    trigger: trigger.clone(),
+                }),
+                Err(err) => {
+                    log::error!("Invalid regex {:?} in triggers: {:#}", trigger.regex, err);
+                    None
+                }
+            })
+            .collect();
+    }
+
+    /// Matches the lines that have been completed since the previous
+    /// call against the triggers, and performs the resulting actions.
+    /// A line is complete once the cursor has moved below it.
+    pub fn process(&mut self, pane_id: PaneId, terminal: &mut Terminal) {
+        self.update_config();
+        if terminal.is_alt_screen_active() {
+            return;
+        }
+
+        let seqno = terminal.current_seqno();
+        let cursor_y = terminal.cursor_pos().y;
+        let screen = terminal.screen_mut();
+        let cursor_row = screen.visible_row_to_stable_row(cursor_y);
+
+        if self.triggers.is_empty() {
+            self.next_row = self.next_row.max(cursor_row);
+            return;
+        }
+        if cursor_row <= self.next_row {
+            return;
+        }
+
+        let start_row = self.next_row;
+        let mut next_row = start_row;
+        let mut skipped = 0;
+        let mut matches = vec![];
+        let triggers = &self.triggers;
+        let limiter = &mut self.limiter;
+
+        screen.for_each_logical_line_in_stable_range_mut(start_row..cursor_row, |range, lines| {
+            if range.end > cursor_row {
+                // The line is still being output
+                return false;
+            }
+            next_row = next_row.max(range.end);
+            if range.start < start_row {
+                // Already matched, but was re-wrapped since
+                return true;
+            }
+            if limiter.non_blocking_admittance_check(1) {
+                match_logical_line(triggers, lines, seqno, &mut matches);
+            } else {
+                skipped += 1;
+            }
+            true
+        });
+        self.next_row = next_row;
+
+        if skipped > 0 {
+            log::debug!(
+                "pane {pane_id}: skipped matching {skipped} lines against triggers \
+                 due to ratelimit_trigger_lines_per_second"
+            );
+        }
+
+        for m in matches {
+            perform_trigger_action(pane_id, terminal, m);
+        }
+    }
+}
+
+/// Matches the text of a logical line against the triggers.
+/// Highlights are applied immediately, while the other actions
+/// are appended to `matches`.
+fn match_logical_line(
+    triggers: &[CompiledTrigger],
+    lines: &mut [&mut Line],
+    seqno: SequenceNo,
+    matches: &mut Vec<TriggerMatch>,
+) {
+    let logical = LogicalLineText::new(lines);
+    let text = logical.text().trim_end();
+    if text.is_empty() {
+        return;
+    }
+
+    for trigger in triggers {
+        for captures in trigger.regex.captures_iter(text) {
+            let captures = match captures {
+                Ok(captures) => captures,
+                Err(err) => {
+                    log::error!(
+                        "Error matching trigger {:?}: {:#}",
+                        trigger.trigger.regex,
+                        err
+                    );
+                    break;
+                }
+            };
+            let range = match captures.get(0) {
+                Some(m) if !m.range().is_empty() => m.range(),
+                _ => continue,
+            };
+            let matched_cells = logical.cells_in_range(range);
+
+            if let Some(zone) = trigger.trigger.semantic_zone {
+                let semantic_type = matched_cells
+                    .clone()
+                    .next()
+                    .and_then(|(line_idx, cell_idx)| lines[line_idx].get_cell(cell_idx))
+                    .map(|cell| cell.attrs().semantic_type());
+                if semantic_type != Some(zone) {
+                    continue;
+                }
+            }
+
+            match &trigger.trigger.action {
+                TriggerAction::Highlight {
+                    foreground,
+                    background,
+                } => {
+                    for (line_idx, cell_idx) in matched_cells {
+                        let line = &mut lines[line_idx];
+                        if let Some(cell) = line.cells_mut_for_attr_changes_only().get_mut(cell_idx)
+                        {
+                            let attrs = cell.attrs_mut();
+                            if let Some(fg) = foreground {
+                                attrs.set_foreground(ColorAttribute::TrueColorWithDefaultFallback(
+                                    (*fg).into(),
+                                ));
+                            }
+                            if let Some(bg) = background {
+                                attrs.set_background(ColorAttribute::TrueColorWithDefaultFallback(
+                                    (*bg).into(),
+                                ));
+                            }
+                        }
+                        line.update_last_change_seqno(seqno);
+                    }
+                }
+                action => matches.push(TriggerMatch {
+                    action: action.clone(),
+                    captures: captures
+                        .iter()
+                        .map(|c| c.map(|c| c.as_str().to_string()).unwrap_or_default())
+                        .collect(),
+                }),
+            }
+        }
+    }
+}
+
+fn perform_trigger_action(pane_id: PaneId, terminal: &mut Terminal, m: TriggerMatch) {
+    let TriggerMatch { action, captures } = m;
+    match action {
+        TriggerAction::Highlight { .. } => {}
+        TriggerAction::Notify { title, body } => {
+            Mux::notify_from_any_thread(MuxNotification::Alert {
+                pane_id,
+                alert: Alert::ToastNotification {
+                    title: title.map(|title| expand_trigger_captures(&title, &captures)),
+                    body: expand_trigger_captures(&body, &captures),
+                    focus: true,
+                    id: None,
+                    urgency: None,
+                    report_activation: false,
+                    report_close: false,
+                    timeout: None,
+                },
+            });
+        }
+        TriggerAction::SetUserVar { name, value } => {
+            terminal.perform_actions(vec![Action::OperatingSystemCommand(Box::new(
+                OperatingSystemCommand::ITermProprietary(ITermProprietary::SetUserVar {
+                    name,
+                    value: expand_trigger_captures(&value, &captures),
+                }),
+            ))]);
+        }
+        action => {
+            Mux::notify_from_any_thread(MuxNotification::TriggerMatched {
+                pane_id,
+                action,
+                captures,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use config::RgbaColor;
+    use termwiz::cell::CellAttributes;
+    use termwiz::surface::SEQ_ZERO;
+
+    fn compile(
+        regex: &str,
+        semantic_zone: Option<SemanticType>,
+        action: TriggerAction,
+    ) -> CompiledTrigger {
+        CompiledTrigger {
+            regex: Regex::new(regex).unwrap(),
+            trigger: Trigger {
+                regex: regex.to_string(),
+                semantic_zone,
+                action,
+            },
+        }
+    }
+
+    #[test]
+    fn match_across_wrapped_lines() {
+        let attrs = CellAttributes::default();
+        let mut first = Line::from_text_with_wrapped_last_col("error: disk ", &attrs, SEQ_ZERO);
+        let mut second = Line::from_text("full on sda   ", &attrs, SEQ_ZERO, None);
+
+        let red: RgbaColor = (255, 0, 0).into();
+        let triggers = vec![
+            compile(
+                r"disk full on (\w+)",
+                None,
+                TriggerAction::EmitEvent("disk-full".to_string()),
+            ),
+            compile(
+                "full",
+                None,
+                TriggerAction::Highlight {
+                    foreground: None,
+                    background: Some(red),
+                },
+            ),
+            compile(
+                "error",
+                Some(SemanticType::Prompt),
+                TriggerAction::EmitEvent("prompt-error".to_string()),
+            ),
+        ];
+
+        let mut matches = vec![];
+        match_logical_line(&triggers, &mut [&mut first, &mut second], 1, &mut matches);
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(
+            matches[0].action,
+            TriggerAction::EmitEvent("disk-full".to_string())
+        );
+        assert_eq!(matches[0].captures, vec!["disk full on sda", "sda"]);
+
+        let highlighted: Vec<usize> = second
+            .visible_cells()
+            .filter(|cell| {
+                cell.attrs().background()
+                    == ColorAttribute::TrueColorWithDefaultFallback(red.into())
+            })
+            .map(|cell| cell.cell_index())
+            .collect();
+        assert_eq!(highlighted, vec![0, 1, 2, 3]);
+        assert_eq!(second.current_seqno(), 1);
+        assert_eq!(first.current_seqno(), SEQ_ZERO);
+    }
+}
@@ -1,6 +1,8 @@
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
 #[cfg(feature = "lua")]
 use wezterm_dynamic::{FromDynamic, ToDynamic};
 
@@ -25,6 +27,68 @@ pub enum LocalProcessStatus {
     Unknown,
 }
 
+/// Resource usage of a process, or of a tree of processes.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "lua", derive(FromDynamic, ToDynamic))]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct LocalProcessResources {
+    /// The resident set size, in bytes
+    pub rss: u64,
+    /// The user and system CPU time consumed, in milliseconds
+    pub cpu_time_ms: u64,
+    /// The CPU usage since the process was previously sampled,
+    /// as a percentage of a single CPU.  `None` if this is the
+    /// first time that the process has been sampled.
+    pub cpu_percent: Option<f64>,
+    /// The number of threads
+    pub threads: u32,
+    /// The number of open file descriptors
+    pub open_fds: u32,
+}
+
+#[cfg(feature = "lua")]
+luahelper::impl_lua_conversion_dynamic!(LocalProcessResources);
+
+impl std::ops::AddAssign for LocalProcessResources {
+    fn add_assign(&mut self, other: Self) {
+        self.rss += other.rss;
+        self.cpu_time_ms += other.cpu_time_ms;
+        self.cpu_percent = match (self.cpu_percent, other.cpu_percent) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+        self.threads += other.threads;
+        self.open_fds += other.open_fds;
+    }
+}
+
+/// A TCP socket that a process is listening on for connections
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "lua", derive(FromDynamic, ToDynamic))]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct LocalListeningSocket {
+    /// The local address, eg: `127.0.0.1`, or `::` for all addresses
+    pub address: String,
+    pub port: u16,
+}
+#[cfg(feature = "lua")]
+luahelper::impl_lua_conversion_dynamic!(LocalListeningSocket);
+
+/// Selects the optional information that
+/// `LocalProcessInfo::with_root_pid_and_details` gathers in addition
+/// to what `LocalProcessInfo::with_root_pid` returns.
+/// Each of these requires examining the file descriptors of every
+/// process in the tree, so they are not gathered by default.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct LocalProcessDetails {
+    /// Populate `LocalProcessInfo::resources`.  Note that this also
+    /// records a CPU time sample for each process, which is the
+    /// baseline for `cpu_percent` the next time that it is sampled.
+    pub resources: bool,
+    /// Populate `LocalProcessInfo::listening_sockets`
+    pub listening_sockets: bool,
+}
+
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "lua", derive(FromDynamic, ToDynamic))]
 pub struct LocalProcessInfo {
@@ -56,6 +120,14 @@ pub struct LocalProcessInfo {
     /// The console handle associated with the process, if any.
     #[cfg(windows)]
     pub console: u64,
+    /// The resources used by the process, excluding its children.
+    /// This is currently only available on Linux, and is only
+    /// populated when requested via `LocalProcessDetails`.
+    pub resources: Option<LocalProcessResources>,
+    /// The TCP sockets that the process is listening on.
+    /// This is currently only available on Linux, and is only
+    /// populated when requested via `LocalProcessDetails`.
+    pub listening_sockets: Vec<LocalListeningSocket>,
     /// Child processes, keyed by pid
     pub children: HashMap<u32, LocalProcessInfo>,
 }
@@ -82,11 +154,72 @@ impl LocalProcessInfo {
         names
     }
 
+    /// Returns the unique set of TCP sockets that this sub-tree of
+    /// processes is listening on, ordered by address and port.
+    pub fn all_listening_sockets(&self) -> Vec<LocalListeningSocket> {
+        fn flatten(item: &LocalProcessInfo, sockets: &mut Vec<LocalListeningSocket>) {
+            sockets.extend(item.listening_sockets.iter().cloned());
+            for proc in item.children.values() {
+                flatten(proc, sockets);
+            }
+        }
+
+        let mut sockets = vec![];
+        flatten(self, &mut sockets);
+        sockets.sort();
+        sockets.dedup();
+        sockets
+    }
+
+    /// Returns the resources used by this sub-tree of processes,
+    /// or `None` if they are not available on this system.
+    pub fn total_resources(&self) -> Option<LocalProcessResources> {
+        let mut total = self.resources?;
+        for proc in self.children.values() {
+            if let Some(resources) = proc.total_resources() {
+                total += resources;
+            }
+        }
+        Some(total)
+    }
+
     #[cfg(not(any(target_os = "macos", target_os = "linux", windows)))]
     pub fn with_root_pid(_pid: u32) -> Option<Self> {
         None
     }
 
+    /// Like `with_root_pid`, but also gathers the information selected
+    /// by `details`.  This is currently only possible on Linux, so
+    /// elsewhere it is the same as `with_root_pid`.
+    #[cfg(not(target_os = "linux"))]
+    pub fn with_root_pid_and_details(pid: u32, _details: LocalProcessDetails) -> Option<Self> {
+        Self::with_root_pid(pid)
+    }
+
+    /// Like `with_root_pid`, but also samples the resources used by
+    /// the processes
+    pub fn with_root_pid_and_resources(pid: u32) -> Option<Self> {
+        Self::with_root_pid_and_details(
+            pid,
+            LocalProcessDetails {
+                resources: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Like `with_root_pid`, but also discovers the TCP sockets that
+    /// the processes are listening on
+    pub fn with_root_pid_and_listening_sockets(pid: u32) -> Option<Self> {
+        Self::with_root_pid_and_details(
+            pid,
+            LocalProcessDetails {
+                listening_sockets: true,
+                ..Default::default()
+            },
+        )
+    }
+
     #[cfg(not(any(target_os = "macos", target_os = "linux", windows)))]
     pub fn current_working_dir(_pid: u32) -> Option<PathBuf> {
         None
@@ -97,3 +230,95 @@ impl LocalProcessInfo {
         None
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn resources(rss: u64, cpu_percent: Option<f64>) -> LocalProcessResources {
+        LocalProcessResources {
+            rss,
+            cpu_time_ms: 10,
+            cpu_percent,
+            threads: 1,
+            open_fds: 3,
+        }
+    }
+
+    fn process(
+        pid: u32,
+        resources: Option<LocalProcessResources>,
+        children: Vec<LocalProcessInfo>,
+    ) -> LocalProcessInfo {
+        LocalProcessInfo {
+            pid,
+            ppid: 1,
+            name: String::new(),
+            executable: PathBuf::new(),
+            argv: vec![],
+            cwd: PathBuf::new(),
+            status: LocalProcessStatus::Run,
+            start_time: 0,
+            #[cfg(windows)]
+            console: 0,
+            resources,
+            listening_sockets: vec![],
+            children: children.into_iter().map(|kid| (kid.pid, kid)).collect(),
+        }
+    }
+
+    #[test]
+    fn add_resources() {
+        let mut total = resources(100, None);
+        total += resources(20, Some(1.5));
+        assert_eq!(
+            total,
+            LocalProcessResources {
+                rss: 120,
+                cpu_time_ms: 20,
+                cpu_percent: Some(1.5),
+                threads: 2,
+                open_fds: 6,
+            }
+        );
+        total += resources(3, Some(2.));
+        assert_eq!(total.cpu_percent, Some(3.5));
+        total += resources(4, None);
+        assert_eq!(total.cpu_percent, Some(3.5));
+        assert_eq!(total.rss, 127);
+    }
+
+    #[test]
+    fn total_resources() {
+        let tree = process(
+            10,
+            Some(resources(100, Some(1.))),
+            vec![
+                process(
+                    11,
+                    Some(resources(20, None)),
+                    vec![process(13, Some(resources(3, Some(2.))), vec![])],
+                ),
+                // A process whose resources are unknown is skipped,
+                // along with its children
+                process(
+                    12,
+                    None,
+                    vec![process(14, Some(resources(4, None)), vec![])],
+                ),
+            ],
+        );
+        assert_eq!(
+            tree.total_resources(),
+            Some(LocalProcessResources {
+                rss: 123,
+                cpu_time_ms: 30,
+                cpu_percent: Some(3.),
+                threads: 3,
+                open_fds: 9,
+            })
+        );
+        assert_eq!(tree.children[&11].total_resources().unwrap().rss, 23);
+        assert_eq!(process(1, None, vec![]).total_resources(), None);
+    }
+}
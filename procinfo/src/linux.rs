@@ -1,5 +1,57 @@
 #![cfg(target_os = "linux")]
 use super::*;
+use libc::pid_t;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The CPU time of a process when it was last sampled
+struct CpuSample {
+    when: Instant,
+    cpu_time_ms: u64,
+    cpu_percent: Option<f64>,
+}
+
+/// Samples are keyed by pid and start time, so that a recycled pid
+/// is not confused with the process that previously used it
+static CPU_SAMPLES: Mutex<BTreeMap<(pid_t, u64), CpuSample>> = Mutex::new(BTreeMap::new());
+
+/// Sampling more frequently than this produces noisy results, as the
+/// CPU time is only accounted in clock ticks
+const MIN_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+/// Samples of processes that haven't been seen for this long are discarded
+const SAMPLE_EXPIRY: Duration = Duration::from_secs(60);
+
+/// Returns the CPU usage, as a percentage of a single CPU, since
+/// the process was previously sampled
+fn recent_cpu_percent(pid: pid_t, start_time: u64, cpu_time_ms: u64) -> Option<f64> {
+    let now = Instant::now();
+    let mut samples = CPU_SAMPLES.lock().unwrap();
+    samples.retain(|_, sample| now.duration_since(sample.when) < SAMPLE_EXPIRY);
+
+    let cpu_percent = match samples.get(&(pid, start_time)) {
+        Some(sample) => {
+            let elapsed = now.duration_since(sample.when);
+            if elapsed < MIN_SAMPLE_INTERVAL {
+                return sample.cpu_percent;
+            }
+            let used_ms = cpu_time_ms.saturating_sub(sample.cpu_time_ms);
+            Some(used_ms as f64 * 100. / elapsed.as_millis() as f64)
+        }
+        None => None,
+    };
+
+    samples.insert(
+        (pid, start_time),
+        CpuSample {
+            when: now,
+            cpu_time_ms,
+            cpu_percent,
+        },
+    );
+    cpu_percent
+}
 
 impl From<&str> for LocalProcessStatus {
     fn from(s: &str) -> Self {
@@ -19,6 +71,93 @@ impl From<&str> for LocalProcessStatus {
     }
 }
 
+/// The state of a listening socket in `/proc/net/tcp`
+const TCP_LISTEN: &str = "0A";
+
+/// Parses an address from `/proc/net/tcp` or `/proc/net/tcp6`, which
+/// the kernel formats as a sequence of 32-bit words in host byte order
+fn parse_proc_net_address(hex: &str) -> Option<IpAddr> {
+    let mut bytes = vec![];
+    for chunk in hex.as_bytes().chunks(8) {
+        let word = u32::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok()?;
+        bytes.extend_from_slice(&word.to_ne_bytes());
+    }
+    match bytes.len() {
+        4 => Some(IpAddr::from(<[u8; 4]>::try_from(bytes).ok()?)),
+        16 => Some(IpAddr::from(<[u8; 16]>::try_from(bytes).ok()?)),
+        _ => None,
+    }
+}
+
+/// Parses a line from `/proc/net/tcp` or `/proc/net/tcp6`, returning
+/// the inode and the address of the socket if it is listening
+fn parse_proc_net_tcp_line(line: &str) -> Option<(u64, LocalListeningSocket)> {
+    // sl local_address rem_address st tx_queue:rx_queue tr:tm->when
+    // retrnsmt uid timeout inode ...
+    let fields = line.split_whitespace().collect::<Vec<_>>();
+    if *fields.get(3)? != TCP_LISTEN {
+        return None;
+    }
+    let (address, port) = fields.get(1)?.split_once(':')?;
+    let inode = fields.get(9)?.parse().ok()?;
+    Some((
+        inode,
+        LocalListeningSocket {
+            address: parse_proc_net_address(address)?.to_string(),
+            port: u16::from_str_radix(port, 16).ok()?,
+        },
+    ))
+}
+
+#[derive(Debug)]
+struct LinuxStat {
+    pid: pid_t,
+    name: String,
+    status: String,
+    ppid: pid_t,
+    // Time process started after boot, measured in ticks
+    starttime: u64,
+    // CPU time spent in user and kernel mode, measured in ticks
+    utime: u64,
+    stime: u64,
+    num_threads: u32,
+    // Resident set size, measured in pages
+    rss: u64,
+}
+
+/// Parse the contents of `/proc/<pid>/stat`; see proc(5).
+/// The fields are numbered from the state, which follows the
+/// name in parentheses, as the name may contain spaces
+fn parse_stat(pid: pid_t, data: &str) -> Option<LinuxStat> {
+    let (_pid_space, name) = data.split_once('(')?;
+    let (name, fields) = name.rsplit_once(')')?;
+    let fields = fields.split_whitespace().collect::<Vec<_>>();
+
+    Some(LinuxStat {
+        pid,
+        name: name.to_string(),
+        status: fields.first()?.to_string(),
+        ppid: fields.get(1)?.parse().ok()?,
+        starttime: fields.get(19)?.parse().ok()?,
+        utime: fields.get(11)?.parse().ok()?,
+        stime: fields.get(12)?.parse().ok()?,
+        num_threads: fields.get(17)?.parse().ok()?,
+        rss: fields.get(21)?.parse().ok()?,
+    })
+}
+
+/// Returns the TCP sockets that are listening, keyed by inode
+fn listening_sockets_by_inode() -> HashMap<u64, LocalListeningSocket> {
+    let mut sockets = HashMap::new();
+    for path in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        if let Ok(data) = std::fs::read_to_string(path) {
+            // The first line holds the column headings
+            sockets.extend(data.lines().skip(1).filter_map(parse_proc_net_tcp_line));
+        }
+    }
+    sockets
+}
+
 impl LocalProcessInfo {
     pub fn current_working_dir(pid: u32) -> Option<PathBuf> {
         std::fs::read_link(format!("/proc/{}/cwd", pid)).ok()
@@ -29,8 +168,10 @@ impl LocalProcessInfo {
     }
 
     pub fn with_root_pid(pid: u32) -> Option<Self> {
-        use libc::pid_t;
+        Self::with_root_pid_and_details(pid, LocalProcessDetails::default())
+    }
 
+    pub fn with_root_pid_and_details(pid: u32, details: LocalProcessDetails) -> Option<Self> {
         let pid = pid as pid_t;
 
         fn all_pids() -> Vec<pid_t> {
@@ -53,28 +194,50 @@ impl LocalProcessInfo {
             pids
         }
 
-        struct LinuxStat {
-            pid: pid_t,
-            name: String,
-            status: String,
-            ppid: pid_t,
-            // Time process started after boot, measured in ticks
-            starttime: u64,
-        }
-
         fn info_for_pid(pid: pid_t) -> Option<LinuxStat> {
             let data = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
-            let (_pid_space, name) = data.split_once('(')?;
-            let (name, fields) = name.rsplit_once(')')?;
-            let fields = fields.split_whitespace().collect::<Vec<_>>();
-
-            Some(LinuxStat {
-                pid,
-                name: name.to_string(),
-                status: fields.first()?.to_string(),
-                ppid: fields.get(1)?.parse().ok()?,
-                starttime: fields.get(19)?.parse().ok()?,
-            })
+            parse_stat(pid, &data)
+        }
+
+        /// Returns the number of open fds, and the inodes of
+        /// the sockets among them if `with_sockets` is true
+        fn fds_for_pid(pid: pid_t, with_sockets: bool) -> (u32, Vec<u64>) {
+            let mut count = 0;
+            let mut sockets = vec![];
+            // We may not be permitted to see the fds of other users' processes
+            if let Ok(dir) = std::fs::read_dir(format!("/proc/{}/fd", pid)) {
+                for entry in dir.flatten() {
+                    count += 1;
+                    if !with_sockets {
+                        continue;
+                    }
+                    if let Ok(target) = std::fs::read_link(entry.path()) {
+                        if let Some(inode) = target
+                            .to_str()
+                            .and_then(|t| t.strip_prefix("socket:["))
+                            .and_then(|t| t.strip_suffix(']'))
+                            .and_then(|t| t.parse().ok())
+                        {
+                            sockets.push(inode);
+                        }
+                    }
+                }
+            }
+            (count, sockets)
+        }
+
+        fn resources_for_pid(info: &LinuxStat, open_fds: u32) -> LocalProcessResources {
+            let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as u64;
+            let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(0) as u64;
+            let cpu_time_ms = (info.utime + info.stime) * 1000 / ticks_per_second;
+
+            LocalProcessResources {
+                rss: info.rss * page_size,
+                cpu_time_ms,
+                cpu_percent: recent_cpu_percent(info.pid, info.starttime, cpu_time_ms),
+                threads: info.num_threads,
+                open_fds,
+            }
         }
 
         fn exe_for_pid(pid: pid_t) -> PathBuf {
@@ -103,13 +266,26 @@ impl LocalProcessInfo {
         }
 
         let procs: Vec<_> = all_pids().into_iter().filter_map(info_for_pid).collect();
+        let sockets = if details.listening_sockets {
+            Some(listening_sockets_by_inode())
+        } else {
+            None
+        };
 
-        fn build_proc(info: &LinuxStat, procs: &[LinuxStat]) -> LocalProcessInfo {
+        fn build_proc(
+            info: &LinuxStat,
+            procs: &[LinuxStat],
+            with_resources: bool,
+            sockets: Option<&HashMap<u64, LocalListeningSocket>>,
+        ) -> LocalProcessInfo {
             let mut children = HashMap::new();
 
             for kid in procs {
                 if kid.ppid == info.pid {
-                    children.insert(kid.pid as u32, build_proc(kid, procs));
+                    children.insert(
+                        kid.pid as u32,
+                        build_proc(kid, procs, with_resources, sockets),
+                    );
                 }
             }
 
@@ -117,6 +293,18 @@ impl LocalProcessInfo {
             let name = info.name.clone();
             let argv = parse_cmdline(info.pid);
 
+            let (open_fds, socket_inodes) = if with_resources || sockets.is_some() {
+                fds_for_pid(info.pid, sockets.is_some())
+            } else {
+                (0, vec![])
+            };
+            let mut listening_sockets = socket_inodes
+                .iter()
+                .filter_map(|inode| sockets?.get(inode).cloned())
+                .collect::<Vec<_>>();
+            listening_sockets.sort();
+            listening_sockets.dedup();
+
             LocalProcessInfo {
                 pid: info.pid as _,
                 ppid: info.ppid as _,
@@ -127,13 +315,108 @@ impl LocalProcessInfo {
                 start_time: info.starttime,
                 status: info.status.as_str().into(),
                 children,
+                resources: if with_resources {
+                    Some(resources_for_pid(info, open_fds))
+                } else {
+                    None
+                },
+                listening_sockets,
             }
         }
 
         if let Some(info) = procs.iter().find(|info| info.pid == pid) {
-            Some(build_proc(info, &procs))
+            Some(build_proc(
+                info,
+                &procs,
+                details.resources,
+                sockets.as_ref(),
+            ))
         } else {
             None
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resources_are_opt_in() {
+        let pid = std::process::id();
+        let info = LocalProcessInfo::with_root_pid(pid).unwrap();
+        assert!(info.resources.is_none());
+
+        let info = LocalProcessInfo::with_root_pid_and_listening_sockets(pid).unwrap();
+        assert!(info.resources.is_none());
+
+        let info = LocalProcessInfo::with_root_pid_and_resources(pid).unwrap();
+        let resources = info.resources.unwrap();
+        assert!(resources.threads > 0);
+        assert!(resources.open_fds > 0);
+    }
+
+    #[test]
+    fn proc_stat() {
+        let stat = parse_stat(
+            1234,
+            "1234 (tmux: (1) server) S 1 1234 1234 34816 1234 4194560 1000 0 0 0 \
+             250 50 7 8 20 0 3 0 987654 12345678 2048 18446744073709551615 1 1 0 0 0 0 \
+             0 3670020 1266777851 0 0 0 17 2 0 0 0 0 0\n",
+        )
+        .unwrap();
+        assert_eq!(stat.pid, 1234);
+        assert_eq!(stat.name, "tmux: (1) server");
+        assert_eq!(stat.status, "S");
+        assert_eq!(stat.ppid, 1);
+        assert_eq!(stat.utime, 250);
+        assert_eq!(stat.stime, 50);
+        assert_eq!(stat.num_threads, 3);
+        assert_eq!(stat.starttime, 987654);
+        assert_eq!(stat.rss, 2048);
+
+        // Truncated or otherwise malformed
+        assert!(parse_stat(1, "1 (bash) S 1 1 1").is_none());
+        assert!(parse_stat(1, "1 bash S").is_none());
+    }
+
+    #[test]
+    #[cfg(target_endian = "little")]
+    fn proc_net_tcp() {
+        assert_eq!(
+            parse_proc_net_tcp_line(
+                "   0: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 \
+                 00000000  1000        0 123456 1 0000000000000000 100 0 0 10 0"
+            ),
+            Some((
+                123456,
+                LocalListeningSocket {
+                    address: "127.0.0.1".to_string(),
+                    port: 8080,
+                }
+            ))
+        );
+        assert_eq!(
+            parse_proc_net_tcp_line(
+                "   0: 00000000000000000000000001000000:0BB8 \
+                 00000000000000000000000000000000:0000 0A 00000000:00000000 \
+                 00:00000000 00000000  1000        0 42 1 0000000000000000 100 0 0 10 0"
+            ),
+            Some((
+                42,
+                LocalListeningSocket {
+                    address: "::1".to_string(),
+                    port: 3000,
+                }
+            ))
+        );
+        // An established connection rather than a listening socket
+        assert_eq!(
+            parse_proc_net_tcp_line(
+                "   1: 0100007F:1F90 0100007F:D2F0 01 00000000:00000000 00:00000000 \
+                 00000000  1000        0 123457 1 0000000000000000 20 4 30 10 -1"
+            ),
+            None
+        );
+    }
+}
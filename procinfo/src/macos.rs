@@ -188,6 +188,8 @@ impl LocalProcessInfo {
                 start_time: info.pbi_start_tvsec,
                 status: LocalProcessStatus::from(info.pbi_status),
                 children,
+                resources: None,
+                listening_sockets: vec![],
             }
         }
 
@@ -407,6 +407,8 @@ impl LocalProcessInfo {
                 status: LocalProcessStatus::Run,
                 children,
                 console,
+                resources: None,
+                listening_sockets: vec![],
             }
         }
 
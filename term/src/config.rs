@@ -1,5 +1,6 @@
 use crate::color::ColorPalette;
 use downcast_rs::{impl_downcast, Downcast};
+use std::time::Duration;
 use termwiz::cell::UnicodeVersion;
 use termwiz::surface::{Line, SequenceNo};
 use wezterm_bidi::ParagraphDirectionHint;
@@ -222,6 +223,13 @@ pub trait TerminalConfiguration: Downcast + std::fmt::Debug + Send + Sync {
     fn log_unknown_escape_sequences(&self) -> bool {
         false
     }
+
+    /// If a command runs for longer than this while the terminal is
+    /// not focused, `Alert::CommandFinished` is raised when it completes.
+    /// Disabled by default.
+    fn command_finished_notification_threshold(&self) -> Option<Duration> {
+        None
+    }
 }
 impl_downcast!(TerminalConfiguration);
 
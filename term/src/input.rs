@@ -98,3 +98,46 @@ impl LastMouseClick {
         }
     }
 }
+
+/// The shape of the mouse pointer that was requested by the
+/// application via `OSC 22`.  Only the shapes that can be shown
+/// by the GUI are represented here.
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PointerShape {
+    Default,
+    Text,
+    Pointer,
+    EwResize,
+    NsResize,
+}
+
+impl PointerShape {
+    /// Resolves a CSS cursor name, or one of the X11 cursor font
+    /// names that are commonly used in its place
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "default" | "left_ptr" | "arrow" | "top_left_arrow" => Some(Self::Default),
+            "text" | "xterm" | "ibeam" => Some(Self::Text),
+            "pointer" | "hand" | "hand1" | "hand2" | "pointing_hand" => Some(Self::Pointer),
+            "ew-resize" | "col-resize" | "sb_h_double_arrow" | "h_double_arrow" => {
+                Some(Self::EwResize)
+            }
+            "ns-resize" | "row-resize" | "sb_v_double_arrow" | "v_double_arrow" => {
+                Some(Self::NsResize)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the CSS name of the shape
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Text => "text",
+            Self::Pointer => "pointer",
+            Self::EwResize => "ew-resize",
+            Self::NsResize => "ns-resize",
+        }
+    }
+}
@@ -30,6 +30,7 @@ pub mod input;
 pub use crate::input::*;
 
 pub use termwiz::cell::{self, *};
+pub use termwiz::escape::osc::{NotificationUrgency, Progress};
 
 pub use termwiz::surface::line::*;
 
@@ -123,6 +124,52 @@ pub struct SemanticZone {
     pub semantic_type: SemanticType,
 }
 
+/// The exit status of a command, as reported by the shell via
+/// `OSC 133;D`.  `output_start_y` and `output_start_x` hold the
+/// position at which the output of the command began, as marked
+/// by `OSC 133;C`, which allows relating the status to the
+/// `SemanticZone`s of the command.
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, FromDynamic, ToDynamic)]
+pub struct CommandExitStatus {
+    pub output_start_y: StableRowIndex,
+    pub output_start_x: usize,
+    pub status: i32,
+}
+
+/// An entry in the command history of a terminal, which is assembled
+/// from the `OSC 133` markers emitted by the shell.
+/// The entry is started by `OSC 133;C` and is completed by `OSC 133;D`;
+/// until then, `end_time` and `exit_status` are `None` and the output
+/// range extends to the current cursor position.
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, Eq, PartialEq, FromDynamic, ToDynamic)]
+pub struct CommandHistoryEntry {
+    /// The text of the command, taken from the `Input` zone that
+    /// preceded the output
+    pub command: String,
+    /// The working directory reported via `OSC 7` when the command
+    /// was started
+    pub cwd: Option<String>,
+    /// When the command was started, in milliseconds since the unix epoch
+    pub start_time: u64,
+    /// When the command finished, in milliseconds since the unix epoch
+    pub end_time: Option<u64>,
+    pub exit_status: Option<i32>,
+    pub output_start_y: StableRowIndex,
+    pub output_start_x: usize,
+    pub output_end_y: StableRowIndex,
+    pub output_end_x: usize,
+}
+
+impl CommandHistoryEntry {
+    /// Returns how long the command ran for, if it has finished
+    pub fn duration(&self) -> Option<std::time::Duration> {
+        self.end_time
+            .map(|end| std::time::Duration::from_millis(end.saturating_sub(self.start_time)))
+    }
+}
+
 pub mod color;
 
 #[cfg(test)]
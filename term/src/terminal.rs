@@ -44,6 +44,24 @@ pub enum Alert {
         /// Whether clicking on the notification should focus the
         /// window/tab/pane that generated it
         focus: bool,
+        /// The identifier that the application assigned to the
+        /// notification via OSC 99
+        id: Option<String>,
+        urgency: Option<NotificationUrgency>,
+        /// Whether to report to the application, via OSC 99,
+        /// when the notification is activated
+        report_activation: bool,
+        /// Whether to report to the application, via OSC 99,
+        /// when the notification is closed
+        report_close: bool,
+        /// How long until the notification expires, or `None`
+        /// if it should persist until it is dismissed
+        timeout: Option<std::time::Duration>,
+    },
+    /// The application asked to close the notification with
+    /// the specified identifier, via OSC 99
+    CloseToastNotification {
+        id: String,
     },
     CurrentWorkingDirectoryChanged,
     IconTitleChanged(Option<String>),
@@ -59,6 +77,18 @@ pub enum Alert {
     /// When something bumps the seqno in the terminal model and
     /// the terminal is not focused
     OutputSinceFocusLost,
+    /// A command, as delimited by OSC 133, finished while the terminal
+    /// was not focused, after running for longer than
+    /// `TerminalConfiguration::command_finished_notification_threshold`
+    CommandFinished {
+        command: String,
+        exit_status: i32,
+        duration: std::time::Duration,
+    },
+    /// The progress reported via OSC 9;4 has changed
+    Progress(Progress),
+    /// The shape of the mouse pointer requested via OSC 22 has changed
+    PointerShapeChanged(Option<PointerShape>),
 }
 
 pub trait AlertHandler: Send + Sync {
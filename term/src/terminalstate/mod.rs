@@ -6,7 +6,7 @@ use crate::color::{ColorPalette, RgbColor};
 use crate::config::{BidiMode, NewlineCanon};
 use log::debug;
 use num_traits::ToPrimitive;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::io::{BufWriter, Write};
 use std::sync::mpsc::{channel, Sender};
 use std::sync::Arc;
@@ -17,6 +17,7 @@ use termwiz::escape::csi::{
     EraseInLine, Mode, Sgr, TabulationClear, TerminalMode, TerminalModeCode, Window, XtSmGraphics,
     XtSmGraphicsAction, XtSmGraphicsItem, XtSmGraphicsStatus, XtermKeyModifierResource,
 };
+use termwiz::escape::osc::PointerShapeRequest;
 use termwiz::escape::{OneBased, OperatingSystemCommand, CSI};
 use termwiz::image::ImageData;
 use termwiz::input::KeyboardEncoding;
@@ -29,10 +30,12 @@ mod iterm;
 mod keyboard;
 mod kitty;
 mod mouse;
+mod notification;
 pub(crate) mod performer;
 mod sixel;
 use crate::terminalstate::image::*;
 use crate::terminalstate::kitty::*;
+use crate::terminalstate::notification::PendingNotification;
 
 lazy_static::lazy_static! {
     static ref DB: Database = {
@@ -264,6 +267,24 @@ pub struct TerminalState {
 
     clear_semantic_attribute_on_newline: bool,
 
+    /// Where the input of the current command began, as marked
+    /// by OSC 133;B or OSC 133;I
+    semantic_input_start: Option<(StableRowIndex, usize)>,
+    /// The command that is currently running, as marked by OSC 133;C
+    current_command: Option<CommandHistoryEntry>,
+    /// Commands that were completed by OSC 133;D, oldest first
+    command_history: VecDeque<CommandHistoryEntry>,
+
+    /// Progress reported via OSC 9;4
+    progress: Progress,
+
+    /// The shape of the mouse pointer requested via OSC 22
+    pointer_shape: Option<PointerShape>,
+
+    /// OSC 99 notifications that are being assembled from
+    /// multiple chunks, keyed by their identifier
+    pending_notifications: HashMap<String, PendingNotification>,
+
     /// If true, writing a character inserts a new cell
     insert: bool,
 
@@ -402,6 +423,14 @@ struct UnicodeVersionStackEntry {
     label: Option<String>,
 }
 
+/// Returns the current time in milliseconds since the unix epoch
+fn unix_time_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
 fn default_color_map() -> HashMap<u16, RgbColor> {
     let mut color_map = HashMap::new();
     // Match colors to the VT340 color table:
@@ -524,6 +553,12 @@ impl TerminalState {
             left_and_right_margin_mode: false,
             wrap_next: false,
             clear_semantic_attribute_on_newline: false,
+            semantic_input_start: None,
+            current_command: None,
+            command_history: VecDeque::new(),
+            progress: Progress::default(),
+            pointer_shape: None,
+            pending_notifications: HashMap::new(),
             // We default auto wrap to true even though the default for
             // a dec terminal is false, because it is more useful this way.
             dec_auto_wrap: true,
@@ -2744,6 +2779,237 @@ impl TerminalState {
         Ok(zones)
     }
 
+    /// Returns the exit statuses that the shell has reported for
+    /// commands whose output is still present in the scrollback,
+    /// oldest first.
+    pub fn get_command_exit_statuses(&self) -> Vec<CommandExitStatus> {
+        let first_stable_row = self.screen().phys_to_stable_row_index(0);
+        self.command_history
+            .iter()
+            .filter(|entry| entry.output_start_y >= first_stable_row)
+            .filter_map(|entry| {
+                Some(CommandExitStatus {
+                    output_start_y: entry.output_start_y,
+                    output_start_x: entry.output_start_x,
+                    status: entry.exit_status?,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the commands whose output is still present in the
+    /// scrollback, oldest first.  If a command is still running,
+    /// it is the last entry in the list.
+    pub fn get_command_history(&self) -> Vec<CommandHistoryEntry> {
+        let first_stable_row = self.screen().phys_to_stable_row_index(0);
+        let mut history: Vec<CommandHistoryEntry> = self
+            .command_history
+            .iter()
+            .filter(|entry| entry.output_start_y >= first_stable_row)
+            .cloned()
+            .collect();
+        if let Some(current) = &self.current_command {
+            let mut current = current.clone();
+            current.output_end_y = self.screen().visible_row_to_stable_row(self.cursor.y);
+            current.output_end_x = self.cursor.x;
+            history.push(current);
+        }
+        history
+    }
+
+    /// Returns the progress that was most recently reported
+    /// by the application via OSC 9;4
+    pub fn get_progress(&self) -> Progress {
+        self.progress
+    }
+
+    pub(crate) fn set_progress(&mut self, progress: Progress) {
+        let previous = match self.progress {
+            Progress::Percentage(p) | Progress::Error(Some(p)) | Progress::Paused(Some(p)) => {
+                Some(p)
+            }
+            Progress::None
+            | Progress::Indeterminate
+            | Progress::Error(None)
+            | Progress::Paused(None) => None,
+        };
+        // Retain the percentage if the update didn't specify one
+        let progress = match progress {
+            Progress::Error(None) => Progress::Error(previous),
+            Progress::Paused(None) => Progress::Paused(previous),
+            progress => progress,
+        };
+
+        if progress != self.progress {
+            self.progress = progress;
+            if let Some(handler) = self.alert_handler.as_mut() {
+                handler.alert(Alert::Progress(progress));
+            }
+        }
+    }
+
+    /// Returns the shape of the mouse pointer that was requested
+    /// by the application, or `None` if it didn't request one
+    pub fn get_pointer_shape(&self) -> Option<PointerShape> {
+        self.pointer_shape
+    }
+
+    pub(crate) fn set_pointer_shape(&mut self, shape: Option<PointerShape>) {
+        if shape != self.pointer_shape {
+            self.pointer_shape = shape;
+            if let Some(handler) = self.alert_handler.as_mut() {
+                handler.alert(Alert::PointerShapeChanged(shape));
+            }
+        }
+    }
+
+    pub(crate) fn pointer_shape_request(&mut self, request: PointerShapeRequest) {
+        match request {
+            PointerShapeRequest::Set(names) if names.is_empty() => {
+                self.set_pointer_shape(None);
+            }
+            PointerShapeRequest::Set(names) => {
+                // Use the first of the requested shapes that we support,
+                // otherwise leave the current shape alone
+                if let Some(shape) = names.iter().find_map(|name| PointerShape::from_name(name)) {
+                    self.set_pointer_shape(Some(shape));
+                }
+            }
+            PointerShapeRequest::Query(names) => {
+                let response = names
+                    .iter()
+                    .map(|name| match name.as_str() {
+                        "__current__" => self
+                            .pointer_shape
+                            .unwrap_or(PointerShape::Text)
+                            .name()
+                            .to_string(),
+                        "__default__" => PointerShape::Text.name().to_string(),
+                        name if PointerShape::from_name(name).is_some() => "1".to_string(),
+                        _ => "0".to_string(),
+                    })
+                    .collect();
+                write!(
+                    self.writer,
+                    "{}",
+                    OperatingSystemCommand::PointerShape(PointerShapeRequest::Response(response))
+                )
+                .ok();
+                self.writer.flush().ok();
+            }
+            PointerShapeRequest::Response(_) => {}
+        }
+    }
+
+    pub(crate) fn mark_start_of_command_input(&mut self) {
+        let y = self.screen().visible_row_to_stable_row(self.cursor.y);
+        self.semantic_input_start = Some((y, self.cursor.x));
+    }
+
+    pub(crate) fn mark_start_of_command_output(&mut self) {
+        let y = self.screen().visible_row_to_stable_row(self.cursor.y);
+        let x = self.cursor.x;
+        let command = match self.semantic_input_start.take() {
+            Some(start) => self.get_command_input_text(start, (y, x)),
+            None => String::new(),
+        };
+        self.current_command = Some(CommandHistoryEntry {
+            command,
+            cwd: self.current_dir.as_ref().map(|url| url.to_string()),
+            start_time: unix_time_millis(),
+            end_time: None,
+            exit_status: None,
+            output_start_y: y,
+            output_start_x: x,
+            output_end_y: y,
+            output_end_x: x,
+        });
+    }
+
+    /// Extracts the text of the cells in the `Input` zone between
+    /// `start` and `end`, which is the command line that the user
+    /// entered at the prompt
+    fn get_command_input_text(
+        &mut self,
+        start: (StableRowIndex, usize),
+        end: (StableRowIndex, usize),
+    ) -> String {
+        let screen = self.screen_mut();
+        let mut text = String::new();
+        for stable_row in start.0..=end.0 {
+            let phys = match screen.stable_row_to_phys(stable_row) {
+                Some(phys) => phys,
+                None => continue,
+            };
+            let line = screen.line_mut(phys);
+            let first_col = if stable_row == start.0 { start.1 } else { 0 };
+            let last_col = if stable_row == end.0 {
+                end.1
+            } else {
+                usize::MAX
+            };
+            let ranges: Vec<Range<usize>> = line
+                .semantic_zone_ranges()
+                .iter()
+                .filter(|zone| zone.semantic_type == SemanticType::Input)
+                // The end of a zone range is the index of its last cell
+                .map(|zone| {
+                    (zone.range.start as usize).max(first_col)
+                        ..(zone.range.end as usize + 1).min(last_col)
+                })
+                .filter(|range| range.start < range.end)
+                .collect();
+            for range in ranges {
+                text.push_str(&line.columns_as_str(range));
+            }
+            if stable_row != end.0 && !line.last_cell_was_wrapped() {
+                text.push('\n');
+            }
+        }
+        text.trim_end().to_string()
+    }
+
+    pub(crate) fn record_command_exit_status(&mut self, status: i32) {
+        // Only record a status for a command whose output was marked;
+        // otherwise we have no way to relate it to the zones.
+        let mut entry = match self.current_command.take() {
+            Some(entry) => entry,
+            None => return,
+        };
+        entry.end_time = Some(unix_time_millis());
+        entry.exit_status = Some(status);
+        entry.output_end_y = self.screen().visible_row_to_stable_row(self.cursor.y);
+        entry.output_end_x = self.cursor.x;
+
+        if !self.focused {
+            if let (Some(threshold), Some(duration)) = (
+                self.config.command_finished_notification_threshold(),
+                entry.duration(),
+            ) {
+                if duration >= threshold {
+                    if let Some(handler) = self.alert_handler.as_mut() {
+                        handler.alert(Alert::CommandFinished {
+                            command: entry.command.clone(),
+                            exit_status: status,
+                            duration,
+                        });
+                    }
+                }
+            }
+        }
+
+        // Forget commands whose output has been trimmed from the scrollback
+        let first_stable_row = self.screen().phys_to_stable_row_index(0);
+        while let Some(front) = self.command_history.front() {
+            if front.output_start_y >= first_stable_row {
+                break;
+            }
+            self.command_history.pop_front();
+        }
+
+        self.command_history.push_back(entry);
+    }
+
     #[inline]
     pub fn get_reverse_video(&self) -> bool {
         self.reverse_video_mode
@@ -0,0 +1,130 @@
+use crate::terminal::Alert;
+use crate::TerminalState;
+use std::io::Write;
+use std::time::Duration;
+use termwiz::escape::osc::{DesktopNotification, NotificationOccasion, NotificationPayloadType};
+use termwiz::escape::OperatingSystemCommand;
+
+/// Limits the resources that an application can consume by
+/// sending notifications that are never completed
+const MAX_PENDING_NOTIFICATIONS: usize = 16;
+const MAX_NOTIFICATION_SIZE: usize = 64 * 1024;
+
+/// A notification that is being assembled from OSC 99 chunks
+#[derive(Debug)]
+pub(crate) struct PendingNotification {
+    /// The metadata of the first chunk, updated with the
+    /// report and urgency settings of the later chunks
+    metadata: DesktopNotification,
+    title: Vec<u8>,
+    body: Vec<u8>,
+}
+
+impl TerminalState {
+    pub(crate) fn desktop_notification(&mut self, notif: DesktopNotification) {
+        match &notif.payload_type {
+            NotificationPayloadType::Query => {
+                let response = DesktopNotification::query_response(notif.identifier.as_deref());
+                write!(
+                    self.writer,
+                    "{}",
+                    OperatingSystemCommand::DesktopNotification(response)
+                )
+                .ok();
+                self.writer.flush().ok();
+                return;
+            }
+            NotificationPayloadType::Close => {
+                if let Some(id) = notif.identifier {
+                    self.pending_notifications.remove(&id);
+                    if let Some(handler) = self.alert_handler.as_mut() {
+                        handler.alert(Alert::CloseToastNotification { id });
+                    }
+                }
+                return;
+            }
+            NotificationPayloadType::Title
+            | NotificationPayloadType::Body
+            | NotificationPayloadType::Other(_) => {}
+        }
+
+        let key = notif.identifier.clone().unwrap_or_default();
+        if !self.pending_notifications.contains_key(&key)
+            && self.pending_notifications.len() >= MAX_PENDING_NOTIFICATIONS
+        {
+            log::warn!("Ignoring OSC 99 notification: too many incomplete notifications");
+            return;
+        }
+
+        let pending = self
+            .pending_notifications
+            .entry(key.clone())
+            .or_insert_with(|| PendingNotification {
+                metadata: DesktopNotification {
+                    payload: vec![],
+                    ..notif.clone()
+                },
+                title: vec![],
+                body: vec![],
+            });
+        pending.metadata.report_close |= notif.report_close;
+        if notif.urgency.is_some() {
+            pending.metadata.urgency = notif.urgency;
+        }
+
+        if pending.title.len() + pending.body.len() + notif.payload.len() > MAX_NOTIFICATION_SIZE {
+            log::warn!("Truncating OSC 99 notification that is too large");
+        } else {
+            match &notif.payload_type {
+                NotificationPayloadType::Title => pending.title.extend_from_slice(&notif.payload),
+                NotificationPayloadType::Body => pending.body.extend_from_slice(&notif.payload),
+                NotificationPayloadType::Other(kind) => {
+                    log::debug!("Ignoring unsupported OSC 99 payload type {}", kind);
+                }
+                NotificationPayloadType::Query | NotificationPayloadType::Close => {}
+            }
+        }
+
+        if !notif.done {
+            return;
+        }
+
+        let pending = match self.pending_notifications.remove(&key) {
+            Some(pending) => pending,
+            None => return,
+        };
+        let metadata = pending.metadata;
+
+        // We can't tell whether the window is visible, so
+        // `invisible` is treated the same as `unfocused`
+        if metadata.occasion != NotificationOccasion::Always && self.focused {
+            return;
+        }
+
+        let title = String::from_utf8_lossy(&pending.title).into_owned();
+        let body = String::from_utf8_lossy(&pending.body).into_owned();
+        let (title, body) = if body.is_empty() {
+            (None, title)
+        } else {
+            (Some(title), body)
+        };
+
+        if let Some(handler) = self.alert_handler.as_mut() {
+            handler.alert(Alert::ToastNotification {
+                title,
+                body,
+                focus: metadata.focus,
+                id: metadata.identifier,
+                urgency: metadata.urgency,
+                report_activation: metadata.report,
+                report_close: metadata.report_close,
+                // 0 means that the notification never expires,
+                // which is the default for a toast
+                timeout: match metadata.expire_ms {
+                    None | Some(0) => None,
+                    Some(ms) => Some(Duration::from_millis(ms.into())),
+                },
+            });
+        }
+    }
+}
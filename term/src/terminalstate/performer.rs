@@ -16,7 +16,7 @@ use termwiz::escape::csi::{
 };
 use termwiz::escape::osc::{
     ChangeColorPair, ColorOrQuery, FinalTermSemanticPrompt, ITermProprietary,
-    ITermUnicodeVersionOp, Selection,
+    ITermUnicodeVersionOp, Progress, Selection,
 };
 use termwiz::escape::{
     Action, ControlCode, DeviceControlMode, Esc, EscCode, OperatingSystemCommand, CSI,
@@ -698,6 +698,9 @@ impl<'a> Performer<'a> {
                 self.unicode_version_stack.clear();
                 self.suppress_initial_title_change = false;
                 self.accumulating_title.take();
+                self.set_progress(Progress::None);
+                self.set_pointer_shape(None);
+                self.pending_notifications.clear();
 
                 self.screen.full_reset();
                 self.screen.activate_alt_screen(seqno);
@@ -866,22 +869,39 @@ impl<'a> Performer<'a> {
                 FinalTermSemanticPrompt::MarkEndOfPromptAndStartOfInputUntilNextMarker { .. },
             ) => {
                 self.pen.set_semantic_type(SemanticType::Input);
+                self.mark_start_of_command_input();
             }
             OperatingSystemCommand::FinalTermSemanticPrompt(
                 FinalTermSemanticPrompt::MarkEndOfPromptAndStartOfInputUntilEndOfLine { .. },
             ) => {
                 self.pen.set_semantic_type(SemanticType::Input);
+                self.mark_start_of_command_input();
                 self.clear_semantic_attribute_on_newline = true;
             }
             OperatingSystemCommand::FinalTermSemanticPrompt(
                 FinalTermSemanticPrompt::MarkEndOfInputAndStartOfOutput { .. },
             ) => {
                 self.pen.set_semantic_type(SemanticType::Output);
+                self.mark_start_of_command_output();
             }
 
             OperatingSystemCommand::FinalTermSemanticPrompt(
-                FinalTermSemanticPrompt::CommandStatus { .. },
-            ) => {}
+                FinalTermSemanticPrompt::CommandStatus { status, .. },
+            ) => {
+                self.record_command_exit_status(status);
+            }
+
+            OperatingSystemCommand::DesktopNotification(notif) => {
+                self.desktop_notification(notif);
+            }
+
+            OperatingSystemCommand::PointerShape(request) => {
+                self.pointer_shape_request(request);
+            }
+
+            OperatingSystemCommand::Progress(progress) => {
+                self.set_progress(progress);
+            }
 
             OperatingSystemCommand::SystemNotification(message) => {
                 if let Some(handler) = self.alert_handler.as_mut() {
@@ -889,6 +909,11 @@ impl<'a> Performer<'a> {
                         title: None,
                         body: message,
                         focus: true,
+                        id: None,
+                        urgency: None,
+                        report_activation: false,
+                        report_close: false,
+                        timeout: None,
                     });
                 } else {
                     log::info!("Application sends SystemNotification: {}", message);
@@ -911,6 +936,11 @@ impl<'a> Performer<'a> {
                             title,
                             body,
                             focus: true,
+                            id: None,
+                            urgency: None,
+                            report_activation: false,
+                            report_close: false,
+                            timeout: None,
                         });
                     }
                 }
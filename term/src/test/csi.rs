@@ -201,6 +201,7 @@ fn test_789() {
         bits: LineBits(
             0x0,
         ),
+        highlights: [],
         appdata: Mutex {
             data: None,
             poisoned: false,
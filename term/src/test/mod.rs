@@ -54,6 +54,12 @@ impl TerminalConfiguration for TestTermConfig {
     fn color_palette(&self) -> ColorPalette {
         ColorPalette::default()
     }
+
+    // Alert for every command, so that the tests don't need to
+    // run for a long time to trigger it
+    fn command_finished_notification_threshold(&self) -> Option<std::time::Duration> {
+        Some(std::time::Duration::ZERO)
+    }
 }
 
 impl TestTerm {
@@ -479,6 +485,447 @@ fn test_semantic() {
     );
 }
 
+#[test]
+fn test_command_exit_status() {
+    use termwiz::escape::osc::FinalTermSemanticPrompt;
+    let mut term = TestTerm::new(5, 10, 2);
+
+    let semantic = |prompt| {
+        format!(
+            "{}",
+            OperatingSystemCommand::FinalTermSemanticPrompt(prompt)
+        )
+    };
+    let mut run_command = |term: &mut TestTerm, command: &str, output: &str, status: i32| {
+        term.print(semantic(FinalTermSemanticPrompt::FreshLineAndStartPrompt {
+            aid: None,
+            cl: None,
+        }));
+        term.print("$ ");
+        term.print(semantic(
+            FinalTermSemanticPrompt::MarkEndOfPromptAndStartOfInputUntilNextMarker,
+        ));
+        term.print(format!("{}\r\n", command));
+        term.print(semantic(
+            FinalTermSemanticPrompt::MarkEndOfInputAndStartOfOutput { aid: None },
+        ));
+        term.print(output);
+        term.print(semantic(FinalTermSemanticPrompt::CommandStatus {
+            status,
+            aid: None,
+        }));
+    };
+
+    run_command(&mut term, "false", "oops\r\n", 1);
+    // A command that produces no output
+    run_command(&mut term, "true", "", 0);
+    // A status without a preceding output marker cannot be
+    // related to a command, so it is ignored
+    term.print(semantic(FinalTermSemanticPrompt::CommandStatus {
+        status: 2,
+        aid: None,
+    }));
+
+    assert_visible_contents(
+        &term,
+        file!(),
+        line!(),
+        &["$ false", "oops", "$ true", "", ""],
+    );
+    assert_eq!(
+        term.get_command_exit_statuses(),
+        vec![
+            CommandExitStatus {
+                output_start_y: 1,
+                output_start_x: 0,
+                status: 1,
+            },
+            CommandExitStatus {
+                output_start_y: 3,
+                output_start_x: 0,
+                status: 0,
+            },
+        ]
+    );
+
+    // Push the first command out of the scrollback
+    run_command(&mut term, "seq 4", "1\r\n2\r\n3\r\n4\r\n", 0);
+    assert_visible_contents(&term, file!(), line!(), &["1", "2", "3", "4", ""]);
+    assert_eq!(
+        term.get_command_exit_statuses(),
+        vec![
+            CommandExitStatus {
+                output_start_y: 3,
+                output_start_x: 0,
+                status: 0,
+            },
+            CommandExitStatus {
+                output_start_y: 4,
+                output_start_x: 0,
+                status: 0,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_command_history() {
+    use termwiz::escape::osc::FinalTermSemanticPrompt;
+    let mut term = TestTerm::new(5, 10, 2);
+
+    let semantic = |prompt| {
+        format!(
+            "{}",
+            OperatingSystemCommand::FinalTermSemanticPrompt(prompt)
+        )
+    };
+    let start_command = |term: &mut TestTerm, command: &str| {
+        term.print(semantic(FinalTermSemanticPrompt::FreshLineAndStartPrompt {
+            aid: None,
+            cl: None,
+        }));
+        term.print("$ ");
+        term.print(semantic(
+            FinalTermSemanticPrompt::MarkEndOfPromptAndStartOfInputUntilNextMarker,
+        ));
+        term.print(format!("{}\r\n", command));
+        term.print(semantic(
+            FinalTermSemanticPrompt::MarkEndOfInputAndStartOfOutput { aid: None },
+        ));
+    };
+    let finish_command = |term: &mut TestTerm, status: i32| {
+        term.print(semantic(FinalTermSemanticPrompt::CommandStatus {
+            status,
+            aid: None,
+        }));
+    };
+
+    term.print(format!(
+        "{}",
+        OperatingSystemCommand::CurrentWorkingDirectory("file://host/tmp".to_string())
+    ));
+    // A long command line wraps onto the next line
+    start_command(&mut term, "ls foo bar");
+    term.print("oops\r\n");
+    finish_command(&mut term, 2);
+    start_command(&mut term, "sleep 5");
+
+    assert_visible_contents(
+        &term,
+        file!(),
+        line!(),
+        &["$ ls foo b", "ar", "oops", "$ sleep 5", ""],
+    );
+
+    let history = term.get_command_history();
+    assert_eq!(history.len(), 2);
+    let (done, running) = (&history[0], &history[1]);
+
+    assert_eq!(done.command, "ls foo bar");
+    assert_eq!(done.cwd.as_deref(), Some("file://host/tmp"));
+    assert_eq!(done.exit_status, Some(2));
+    assert!(done.end_time.unwrap() >= done.start_time);
+    assert_eq!(
+        (
+            done.output_start_y,
+            done.output_start_x,
+            done.output_end_y,
+            done.output_end_x
+        ),
+        (2, 0, 3, 0)
+    );
+
+    assert_eq!(running.command, "sleep 5");
+    assert_eq!(running.exit_status, None);
+    assert_eq!(running.end_time, None);
+    assert_eq!(running.duration(), None);
+    assert_eq!((running.output_start_y, running.output_end_y), (4, 4));
+
+    term.print("zzz");
+    finish_command(&mut term, 0);
+    let history = term.get_command_history();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[1].exit_status, Some(0));
+    assert_eq!((history[1].output_end_y, history[1].output_end_x), (4, 3));
+    assert_eq!(
+        term.get_command_exit_statuses(),
+        vec![
+            CommandExitStatus {
+                output_start_y: 2,
+                output_start_x: 0,
+                status: 2,
+            },
+            CommandExitStatus {
+                output_start_y: 4,
+                output_start_x: 0,
+                status: 0,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_command_finished_alert() {
+    use termwiz::escape::osc::FinalTermSemanticPrompt;
+
+    struct AlertCollector(Arc<Mutex<Vec<Alert>>>);
+    impl AlertHandler for AlertCollector {
+        fn alert(&mut self, alert: Alert) {
+            self.0.lock().unwrap().push(alert);
+        }
+    }
+
+    let mut term = TestTerm::new(5, 10, 0);
+    let alerts = Arc::new(Mutex::new(vec![]));
+    term.set_notification_handler(Box::new(AlertCollector(Arc::clone(&alerts))));
+
+    let semantic = |prompt| {
+        format!(
+            "{}",
+            OperatingSystemCommand::FinalTermSemanticPrompt(prompt)
+        )
+    };
+    let run_command = |term: &mut TestTerm, command: &str, status: i32| {
+        term.print(semantic(
+            FinalTermSemanticPrompt::MarkEndOfPromptAndStartOfInputUntilNextMarker,
+        ));
+        term.print(format!("{}\r\n", command));
+        term.print(semantic(
+            FinalTermSemanticPrompt::MarkEndOfInputAndStartOfOutput { aid: None },
+        ));
+        term.print(semantic(FinalTermSemanticPrompt::CommandStatus {
+            status,
+            aid: None,
+        }));
+    };
+
+    let finished = || -> Vec<Alert> {
+        alerts
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|alert| matches!(alert, Alert::CommandFinished { .. }))
+            .cloned()
+            .collect()
+    };
+
+    // No alert is raised while the terminal is focused
+    run_command(&mut term, "true", 0);
+    assert!(finished().is_empty());
+
+    term.focus_changed(false);
+    run_command(&mut term, "make", 2);
+    let alerts = finished();
+    assert_eq!(alerts.len(), 1);
+    match &alerts[0] {
+        Alert::CommandFinished {
+            command,
+            exit_status,
+            ..
+        } => {
+            assert_eq!(command, "make");
+            assert_eq!(*exit_status, 2);
+        }
+        alert => panic!("unexpected alert {:?}", alert),
+    }
+}
+
+#[test]
+fn test_progress() {
+    use termwiz::escape::osc::Progress;
+    let mut term = TestTerm::new(5, 10, 0);
+    let progress = |term: &mut TestTerm, progress: Progress| {
+        term.print(format!("{}", OperatingSystemCommand::Progress(progress)));
+        term.get_progress()
+    };
+
+    assert_eq!(term.get_progress(), Progress::None);
+    assert_eq!(
+        progress(&mut term, Progress::Percentage(30)),
+        Progress::Percentage(30)
+    );
+    // The percentage is retained when it isn't specified
+    assert_eq!(
+        progress(&mut term, Progress::Error(None)),
+        Progress::Error(Some(30))
+    );
+    assert_eq!(
+        progress(&mut term, Progress::Paused(Some(40))),
+        Progress::Paused(Some(40))
+    );
+    assert_eq!(
+        progress(&mut term, Progress::Indeterminate),
+        Progress::Indeterminate
+    );
+    assert_eq!(
+        progress(&mut term, Progress::Paused(None)),
+        Progress::Paused(None)
+    );
+    assert_eq!(progress(&mut term, Progress::None), Progress::None);
+
+    progress(&mut term, Progress::Percentage(50));
+    term.print("\x1bc");
+    assert_eq!(term.get_progress(), Progress::None);
+}
+
+#[test]
+fn test_pointer_shape() {
+    use termwiz::escape::osc::PointerShapeRequest;
+    let mut term = TestTerm::new(5, 10, 0);
+    let set = |term: &mut TestTerm, names: &[&str]| {
+        term.print(format!(
+            "{}",
+            OperatingSystemCommand::PointerShape(PointerShapeRequest::Set(
+                names.iter().map(|name| name.to_string()).collect()
+            ))
+        ));
+        term.get_pointer_shape()
+    };
+
+    assert_eq!(term.get_pointer_shape(), None);
+    assert_eq!(set(&mut term, &["pointer"]), Some(PointerShape::Pointer));
+    // The first supported shape is used
+    assert_eq!(
+        set(&mut term, &["grab", "sb_h_double_arrow"]),
+        Some(PointerShape::EwResize)
+    );
+    // Unsupported shapes leave the shape unchanged
+    assert_eq!(set(&mut term, &["wait"]), Some(PointerShape::EwResize));
+    assert_eq!(set(&mut term, &[]), None);
+
+    set(&mut term, &["text"]);
+    term.print("\x1bc");
+    assert_eq!(term.get_pointer_shape(), None);
+}
+
+#[test]
+fn test_desktop_notification() {
+    use termwiz::escape::osc::{
+        DesktopNotification, NotificationOccasion, NotificationPayloadType,
+    };
+
+    struct AlertCollector(Arc<Mutex<Vec<Alert>>>);
+    impl AlertHandler for AlertCollector {
+        fn alert(&mut self, alert: Alert) {
+            self.0.lock().unwrap().push(alert);
+        }
+    }
+
+    let mut term = TestTerm::new(5, 10, 0);
+    let alerts = Arc::new(Mutex::new(vec![]));
+    term.set_notification_handler(Box::new(AlertCollector(Arc::clone(&alerts))));
+
+    let notify = |term: &mut TestTerm, notif: DesktopNotification| {
+        term.print(format!(
+            "{}",
+            OperatingSystemCommand::DesktopNotification(notif)
+        ));
+    };
+    let take_alerts = || -> Vec<Alert> { alerts.lock().unwrap().drain(..).collect() };
+
+    // A notification whose title and body are sent in separate chunks
+    notify(
+        &mut term,
+        DesktopNotification {
+            identifier: Some("build".to_string()),
+            done: false,
+            report: true,
+            payload: b"Build ".to_vec(),
+            ..Default::default()
+        },
+    );
+    notify(
+        &mut term,
+        DesktopNotification {
+            identifier: Some("build".to_string()),
+            done: false,
+            payload: b"finished".to_vec(),
+            ..Default::default()
+        },
+    );
+    assert!(take_alerts().is_empty());
+    notify(
+        &mut term,
+        DesktopNotification {
+            identifier: Some("build".to_string()),
+            payload_type: NotificationPayloadType::Body,
+            encoded: true,
+            payload: b"all good".to_vec(),
+            ..Default::default()
+        },
+    );
+    match take_alerts().as_slice() {
+        [Alert::ToastNotification {
+            title,
+            body,
+            id,
+            report_activation,
+            report_close,
+            ..
+        }] => {
+            assert_eq!(title.as_deref(), Some("Build finished"));
+            assert_eq!(body, "all good");
+            assert_eq!(id.as_deref(), Some("build"));
+            assert!(*report_activation);
+            assert!(!*report_close);
+        }
+        alerts => panic!("unexpected alerts {:?}", alerts),
+    }
+
+    // Notifications that are only for unfocused windows are dropped
+    // while the terminal has focus
+    notify(
+        &mut term,
+        DesktopNotification {
+            occasion: NotificationOccasion::Unfocused,
+            payload: b"hidden".to_vec(),
+            ..Default::default()
+        },
+    );
+    assert!(take_alerts().is_empty());
+
+    notify(
+        &mut term,
+        DesktopNotification {
+            identifier: Some("build".to_string()),
+            payload_type: NotificationPayloadType::Close,
+            ..Default::default()
+        },
+    );
+    match take_alerts().as_slice() {
+        [Alert::CloseToastNotification { id }] => assert_eq!(id, "build"),
+        alerts => panic!("unexpected alerts {:?}", alerts),
+    }
+
+    // A full reset discards partially received notifications
+    notify(
+        &mut term,
+        DesktopNotification {
+            identifier: Some("build".to_string()),
+            done: false,
+            payload: b"Stale ".to_vec(),
+            ..Default::default()
+        },
+    );
+    term.print("\x1bc");
+    take_alerts();
+    notify(
+        &mut term,
+        DesktopNotification {
+            identifier: Some("build".to_string()),
+            payload: b"Fresh".to_vec(),
+            ..Default::default()
+        },
+    );
+    match take_alerts().as_slice() {
+        [Alert::ToastNotification { title, body, .. }] => {
+            assert_eq!(title.as_deref(), None);
+            assert_eq!(body, "Fresh");
+        }
+        alerts => panic!("unexpected alerts {:?}", alerts),
+    }
+}
+
 #[test]
 fn issue_1161() {
     let mut term = TestTerm::new(1, 5, 0);
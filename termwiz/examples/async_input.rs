@@ -0,0 +1,44 @@
+//! This example shows how to consume terminal input from an async
+//! executor using `AsyncTerminal`, and how to use
+//! `BufferedTerminal::flush_async` to render without blocking.
+//! Press any key to see the decoded event; press `q` to quit.
+//!
+//! Run it with `cargo run --example async_input --features async`
+use termwiz::caps::Capabilities;
+use termwiz::input::{InputEvent, KeyCode, KeyEvent};
+use termwiz::surface::Change;
+use termwiz::terminal::asynchronous::AsyncTerminal;
+use termwiz::terminal::buffered::BufferedTerminal;
+use termwiz::terminal::{Terminal, UnixTerminal};
+use termwiz::Error;
+
+fn main() -> Result<(), Error> {
+    smol::block_on(async {
+        let caps = Capabilities::new_from_env()?;
+        let mut terminal = AsyncTerminal::new(UnixTerminal::new(caps)?)?;
+        terminal.set_raw_mode()?;
+
+        let mut buf = BufferedTerminal::new(terminal)?;
+        buf.add_change("Press keys; q to quit\r\n");
+        buf.flush_async().await?;
+
+        loop {
+            match buf.terminal().next_input().await? {
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('q'),
+                    ..
+                }) => break,
+                InputEvent::Resized { .. } => {
+                    buf.check_for_resize()?;
+                }
+                event => {
+                    buf.add_change(format!("{:?}\r\n", event));
+                }
+            }
+            buf.add_change(Change::ClearToEndOfScreen(Default::default()));
+            buf.flush_async().await?;
+        }
+
+        Ok(())
+    })
+}
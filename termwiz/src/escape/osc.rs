@@ -10,6 +10,7 @@ use std::collections::HashMap;
 use std::fmt::{Display, Error as FmtError, Formatter, Result as FmtResult};
 use std::str;
 use std::str::FromStr;
+use wezterm_dynamic::{FromDynamic, ToDynamic};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ColorOrQuery {
@@ -38,6 +39,9 @@ pub enum OperatingSystemCommand {
     QuerySelection(Selection),
     SetSelection(Selection, String),
     SystemNotification(String),
+    Progress(Progress),
+    DesktopNotification(DesktopNotification),
+    PointerShape(PointerShapeRequest),
     ITermProprietary(ITermProprietary),
     FinalTermSemanticPrompt(FinalTermSemanticPrompt),
     ChangeColorNumber(Vec<ChangeColorPair>),
@@ -311,8 +315,16 @@ impl OperatingSystemCommand {
             )),
             SetHyperlink => Ok(OperatingSystemCommand::SetHyperlink(Hyperlink::parse(osc)?)),
             ManipulateSelectionData => Self::parse_selection(osc),
+            SystemNotification if osc.len() > 2 && osc[1] == b"4" => {
+                self::Progress::parse(osc).map(OperatingSystemCommand::Progress)
+            }
             SystemNotification => single_string!(SystemNotification),
             SetCurrentWorkingDirectory => single_string!(CurrentWorkingDirectory),
+            DesktopNotification => self::DesktopNotification::parse(osc)
+                .map(OperatingSystemCommand::DesktopNotification),
+            SetPointerShape => {
+                PointerShapeRequest::parse(osc).map(OperatingSystemCommand::PointerShape)
+            }
             ITermProprietary => {
                 self::ITermProprietary::parse(osc).map(OperatingSystemCommand::ITermProprietary)
             }
@@ -419,6 +431,8 @@ osc_entries!(
     SetHighlightBackgroundColor = "17",
     SetTektronixCursorColor = "18",
     SetHighlightForegroundColor = "19",
+    /// See <https://sw.kovidgoyal.net/kitty/pointer-shapes/>
+    SetPointerShape = "22",
     SetLogFileName = "46",
     SetFont = "50",
     EmacsShell = "51",
@@ -435,6 +449,8 @@ osc_entries!(
     ResetHighlightColor = "117",
     ResetTektronixCursorColor = "118",
     ResetHighlightForegroundColor = "119",
+    /// kitty
+    DesktopNotification = "99",
     RxvtProprietary = "777",
     FinalTermSemanticPrompt = "133",
     ITermProprietary = "1337",
@@ -509,6 +525,9 @@ impl Display for OperatingSystemCommand {
             QuerySelection(s) => write!(f, "52;{};?", s)?,
             SetSelection(s, val) => write!(f, "52;{};{}", s, base64_encode(val))?,
             SystemNotification(s) => write!(f, "9;{}", s)?,
+            Progress(p) => p.fmt(f)?,
+            DesktopNotification(n) => n.fmt(f)?,
+            PointerShape(p) => p.fmt(f)?,
             ITermProprietary(i) => i.fmt(f)?,
             FinalTermSemanticPrompt(i) => i.fmt(f)?,
             ResetColors(colors) => {
@@ -540,6 +559,378 @@ impl Display for OperatingSystemCommand {
     }
 }
 
+/// Progress reporting, as defined by ConEmu's `OSC 9;4;state;percent`.
+/// <https://conemu.github.io/en/AnsiEscapeCodes.html#ConEmu_specific_OSC>
+#[cfg_attr(feature = "use_serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub enum Progress {
+    /// Remove the progress indicator
+    #[default]
+    None,
+    /// Set the progress to the specified percentage, 0-100
+    Percentage(u8),
+    /// Show the progress in the error state.  If the percentage
+    /// is omitted, the previously reported percentage is retained.
+    Error(Option<u8>),
+    /// Show an indeterminate, busy, progress indicator
+    Indeterminate,
+    /// Show the progress in the paused state.  If the percentage
+    /// is omitted, the previously reported percentage is retained.
+    Paused(Option<u8>),
+}
+
+impl Progress {
+    fn parse(osc: &[&[u8]]) -> Result<Self> {
+        ensure!(osc.len() <= 4, "too many args");
+        let state = str::from_utf8(osc[2])?;
+        let percentage = match osc.get(3) {
+            Some(p) if !p.is_empty() => Some(str::from_utf8(p)?.parse::<u8>()?.min(100)),
+            _ => None,
+        };
+
+        match state {
+            "0" => Ok(Self::None),
+            "1" => Ok(Self::Percentage(percentage.unwrap_or(0))),
+            "2" => Ok(Self::Error(percentage)),
+            "3" => Ok(Self::Indeterminate),
+            "4" => Ok(Self::Paused(percentage)),
+            _ => bail!("invalid progress state {}", state),
+        }
+    }
+}
+
+impl Display for Progress {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::None => write!(f, "9;4;0"),
+            Self::Percentage(p) => write!(f, "9;4;1;{}", p),
+            Self::Error(None) => write!(f, "9;4;2"),
+            Self::Error(Some(p)) => write!(f, "9;4;2;{}", p),
+            Self::Indeterminate => write!(f, "9;4;3"),
+            Self::Paused(None) => write!(f, "9;4;4"),
+            Self::Paused(Some(p)) => write!(f, "9;4;4;{}", p),
+        }
+    }
+}
+
+/// Requests to change the shape of the mouse pointer, via `OSC 22`.
+/// Shapes are named using the CSS cursor names, although terminals
+/// typically also accept the X11 cursor font names.
+/// <https://sw.kovidgoyal.net/kitty/pointer-shapes/>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointerShapeRequest {
+    /// Use the first of the listed shapes that is supported.
+    /// An empty list restores the default shape.
+    Set(Vec<String>),
+    /// Ask whether each of the listed shapes is supported.
+    /// The special names `__current__` and `__default__` ask
+    /// for the name of the current and the default shape.
+    Query(Vec<String>),
+    /// The terminal's response to a `Query`, in the same order
+    /// as the queried names: `1` or `0` for supported or unsupported
+    /// shapes, or the shape name for the special names.
+    /// Note that this is parsed as a `Set`.
+    Response(Vec<String>),
+}
+
+impl PointerShapeRequest {
+    fn parse(osc: &[&[u8]]) -> Result<Self> {
+        let mut param = String::new();
+        for (idx, p) in osc.iter().enumerate().skip(1) {
+            if idx > 1 {
+                param.push(';');
+            }
+            param.push_str(str::from_utf8(p)?);
+        }
+
+        let split = |s: &str| -> Vec<String> {
+            s.split(',')
+                .filter(|name| !name.is_empty())
+                .map(|name| name.to_string())
+                .collect()
+        };
+
+        match param.strip_prefix('?') {
+            Some(names) => Ok(Self::Query(split(names))),
+            None => Ok(Self::Set(split(&param))),
+        }
+    }
+}
+
+impl Display for PointerShapeRequest {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Set(names) | Self::Response(names) => write!(f, "22;{}", names.join(",")),
+            Self::Query(names) => write!(f, "22;?{}", names.join(",")),
+        }
+    }
+}
+
+/// The type of the payload of a `DesktopNotification`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationPayloadType {
+    Title,
+    Body,
+    /// Close the notification with the specified identifier,
+    /// or report that it has been closed
+    Close,
+    /// Query the capabilities of the terminal
+    Query,
+    /// A payload type that we don't support, which must be ignored
+    Other(String),
+}
+
+impl NotificationPayloadType {
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Title => "title",
+            Self::Body => "body",
+            Self::Close => "close",
+            Self::Query => "?",
+            Self::Other(s) => s,
+        }
+    }
+}
+
+/// Controls when a `DesktopNotification` should be shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationOccasion {
+    Always,
+    /// Only when the window doesn't have the focus
+    Unfocused,
+    /// Only when the window is not visible
+    Invisible,
+}
+
+impl NotificationOccasion {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::Unfocused => "unfocused",
+            Self::Invisible => "invisible",
+        }
+    }
+}
+
+#[cfg_attr(feature = "use_serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationUrgency {
+    Low = 0,
+    Normal = 1,
+    Critical = 2,
+}
+
+/// The kitty desktop notification protocol, `OSC 99 ; metadata ; payload`.
+/// Each field corresponds to a key in the `:` separated metadata.
+/// <https://sw.kovidgoyal.net/kitty/desktop-notifications/>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesktopNotification {
+    /// `i`: identifies the notification, so that it can be assembled
+    /// from multiple chunks and so that it can be closed or reported on
+    pub identifier: Option<String>,
+    /// `d`: false if more chunks of the notification will follow
+    pub done: bool,
+    /// `p`: what the payload represents
+    pub payload_type: NotificationPayloadType,
+    /// `e`: whether the payload is base64 encoded on the wire
+    pub encoded: bool,
+    /// `a`: focus the window when the notification is activated
+    pub focus: bool,
+    /// `a`: report to the application when the notification is activated
+    pub report: bool,
+    /// `o`: when the notification should be shown
+    pub occasion: NotificationOccasion,
+    /// `u`
+    pub urgency: Option<NotificationUrgency>,
+    /// `c`: report to the application when the notification is closed
+    pub report_close: bool,
+    /// `w`: how long until the notification expires, in milliseconds.
+    /// `None` uses the system default, and `Some(0)` never expires.
+    pub expire_ms: Option<u32>,
+    /// The payload, after decoding it if it was base64 encoded
+    pub payload: Vec<u8>,
+}
+
+impl Default for DesktopNotification {
+    fn default() -> Self {
+        Self {
+            identifier: None,
+            done: true,
+            payload_type: NotificationPayloadType::Title,
+            encoded: false,
+            focus: true,
+            report: false,
+            occasion: NotificationOccasion::Always,
+            urgency: None,
+            report_close: false,
+            expire_ms: None,
+            payload: vec![],
+        }
+    }
+}
+
+impl DesktopNotification {
+    /// The response to send to the application when the notification
+    /// with the specified identifier has been activated
+    pub fn activation_report(identifier: &str) -> Self {
+        Self {
+            identifier: Some(identifier.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// The response to send to the application when the notification
+    /// with the specified identifier has been closed
+    pub fn close_report(identifier: &str) -> Self {
+        Self {
+            identifier: Some(identifier.to_string()),
+            payload_type: NotificationPayloadType::Close,
+            ..Self::default()
+        }
+    }
+
+    /// The response to a `NotificationPayloadType::Query`, which
+    /// describes the capabilities of the terminal
+    pub fn query_response(identifier: Option<&str>) -> Self {
+        Self {
+            identifier: identifier.map(|s| s.to_string()),
+            payload_type: NotificationPayloadType::Query,
+            payload:
+                b"a=focus,report:o=always,unfocused,invisible:u=0,1,2:p=title,body,close,?:c=1:w=1"
+                    .to_vec(),
+            ..Self::default()
+        }
+    }
+
+    fn parse(osc: &[&[u8]]) -> Result<Self> {
+        ensure!(osc.len() > 1, "not enough args");
+        let mut notif = Self::default();
+
+        for item in osc[1].split(|&c| c == b':') {
+            if item.is_empty() {
+                continue;
+            }
+            let equal = item
+                .iter()
+                .position(|&c| c == b'=')
+                .ok_or_else(|| "malformed DesktopNotification metadata".to_string())?;
+            let key = str::from_utf8(&item[..equal])?;
+            let value = str::from_utf8(&item[equal + 1..])?;
+            match key {
+                "i" => notif.identifier = Some(value.to_string()),
+                "d" => notif.done = value != "0",
+                "e" => notif.encoded = value == "1",
+                "p" => {
+                    notif.payload_type = match value {
+                        "title" => NotificationPayloadType::Title,
+                        "body" => NotificationPayloadType::Body,
+                        "close" => NotificationPayloadType::Close,
+                        "?" => NotificationPayloadType::Query,
+                        other => NotificationPayloadType::Other(other.to_string()),
+                    }
+                }
+                "a" => {
+                    for action in value.split(',') {
+                        let (enable, action) = match action.strip_prefix('-') {
+                            Some(action) => (false, action),
+                            None => (true, action),
+                        };
+                        match action {
+                            "focus" => notif.focus = enable,
+                            "report" => notif.report = enable,
+                            _ => {}
+                        }
+                    }
+                }
+                "o" => {
+                    notif.occasion = match value {
+                        "unfocused" => NotificationOccasion::Unfocused,
+                        "invisible" => NotificationOccasion::Invisible,
+                        _ => NotificationOccasion::Always,
+                    }
+                }
+                "u" => {
+                    notif.urgency = match value {
+                        "0" => Some(NotificationUrgency::Low),
+                        "1" => Some(NotificationUrgency::Normal),
+                        "2" => Some(NotificationUrgency::Critical),
+                        _ => None,
+                    }
+                }
+                "c" => notif.report_close = value == "1",
+                "w" => {
+                    // Ignore a malformed timeout rather than the
+                    // whole notification
+                    if let Ok(ms) = value.parse::<i64>() {
+                        notif.expire_ms = if ms < 0 {
+                            None
+                        } else {
+                            Some(ms.min(u32::MAX as i64) as u32)
+                        };
+                    }
+                }
+                // The protocol requires that unknown keys are ignored
+                _ => {}
+            }
+        }
+
+        // The payload may itself contain semicolons
+        let payload = osc[2..].join(&b';');
+        notif.payload = if notif.encoded {
+            base64_decode(payload)?
+        } else {
+            payload
+        };
+
+        Ok(notif)
+    }
+}
+
+impl Display for DesktopNotification {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let defaults = Self::default();
+        let mut metadata = vec![];
+        if let Some(id) = &self.identifier {
+            metadata.push(format!("i={}", id));
+        }
+        if !self.done {
+            metadata.push("d=0".to_string());
+        }
+        if self.payload_type != defaults.payload_type {
+            metadata.push(format!("p={}", self.payload_type.as_str()));
+        }
+        if self.encoded {
+            metadata.push("e=1".to_string());
+        }
+        if self.focus != defaults.focus || self.report != defaults.report {
+            metadata.push(format!(
+                "a={}focus,{}report",
+                if self.focus { "" } else { "-" },
+                if self.report { "" } else { "-" }
+            ));
+        }
+        if self.occasion != defaults.occasion {
+            metadata.push(format!("o={}", self.occasion.as_str()));
+        }
+        if let Some(urgency) = self.urgency {
+            metadata.push(format!("u={}", urgency as u8));
+        }
+        if self.report_close {
+            metadata.push("c=1".to_string());
+        }
+        if let Some(ms) = self.expire_ms {
+            metadata.push(format!("w={}", ms));
+        }
+
+        write!(f, "99;{};", metadata.join(":"))?;
+        if self.encoded {
+            f.write_str(&base64_encode(&self.payload))
+        } else {
+            f.write_str(&String::from_utf8_lossy(&self.payload))
+        }
+    }
+}
+
 /// https://gitlab.freedesktop.org/Per_Bothner/specifications/blob/master/proposals/semantic-prompts.md
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FinalTermClick {
@@ -1311,6 +1702,153 @@ mod test {
         result
     }
 
+    #[test]
+    fn pointer_shape() {
+        assert_eq!(
+            parse(&["22", "pointer"], "\x1b]22;pointer\x1b\\"),
+            OperatingSystemCommand::PointerShape(PointerShapeRequest::Set(vec![
+                "pointer".to_string()
+            ]))
+        );
+        assert_eq!(
+            parse(&["22", "grab,hand2"], "\x1b]22;grab,hand2\x1b\\"),
+            OperatingSystemCommand::PointerShape(PointerShapeRequest::Set(vec![
+                "grab".to_string(),
+                "hand2".to_string()
+            ]))
+        );
+        assert_eq!(
+            parse(&["22", ""], "\x1b]22;\x1b\\"),
+            OperatingSystemCommand::PointerShape(PointerShapeRequest::Set(vec![]))
+        );
+        assert_eq!(
+            parse(
+                &["22", "?text,__current__"],
+                "\x1b]22;?text,__current__\x1b\\"
+            ),
+            OperatingSystemCommand::PointerShape(PointerShapeRequest::Query(vec![
+                "text".to_string(),
+                "__current__".to_string()
+            ]))
+        );
+        assert_eq!(
+            format!(
+                "{}",
+                OperatingSystemCommand::PointerShape(PointerShapeRequest::Response(vec![
+                    "1".to_string(),
+                    "text".to_string()
+                ]))
+            ),
+            "\x1b]22;1,text\x1b\\"
+        );
+    }
+
+    #[test]
+    fn progress() {
+        assert_eq!(
+            parse(&["9", "4", "1", "42"], "\x1b]9;4;1;42\x1b\\"),
+            OperatingSystemCommand::Progress(Progress::Percentage(42))
+        );
+        assert_eq!(
+            parse(&["9", "4", "1", "200"], "\x1b]9;4;1;100\x1b\\"),
+            OperatingSystemCommand::Progress(Progress::Percentage(100))
+        );
+        assert_eq!(
+            parse(&["9", "4", "0"], "\x1b]9;4;0\x1b\\"),
+            OperatingSystemCommand::Progress(Progress::None)
+        );
+        assert_eq!(
+            parse(&["9", "4", "2"], "\x1b]9;4;2\x1b\\"),
+            OperatingSystemCommand::Progress(Progress::Error(None))
+        );
+        assert_eq!(
+            parse(&["9", "4", "2", "10"], "\x1b]9;4;2;10\x1b\\"),
+            OperatingSystemCommand::Progress(Progress::Error(Some(10)))
+        );
+        assert_eq!(
+            parse(&["9", "4", "3", "0"], "\x1b]9;4;3\x1b\\"),
+            OperatingSystemCommand::Progress(Progress::Indeterminate)
+        );
+        assert_eq!(
+            parse(&["9", "4", "4", "5"], "\x1b]9;4;4;5\x1b\\"),
+            OperatingSystemCommand::Progress(Progress::Paused(Some(5)))
+        );
+        // Not a progress report; just a notification
+        assert_eq!(
+            parse(&["9", "4"], "\x1b]9;4\x1b\\"),
+            OperatingSystemCommand::SystemNotification("4".to_string())
+        );
+        assert_eq!(
+            parse(&["9", "4", "7"], "\x1b]9;4;7\x1b\\"),
+            OperatingSystemCommand::Unspecified(vec![b"9".to_vec(), b"4".to_vec(), b"7".to_vec()])
+        );
+    }
+
+    #[test]
+    fn desktop_notification() {
+        assert_eq!(
+            parse(&["99", "", "hello"], "\x1b]99;;hello\x1b\\"),
+            OperatingSystemCommand::DesktopNotification(DesktopNotification {
+                payload: b"hello".to_vec(),
+                ..Default::default()
+            })
+        );
+
+        // The payload can contain semicolons
+        assert_eq!(
+            parse(
+                &["99", "i=1:d=0:p=body:u=2:c=1:a=-focus,report", "a", "b"],
+                "\x1b]99;i=1:d=0:p=body:a=-focus,report:u=2:c=1;a;b\x1b\\"
+            ),
+            OperatingSystemCommand::DesktopNotification(DesktopNotification {
+                identifier: Some("1".to_string()),
+                done: false,
+                payload_type: NotificationPayloadType::Body,
+                focus: false,
+                report: true,
+                urgency: Some(NotificationUrgency::Critical),
+                report_close: true,
+                payload: b"a;b".to_vec(),
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(
+            parse(
+                &["99", "e=1:o=unfocused:w=-1:x=ignored", "aGVsbG8="],
+                "\x1b]99;e=1:o=unfocused;aGVsbG8=\x1b\\"
+            ),
+            OperatingSystemCommand::DesktopNotification(DesktopNotification {
+                encoded: true,
+                occasion: NotificationOccasion::Unfocused,
+                payload: b"hello".to_vec(),
+                ..Default::default()
+            })
+        );
+
+        // A malformed timeout is ignored rather than failing the notification
+        assert_eq!(
+            parse(&["99", "w=soon", "hi"], "\x1b]99;;hi\x1b\\"),
+            OperatingSystemCommand::DesktopNotification(DesktopNotification {
+                payload: b"hi".to_vec(),
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(
+            encode(&OperatingSystemCommand::DesktopNotification(
+                DesktopNotification::close_report("x")
+            )),
+            "\x1b]99;i=x:p=close;\x1b\\"
+        );
+        assert_eq!(
+            encode(&OperatingSystemCommand::DesktopNotification(
+                DesktopNotification::activation_report("x")
+            )),
+            "\x1b]99;i=x;\x1b\\"
+        );
+    }
+
     #[test]
     fn reset_colors() {
         assert_eq!(
@@ -0,0 +1,125 @@
+//! Highlight rules recolor text from screen lines that matches
+//! a regex, without changing the text itself.  This can be used
+//! to make words like `ERROR` or issue numbers stand out in the
+//! output of programs that don't colorize it themselves.
+use crate::cell::{CellAttributes, Underline};
+use crate::color::{ColorAttribute, SrgbaTuple};
+use crate::hyperlink;
+use crate::Result;
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use wezterm_dynamic::{FromDynamic, ToDynamic};
+
+/// A Rule defines a regex to match against a line of text from
+/// the screen, along with the style that should be applied to the
+/// matching text.
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, FromDynamic, ToDynamic)]
+pub struct Rule {
+    /// Matches the text to highlight, using the `regex` and
+    /// `highlight` fields of the hyperlink rule.  The `format`
+    /// field is not used.
+    #[cfg_attr(feature = "use_serde", serde(flatten))]
+    #[dynamic(flatten)]
+    pub rule: hyperlink::Rule,
+
+    /// The overrides to apply to the matched text
+    #[cfg_attr(feature = "use_serde", serde(flatten))]
+    #[dynamic(flatten)]
+    pub style: Style,
+}
+
+/// The attribute overrides that a Rule applies to the text that
+/// it matches.
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Hash, FromDynamic, ToDynamic)]
+pub struct Style {
+    /// If set, replaces the foreground color of the matched text
+    #[dynamic(default)]
+    pub foreground: Option<SrgbaTuple>,
+
+    /// If set, replaces the background color of the matched text
+    #[dynamic(default)]
+    pub background: Option<SrgbaTuple>,
+
+    /// If set, replaces the underline style of the matched text
+    #[dynamic(default)]
+    pub underline: Option<Underline>,
+}
+
+impl Style {
+    /// Applies the overrides from this style to the provided attributes
+    pub fn apply_to(&self, attrs: &mut CellAttributes) {
+        if let Some(fg) = self.foreground {
+            attrs.set_foreground(ColorAttribute::TrueColorWithDefaultFallback(fg));
+        }
+        if let Some(bg) = self.background {
+            attrs.set_background(ColorAttribute::TrueColorWithDefaultFallback(bg));
+        }
+        if let Some(underline) = self.underline {
+            attrs.set_underline(underline);
+        }
+    }
+}
+
+/// Holds a resolved rule match.
+#[derive(Debug, PartialEq)]
+pub struct RuleMatch {
+    /// Holds the span (measured in bytes) of the matched text
+    pub range: Range<usize>,
+    /// The style of the rule that produced the match
+    pub style: Style,
+}
+
+impl Rule {
+    /// Construct a new rule with no overrides.
+    /// It may fail if the regex is invalid.
+    pub fn new(regex: &str) -> Result<Self> {
+        Ok(Self {
+            rule: hyperlink::Rule::new(regex, "$0")?,
+            style: Style::default(),
+        })
+    }
+
+    /// Given a line of text from the terminal screen, and a set of
+    /// rules, return the set of RuleMatches.
+    /// The matches are returned in the same order as the rules, so
+    /// that applying them in order allows later rules to take
+    /// precedence over earlier rules when they overlap.
+    pub fn match_highlights(line: &str, rules: &[Rule]) -> Vec<RuleMatch> {
+        let mut matches = Vec::new();
+        for rule in rules.iter() {
+            for range in rule.rule.highlight_ranges(line) {
+                matches.push(RuleMatch {
+                    range,
+                    style: rule.style,
+                });
+            }
+        }
+        matches
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn match_highlights() {
+        let rules = vec![
+            Rule::new(r"\bERROR\b").unwrap(),
+            Rule {
+                rule: hyperlink::Rule::with_highlight(r"see (PROJ-\d+)", "$0", 1).unwrap(),
+                style: Style::default(),
+            },
+        ];
+
+        let ranges: Vec<Range<usize>> =
+            Rule::match_highlights("ERROR: failed, see PROJ-123; ERRORS", &rules)
+                .into_iter()
+                .map(|m| m.range)
+                .collect();
+        assert_eq!(ranges, vec![0..5, 19..27]);
+    }
+}
@@ -159,6 +159,10 @@ pub struct Rule {
     /// with the highest numbered capture first.  This avoids issues
     /// with ambiguous replacement of `$11` vs `$1` in the case of
     /// more complex regexes.
+    /// Defaults to `$0`, which is useful for rules that are only
+    /// used to match text, such as the highlight rules.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_format"))]
+    #[dynamic(default = "default_format")]
     pub format: String,
 
     /// Which capture to highlight
@@ -166,6 +170,10 @@ pub struct Rule {
     pub highlight: usize,
 }
 
+fn default_format() -> String {
+    "$0".to_string()
+}
+
 struct RegexWrap(Regex);
 
 impl FromDynamic for RegexWrap {
@@ -280,19 +288,35 @@ impl Rule {
         })
     }
 
+    /// Returns the matches of this rule in a line of text from the
+    /// terminal screen that have the capture selected by `highlight`.
+    fn matches<'t>(&'t self, line: &'t str) -> impl Iterator<Item = Match<'t>> {
+        self.regex
+            .captures_iter(line)
+            .filter_map(|capture_result| capture_result.ok())
+            .map(move |captures| Match {
+                rule: self,
+                captures,
+            })
+            .filter(|m| m.highlight().is_some())
+    }
+
+    /// Returns the spans (measured in bytes) of the non-empty text
+    /// selected by `highlight` for each match of this rule in a line
+    /// of text from the terminal screen.
+    pub fn highlight_ranges(&self, line: &str) -> Vec<Range<usize>> {
+        self.matches(line)
+            .filter(|m| m.len() > 0)
+            .map(|m| m.range())
+            .collect()
+    }
+
     /// Given a line of text from the terminal screen, and a set of
     /// rules, return the set of RuleMatches.
     pub fn match_hyperlinks(line: &str, rules: &[Rule]) -> Vec<RuleMatch> {
         let mut matches = Vec::new();
         for rule in rules.iter() {
-            for capture_result in rule.regex.captures_iter(line) {
-                if let Ok(captures) = capture_result {
-                    let m = Match { rule, captures };
-                    if m.highlight().is_some() {
-                        matches.push(m);
-                    }
-                }
-            }
+            matches.extend(rule.matches(line));
         }
         // Sort the matches by descending match length.
         // This is to avoid confusion if multiple rules match the
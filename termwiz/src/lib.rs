@@ -39,6 +39,8 @@
 //!
 //! * `widgets` - enables the widget layout and related traits
 //! * `use_serde` - makes a number of structs serde serializable
+//! * `async` - enables `terminal::asynchronous`, an adapter that exposes
+//!   terminal input as a `Stream` and renders without blocking (unix only)
 
 mod emoji;
 mod emoji_presentation;
@@ -51,6 +53,7 @@ pub mod cellcluster;
 pub mod color;
 pub mod error;
 pub mod escape;
+pub mod highlight;
 pub mod hyperlink;
 pub mod image;
 pub mod input;
@@ -1,3 +1,5 @@
+use crate::lineedit::vi::ViCommand;
+
 pub type RepeatCount = usize;
 
 #[derive(Debug, Clone, Copy)]
@@ -6,6 +8,10 @@ pub enum Movement {
     BackwardWord(RepeatCount),
     ForwardChar(RepeatCount),
     ForwardWord(RepeatCount),
+    /// Move to the same column on a prior line of a multi-line buffer
+    BackwardLine(RepeatCount),
+    /// Move to the same column on a following line of a multi-line buffer
+    ForwardLine(RepeatCount),
     StartOfLine,
     EndOfLine,
     None,
@@ -22,10 +28,23 @@ pub enum Action {
     Move(Movement),
     Kill(Movement),
     KillAndMove(Movement, Movement),
+    /// Kill the entire line, regardless of the cursor position
+    KillWholeLine,
+    /// Insert the most recently killed text at the cursor
+    Yank,
+    /// Immediately after a `Yank` or `YankPop`, replace the yanked
+    /// text with the next older entry from the kill ring
+    YankPop,
+    /// Revert the most recent change to the line
+    Undo,
+    /// Re-apply the most recently undone change
+    Redo,
     HistoryPrevious,
     HistoryNext,
     Complete,
     NoAction,
     HistoryIncSearchBackwards,
     HistoryIncSearchForwards,
+    /// A command from the vi keymap, which may also change the vi mode
+    Vi(Box<ViCommand>),
 }
@@ -1,5 +1,9 @@
+use crate::{Context, Result};
 use std::borrow::Cow;
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
 
 /// Represents a position within the history.
 /// Smaller numbers are assumed to be before larger numbers,
@@ -135,3 +139,265 @@ impl History for BasicHistory {
         }
     }
 }
+
+/// A history implementation that is persisted to a file.
+///
+/// Entries are stored one per line; backslashes and newlines are
+/// escaped so that multi-line entries round trip.  Each new entry is
+/// appended to the file with a single write to a file that was opened
+/// in append mode, so several processes may safely share the same
+/// history file.  Entries added by other processes become visible
+/// after calling `FileHistory::reload`.
+///
+/// ```no_run
+/// use termwiz::lineedit::FileHistory;
+///
+/// let history = FileHistory::open("/tmp/history.txt")?
+///     .erase_duplicates(true)
+///     .max_entries(Some(1000));
+/// # Ok::<(), termwiz::Error>(())
+/// ```
+pub struct FileHistory {
+    path: PathBuf,
+    history: BasicHistory,
+    erase_duplicates: bool,
+    max_entries: Option<usize>,
+}
+
+impl FileHistory {
+    /// Load the history from `path`.
+    /// The file will be created when the first entry is added if
+    /// it does not already exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut history = Self {
+            path: path.as_ref().to_path_buf(),
+            history: BasicHistory::default(),
+            erase_duplicates: false,
+            max_entries: None,
+        };
+        history.reload()?;
+        Ok(history)
+    }
+
+    /// When set to true, adding an entry that matches an older entry
+    /// will remove the older entry, so that each distinct line appears
+    /// only once, at its most recent position.
+    /// Otherwise, only consecutive duplicates are suppressed.
+    pub fn erase_duplicates(mut self, erase: bool) -> Self {
+        self.erase_duplicates = erase;
+        self.apply_limits();
+        self
+    }
+
+    /// Limit the number of entries that are retained in memory.
+    /// The file itself is not truncated.
+    pub fn max_entries(mut self, max_entries: Option<usize>) -> Self {
+        self.max_entries = max_entries;
+        self.apply_limits();
+        self
+    }
+
+    /// Re-read the history file, picking up any entries that were
+    /// appended by other processes since it was last read.
+    pub fn reload(&mut self) -> Result<()> {
+        let mut history = BasicHistory::default();
+        match std::fs::File::open(&self.path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    let line = line.with_context(|| format!("reading {}", self.path.display()))?;
+                    if !line.is_empty() {
+                        history.add(&unescape(&line));
+                    }
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err).with_context(|| format!("opening {}", self.path.display()));
+            }
+        }
+        self.history = history;
+        self.apply_limits();
+        Ok(())
+    }
+
+    fn apply_limits(&mut self) {
+        let entries = &mut self.history.entries;
+        if self.erase_duplicates {
+            let mut seen = HashSet::new();
+            let mut deduped: VecDeque<String> = entries
+                .drain(..)
+                .rev()
+                .filter(|line| seen.insert(line.clone()))
+                .collect();
+            deduped.make_contiguous().reverse();
+            *entries = deduped;
+        }
+        if let Some(max) = self.max_entries {
+            let excess = entries.len().saturating_sub(max);
+            entries.drain(..excess);
+        }
+    }
+
+    fn append_to_file(&self, line: &str) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("opening {}", self.path.display()))?;
+        // Build the complete record up front so that it is emitted
+        // by a single write and cannot interleave with other writers
+        let mut record = escape(line);
+        record.push('\n');
+        file.write_all(record.as_bytes())
+            .with_context(|| format!("writing to {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+impl History for FileHistory {
+    fn get(&self, idx: HistoryIndex) -> Option<Cow<str>> {
+        self.history.get(idx)
+    }
+
+    fn last(&self) -> Option<HistoryIndex> {
+        self.history.last()
+    }
+
+    fn add(&mut self, line: &str) {
+        if line.is_empty() || self.history.entries.back().map(String::as_str) == Some(line) {
+            return;
+        }
+        if let Err(err) = self.append_to_file(line) {
+            log::error!("failed to save history: {:#}", err);
+        }
+        if self.erase_duplicates {
+            self.history.entries.retain(|entry| entry != line);
+        }
+        self.history.add(line);
+        self.apply_limits();
+    }
+
+    fn search(
+        &self,
+        idx: HistoryIndex,
+        style: SearchStyle,
+        direction: SearchDirection,
+        pattern: &str,
+    ) -> Option<SearchResult> {
+        self.history.search(idx, style, direction, pattern)
+    }
+}
+
+fn escape(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    for c in line.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn unescape(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some(c) => result.push(c),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entries(history: &dyn History) -> Vec<String> {
+        let mut result = vec![];
+        let mut idx = 0;
+        while let Some(line) = history.get(idx) {
+            result.push(line.to_string());
+            idx += 1;
+        }
+        result
+    }
+
+    #[test]
+    fn escaping() {
+        for line in ["plain", "back\\slash", "two\nlines", "\\n", "trailing\\"] {
+            assert_eq!(unescape(&escape(line)), line);
+            assert!(!escape(line).contains('\n'));
+        }
+    }
+
+    #[test]
+    fn persists_and_dedups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+
+        let mut history = FileHistory::open(&path).unwrap();
+        history.add("one");
+        history.add("one");
+        history.add("two\nlines");
+        history.add("three");
+        history.add("one");
+        assert_eq!(entries(&history), vec!["one", "two\nlines", "three", "one"]);
+
+        let history = FileHistory::open(&path).unwrap();
+        assert_eq!(entries(&history), vec!["one", "two\nlines", "three", "one"]);
+
+        let mut history = FileHistory::open(&path)
+            .unwrap()
+            .erase_duplicates(true)
+            .max_entries(Some(2));
+        assert_eq!(entries(&history), vec!["three", "one"]);
+        history.add("three");
+        assert_eq!(entries(&history), vec!["one", "three"]);
+    }
+
+    #[test]
+    fn concurrent_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let mut history = FileHistory::open(&path).unwrap();
+                    for i in 0..100 {
+                        history.add(&format!("thread {} entry {}", t, i));
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        let mut history = FileHistory::open(&path).unwrap();
+        assert_eq!(history.last(), Some(399));
+        history.reload().unwrap();
+        let lines = entries(&history);
+        for t in 0..4 {
+            let mine: Vec<_> = lines
+                .iter()
+                .filter(|line| line.starts_with(&format!("thread {} ", t)))
+                .cloned()
+                .collect();
+            let expected: Vec<_> = (0..100)
+                .map(|i| format!("thread {} entry {}", t, i))
+                .collect();
+            assert_eq!(mine, expected);
+        }
+    }
+}
@@ -49,6 +49,29 @@ pub trait LineEditorHost {
         vec![OutputElement::Text(prompt.to_owned())]
     }
 
+    /// Given a prompt string, return the rendered form of the prompt
+    /// that is shown at the start of the second and subsequent lines
+    /// of a multi-line buffer.
+    /// The default implementation returns blank space with the same
+    /// width as the last line of the prompt, so that the continuation
+    /// lines are aligned with the first line.
+    fn render_continuation_prompt(&self, prompt: &str) -> Vec<OutputElement> {
+        let last_line = prompt.rsplit('\n').next().unwrap_or(prompt);
+        let width = crate::cell::unicode_column_width(last_line, None);
+        vec![OutputElement::Text(" ".repeat(width))]
+    }
+
+    /// Called when the user asks to accept the buffer.
+    /// Return `false` to indicate that the input is incomplete, in
+    /// which case a newline is inserted at the cursor and editing
+    /// continues, rather than accepting the input.
+    /// This is useful for REPLs whose statements may span multiple
+    /// lines.
+    /// The default implementation considers all input to be complete.
+    fn is_input_complete(&self, _line: &str) -> bool {
+        true
+    }
+
     /// Given a reference to the current line being edited, render a preview
     /// of its outcome. The preview is cleared when the input is accepted,
     /// or canceled.
@@ -59,6 +82,8 @@ pub trait LineEditorHost {
     /// Given a reference to the current line being edited and the position
     /// of the cursor, return the rendered form of the line as a sequence
     /// of `OutputElement` instances.
+    /// When the buffer spans multiple lines, this is called separately
+    /// for each line.
     /// While this interface technically allows returning arbitrary Text sequences,
     /// the application should preserve the column positions of the graphemes,
     /// otherwise the terminal cursor position won't match up to the correct
@@ -96,6 +121,23 @@ pub trait LineEditorHost {
     fn resolve_action(&mut self, _event: &InputEvent, _editor: &mut LineEditor) -> Option<Action> {
         None
     }
+
+    /// Selects the keymap that is used to resolve actions when
+    /// `resolve_action` returns `None`.
+    /// The default is `EditMode::Emacs`.
+    fn edit_mode(&self) -> EditMode {
+        EditMode::Emacs
+    }
+}
+
+/// The set of default key bindings used by the line editor
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EditMode {
+    /// Readline/emacs style bindings; this is the default
+    Emacs,
+    /// vi style modal editing.  Each line starts out in insert
+    /// mode; pressing Escape switches to normal mode.
+    Vi,
 }
 
 /// A candidate for tab completion.
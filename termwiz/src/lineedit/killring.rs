@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+
+/// The maximum number of entries retained by the kill ring
+const KILL_RING_SIZE: usize = 32;
+
+/// An emacs style kill ring.
+/// Killed text is pushed onto the ring; `Yank` inserts the most
+/// recent entry and `YankPop` cycles back through older entries.
+#[derive(Default)]
+pub(crate) struct KillRing {
+    /// The most recently killed text is at the front
+    entries: VecDeque<String>,
+    /// The offset of the entry that was most recently yanked
+    yank_index: usize,
+}
+
+/// Controls how killed text combines with the text from an immediately
+/// preceding kill
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum KillDirection {
+    /// The text was killed from after the cursor, so it follows
+    /// the previously killed text
+    Forwards,
+    /// The text was killed from before the cursor, so it precedes
+    /// the previously killed text
+    Backwards,
+}
+
+impl KillRing {
+    /// Record killed text.  If `accumulate` is true then the text is
+    /// combined with the most recent entry rather than starting a new one,
+    /// so that a sequence of kills can be yanked back as a single unit.
+    pub fn push(&mut self, text: &str, direction: KillDirection, accumulate: bool) {
+        if text.is_empty() {
+            return;
+        }
+        match self.entries.front_mut() {
+            Some(front) if accumulate => match direction {
+                KillDirection::Forwards => front.push_str(text),
+                KillDirection::Backwards => front.insert_str(0, text),
+            },
+            _ => {
+                self.entries.push_front(text.to_string());
+                self.entries.truncate(KILL_RING_SIZE);
+            }
+        }
+        self.yank_index = 0;
+    }
+
+    /// Returns the most recently killed text
+    pub fn yank(&mut self) -> Option<&str> {
+        self.yank_index = 0;
+        self.entries.front().map(String::as_str)
+    }
+
+    /// Rotates to the next older entry, wrapping around at the end
+    /// of the ring, and returns it
+    pub fn yank_pop(&mut self) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        self.yank_index = (self.yank_index + 1) % self.entries.len();
+        self.entries.get(self.yank_index).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn accumulate_and_rotate() {
+        let mut ring = KillRing::default();
+        assert_eq!(ring.yank(), None);
+        assert_eq!(ring.yank_pop(), None);
+
+        ring.push("one", KillDirection::Forwards, false);
+        ring.push("two", KillDirection::Forwards, false);
+        ring.push(" three", KillDirection::Forwards, true);
+        ring.push("zero ", KillDirection::Backwards, true);
+
+        assert_eq!(ring.yank(), Some("zero two three"));
+        assert_eq!(ring.yank_pop(), Some("one"));
+        assert_eq!(ring.yank_pop(), Some("zero two three"));
+        assert_eq!(ring.yank(), Some("zero two three"));
+    }
+
+    #[test]
+    fn bounded() {
+        let mut ring = KillRing::default();
+        for i in 0..KILL_RING_SIZE * 2 {
+            ring.push(&i.to_string(), KillDirection::Forwards, false);
+        }
+        assert_eq!(ring.entries.len(), KILL_RING_SIZE);
+        assert_eq!(ring.yank(), Some("63"));
+    }
+}
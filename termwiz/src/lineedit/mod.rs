@@ -24,6 +24,8 @@
 //! ---------     | ------
 //! Ctrl-A, Home  | Move cursor to the beginning of the line
 //! Ctrl-E, End   | Move cursor to the end of the line
+//! Ctrl-P, Up    | Move cursor up a line, or recall the previous history entry
+//! Ctrl-N, Down  | Move cursor down a line, or recall the next history entry
 //! Ctrl-B, Left  | Move cursor one grapheme to the left
 //! Ctrl-C        | Cancel the line editor
 //! Ctrl-D        | Cancel the line editor with an End-of-File result
@@ -31,26 +33,52 @@
 //! Ctrl-H, Backspace | Delete the grapheme to the left of the cursor
 //! Delete        | Delete the grapheme to the right of the cursor
 //! Ctrl-J, Ctrl-M, Enter | Finish line editing and accept the current line
+//! Alt-Enter     | Insert a newline
 //! Ctrl-K        | Delete from cursor to end of line
 //! Ctrl-L        | Move the cursor to the top left, clear screen and repaint
 //! Ctrl-R        | Incremental history search mode
+//! Ctrl-U        | Delete from start of line to cursor
 //! Ctrl-W        | Delete word leading up to cursor
+//! Ctrl-Y        | Insert the most recently deleted text
+//! Ctrl-_        | Undo the last change
 //! Alt-b, Alt-Left | Move the cursor backwards one word
+//! Alt-d         | Delete word following the cursor
 //! Alt-f, Alt-Right | Move the cursor forwards one word
+//! Alt-y         | After Ctrl-Y, replace the inserted text with older deleted text
+//!
+//! Text deleted by any of the commands above, other than the single
+//! grapheme deletions performed by Ctrl-H, Backspace and Delete, is
+//! saved in a kill ring.  Consecutive deletions accumulate into a
+//! single kill ring entry.
+//!
+//! ## Multi-line Input
+//!
+//! The buffer may span multiple lines.  When Enter is pressed the
+//! host is consulted via `LineEditorHost::is_input_complete`; if the
+//! input is incomplete then a newline is inserted and editing continues.
+//! Line oriented movement and deletion, such as Ctrl-A, Ctrl-E and
+//! Ctrl-K, operate on the line that contains the cursor.
+//!
+//! A vi style modal keymap can be selected by returning `EditMode::Vi`
+//! from `LineEditorHost::edit_mode`; see the `vi` module for its bindings.
 use crate::caps::{Capabilities, ProbeHints};
 use crate::input::{InputEvent, KeyCode, KeyEvent, Modifiers};
 use crate::surface::change::ChangeSequence;
 use crate::surface::{Change, Position};
 use crate::terminal::{new_terminal, Terminal};
 use crate::{bail, ensure, Result};
-use unicode_segmentation::GraphemeCursor;
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
 
 mod actions;
 mod history;
 mod host;
+mod killring;
+pub mod vi;
 pub use actions::{Action, Movement, RepeatCount};
 pub use history::*;
 pub use host::*;
+use killring::{KillDirection, KillRing};
+pub use vi::{ViCommand, ViMode};
 
 /// The `LineEditor` struct provides line editing facilities similar
 /// to those in the unix shell.
@@ -85,6 +113,24 @@ pub struct LineEditor<'term> {
     move_to_editor_end: Option<Change>,
 
     state: EditorState,
+
+    kill_ring: KillRing,
+    /// The range of the text inserted by the most recent yank,
+    /// which is replaced by a subsequent `YankPop`
+    yank_range: Option<std::ops::Range<usize>>,
+    undo_stack: Vec<UndoEntry>,
+    redo_stack: Vec<UndoEntry>,
+    /// The most recently applied action; used to coalesce
+    /// consecutive inserts and kills
+    last_action: Option<Action>,
+
+    vi: vi::ViState,
+}
+
+/// A snapshot of the line buffer, used to implement undo and redo
+struct UndoEntry {
+    line: String,
+    cursor: usize,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -163,6 +209,12 @@ impl<'term> LineEditor<'term> {
             move_to_editor_start: None,
             move_to_editor_end: None,
             state: EditorState::Inactive,
+            kill_ring: KillRing::default(),
+            yank_range: None,
+            undo_stack: vec![],
+            redo_stack: vec![],
+            last_action: None,
+            vi: vi::ViState::default(),
         }
     }
 
@@ -189,9 +241,8 @@ impl<'term> LineEditor<'term> {
             _ => (&self.line, self.cursor),
         };
 
-        let cursor_position_after_printing_prompt = changes.current_cursor_position();
-
-        let (elements, cursor_x_pos) = host.highlight_line(line_to_display, cursor);
+        let mut cursor_position = changes.current_cursor_position();
+        let mut line_start = 0;
 
         // Calculate what the cursor position would be after printing X columns
         // of text from the specified location.
@@ -210,24 +261,54 @@ impl<'term> LineEditor<'term> {
 
             (col, row)
         }
-        let cursor_position = compute_cursor_after_printing_x_columns(
-            cursor_position_after_printing_prompt.0,
-            cursor_position_after_printing_prompt.1,
-            cursor_x_pos,
-            screen_size.cols,
-        );
 
-        for ele in elements {
-            changes.add(ele);
-        }
+        for (idx, text) in line_to_display.split('\n').enumerate() {
+            if idx > 0 {
+                changes.add("\r\n");
+                changes.add(Change::AllAttributes(Default::default()));
+                for ele in host.render_continuation_prompt(&self.prompt) {
+                    changes.add(ele);
+                }
+                changes.add(Change::AllAttributes(Default::default()));
+            }
+
+            let line_end = line_start + text.len();
+            let cursor_in_line = cursor >= line_start && cursor <= line_end;
+            let cursor_position_after_printing_prompt = changes.current_cursor_position();
 
-        let cursor_after_line_render = changes.current_cursor_position();
-        if cursor_after_line_render.0 == screen_size.cols {
-            // If the cursor position remains in the first column
-            // then the renderer may still consider itself to be on
-            // the prior line; force out an additional character to force
-            // it to apply wrapping/flush.
-            changes.add(" ");
+            let (elements, cursor_x_pos) = host.highlight_line(
+                text,
+                if cursor_in_line {
+                    cursor - line_start
+                } else {
+                    0
+                },
+            );
+
+            if cursor_in_line {
+                cursor_position = compute_cursor_after_printing_x_columns(
+                    cursor_position_after_printing_prompt.0,
+                    cursor_position_after_printing_prompt.1,
+                    cursor_x_pos,
+                    screen_size.cols,
+                );
+            }
+
+            for ele in elements {
+                changes.add(ele);
+            }
+
+            let cursor_after_line_render = changes.current_cursor_position();
+            if cursor_after_line_render.0 == screen_size.cols {
+                // If the cursor position remains in the first column
+                // then the renderer may still consider itself to be on
+                // the prior line; force out an additional character to force
+                // it to apply wrapping/flush.
+                changes.add(" ");
+            }
+
+            // Skip over the newline
+            line_start = line_end + 1;
         }
 
         if let EditorState::Editing = &self.state {
@@ -336,6 +417,29 @@ impl<'term> LineEditor<'term> {
             return Some(action);
         }
 
+        // Terminals report CTRL-letter combinations using lower case
+        // letters, but the keymaps are expressed in upper case
+        let event = match event {
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char(c),
+                modifiers,
+            }) if modifiers.contains(Modifiers::CTRL) && c.is_ascii_lowercase() => {
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char(c.to_ascii_uppercase()),
+                    modifiers: *modifiers,
+                })
+            }
+            event => event.clone(),
+        };
+
+        match host.edit_mode() {
+            EditMode::Emacs => Self::resolve_emacs_action(&event),
+            EditMode::Vi => self.resolve_vi_action(&event),
+        }
+    }
+
+    /// The default emacs style keymap
+    fn resolve_emacs_action(event: &InputEvent) -> Option<Action> {
         match event {
             InputEvent::Key(KeyEvent {
                 key: KeyCode::Char('C'),
@@ -364,6 +468,10 @@ impl<'term> LineEditor<'term> {
                 key: KeyCode::Enter,
                 modifiers: Modifiers::NONE,
             }) => Some(Action::AcceptLine),
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Enter,
+                modifiers: Modifiers::ALT,
+            }) => Some(Action::InsertChar(1, '\n')),
             InputEvent::Key(KeyEvent {
                 key: KeyCode::Char('H'),
                 modifiers: Modifiers::CTRL,
@@ -424,6 +532,38 @@ impl<'term> LineEditor<'term> {
                 modifiers: Modifiers::CTRL,
             }) => Some(Action::Kill(Movement::BackwardWord(1))),
 
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('U'),
+                modifiers: Modifiers::CTRL,
+            }) => Some(Action::Kill(Movement::StartOfLine)),
+
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('d'),
+                modifiers: Modifiers::ALT,
+            }) => Some(Action::KillAndMove(
+                Movement::ForwardWord(1),
+                Movement::None,
+            )),
+
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('Y'),
+                modifiers: Modifiers::CTRL,
+            }) => Some(Action::Yank),
+
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('y'),
+                modifiers: Modifiers::ALT,
+            }) => Some(Action::YankPop),
+
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('_'),
+                modifiers: Modifiers::CTRL,
+            })
+            | InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('/'),
+                modifiers: Modifiers::CTRL,
+            }) => Some(Action::Undo),
+
             InputEvent::Key(KeyEvent {
                 key: KeyCode::Char('b'),
                 modifiers: Modifiers::ALT,
@@ -512,6 +652,40 @@ impl<'term> LineEditor<'term> {
         }
     }
 
+    /// Returns the byte index of the start of the line containing `pos`
+    fn line_start(&self, pos: usize) -> usize {
+        self.line[..pos].rfind('\n').map(|idx| idx + 1).unwrap_or(0)
+    }
+
+    /// Returns the byte index of the end of the line containing `pos`,
+    /// excluding its terminating newline
+    fn line_end(&self, pos: usize) -> usize {
+        self.line[pos..]
+            .find('\n')
+            .map(|idx| pos + idx)
+            .unwrap_or(self.line.len())
+    }
+
+    /// Returns the display column of the cursor within its line
+    fn cursor_column(&self) -> usize {
+        let start = self.line_start(self.cursor);
+        crate::cell::unicode_column_width(&self.line[start..self.cursor], None)
+    }
+
+    /// Returns the byte index of the grapheme at `column` within the
+    /// line starting at `start`, or the end of that line if it is shorter
+    fn position_at_column(&self, start: usize, column: usize) -> usize {
+        let end = self.line_end(start);
+        let mut width = 0;
+        for (idx, grapheme) in self.line[start..end].grapheme_indices(true) {
+            if width >= column {
+                return start + idx;
+            }
+            width += crate::cell::unicode_column_width(grapheme, None);
+        }
+        end
+    }
+
     /// Compute the cursor position after applying movement
     fn eval_movement(&self, movement: Movement) -> usize {
         match movement {
@@ -598,43 +772,153 @@ impl<'term> LineEditor<'term> {
                 }
                 position
             }
-            Movement::StartOfLine => 0,
-            Movement::EndOfLine => {
-                let mut cursor =
-                    GraphemeCursor::new(self.line.len().saturating_sub(1), self.line.len(), false);
-                if let Ok(Some(pos)) = cursor.next_boundary(&self.line, 0) {
-                    pos
-                } else {
-                    self.cursor
+            Movement::BackwardLine(rep) => {
+                let column = self.cursor_column();
+                let mut start = self.line_start(self.cursor);
+                for _ in 0..rep {
+                    if start == 0 {
+                        break;
+                    }
+                    start = self.line_start(start - 1);
                 }
+                self.position_at_column(start, column)
             }
+            Movement::ForwardLine(rep) => {
+                let column = self.cursor_column();
+                let mut start = self.line_start(self.cursor);
+                for _ in 0..rep {
+                    let end = self.line_end(start);
+                    if end == self.line.len() {
+                        break;
+                    }
+                    start = end + 1;
+                }
+                self.position_at_column(start, column)
+            }
+            Movement::StartOfLine => self.line_start(self.cursor),
+            Movement::EndOfLine => self.line_end(self.cursor),
             Movement::None => self.cursor,
         }
     }
 
-    fn kill_text(&mut self, kill_movement: Movement, move_movement: Movement) {
+    fn kill_text(
+        &mut self,
+        kill_movement: Movement,
+        move_movement: Movement,
+        host: &mut dyn LineEditorHost,
+    ) {
         self.clear_completion();
         let kill_pos = self.eval_movement(kill_movement);
         let new_cursor = self.eval_movement(move_movement);
 
-        let (lower, upper) = if kill_pos < self.cursor {
-            (kill_pos, self.cursor)
+        let (lower, upper, direction) = if kill_pos < self.cursor {
+            (kill_pos, self.cursor, KillDirection::Backwards)
         } else {
-            (self.cursor, kill_pos)
+            (self.cursor, kill_pos, KillDirection::Forwards)
         };
 
+        // Single grapheme deletions are not saved to the kill ring
+        // in emacs mode, in the same way as readline
+        let is_char_kill = matches!(
+            kill_movement,
+            Movement::BackwardChar(_) | Movement::ForwardChar(_)
+        );
+        let searching = matches!(self.state, EditorState::Searching { .. });
+        let emacs = host.edit_mode() == EditMode::Emacs;
+        let save = !(searching || (is_char_kill && emacs));
+        if save {
+            let accumulate = emacs
+                && matches!(
+                    self.last_action,
+                    Some(Action::Kill(_)) | Some(Action::KillAndMove(..))
+                );
+            self.kill_ring
+                .push(&self.line[lower..upper], direction, accumulate);
+        }
+
         self.line.replace_range(lower..upper, "");
 
-        // Clamp to the line length, otherwise a kill to end of line
+        // Adjust for the removed text, otherwise a kill to end of line
         // command will leave the cursor way off beyond the end of
         // the line.
-        self.cursor = new_cursor.min(self.line.len());
+        self.cursor = if new_cursor >= upper {
+            new_cursor - (upper - lower)
+        } else {
+            new_cursor.min(lower)
+        };
     }
 
     fn clear_completion(&mut self) {
         self.completion = None;
     }
 
+    /// Insert the most recent kill ring entry at the cursor
+    fn yank(&mut self) {
+        self.clear_completion();
+        self.cancel_search_state();
+        if let Some(text) = self.kill_ring.yank() {
+            let start = self.cursor;
+            self.line.insert_str(start, text);
+            self.cursor = start + text.len();
+            self.yank_range = Some(start..self.cursor);
+        }
+    }
+
+    /// Replace the text inserted by the prior yank with the next
+    /// older kill ring entry
+    fn yank_pop(&mut self) {
+        let range = match self.yank_range.take() {
+            Some(range) => range,
+            None => return,
+        };
+        if let Some(text) = self.kill_ring.yank_pop() {
+            self.line.replace_range(range.clone(), text);
+            self.cursor = range.start + text.len();
+            self.yank_range = Some(range.start..self.cursor);
+        }
+    }
+
+    fn undo(&mut self) {
+        self.clear_completion();
+        self.cancel_search_state();
+        if let Some(entry) = self.undo_stack.pop() {
+            let line = std::mem::replace(&mut self.line, entry.line);
+            self.redo_stack.push(UndoEntry {
+                line,
+                cursor: self.cursor,
+            });
+            self.cursor = entry.cursor.min(self.line.len());
+        }
+    }
+
+    fn redo(&mut self) {
+        self.clear_completion();
+        self.cancel_search_state();
+        if let Some(entry) = self.redo_stack.pop() {
+            let line = std::mem::replace(&mut self.line, entry.line);
+            self.undo_stack.push(UndoEntry {
+                line,
+                cursor: self.cursor,
+            });
+            self.cursor = entry.cursor.min(self.line.len());
+        }
+    }
+
+    /// Records the state of the line prior to an action so that it
+    /// can be undone.  Runs of inserted characters are coalesced into
+    /// a single undo step.
+    fn record_undo(&mut self, action: &Action, line: String, cursor: usize) {
+        if line == self.line {
+            return;
+        }
+        let coalesce = matches!(action, Action::InsertChar(..))
+            && matches!(self.last_action, Some(Action::InsertChar(..)));
+        if !coalesce {
+            self.undo_stack.push(UndoEntry { line, cursor });
+        }
+        self.redo_stack.clear();
+    }
+
     fn cancel_search_state(&mut self) {
         if let EditorState::Searching {
             matching_line,
@@ -788,6 +1072,10 @@ impl<'term> LineEditor<'term> {
     /// You don't normally need to call this unless you are defining
     /// custom key mapping or custom actions in your embedding application.
     pub fn apply_action(&mut self, host: &mut dyn LineEditorHost, action: Action) -> Result<()> {
+        if let Action::Vi(command) = action {
+            return self.apply_vi_command(host, *command);
+        }
+
         // When searching, reinterpret history next/prev as repeated
         // search actions in the appropriate direction
         let action = match (action, &self.state) {
@@ -808,16 +1096,51 @@ impl<'term> LineEditor<'term> {
             (action, _) => action,
         };
 
+        // Edits to the search pattern are not recorded for undo
+        let searching = matches!(self.state, EditorState::Searching { .. });
+        let prior_line = if searching {
+            None
+        } else {
+            Some((self.line.clone(), self.cursor))
+        };
+
+        self.apply_action_impl(host, action.clone())?;
+
+        if let Some((line, cursor)) = prior_line {
+            if !matches!(action, Action::Undo | Action::Redo)
+                && !matches!(self.state, EditorState::Searching { .. })
+            {
+                self.record_undo(&action, line, cursor);
+            }
+        }
+        if !matches!(action, Action::Yank | Action::YankPop) {
+            self.yank_range = None;
+        }
+        self.last_action = Some(action);
+
+        Ok(())
+    }
+
+    fn apply_action_impl(&mut self, host: &mut dyn LineEditorHost, action: Action) -> Result<()> {
         match action {
             Action::Cancel => self.state = EditorState::Cancelled,
             Action::NoAction => {}
+            // Vi commands are dispatched by apply_action before it records
+            // undo state, as they apply other actions in turn
+            Action::Vi(_) => unreachable!("vi commands are applied by apply_action"),
             Action::AcceptLine => {
                 // Make sure that hitting Enter for a line that
                 // shows in the incremental search causes that
                 // line to be accepted, rather than the search pattern!
                 self.cancel_search_state();
 
-                self.state = EditorState::Accepted;
+                if host.is_input_complete(&self.line) {
+                    self.state = EditorState::Accepted;
+                } else {
+                    self.clear_completion();
+                    self.line.insert(self.cursor, '\n');
+                    self.cursor += 1;
+                }
             }
             Action::EndOfFile => {
                 return Err(
@@ -825,13 +1148,26 @@ impl<'term> LineEditor<'term> {
                 )
             }
             Action::Kill(movement) => {
-                self.kill_text(movement, movement);
+                self.kill_text(movement, movement, host);
                 self.reapply_search_pattern(host);
             }
             Action::KillAndMove(kill_movement, move_movement) => {
-                self.kill_text(kill_movement, move_movement);
+                self.kill_text(kill_movement, move_movement, host);
                 self.reapply_search_pattern(host);
             }
+            Action::KillWholeLine => {
+                self.cursor = self.eval_movement(Movement::StartOfLine);
+                self.kill_text(Movement::EndOfLine, Movement::None, host);
+                self.reapply_search_pattern(host);
+            }
+            Action::Yank => self.yank(),
+            Action::YankPop => {
+                if matches!(self.last_action, Some(Action::Yank) | Some(Action::YankPop)) {
+                    self.yank_pop();
+                }
+            }
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
 
             Action::Move(movement) => {
                 self.clear_completion();
@@ -862,6 +1198,18 @@ impl<'term> LineEditor<'term> {
                 self.terminal
                     .render(&[Change::ClearScreen(Default::default())])?;
             }
+            Action::HistoryPrevious if self.line_start(self.cursor) > 0 => {
+                // Within a multi-line buffer, move between lines
+                // until we reach the first line
+                self.clear_completion();
+                self.cancel_search_state();
+                self.cursor = self.eval_movement(Movement::BackwardLine(1));
+            }
+            Action::HistoryNext if self.line_end(self.cursor) < self.line.len() => {
+                self.clear_completion();
+                self.cancel_search_state();
+                self.cursor = self.eval_movement(Movement::ForwardLine(1));
+            }
             Action::HistoryPrevious => {
                 self.clear_completion();
                 self.cancel_search_state();
@@ -953,6 +1301,11 @@ impl<'term> LineEditor<'term> {
         self.history_pos = None;
         self.bottom_line = None;
         self.clear_completion();
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.yank_range = None;
+        self.last_action = None;
+        self.vi = vi::ViState::default();
 
         self.render(host)?;
         while let Some(event) = self.terminal.poll_input(None)? {
@@ -982,3 +1335,295 @@ pub fn line_editor_terminal() -> Result<impl Terminal> {
     let caps = Capabilities::new_with_hints(hints)?;
     new_terminal(caps)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::caps::probed::ProbeCapabilities;
+    use crate::terminal::{ScreenSize, TerminalWaker};
+    use std::time::Duration;
+
+    /// A terminal that discards output; the tests below drive the
+    /// editor directly rather than via `read_line`
+    struct NullTerminal {
+        waker: TerminalWaker,
+        #[cfg(unix)]
+        _wake_pipe: std::os::unix::net::UnixStream,
+    }
+
+    impl NullTerminal {
+        #[cfg(unix)]
+        fn new() -> Self {
+            let (waker, _wake_pipe) = TerminalWaker::detached().unwrap();
+            Self { waker, _wake_pipe }
+        }
+
+        #[cfg(windows)]
+        fn new() -> Self {
+            Self {
+                waker: TerminalWaker::detached().unwrap(),
+            }
+        }
+    }
+
+    impl Terminal for NullTerminal {
+        fn set_raw_mode(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn set_cooked_mode(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn enter_alternate_screen(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn exit_alternate_screen(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn get_screen_size(&mut self) -> Result<ScreenSize> {
+            Ok(ScreenSize {
+                rows: 24,
+                cols: 80,
+                xpixel: 0,
+                ypixel: 0,
+            })
+        }
+
+        fn probe_capabilities(&mut self) -> Option<ProbeCapabilities> {
+            None
+        }
+
+        fn set_screen_size(&mut self, _size: ScreenSize) -> Result<()> {
+            Ok(())
+        }
+
+        fn render(&mut self, _changes: &[Change]) -> Result<()> {
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn poll_input(&mut self, _wait: Option<Duration>) -> Result<Option<InputEvent>> {
+            Ok(None)
+        }
+
+        fn waker(&self) -> TerminalWaker {
+            self.waker.clone()
+        }
+    }
+
+    #[derive(Default)]
+    struct TestHost {
+        history: BasicHistory,
+        vi: bool,
+    }
+
+    fn brackets_balanced(line: &str) -> bool {
+        line.matches('{').count() <= line.matches('}').count()
+    }
+
+    impl LineEditorHost for TestHost {
+        fn history(&mut self) -> &mut dyn History {
+            &mut self.history
+        }
+
+        fn is_input_complete(&self, line: &str) -> bool {
+            brackets_balanced(line)
+        }
+
+        fn edit_mode(&self) -> EditMode {
+            if self.vi {
+                EditMode::Vi
+            } else {
+                EditMode::Emacs
+            }
+        }
+    }
+
+    fn key(key: KeyCode, modifiers: Modifiers) -> InputEvent {
+        InputEvent::Key(KeyEvent { key, modifiers })
+    }
+
+    fn feed(editor: &mut LineEditor, host: &mut TestHost, events: &[InputEvent]) {
+        for event in events {
+            if let Some(action) = editor.resolve_action(event, host) {
+                editor.apply_action(host, action).unwrap();
+            }
+        }
+    }
+
+    fn type_str(editor: &mut LineEditor, host: &mut TestHost, text: &str) {
+        let events: Vec<_> = text
+            .chars()
+            .map(|c| key(KeyCode::Char(c), Modifiers::NONE))
+            .collect();
+        feed(editor, host, &events);
+    }
+
+    fn ctrl(c: char) -> InputEvent {
+        // Terminals report control keys in lower case
+        key(KeyCode::Char(c), Modifiers::CTRL)
+    }
+
+    fn alt(c: char) -> InputEvent {
+        key(KeyCode::Char(c), Modifiers::ALT)
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut terminal = NullTerminal::new();
+        let mut editor = LineEditor::new(&mut terminal);
+        let mut host = TestHost::default();
+
+        type_str(&mut editor, &mut host, "hello world");
+        feed(&mut editor, &mut host, &[ctrl('w')]);
+        assert_eq!(editor.get_line_and_cursor(), ("hello ", 6));
+
+        feed(&mut editor, &mut host, &[ctrl('_')]);
+        assert_eq!(editor.get_line_and_cursor(), ("hello world", 11));
+
+        // The typed text is undone as a single unit
+        feed(&mut editor, &mut host, &[ctrl('_')]);
+        assert_eq!(editor.get_line_and_cursor(), ("", 0));
+        feed(&mut editor, &mut host, &[ctrl('_')]);
+        assert_eq!(editor.get_line_and_cursor(), ("", 0));
+
+        editor.apply_action(&mut host, Action::Redo).unwrap();
+        assert_eq!(editor.get_line_and_cursor(), ("hello world", 11));
+        editor.apply_action(&mut host, Action::Redo).unwrap();
+        assert_eq!(editor.get_line_and_cursor(), ("hello ", 6));
+
+        // A new edit discards the redo history
+        feed(&mut editor, &mut host, &[ctrl('_')]);
+        type_str(&mut editor, &mut host, "!");
+        editor.apply_action(&mut host, Action::Redo).unwrap();
+        assert_eq!(editor.get_line_and_cursor(), ("hello world!", 12));
+    }
+
+    #[test]
+    fn yank_and_yank_pop() {
+        let mut terminal = NullTerminal::new();
+        let mut editor = LineEditor::new(&mut terminal);
+        let mut host = TestHost::default();
+
+        type_str(&mut editor, &mut host, "one two three");
+        // Consecutive kills accumulate into a single entry
+        feed(&mut editor, &mut host, &[ctrl('w'), ctrl('w')]);
+        assert_eq!(editor.get_line_and_cursor(), ("one ", 4));
+        feed(&mut editor, &mut host, &[ctrl('a'), alt('d')]);
+        assert_eq!(editor.get_line_and_cursor(), ("", 0));
+
+        feed(&mut editor, &mut host, &[ctrl('y')]);
+        assert_eq!(editor.get_line_and_cursor(), ("one ", 4));
+        feed(&mut editor, &mut host, &[alt('y')]);
+        assert_eq!(editor.get_line_and_cursor(), ("two three", 9));
+        feed(&mut editor, &mut host, &[alt('y')]);
+        assert_eq!(editor.get_line_and_cursor(), ("one ", 4));
+
+        // Yank-pop is only meaningful immediately after a yank
+        feed(&mut editor, &mut host, &[ctrl('b'), alt('y')]);
+        assert_eq!(editor.get_line_and_cursor(), ("one ", 3));
+
+        // Single grapheme deletions are not saved
+        feed(
+            &mut editor,
+            &mut host,
+            &[key(KeyCode::Backspace, Modifiers::NONE)],
+        );
+        feed(&mut editor, &mut host, &[ctrl('y')]);
+        assert_eq!(editor.get_line_and_cursor(), ("onone  ", 6));
+    }
+
+    #[test]
+    fn multi_line() {
+        let mut terminal = NullTerminal::new();
+        let mut editor = LineEditor::new(&mut terminal);
+        let mut host = TestHost::default();
+        let enter = key(KeyCode::Enter, Modifiers::NONE);
+        let up = key(KeyCode::UpArrow, Modifiers::NONE);
+        let down = key(KeyCode::DownArrow, Modifiers::NONE);
+        editor.state = EditorState::Editing;
+
+        type_str(&mut editor, &mut host, "fn {");
+        feed(&mut editor, &mut host, &[enter.clone()]);
+        assert_eq!(editor.state, EditorState::Editing);
+        type_str(&mut editor, &mut host, "  body");
+        feed(&mut editor, &mut host, &[enter.clone()]);
+        type_str(&mut editor, &mut host, "}");
+        assert_eq!(editor.get_line_and_cursor(), ("fn {\n  body\n}", 13));
+
+        // Up and down move between lines, preserving the column
+        feed(&mut editor, &mut host, &[up.clone()]);
+        assert_eq!(editor.get_line_and_cursor().1, 6);
+        feed(
+            &mut editor,
+            &mut host,
+            &[key(KeyCode::End, Modifiers::NONE)],
+        );
+        assert_eq!(editor.get_line_and_cursor().1, 11);
+        feed(&mut editor, &mut host, &[up.clone()]);
+        assert_eq!(editor.get_line_and_cursor().1, 4);
+        feed(&mut editor, &mut host, &[down.clone(), down]);
+        assert_eq!(editor.get_line_and_cursor().1, 13);
+
+        // Line oriented commands affect only the current line
+        feed(&mut editor, &mut host, &[up, ctrl('a'), ctrl('k')]);
+        assert_eq!(editor.get_line_and_cursor(), ("fn {\n\n}", 5));
+        type_str(&mut editor, &mut host, "x");
+
+        // Alt-Enter inserts a newline even when the input is complete
+        feed(
+            &mut editor,
+            &mut host,
+            &[key(KeyCode::Enter, Modifiers::ALT)],
+        );
+        assert_eq!(editor.get_line_and_cursor(), ("fn {\nx\n\n}", 7));
+
+        feed(&mut editor, &mut host, &[enter]);
+        assert_eq!(editor.state, EditorState::Accepted);
+    }
+
+    #[test]
+    fn vi_mode() {
+        let mut terminal = NullTerminal::new();
+        let mut editor = LineEditor::new(&mut terminal);
+        let mut host = TestHost {
+            vi: true,
+            ..Default::default()
+        };
+        let esc = key(KeyCode::Escape, Modifiers::NONE);
+
+        type_str(&mut editor, &mut host, "one two three");
+        feed(&mut editor, &mut host, &[esc.clone()]);
+        assert_eq!(editor.vi_mode(), ViMode::Normal);
+        assert_eq!(editor.get_line_and_cursor(), ("one two three", 12));
+
+        type_str(&mut editor, &mut host, "0dw");
+        assert_eq!(editor.get_line_and_cursor(), ("two three", 0));
+
+        type_str(&mut editor, &mut host, "$p");
+        assert_eq!(editor.get_line_and_cursor(), ("two threeone ", 13));
+
+        type_str(&mut editor, &mut host, "u");
+        assert_eq!(editor.get_line_and_cursor(), ("two three", 9));
+
+        type_str(&mut editor, &mut host, "02x");
+        assert_eq!(editor.get_line_and_cursor(), ("o three", 0));
+
+        type_str(&mut editor, &mut host, "wcwfour");
+        assert_eq!(editor.vi_mode(), ViMode::Insert);
+        assert_eq!(editor.get_line_and_cursor(), ("o four", 6));
+
+        feed(&mut editor, &mut host, &[esc]);
+        type_str(&mut editor, &mut host, "yyddP");
+        assert_eq!(editor.get_line_and_cursor(), ("o four", 6));
+
+        type_str(&mut editor, &mut host, "ccfive");
+        assert_eq!(editor.get_line_and_cursor(), ("five", 4));
+    }
+}
@@ -0,0 +1,374 @@
+//! The vi style keymap for the line editor.
+//!
+//! Keystroke     | Action
+//! ---------     | ------
+//! Escape        | Leave insert mode and enter normal mode
+//! h, l, Left, Right | Move cursor one grapheme left or right
+//! w, b          | Move cursor forwards or backwards one word
+//! 0, ^, $       | Move cursor to the start or end of the line
+//! i, a, I, A    | Enter insert mode before/after the cursor, or at the start/end of the line
+//! x, X          | Delete the grapheme under or before the cursor
+//! d{motion}, dd, D | Kill text covered by the motion, the whole line, or to the end of the line
+//! c{motion}, cc, C | As for `d`, then enter insert mode
+//! s, S          | Substitute the grapheme under the cursor, or the whole line
+//! y{motion}, yy | Copy text covered by the motion, or the whole line, into the kill ring
+//! p, P          | Insert the most recently killed text after or before the cursor
+//! u, Ctrl-R     | Undo, Redo
+//! k, j, Up, Down | Move through history
+//! /             | Incremental history search
+//!
+//! Motions and `x`, `X` and `s` accept a numeric repeat count prefix.
+//! In insert mode the emacs bindings are used, with the exception of
+//! Escape.
+use crate::input::{InputEvent, KeyCode, KeyEvent, Modifiers};
+use crate::lineedit::killring::KillDirection;
+use crate::lineedit::{Action, LineEditor, LineEditorHost, Movement};
+use crate::Result;
+
+/// The mode of a vi style line editor
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ViMode {
+    Insert,
+    Normal,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+/// Tracks the modal state and any partially entered command
+#[derive(Debug, Clone)]
+pub(crate) struct ViState {
+    pub mode: ViMode,
+    count: Option<usize>,
+    operator: Option<Operator>,
+}
+
+impl Default for ViState {
+    fn default() -> Self {
+        Self {
+            mode: ViMode::Insert,
+            count: None,
+            operator: None,
+        }
+    }
+}
+
+impl ViState {
+    fn reset_pending(&mut self) {
+        self.count = None;
+        self.operator = None;
+    }
+
+    fn with_mode(mut self, mode: ViMode) -> Self {
+        self.mode = mode;
+        self.reset_pending();
+        self
+    }
+}
+
+/// A command resolved from the vi keymap.  Applying it replaces the
+/// modal state of the editor and then performs its effect on the line.
+#[derive(Debug, Clone)]
+pub struct ViCommand {
+    state: ViState,
+    effect: ViEffect,
+}
+
+#[derive(Debug, Clone)]
+enum ViEffect {
+    Action(Action),
+    /// Copy the text covered by the movement into the kill ring
+    Copy(Movement),
+    /// Copy the whole line into the kill ring
+    CopyLine,
+    /// Insert the most recently killed text after the cursor
+    PasteAfter,
+}
+
+fn command(state: ViState, effect: ViEffect) -> Action {
+    Action::Vi(Box::new(ViCommand { state, effect }))
+}
+
+/// Returns true if the movement ends after the cursor position,
+/// in which case a kill should leave the cursor where it is.
+fn is_forwards(movement: Movement) -> bool {
+    matches!(
+        movement,
+        Movement::ForwardChar(_)
+            | Movement::ForwardWord(_)
+            | Movement::ForwardLine(_)
+            | Movement::EndOfLine
+    )
+}
+
+fn kill_direction(movement: Movement) -> KillDirection {
+    if is_forwards(movement) {
+        KillDirection::Forwards
+    } else {
+        KillDirection::Backwards
+    }
+}
+
+impl<'term> LineEditor<'term> {
+    /// Returns the current vi mode.  This is only meaningful when
+    /// the host is using `EditMode::Vi`.
+    pub fn vi_mode(&self) -> ViMode {
+        self.vi.mode
+    }
+
+    /// Changes the vi mode.  This is useful for hosts that remap keys
+    /// via `LineEditorHost::resolve_action`.
+    pub fn set_vi_mode(&mut self, mode: ViMode) {
+        self.vi.mode = mode;
+        self.vi.reset_pending();
+    }
+
+    /// Resolves an event using the vi keymap.  This doesn't modify the
+    /// editor; any change to the modal state is carried by the returned
+    /// action and takes effect when it is applied.
+    pub(crate) fn resolve_vi_action(&self, event: &InputEvent) -> Option<Action> {
+        let mut state = self.vi.clone();
+
+        if state.mode == ViMode::Insert {
+            return match event {
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Escape,
+                    modifiers: Modifiers::NONE,
+                }) => Some(command(
+                    state.with_mode(ViMode::Normal),
+                    ViEffect::Action(Action::Move(Movement::BackwardChar(1))),
+                )),
+                _ => Self::resolve_emacs_action(event),
+            };
+        }
+
+        let (key, modifiers) = match event {
+            InputEvent::Key(KeyEvent { key, modifiers }) => (*key, *modifiers),
+            InputEvent::Paste(text) => {
+                state.reset_pending();
+                return Some(command(
+                    state,
+                    ViEffect::Action(Action::InsertText(1, text.clone())),
+                ));
+            }
+            _ => return None,
+        };
+
+        // Commands that work the same way regardless of pending state
+        match (key, modifiers) {
+            (KeyCode::Char('C'), Modifiers::CTRL) => return Some(Action::Cancel),
+            (KeyCode::Char('D'), Modifiers::CTRL) => return Some(Action::EndOfFile),
+            (KeyCode::Char('L'), Modifiers::CTRL) => return Some(Action::Repaint),
+            (KeyCode::Char('J'), Modifiers::CTRL)
+            | (KeyCode::Char('M'), Modifiers::CTRL)
+            | (KeyCode::Enter, Modifiers::NONE) => return Some(Action::AcceptLine),
+            (KeyCode::Escape, Modifiers::NONE) => {
+                state.reset_pending();
+                return Some(command(state, ViEffect::Action(Action::NoAction)));
+            }
+            _ => {}
+        }
+
+        let modifiers = modifiers - Modifiers::SHIFT;
+
+        if let (KeyCode::Char(c @ '0'..='9'), Modifiers::NONE) = (key, modifiers) {
+            // A leading zero is a motion rather than a count
+            if c != '0' || state.count.is_some() {
+                let digit = c.to_digit(10).unwrap() as usize;
+                state.count = Some(
+                    state
+                        .count
+                        .unwrap_or(0)
+                        .saturating_mul(10)
+                        .saturating_add(digit),
+                );
+                return Some(command(state, ViEffect::Action(Action::NoAction)));
+            }
+        }
+
+        let count = state.count.take().unwrap_or(1).max(1);
+
+        let movement = match (key, modifiers) {
+            (KeyCode::Char('h'), Modifiers::NONE)
+            | (KeyCode::LeftArrow, Modifiers::NONE)
+            | (KeyCode::ApplicationLeftArrow, Modifiers::NONE)
+            | (KeyCode::Backspace, Modifiers::NONE) => Some(Movement::BackwardChar(count)),
+            (KeyCode::Char('l'), Modifiers::NONE)
+            | (KeyCode::Char(' '), Modifiers::NONE)
+            | (KeyCode::RightArrow, Modifiers::NONE)
+            | (KeyCode::ApplicationRightArrow, Modifiers::NONE) => {
+                Some(Movement::ForwardChar(count))
+            }
+            (KeyCode::Char('w'), Modifiers::NONE) | (KeyCode::Char('W'), Modifiers::NONE) => {
+                Some(Movement::ForwardWord(count))
+            }
+            (KeyCode::Char('b'), Modifiers::NONE) | (KeyCode::Char('B'), Modifiers::NONE) => {
+                Some(Movement::BackwardWord(count))
+            }
+            (KeyCode::Char('0'), Modifiers::NONE)
+            | (KeyCode::Char('^'), Modifiers::NONE)
+            | (KeyCode::Home, Modifiers::NONE) => Some(Movement::StartOfLine),
+            (KeyCode::Char('$'), Modifiers::NONE) | (KeyCode::End, Modifiers::NONE) => {
+                Some(Movement::EndOfLine)
+            }
+            _ => None,
+        };
+
+        if let Some(movement) = movement {
+            return Some(match state.operator.take() {
+                None => command(state, ViEffect::Action(Action::Move(movement))),
+                Some(op) => Self::resolve_operator(state, op, movement),
+            });
+        }
+
+        // The operator keys; doubling them applies to the whole line
+        let operator = match (key, modifiers) {
+            (KeyCode::Char('d'), Modifiers::NONE) => Some(Operator::Delete),
+            (KeyCode::Char('c'), Modifiers::NONE) => Some(Operator::Change),
+            (KeyCode::Char('y'), Modifiers::NONE) => Some(Operator::Yank),
+            _ => None,
+        };
+        if let Some(operator) = operator {
+            return Some(match state.operator.take() {
+                Some(pending) if pending == operator => {
+                    Self::resolve_operator_on_line(state, operator)
+                }
+                _ => {
+                    state.operator = Some(operator);
+                    // Preserve the count for the motion that follows
+                    state.count = Some(count);
+                    command(state, ViEffect::Action(Action::NoAction))
+                }
+            });
+        }
+
+        // Anything else cancels a pending operator
+        state.operator = None;
+
+        let (state, effect) = match (key, modifiers) {
+            (KeyCode::Char('i'), Modifiers::NONE) => (
+                state.with_mode(ViMode::Insert),
+                ViEffect::Action(Action::NoAction),
+            ),
+            (KeyCode::Char('a'), Modifiers::NONE) => (
+                state.with_mode(ViMode::Insert),
+                ViEffect::Action(Action::Move(Movement::ForwardChar(1))),
+            ),
+            (KeyCode::Char('I'), Modifiers::NONE) => (
+                state.with_mode(ViMode::Insert),
+                ViEffect::Action(Action::Move(Movement::StartOfLine)),
+            ),
+            (KeyCode::Char('A'), Modifiers::NONE) => (
+                state.with_mode(ViMode::Insert),
+                ViEffect::Action(Action::Move(Movement::EndOfLine)),
+            ),
+            (KeyCode::Char('x'), Modifiers::NONE) | (KeyCode::Delete, Modifiers::NONE) => (
+                state,
+                ViEffect::Action(Action::KillAndMove(
+                    Movement::ForwardChar(count),
+                    Movement::None,
+                )),
+            ),
+            (KeyCode::Char('X'), Modifiers::NONE) => (
+                state,
+                ViEffect::Action(Action::Kill(Movement::BackwardChar(count))),
+            ),
+            (KeyCode::Char('D'), Modifiers::NONE) => {
+                (state, ViEffect::Action(Action::Kill(Movement::EndOfLine)))
+            }
+            (KeyCode::Char('C'), Modifiers::NONE) => (
+                state.with_mode(ViMode::Insert),
+                ViEffect::Action(Action::Kill(Movement::EndOfLine)),
+            ),
+            (KeyCode::Char('s'), Modifiers::NONE) => (
+                state.with_mode(ViMode::Insert),
+                ViEffect::Action(Action::KillAndMove(
+                    Movement::ForwardChar(count),
+                    Movement::None,
+                )),
+            ),
+            (KeyCode::Char('S'), Modifiers::NONE) => {
+                return Some(Self::resolve_operator_on_line(state, Operator::Change));
+            }
+            (KeyCode::Char('p'), Modifiers::NONE) => (state, ViEffect::PasteAfter),
+            (KeyCode::Char('P'), Modifiers::NONE) => (state, ViEffect::Action(Action::Yank)),
+            (KeyCode::Char('u'), Modifiers::NONE) => (state, ViEffect::Action(Action::Undo)),
+            (KeyCode::Char('R'), Modifiers::CTRL) => (state, ViEffect::Action(Action::Redo)),
+            (KeyCode::Char('k'), Modifiers::NONE)
+            | (KeyCode::UpArrow, Modifiers::NONE)
+            | (KeyCode::ApplicationUpArrow, Modifiers::NONE) => {
+                (state, ViEffect::Action(Action::HistoryPrevious))
+            }
+            (KeyCode::Char('j'), Modifiers::NONE)
+            | (KeyCode::DownArrow, Modifiers::NONE)
+            | (KeyCode::ApplicationDownArrow, Modifiers::NONE) => {
+                (state, ViEffect::Action(Action::HistoryNext))
+            }
+            (KeyCode::Char('/'), Modifiers::NONE) => {
+                (state, ViEffect::Action(Action::HistoryIncSearchBackwards))
+            }
+            // Normal mode never inserts unbound keys into the line
+            _ => (state, ViEffect::Action(Action::NoAction)),
+        };
+        Some(command(state, effect))
+    }
+
+    fn resolve_operator(state: ViState, operator: Operator, movement: Movement) -> Action {
+        let kill = if is_forwards(movement) {
+            Action::KillAndMove(movement, Movement::None)
+        } else {
+            Action::Kill(movement)
+        };
+        match operator {
+            Operator::Delete => command(state, ViEffect::Action(kill)),
+            Operator::Change => command(state.with_mode(ViMode::Insert), ViEffect::Action(kill)),
+            Operator::Yank => command(state, ViEffect::Copy(movement)),
+        }
+    }
+
+    fn resolve_operator_on_line(state: ViState, operator: Operator) -> Action {
+        match operator {
+            Operator::Delete => command(state, ViEffect::Action(Action::KillWholeLine)),
+            Operator::Change => command(
+                state.with_mode(ViMode::Insert),
+                ViEffect::Action(Action::KillWholeLine),
+            ),
+            Operator::Yank => command(state, ViEffect::CopyLine),
+        }
+    }
+
+    pub(crate) fn apply_vi_command(
+        &mut self,
+        host: &mut dyn LineEditorHost,
+        command: ViCommand,
+    ) -> Result<()> {
+        let ViCommand { state, effect } = command;
+        self.vi = state;
+        let action = match effect {
+            ViEffect::Action(action) => action,
+            ViEffect::Copy(movement) => {
+                let target = self.eval_movement(movement);
+                let range = target.min(self.cursor)..target.max(self.cursor);
+                self.kill_ring
+                    .push(&self.line[range], kill_direction(movement), false);
+                Action::NoAction
+            }
+            ViEffect::CopyLine => {
+                self.kill_ring
+                    .push(&self.line, KillDirection::Forwards, false);
+                Action::NoAction
+            }
+            ViEffect::PasteAfter => {
+                if !self.line.is_empty() {
+                    self.cursor = self.eval_movement(Movement::ForwardChar(1));
+                }
+                Action::Yank
+            }
+        };
+        self.apply_action(host, action)
+    }
+}
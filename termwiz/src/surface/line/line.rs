@@ -1,9 +1,11 @@
 use crate::cell::{Cell, CellAttributes, SemanticType, UnicodeVersion};
 use crate::cellcluster::CellCluster;
+use crate::highlight::{Rule as HighlightRule, Style as HighlightStyle};
 use crate::hyperlink::Rule;
 use crate::surface::line::cellref::CellRef;
 use crate::surface::line::clusterline::ClusteredLine;
 use crate::surface::line::linebits::LineBits;
+use crate::surface::line::logicaltext::LogicalLineText;
 use crate::surface::line::storage::{CellStorage, VisibleCellIter};
 use crate::surface::line::vecstorage::{VecStorage, VecStorageIter};
 use crate::surface::{Change, SequenceNo, SEQ_ZERO};
@@ -38,6 +40,10 @@ pub struct Line {
     zones: Vec<ZoneRange>,
     seqno: SequenceNo,
     bits: LineBits,
+    /// The ranges of cells that match the highlight rules, along with
+    /// the style to overlay on them when clustering the line.
+    #[cfg_attr(feature = "use_serde", serde(skip))]
+    highlights: Vec<(Range<usize>, HighlightStyle)>,
     #[cfg_attr(feature = "use_serde", serde(skip))]
     appdata: Mutex<Option<Weak<dyn Any + Send + Sync>>>,
 }
@@ -49,6 +55,7 @@ impl Clone for Line {
             zones: self.zones.clone(),
             seqno: self.seqno,
             bits: self.bits,
+            highlights: self.highlights.clone(),
             appdata: Mutex::new(self.appdata.lock().unwrap().clone()),
         }
     }
@@ -70,6 +77,7 @@ impl Line {
             cells: CellStorage::V(VecStorage::new(cells)),
             seqno,
             zones: vec![],
+            highlights: vec![],
             appdata: Mutex::new(None),
         }
     }
@@ -81,6 +89,7 @@ impl Line {
             cells: CellStorage::V(VecStorage::new(cells)),
             seqno,
             zones: vec![],
+            highlights: vec![],
             appdata: Mutex::new(None),
         }
     }
@@ -95,6 +104,7 @@ impl Line {
             cells: CellStorage::C(ClusteredLine::new()),
             seqno,
             zones: vec![],
+            highlights: vec![],
             appdata: Mutex::new(None),
         }
     }
@@ -113,6 +123,7 @@ impl Line {
         for cell in self.visible_cells() {
             cell.compute_shape_hash(&mut hasher);
         }
+        self.highlights.hash(&mut hasher);
         hasher.finish128().as_bytes()
     }
 
@@ -125,6 +136,7 @@ impl Line {
             cells: CellStorage::V(VecStorage::new(cells)),
             seqno,
             zones: vec![],
+            highlights: vec![],
             appdata: Mutex::new(None),
         }
     }
@@ -151,6 +163,7 @@ impl Line {
             bits: LineBits::NONE,
             seqno,
             zones: vec![],
+            highlights: vec![],
             appdata: Mutex::new(None),
         }
     }
@@ -270,10 +283,13 @@ impl Line {
 
     /// Annotate the line with the sequence number of a change.
     /// This can be used together with Line::changed_since to
-    /// manage caching and rendering
+    /// manage caching and rendering.
+    /// This also discards the highlights, so that the highlight
+    /// rules are applied again to the changed line.
     #[inline]
     pub fn update_last_change_seqno(&mut self, seqno: SequenceNo) {
         self.seqno = self.seqno.max(seqno);
+        self.clear_highlights();
     }
 
     /// Check whether the line is single-width.
@@ -598,6 +614,63 @@ impl Line {
         }
     }
 
+    /// Scan through a logical line that is comprised of an array of
+    /// physical lines and record the cells that match the highlight
+    /// rules.  The style of the matching rules is overlaid on those
+    /// cells by Line::cluster; the cell attributes are not changed.
+    /// This function will only make changes if one of the lines has been
+    /// modified since the last time this function was called.
+    /// This function does not remember the values of the `rules` slice, so it
+    /// is the responsibility of the caller to call `clear_highlights`
+    /// if it wishes to call this function with different `rules`.
+    ///
+    /// This function will call Line::clear_appdata on lines where
+    /// highlights are adjusted.
+    pub fn apply_highlight_rules(rules: &[HighlightRule], logical_line: &mut [&mut Line]) {
+        if rules.is_empty() || logical_line.is_empty() {
+            return;
+        }
+
+        if logical_line
+            .iter()
+            .all(|line| line.bits.contains(LineBits::SCANNED_HIGHLIGHTS))
+        {
+            return;
+        }
+
+        let text = LogicalLineText::new(logical_line);
+        let mut highlights = vec![vec![]; logical_line.len()];
+        for m in HighlightRule::match_highlights(text.text(), rules) {
+            for (line_idx, cell_idx) in text.cells_in_range(m.range) {
+                let spans: &mut Vec<(Range<usize>, HighlightStyle)> = &mut highlights[line_idx];
+                match spans.last_mut() {
+                    Some((range, style)) if range.end == cell_idx && *style == m.style => {
+                        range.end = cell_idx + 1;
+                    }
+                    _ => spans.push((cell_idx..cell_idx + 1, m.style)),
+                }
+            }
+        }
+
+        for (line, highlights) in logical_line.iter_mut().zip(highlights) {
+            line.bits.set(LineBits::SCANNED_HIGHLIGHTS, true);
+            if line.highlights != highlights {
+                line.highlights = highlights;
+                line.clear_appdata();
+            }
+        }
+    }
+
+    /// Discards the highlights, so that the next call to
+    /// `apply_highlight_rules` matches the line again.
+    pub fn clear_highlights(&mut self) {
+        self.bits.remove(LineBits::SCANNED_HIGHLIGHTS);
+        if !self.highlights.is_empty() {
+            self.highlights.clear();
+            self.clear_appdata();
+        }
+    }
+
     /// Returns true if the line contains a hyperlink
     #[inline]
     pub fn has_hyperlink(&self) -> bool {
@@ -626,11 +699,13 @@ impl Line {
         // <https://github.com/wez/wezterm/issues/2355>
         let idx = idx.min(my_cells.len());
         let cells = my_cells.split_off(idx);
+        self.clear_highlights();
         Self {
-            bits: self.bits,
+            bits: self.bits & !LineBits::SCANNED_HIGHLIGHTS,
             cells: CellStorage::V(VecStorage::new(cells)),
             seqno,
             zones: vec![],
+            highlights: vec![],
             appdata: Mutex::new(None),
         }
     }
@@ -715,6 +790,7 @@ impl Line {
             cells: CellStorage::V(VecStorage::new(cells)),
             seqno: self.current_seqno(),
             zones: vec![],
+            highlights: vec![],
             appdata: Mutex::new(None),
         }
     }
@@ -1016,8 +1092,34 @@ impl Line {
             .find(|cell| cell.cell_index() == cell_index)
     }
 
+    /// Computes the clusters of the line, with the style of any
+    /// highlights overlaid on the attributes of the cells.
     pub fn cluster(&self, bidi_hint: Option<ParagraphDirectionHint>) -> Vec<CellCluster> {
-        CellCluster::make_cluster(self.len(), self.visible_cells(), bidi_hint)
+        if self.highlights.is_empty() {
+            return CellCluster::make_cluster(self.len(), self.visible_cells(), bidi_hint);
+        }
+
+        let cells: Vec<(usize, Cell)> = self
+            .visible_cells()
+            .map(|cell| {
+                let cell_index = cell.cell_index();
+                let mut cell = cell.as_cell();
+                for (range, style) in &self.highlights {
+                    if range.contains(&cell_index) {
+                        style.apply_to(cell.attrs_mut());
+                    }
+                }
+                (cell_index, cell)
+            })
+            .collect();
+        CellCluster::make_cluster(
+            self.len(),
+            cells.iter().map(|(cell_index, cell)| CellRef::CellRef {
+                cell_index: *cell_index,
+                cell,
+            }),
+            bidi_hint,
+        )
     }
 
     fn make_cells(&mut self) {
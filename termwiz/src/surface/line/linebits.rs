@@ -49,5 +49,8 @@ bitflags! {
         /// Otherwise, the auto-detect direction is used, falling back
         /// to the direction specified by the RTL bit.
         const AUTO_DETECT_DIRECTION = 1<<8;
+
+        /// true if we have applied the highlight rules
+        const SCANNED_HIGHLIGHTS = 1<<9;
     }
 }
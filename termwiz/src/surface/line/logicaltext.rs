@@ -0,0 +1,48 @@
+use crate::surface::line::Line;
+use std::ops::Range;
+
+/// The text of a logical line that is comprised of an array of
+/// physical lines, along with a mapping from byte offsets in
+/// that text back to the cells that produced it.
+/// This is used to match regexes against a logical line and
+/// then act on the cells that correspond to the matches.
+#[derive(Debug, Default)]
+pub struct LogicalLineText {
+    text: String,
+    /// (line index, cell index, byte offset into text), in text order
+    cells: Vec<(usize, usize, usize)>,
+}
+
+impl LogicalLineText {
+    pub fn new(logical_line: &[&mut Line]) -> Self {
+        let mut result = Self::default();
+        for (line_idx, line) in logical_line.iter().enumerate() {
+            for cell in line.visible_cells() {
+                result
+                    .cells
+                    .push((line_idx, cell.cell_index(), result.text.len()));
+                result.text.push_str(cell.str());
+            }
+        }
+        result
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the (line index, cell index) of each cell whose text
+    /// starts within the provided byte range of the text.
+    pub fn cells_in_range(
+        &self,
+        range: Range<usize>,
+    ) -> impl Iterator<Item = (usize, usize)> + Clone + '_ {
+        let start = self
+            .cells
+            .partition_point(|(_, _, offset)| *offset < range.start);
+        self.cells[start..]
+            .iter()
+            .take_while(move |(_, _, offset)| *offset < range.end)
+            .map(|(line_idx, cell_idx, _)| (*line_idx, *cell_idx))
+    }
+}
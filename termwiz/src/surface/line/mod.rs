@@ -2,9 +2,11 @@ mod cellref;
 mod clusterline;
 mod line;
 mod linebits;
+mod logicaltext;
 mod storage;
 mod test;
 mod vecstorage;
 
 pub use cellref::CellRef;
 pub use line::{DoubleClickRange, Line};
+pub use logicaltext::LogicalLineText;
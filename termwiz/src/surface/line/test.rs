@@ -2,8 +2,11 @@
 
 use super::*;
 use crate::cell::{Cell, CellAttributes};
+use crate::color::ColorAttribute;
+use crate::highlight::{Rule as HighlightRule, Style as HighlightStyle};
 use crate::hyperlink::{Hyperlink, Rule};
 use crate::surface::line::clusterline::ClusteredLine;
+use crate::surface::line::storage::CellStorage;
 use crate::surface::SEQ_ZERO;
 use k9::assert_equal as assert_eq;
 use std::sync::Arc;
@@ -291,6 +294,7 @@ Line {
     bits: LineBits(
         0x0,
     ),
+    highlights: [],
     appdata: Mutex {
         data: None,
         poisoned: false,
@@ -308,6 +312,60 @@ fn bold() -> CellAttributes {
     attr
 }
 
+#[test]
+fn highlights() {
+    let rules = vec![HighlightRule {
+        style: HighlightStyle {
+            background: Some((255, 0, 0).into()),
+            ..HighlightStyle::default()
+        },
+        ..HighlightRule::new(r"PROJ-\d+").unwrap()
+    }];
+    let red = ColorAttribute::TrueColorWithDefaultFallback((255, 0, 0).into());
+    let highlighted = |line: &Line| -> Vec<usize> {
+        line.cluster(None)
+            .into_iter()
+            .filter(|cluster| cluster.attrs.background() == red)
+            .flat_map(|cluster| cluster.first_cell_idx..cluster.first_cell_idx + cluster.width)
+            .collect()
+    };
+
+    let mut line1 =
+        Line::from_text_with_wrapped_last_col("see PROJ-", &CellAttributes::default(), 1);
+    let mut line2: Line = "123 ok".into();
+    line1.compress_for_scrollback();
+
+    Line::apply_highlight_rules(&rules, &mut [&mut line1, &mut line2]);
+    assert_eq!(highlighted(&line1), vec![4, 5, 6, 7, 8]);
+    assert_eq!(highlighted(&line2), vec![0, 1, 2]);
+    assert!(matches!(&line1.cells, CellStorage::C(_)));
+    assert_eq!(line1.current_seqno(), 1);
+    // The highlights are an overlay: the cells keep their attributes
+    assert!(line1
+        .visible_cells()
+        .all(|cell| cell.attrs().background() != red));
+
+    // Modifying a line discards its highlights until they are re-applied
+    line2.set_cell(2, Cell::new('x', CellAttributes::default()), 2);
+    assert_eq!(highlighted(&line2), vec![]);
+    Line::apply_highlight_rules(&rules, &mut [&mut line1, &mut line2]);
+    assert_eq!(highlighted(&line1), vec![4, 5, 6, 7, 8]);
+    assert_eq!(highlighted(&line2), vec![0, 1]);
+
+    // Changing the rules takes effect once the highlights are cleared
+    let rules = vec![HighlightRule {
+        style: rules[0].style,
+        ..HighlightRule::new("see").unwrap()
+    }];
+    Line::apply_highlight_rules(&rules, &mut [&mut line1, &mut line2]);
+    assert_eq!(highlighted(&line1), vec![4, 5, 6, 7, 8]);
+    line1.clear_highlights();
+    line2.clear_highlights();
+    Line::apply_highlight_rules(&rules, &mut [&mut line1, &mut line2]);
+    assert_eq!(highlighted(&line1), vec![0, 1, 2]);
+    assert_eq!(highlighted(&line2), vec![]);
+}
+
 #[test]
 fn cluster_representation_attributes() {
     let line = Line::from_cells(
@@ -603,6 +661,7 @@ Line {
     bits: LineBits(
         0x0,
     ),
+    highlights: [],
     appdata: Mutex {
         data: None,
         poisoned: false,
@@ -0,0 +1,266 @@
+//! An async adapter for `UnixTerminal`.
+//!
+//! `Terminal::poll_input` is a blocking interface, which makes it awkward
+//! to drive a terminal from an async runtime.  `AsyncTerminal` registers
+//! the same descriptors that `poll_input` waits on (the tty, the SIGWINCH
+//! self-pipe and the waker pipe) with the `async-io` reactor, and exposes
+//! the parsed `InputEvent`s as a `Stream`.  Output can be rendered without
+//! blocking the executor via `AsyncTerminal::render_async` and
+//! `BufferedTerminal::flush_async`.
+//!
+//! This module is available when the `async` feature is enabled.
+use crate::caps::probed::ProbeCapabilities;
+use crate::input::InputEvent;
+use crate::surface::Change;
+use crate::terminal::unix::PolledInput;
+use crate::terminal::{Blocking, ScreenSize, Terminal, TerminalWaker, UnixTerminal};
+use crate::Result;
+use async_io::Async;
+use filedescriptor::FileDescriptor;
+use futures_core::Stream;
+use std::io::Write;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Wraps a `UnixTerminal` so that it can be used from async code.
+///
+/// While wrapped, the underlying tty is placed into non-blocking mode;
+/// the synchronous `Terminal` methods remain usable and temporarily
+/// switch back to blocking mode for the duration of the call.
+/// Use `into_inner` to recover the `UnixTerminal` and restore
+/// blocking mode.
+pub struct AsyncTerminal {
+    terminal: Option<UnixTerminal>,
+    input: Async<FileDescriptor>,
+    sigwinch: Async<FileDescriptor>,
+    wake: Async<FileDescriptor>,
+    output: Async<FileDescriptor>,
+}
+
+impl AsyncTerminal {
+    pub fn new(mut terminal: UnixTerminal) -> Result<Self> {
+        let [input, sigwinch, wake, output] = terminal.dup_async_sources()?;
+        terminal.set_blocking(Blocking::DoNotWait)?;
+        Ok(Self {
+            terminal: Some(terminal),
+            input: Async::new(input)?,
+            sigwinch: Async::new(sigwinch)?,
+            wake: Async::new(wake)?,
+            output: Async::new(output)?,
+        })
+    }
+
+    /// Returns the wrapped terminal, restoring blocking mode
+    pub fn into_inner(mut self) -> Result<UnixTerminal> {
+        let mut terminal = self.terminal.take().expect("terminal present until drop");
+        terminal.set_blocking(Blocking::Wait)?;
+        Ok(terminal)
+    }
+
+    fn terminal(&mut self) -> &mut UnixTerminal {
+        self.terminal.as_mut().expect("terminal present until drop")
+    }
+
+    /// Runs `func` with the tty in blocking mode, so that the synchronous
+    /// `Terminal` methods behave as they normally would.
+    fn with_blocking<R>(&mut self, func: impl FnOnce(&mut UnixTerminal) -> Result<R>) -> Result<R> {
+        let terminal = self.terminal();
+        terminal.set_blocking(Blocking::Wait)?;
+        let result = func(terminal);
+        terminal.set_blocking(Blocking::DoNotWait)?;
+        result
+    }
+
+    /// Waits for the next input event.
+    /// This is a convenience for callers that would otherwise need
+    /// a `StreamExt` implementation just to call `next`.
+    /// Returns an `UnexpectedEof` error once the input has reached
+    /// end of file.
+    pub async fn next_input(&mut self) -> Result<InputEvent> {
+        std::future::poll_fn(|cx| match Pin::new(&mut *self).poll_next(cx) {
+            Poll::Ready(Some(result)) => Poll::Ready(result),
+            Poll::Ready(None) => Poll::Ready(Err(std::io::Error::from(
+                std::io::ErrorKind::UnexpectedEof,
+            )
+            .into())),
+            Poll::Pending => Poll::Pending,
+        })
+        .await
+    }
+
+    /// Render a series of changes to the terminal output without
+    /// blocking the executor if the tty is not ready to accept
+    /// more data.
+    pub async fn render_async(&mut self, changes: &[Change]) -> Result<()> {
+        let data = self.terminal().render_to_vec(changes)?;
+        self.write_all_async(&data).await
+    }
+
+    /// Writes out any output that was buffered by the synchronous
+    /// `render` method.
+    pub async fn flush_async(&mut self) -> Result<()> {
+        self.render_async(&[]).await
+    }
+
+    async fn write_all_async(&mut self, mut data: &[u8]) -> Result<()> {
+        while !data.is_empty() {
+            let n = self.output.write_with_mut(|fd| fd.write(data)).await?;
+            if n == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::WriteZero).into());
+            }
+            data = &data[n..];
+        }
+        Ok(())
+    }
+}
+
+impl Stream for AsyncTerminal {
+    type Item = Result<InputEvent>;
+
+    /// The stream ends once the input reaches end of file, which
+    /// happens when the tty is hung up.
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.terminal().poll_input_or_eof(Some(Duration::ZERO)) {
+                Ok(PolledInput::Event(event)) => return Poll::Ready(Some(Ok(event))),
+                Ok(PolledInput::Empty) => {}
+                Ok(PolledInput::Eof) => return Poll::Ready(None),
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+
+            // Nothing was immediately available; ask the reactor to wake
+            // us when any of the sources become readable.  If one of them
+            // already became readable since we last looked, go around
+            // again and let poll_input consume it.
+            let mut ready = false;
+            for source in [&self.input, &self.sigwinch, &self.wake] {
+                match source.poll_readable(cx) {
+                    Poll::Ready(Ok(())) => ready = true,
+                    Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err.into()))),
+                    Poll::Pending => {}
+                }
+            }
+            if !ready {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+impl Terminal for AsyncTerminal {
+    fn set_raw_mode(&mut self) -> Result<()> {
+        self.with_blocking(|t| t.set_raw_mode())
+    }
+
+    fn set_cooked_mode(&mut self) -> Result<()> {
+        self.with_blocking(|t| t.set_cooked_mode())
+    }
+
+    fn enter_alternate_screen(&mut self) -> Result<()> {
+        self.with_blocking(|t| t.enter_alternate_screen())
+    }
+
+    fn exit_alternate_screen(&mut self) -> Result<()> {
+        self.with_blocking(|t| t.exit_alternate_screen())
+    }
+
+    fn get_screen_size(&mut self) -> Result<ScreenSize> {
+        self.terminal().get_screen_size()
+    }
+
+    /// Probing relies on blocking reads from the tty, so it is not
+    /// available while the terminal is in async mode.  Probe the
+    /// `UnixTerminal` before wrapping it instead.
+    fn probe_capabilities(&mut self) -> Option<ProbeCapabilities> {
+        None
+    }
+
+    fn set_screen_size(&mut self, size: ScreenSize) -> Result<()> {
+        self.terminal().set_screen_size(size)
+    }
+
+    fn render(&mut self, changes: &[Change]) -> Result<()> {
+        self.with_blocking(|t| t.render(changes))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.with_blocking(|t| t.flush())
+    }
+
+    fn poll_input(&mut self, wait: Option<Duration>) -> Result<Option<InputEvent>> {
+        self.terminal().poll_input(wait)
+    }
+
+    fn waker(&self) -> TerminalWaker {
+        self.terminal
+            .as_ref()
+            .expect("terminal present until drop")
+            .waker()
+    }
+}
+
+impl Drop for AsyncTerminal {
+    fn drop(&mut self) {
+        if let Some(mut terminal) = self.terminal.take() {
+            // Restore blocking mode before UnixTerminal's own Drop
+            // impl writes out the sequences that reset the tty.
+            terminal.set_blocking(Blocking::Wait).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::caps::Capabilities;
+    use crate::error::InternalError;
+    use crate::input::{KeyCode, KeyEvent, Modifiers};
+    use filedescriptor::Pipe;
+
+    /// Returns an AsyncTerminal that reads its input from the returned
+    /// pipe. The output goes to a pty, as the terminal needs a tty to
+    /// save and restore its settings.
+    fn pipe_terminal() -> (AsyncTerminal, FileDescriptor, nix::pty::OpenptyResult) {
+        let pipe = Pipe::new().unwrap();
+        let pty = nix::pty::openpty(None, None).unwrap();
+        let caps = Capabilities::new_with_hints(Default::default()).unwrap();
+        let terminal = UnixTerminal::new_with(caps, &pipe.read, &pty.slave).unwrap();
+        (AsyncTerminal::new(terminal).unwrap(), pipe.write, pty)
+    }
+
+    #[test]
+    fn stream_ends_at_eof() {
+        let (mut terminal, mut write, _pty) = pipe_terminal();
+        write.write_all(b"a").unwrap();
+        drop(write);
+
+        let mut events = vec![];
+        async_io::block_on(async {
+            while let Some(event) =
+                std::future::poll_fn(|cx| Pin::new(&mut terminal).poll_next(cx)).await
+            {
+                events.push(event.unwrap());
+            }
+        });
+        assert_eq!(
+            events,
+            vec![InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('a'),
+                modifiers: Modifiers::NONE,
+            })]
+        );
+    }
+
+    #[test]
+    fn next_input_fails_at_eof() {
+        let (mut terminal, write, _pty) = pipe_terminal();
+        drop(write);
+
+        let err = async_io::block_on(terminal.next_input()).unwrap_err();
+        assert!(matches!(
+            err.0,
+            InternalError::Io(ref err) if err.kind() == std::io::ErrorKind::UnexpectedEof
+        ));
+    }
+}
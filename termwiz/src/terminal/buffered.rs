@@ -106,6 +106,22 @@ impl<T: Terminal> BufferedTerminal<T> {
     }
 }
 
+#[cfg(all(unix, feature = "async"))]
+impl BufferedTerminal<crate::terminal::asynchronous::AsyncTerminal> {
+    /// Like `flush`, but writes the changes to the terminal without
+    /// blocking the executor.
+    pub async fn flush_async(&mut self) -> Result<()> {
+        {
+            let (seq, changes) = self.surface.get_changes(self.seqno);
+            self.seqno = 0;
+            self.terminal.render_async(&changes).await?;
+            self.seqno = seq;
+        }
+        self.surface.flush_changes_older_than(self.seqno);
+        Ok(())
+    }
+}
+
 impl<T: Terminal> Deref for BufferedTerminal<T> {
     type Target = Surface;
 
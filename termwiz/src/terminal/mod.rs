@@ -16,6 +16,9 @@ pub mod windows;
 
 pub mod buffered;
 
+#[cfg(all(unix, feature = "async"))]
+pub mod asynchronous;
+
 #[cfg(unix)]
 pub use self::unix::{UnixTerminal, UnixTerminalWaker as TerminalWaker};
 #[cfg(windows)]
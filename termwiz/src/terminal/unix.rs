@@ -27,6 +27,15 @@ use crate::terminal::{cast, Blocking, ScreenSize, Terminal};
 
 const BUF_SIZE: usize = 4096;
 
+/// The outcome of `UnixTerminal::poll_input_or_eof`
+pub(crate) enum PolledInput {
+    Event(InputEvent),
+    /// No input is available yet
+    Empty,
+    /// The input reached end of file
+    Eof,
+}
+
 pub enum Purge {
     InputQueue,
     OutputQueue,
@@ -267,6 +276,151 @@ impl UnixTerminal {
         Self::new_with(caps, &file, &file)
     }
 
+    /// Returns duplicates of the descriptors that `poll_input` waits on,
+    /// along with the output descriptor, so that an async reactor can
+    /// be told about them.  The order is: tty input, sigwinch pipe,
+    /// wake pipe, tty output.
+    #[cfg(feature = "async")]
+    pub(crate) fn dup_async_sources(&self) -> Result<[FileDescriptor; 4]> {
+        Ok([
+            FileDescriptor::dup(&self.read.fd)?,
+            FileDescriptor::dup(&self.sigwinch_pipe)?,
+            FileDescriptor::dup(&self.wake_pipe)?,
+            FileDescriptor::dup(&self.write.fd)?,
+        ])
+    }
+
+    /// Switches both the input and output handles between blocking
+    /// and non-blocking modes.
+    #[cfg(feature = "async")]
+    pub(crate) fn set_blocking(&mut self, blocking: Blocking) -> Result<()> {
+        self.read.set_blocking(blocking)?;
+        self.write
+            .fd
+            .set_non_blocking(blocking == Blocking::DoNotWait)?;
+        Ok(())
+    }
+
+    /// Like `Terminal::poll_input`, but distinguishes the input having
+    /// reached end of file (eg: the tty was hung up) from there being
+    /// no input available yet.
+    pub(crate) fn poll_input_or_eof(&mut self, wait: Option<Duration>) -> Result<PolledInput> {
+        if let Some(event) = self.input_queue.pop_front() {
+            return Ok(PolledInput::Event(event));
+        }
+
+        // Some unfortunately verbose code here.  In order to safely hook and process
+        // SIGWINCH we need to use the self-pipe trick to deliver signals to a pipe
+        // so that we can use poll(2) to wait for events on both the tty input and
+        // the sigwinch pipe at the same time.  In theory we could do away with this
+        // and use sigaction to register SIGWINCH without SA_RESTART set; that way
+        // we could do a blocking read and have it get EINTR on a resize.
+        // Doing such a thing may introduce more problems for other components in
+        // the rust crate ecosystem if they're not ready to deal with EINTR, so
+        // we opt to take on the complexity here to make things overall easier to
+        // integrate.
+
+        let mut pfd = [
+            pollfd {
+                fd: self.sigwinch_pipe.as_raw_fd(),
+                events: POLLIN,
+                revents: 0,
+            },
+            pollfd {
+                fd: self.read.fd.as_raw_fd(),
+                events: POLLIN,
+                revents: 0,
+            },
+            pollfd {
+                fd: self.wake_pipe.as_raw_fd(),
+                events: POLLIN,
+                revents: 0,
+            },
+        ];
+
+        if let Err(err) = poll(&mut pfd, wait) {
+            return match err
+                .source()
+                .ok_or_else(|| anyhow::anyhow!("error has no source! {:#}", err))?
+                .downcast_ref::<std::io::Error>()
+            {
+                Some(err) => {
+                    if err.kind() == ErrorKind::Interrupted {
+                        // SIGWINCH may have been the source of the interrupt.
+                        // Check for that now so that we reduce the latency of
+                        // processing the resize
+                        if let Some(resize) = self.caught_sigwinch()? {
+                            Ok(PolledInput::Event(resize))
+                        } else {
+                            Ok(PolledInput::Empty)
+                        }
+                    } else {
+                        bail!("poll(2) error: {}", err)
+                    }
+                }
+                None => bail!("poll(2) error: {}", err),
+            };
+        };
+
+        if pfd[0].revents != 0 {
+            // SIGWINCH received via our pipe?
+            if let Some(resize) = self.caught_sigwinch()? {
+                return Ok(PolledInput::Event(resize));
+            }
+        }
+
+        if pfd[1].revents != 0 {
+            let mut buf = [0u8; 64];
+            match self.read.read(&mut buf) {
+                Ok(n) => {
+                    let input_queue = &mut self.input_queue;
+                    self.input_parser.parse(
+                        &buf[0..n],
+                        |evt| input_queue.push_back(evt),
+                        n == buf.len(),
+                    );
+                    return Ok(match self.input_queue.pop_front() {
+                        Some(event) => PolledInput::Event(event),
+                        // A zero byte read means that we hit end of file;
+                        // the parse above flushed anything it was holding
+                        None if n == 0 => PolledInput::Eof,
+                        None => PolledInput::Empty,
+                    });
+                }
+                Err(ref e)
+                    if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => bail!("failed to read input {}", e),
+            }
+        }
+
+        if pfd[2].revents != 0 {
+            let mut buf = [0u8; 64];
+            if let Ok(n) = self.wake_pipe.read(&mut buf) {
+                if n > 0 {
+                    return Ok(PolledInput::Event(InputEvent::Wake));
+                }
+            }
+        }
+
+        Ok(PolledInput::Empty)
+    }
+
+    /// Renders `changes` into a buffer rather than to the tty.
+    /// Any output that was previously buffered in the write handle
+    /// is included at the front of the returned data.
+    #[cfg(feature = "async")]
+    pub(crate) fn render_to_vec(&mut self, changes: &[Change]) -> Result<Vec<u8>> {
+        let size = self.write.get_size()?;
+        let mut out = RenderBuffer {
+            // Drain rather than take, so that the write handle keeps its
+            // capacity and continues to buffer subsequent writes
+            buf: self.write.write_buffer.drain(..).collect(),
+            size: (size.ws_col as usize, size.ws_row as usize),
+        };
+        self.renderer.render_to(changes, &mut out)?;
+        Ok(out.buf)
+    }
+
     /// Test whether we caught delivery of SIGWINCH.
     /// If so, yield an `InputEvent` with the current size of the tty.
     fn caught_sigwinch(&mut self) -> Result<Option<InputEvent>> {
@@ -291,12 +445,53 @@ impl UnixTerminal {
     }
 }
 
+/// Collects rendered output in memory so that it can be written
+/// out by something other than a blocking `write(2)`.
+#[cfg(feature = "async")]
+struct RenderBuffer {
+    buf: Vec<u8>,
+    size: (usize, usize),
+}
+
+#[cfg(feature = "async")]
+impl Write for RenderBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::result::Result<usize, IoError> {
+        self.buf.write(buf)
+    }
+
+    fn flush(&mut self) -> std::result::Result<(), IoError> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl RenderTty for RenderBuffer {
+    fn get_size_in_cells(&mut self) -> Result<(usize, usize)> {
+        Ok(self.size)
+    }
+}
+
 #[derive(Clone)]
 pub struct UnixTerminalWaker {
     pipe: Arc<Mutex<UnixStream>>,
 }
 
 impl UnixTerminalWaker {
+    /// Returns a waker that isn't associated with a terminal, together
+    /// with the receiving end of its pipe, which must be kept alive for
+    /// the waker to remain usable.  This is intended for use by `Terminal`
+    /// implementations in tests.
+    #[cfg(test)]
+    pub(crate) fn detached() -> std::result::Result<(Self, UnixStream), IoError> {
+        let (read, write) = UnixStream::pair()?;
+        read.set_nonblocking(true)?;
+        write.set_nonblocking(true)?;
+        let waker = Self {
+            pipe: Arc::new(Mutex::new(write)),
+        };
+        Ok((waker, read))
+    }
+
     pub fn wake(&self) -> std::result::Result<(), IoError> {
         let mut pipe = self.pipe.lock().unwrap();
         match pipe.write(b"W") {
@@ -408,98 +603,10 @@ impl Terminal for UnixTerminal {
     }
 
     fn poll_input(&mut self, wait: Option<Duration>) -> Result<Option<InputEvent>> {
-        if let Some(event) = self.input_queue.pop_front() {
-            return Ok(Some(event));
+        match self.poll_input_or_eof(wait)? {
+            PolledInput::Event(event) => Ok(Some(event)),
+            PolledInput::Empty | PolledInput::Eof => Ok(None),
         }
-
-        // Some unfortunately verbose code here.  In order to safely hook and process
-        // SIGWINCH we need to use the self-pipe trick to deliver signals to a pipe
-        // so that we can use poll(2) to wait for events on both the tty input and
-        // the sigwinch pipe at the same time.  In theory we could do away with this
-        // and use sigaction to register SIGWINCH without SA_RESTART set; that way
-        // we could do a blocking read and have it get EINTR on a resize.
-        // Doing such a thing may introduce more problems for other components in
-        // the rust crate ecosystem if they're not ready to deal with EINTR, so
-        // we opt to take on the complexity here to make things overall easier to
-        // integrate.
-
-        let mut pfd = [
-            pollfd {
-                fd: self.sigwinch_pipe.as_raw_fd(),
-                events: POLLIN,
-                revents: 0,
-            },
-            pollfd {
-                fd: self.read.fd.as_raw_fd(),
-                events: POLLIN,
-                revents: 0,
-            },
-            pollfd {
-                fd: self.wake_pipe.as_raw_fd(),
-                events: POLLIN,
-                revents: 0,
-            },
-        ];
-
-        if let Err(err) = poll(&mut pfd, wait) {
-            return match err
-                .source()
-                .ok_or_else(|| anyhow::anyhow!("error has no source! {:#}", err))?
-                .downcast_ref::<std::io::Error>()
-            {
-                Some(err) => {
-                    if err.kind() == ErrorKind::Interrupted {
-                        // SIGWINCH may have been the source of the interrupt.
-                        // Check for that now so that we reduce the latency of
-                        // processing the resize
-                        if let Some(resize) = self.caught_sigwinch()? {
-                            Ok(Some(resize))
-                        } else {
-                            Ok(None)
-                        }
-                    } else {
-                        bail!("poll(2) error: {}", err)
-                    }
-                }
-                None => bail!("poll(2) error: {}", err),
-            };
-        };
-
-        if pfd[0].revents != 0 {
-            // SIGWINCH received via our pipe?
-            if let Some(resize) = self.caught_sigwinch()? {
-                return Ok(Some(resize));
-            }
-        }
-
-        if pfd[1].revents != 0 {
-            let mut buf = [0u8; 64];
-            match self.read.read(&mut buf) {
-                Ok(n) => {
-                    let input_queue = &mut self.input_queue;
-                    self.input_parser.parse(
-                        &buf[0..n],
-                        |evt| input_queue.push_back(evt),
-                        n == buf.len(),
-                    );
-                    return Ok(self.input_queue.pop_front());
-                }
-                Err(ref e)
-                    if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::Interrupted => {}
-                Err(e) => bail!("failed to read input {}", e),
-            }
-        }
-
-        if pfd[2].revents != 0 {
-            let mut buf = [0u8; 64];
-            if let Ok(n) = self.wake_pipe.read(&mut buf) {
-                if n > 0 {
-                    return Ok(Some(InputEvent::Wake));
-                }
-            }
-        }
-
-        Ok(None)
     }
 
     fn waker(&self) -> UnixTerminalWaker {
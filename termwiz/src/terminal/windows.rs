@@ -656,6 +656,15 @@ pub struct WindowsTerminalWaker {
 }
 
 impl WindowsTerminalWaker {
+    /// Returns a waker that isn't associated with a terminal.
+    /// This is intended for use by `Terminal` implementations in tests.
+    #[cfg(test)]
+    pub(crate) fn detached() -> IoResult<Self> {
+        Ok(Self {
+            handle: Arc::new(EventHandle::new()?),
+        })
+    }
+
     pub fn wake(&self) -> IoResult<()> {
         self.handle.set()?;
         Ok(())
@@ -20,15 +20,17 @@ use portable_pty::Child;
 use smol::channel::{bounded, unbounded, Receiver, Sender};
 use smol::prelude::*;
 use smol::{block_on, Async};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::{Read, Write};
 use std::marker::Unpin;
 use std::net::TcpStream;
 #[cfg(unix)]
 use std::os::unix::process::CommandExt;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -44,7 +46,13 @@ enum ReaderMessage {
         pdu: Pdu,
         promise: Sender<anyhow::Result<Pdu>>,
     },
+    /// Route unilateral `MuxEventNotification`s to the provided channel
+    SubscribeToMuxEvents {
+        events: Sender<MuxEvent>,
+    },
     Readable,
+    /// It is time to check whether the server is still responding
+    LivenessCheck,
 }
 
 #[derive(Clone)]
@@ -54,6 +62,7 @@ pub struct Client {
     client_id: ClientId,
     pub is_reconnectable: bool,
     pub is_local: bool,
+    reconnecting: Arc<AtomicBool>,
 }
 
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
@@ -345,6 +354,12 @@ async fn client_thread_async(
     local_domain_id: Option<DomainId>,
     rx: &mut Receiver<ReaderMessage>,
 ) -> anyhow::Result<()> {
+    /// How long the connection may be idle before we ping the server
+    const PING_INTERVAL: Duration = Duration::from_secs(5);
+    /// How long we wait for the server to respond to a ping before
+    /// deciding that the transport is dead
+    const LIVENESS_TIMEOUT: Duration = Duration::from_secs(15);
+
     let mut next_serial = 1u64;
 
     struct Promises {
@@ -369,15 +384,38 @@ async fn client_thread_async(
         map: HashMap::new(),
     };
 
+    let mut mux_events: Option<Sender<MuxEvent>> = None;
+
     let mut stream = reconnectable.take_stream().unwrap();
 
+    // A network level disconnect (eg: the laptop went to sleep, or the
+    // wifi dropped) may never be reported by the OS, so remote connections
+    // ping the server when idle and treat a lack of response as an error,
+    // which will trigger a reconnect.
+    let check_liveness = reconnectable.reconnectable();
+    let mut last_recv = Instant::now();
+    let mut ping_sent: Option<Instant> = None;
+    let mut ping_serials = HashSet::new();
+
     loop {
         let rx_msg = rx.recv();
         let wait_for_read = stream
             .wait_for_readable()
             .map(|_| Ok(ReaderMessage::Readable));
+        let deadline = match ping_sent {
+            Some(sent) => sent + LIVENESS_TIMEOUT,
+            None => last_recv + PING_INTERVAL,
+        };
+        let liveness = async move {
+            if check_liveness {
+                smol::Timer::at(deadline).await;
+            } else {
+                smol::future::pending::<()>().await;
+            }
+            Ok(ReaderMessage::LivenessCheck)
+        };
 
-        match smol::future::or(rx_msg, wait_for_read).await {
+        match smol::future::or(smol::future::or(rx_msg, wait_for_read), liveness).await {
             Ok(ReaderMessage::SendPdu { pdu, promise }) => {
                 let serial = next_serial;
                 next_serial += 1;
@@ -388,6 +426,31 @@ async fn client_thread_async(
                     .context("encoding a PDU to send to the server")?;
                 stream.flush().await.context("flushing PDU to server")?;
             }
+            Ok(ReaderMessage::SubscribeToMuxEvents { events }) => {
+                mux_events.replace(events);
+            }
+            Ok(ReaderMessage::LivenessCheck) => match ping_sent {
+                Some(sent) if sent.elapsed() >= LIVENESS_TIMEOUT => {
+                    let reason = format!("no response from server for {:?}", LIVENESS_TIMEOUT);
+                    log::error!("{}", reason);
+                    promises.fail_all(&reason);
+                    return Err(Timeout).context(reason);
+                }
+                Some(_) => {}
+                None if last_recv.elapsed() >= PING_INTERVAL => {
+                    let serial = next_serial;
+                    next_serial += 1;
+                    ping_serials.insert(serial);
+                    ping_sent.replace(Instant::now());
+
+                    Pdu::Ping(Ping {})
+                        .encode_async(&mut stream, serial)
+                        .await
+                        .context("encoding a Ping to send to the server")?;
+                    stream.flush().await.context("flushing Ping to server")?;
+                }
+                None => {}
+            },
             Ok(ReaderMessage::Readable) => {
                 match Pdu::decode_async(&mut stream, Some(next_serial)).await {
                     Ok(decoded) => {
@@ -396,7 +459,22 @@ async fn client_thread_async(
                             decoded.serial,
                             decoded.pdu.pdu_name()
                         );
-                        if decoded.serial == 0 {
+                        // Any response shows that the server is alive
+                        last_recv = Instant::now();
+                        ping_sent.take();
+
+                        if ping_serials.remove(&decoded.serial) {
+                            log::trace!("got Pong for serial {}", decoded.serial);
+                        } else if let (0, Pdu::MuxEventNotification(notif)) =
+                            (decoded.serial, &decoded.pdu)
+                        {
+                            if let Some(events) = &mux_events {
+                                if events.try_send(notif.event.clone()).is_err() {
+                                    // The subscriber went away
+                                    mux_events.take();
+                                }
+                            }
+                        } else if decoded.serial == 0 {
                             process_unilateral(local_domain_id, decoded)
                                 .context("processing unilateral PDU from server")
                                 .map_err(|e| {
@@ -614,12 +692,10 @@ impl Reconnectable {
             // the set of tabs and we'd have confusing and inconsistent state
             ClientDomainConfig::Unix(_) => false,
             ClientDomainConfig::Tls(_) => true,
-            // It *does* make sense to reconnect with an ssh session, but we
-            // need to grow some smarts about whether the disconnect was because
-            // we sent CTRL-D to close the last session, or whether it was a network
-            // level disconnect, because we will otherwise throw up authentication
-            // dialogs that would be annoying
-            ClientDomainConfig::Ssh(_) => false,
+            // A clean EOF from the proxy doesn't trigger a reconnect, and
+            // reconnect attempts are made headless so that we only show
+            // authentication prompts if the session actually needs them
+            ClientDomainConfig::Ssh(_) => true,
         }
     }
 
@@ -1019,6 +1095,8 @@ impl Client {
         let is_local = reconnectable.is_local();
         let (sender, mut receiver) = unbounded();
         let client_id = ClientId::new();
+        let reconnecting = Arc::new(AtomicBool::new(false));
+        let is_reconnecting = Arc::clone(&reconnecting);
 
         thread::spawn(move || {
             const BASE_INTERVAL: Duration = Duration::from_secs(1);
@@ -1047,33 +1125,71 @@ impl Client {
                         break;
                     }
 
-                    let mut ui = ConnectionUI::new();
-                    ui.title("wezterm: Reconnecting...");
+                    reconnecting.store(true, Ordering::SeqCst);
+                    promise::spawn::spawn_into_main_thread(async move {
+                        ClientDomain::connection_lost(local_domain_id);
+                    })
+                    .detach();
+
+                    // Reconnect attempts are made with a headless UI so that
+                    // the user just sees the reconnecting indicator in their
+                    // panes.  If an attempt needs to prompt for something, such
+                    // as an ssh password, we switch to an interactive UI.
+                    let mut interactive_ui: Option<ConnectionUI> = None;
 
                     loop {
-                        ui.sleep_with_reason(
-                            &format!("client disconnected {}; will reconnect", e),
-                            backoff,
-                        )
-                        .ok();
+                        let reason = format!("client disconnected {}; will reconnect", e);
+                        match &interactive_ui {
+                            Some(ui) => {
+                                ui.sleep_with_reason(&reason, backoff).ok();
+                            }
+                            None => {
+                                log::warn!("{} in {:?}", reason, backoff);
+                                thread::sleep(backoff);
+                            }
+                        }
+
+                        let mut ui = interactive_ui
+                            .clone()
+                            .unwrap_or_else(ConnectionUI::new_headless);
                         let initial = false;
                         let no_auto_start = true; // Don't auto-start on a reconnect
                         match reconnectable.connect(initial, &mut ui, no_auto_start) {
                             Ok(_) => {
                                 backoff = BASE_INTERVAL;
                                 log::error!("Reconnected!");
+                                let reconnecting = Arc::clone(&reconnecting);
                                 promise::spawn::spawn_into_main_thread(async move {
-                                    ClientDomain::reattach(local_domain_id, ui).await.ok();
+                                    reconnecting.store(false, Ordering::SeqCst);
+                                    if let Err(err) =
+                                        ClientDomain::reattach(local_domain_id, ui).await
+                                    {
+                                        log::error!("failed to resync after reconnect: {:#}", err);
+                                        detach(local_domain_id).await.ok();
+                                    }
                                 })
                                 .detach();
                                 break;
                             }
                             Err(err) => {
                                 backoff = (backoff + backoff).min(MAX_INTERVAL);
-                                ui.output_str(&format!(
+                                if interactive_ui.is_none() {
+                                    ui.close();
+                                    if ui.headless_input_requested() {
+                                        let ui = ConnectionUI::new();
+                                        ui.title("wezterm: Reconnecting...");
+                                        interactive_ui.replace(ui);
+                                        backoff = BASE_INTERVAL;
+                                    }
+                                }
+                                let msg = format!(
                                     "problem reconnecting: {}; will reconnect in {:?}\n",
                                     err, backoff
-                                ));
+                                );
+                                match &interactive_ui {
+                                    Some(ui) => ui.output_str(&msg),
+                                    None => log::warn!("{}", msg.trim_end()),
+                                }
                             }
                         }
                     }
@@ -1112,9 +1228,16 @@ impl Client {
             is_reconnectable,
             is_local,
             client_id,
+            reconnecting: is_reconnecting,
         }
     }
 
+    /// Returns true while the connection to the server is down and
+    /// we are trying to re-establish it
+    pub fn is_reconnecting(&self) -> bool {
+        self.reconnecting.load(Ordering::SeqCst)
+    }
+
     pub async fn verify_version_compat(
         &self,
         ui: &ConnectionUI,
@@ -1305,7 +1428,7 @@ impl Client {
     }
 
     rpc!(ping, Ping = (), Pong);
-    rpc!(list_panes, ListPanes = (), ListPanesResponse);
+    rpc!(list_panes, ListPanes, ListPanesResponse);
     rpc!(spawn_v2, SpawnV2, SpawnResponse);
     rpc!(split_pane, SplitPane, SpawnResponse);
     rpc!(
@@ -1355,4 +1478,39 @@ impl Client {
         GetPaneDirectionResponse
     );
     rpc!(adjust_pane_size, AdjustPaneSize, UnitResponse);
+    rpc!(
+        get_semantic_zones,
+        GetSemanticZones,
+        GetSemanticZonesResponse
+    );
+    rpc!(get_palette, GetPalette, GetPaletteResponse);
+    rpc!(
+        get_command_history,
+        GetCommandHistory,
+        GetCommandHistoryResponse
+    );
+
+    /// Ask the server to send us mux events, returning a channel
+    /// through which they will be delivered.
+    /// The channel is closed if the connection to the server is lost.
+    pub async fn subscribe_to_mux_events(
+        &self,
+        subscription: SubscribeToMuxEvents,
+    ) -> anyhow::Result<Receiver<MuxEvent>> {
+        let (events, rx) = unbounded();
+        // The reader processes messages in order, so the channel is
+        // in place before the server can start sending events
+        self.sender
+            .send(ReaderMessage::SubscribeToMuxEvents { events })
+            .await
+            .map_err(|_| ChannelSendError)
+            .context("subscribe_to_mux_events send")?;
+        match self
+            .send_pdu(Pdu::SubscribeToMuxEvents(subscription))
+            .await?
+        {
+            Pdu::UnitResponse(_) => Ok(rx),
+            pdu => bail!("unexpected response {:?}", pdu),
+        }
+    }
 }
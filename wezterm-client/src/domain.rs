@@ -2,7 +2,7 @@ use crate::client::Client;
 use crate::pane::ClientPane;
 use anyhow::{anyhow, bail};
 use async_trait::async_trait;
-use codec::{ListPanesResponse, SpawnV2, SplitPane};
+use codec::{ListPanes, ListPanesResponse, SpawnV2, SplitPane};
 use config::keyassignment::SpawnTabDomain;
 use config::{SshDomain, TlsDomainClient, UnixDomain};
 use mux::connui::{ConnectionUI, ConnectionUIParams};
@@ -463,19 +463,54 @@ impl ClientDomain {
     /// more tabs at the time that a disconnect was detected, and
     /// it's also possible that another client connected and adjusted
     /// the set of tabs since we were connected, so we need to re-sync.
+    /// The existing local panes, tabs and windows are rebound to their
+    /// remote counterparts rather than being replaced.
     pub async fn reattach(domain_id: DomainId, ui: ConnectionUI) -> anyhow::Result<()> {
         let inner = Self::get_client_inner_for_domain(domain_id)?;
 
-        let panes = inner.client.list_panes().await?;
+        // The server sees this as a brand new client
+        inner.client.verify_version_compat(&ui).await?;
+
+        let panes = inner.client.list_panes(ListPanes::default()).await?;
         Self::process_pane_list(inner, panes, None)?;
 
+        // Whatever we had cached may have changed while we were
+        // disconnected, so fetch it again
+        let mux = Mux::get();
+        for pane in mux.iter_panes() {
+            if pane.domain_id() != domain_id {
+                continue;
+            }
+            if let Some(client_pane) = pane.downcast_ref::<ClientPane>() {
+                client_pane
+                    .renderable
+                    .lock()
+                    .inner
+                    .borrow_mut()
+                    .make_all_stale();
+            }
+            mux.notify(MuxNotification::PaneOutput(pane.pane_id()));
+        }
+
         ui.close();
         Ok(())
     }
 
+    /// Called when the connection to the server is lost, so that the
+    /// panes from this domain are repainted with the reconnecting
+    /// indicator.
+    pub fn connection_lost(domain_id: DomainId) {
+        let mux = Mux::get();
+        for pane in mux.iter_panes() {
+            if pane.domain_id() == domain_id {
+                mux.notify(MuxNotification::PaneOutput(pane.pane_id()));
+            }
+        }
+    }
+
     pub async fn resync(&self) -> anyhow::Result<()> {
         if let Some(inner) = self.inner() {
-            let panes = inner.client.list_panes().await?;
+            let panes = inner.client.list_panes(ListPanes::default()).await?;
             Self::process_pane_list(inner, panes, None)?;
         }
         Ok(())
@@ -969,7 +1004,7 @@ impl Domain for ClientDomain {
                 client.verify_version_compat(&ui).await?;
 
                 ui.output_str("Version check OK!  Requesting pane list...\n");
-                let panes = client.list_panes().await?;
+                let panes = client.list_panes(ListPanes::default()).await?;
                 ui.output_str(&format!(
                     "Server has {} tabs.  Attaching to local UI...\n",
                     panes.tabs.len()
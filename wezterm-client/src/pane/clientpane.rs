@@ -24,11 +24,11 @@ use std::sync::Arc;
 use termwiz::input::KeyEvent;
 use termwiz::surface::SequenceNo;
 use url::Url;
-use wezterm_dynamic::Value;
+use wezterm_dynamic::{ToDynamic, Value};
 use wezterm_term::color::ColorPalette;
 use wezterm_term::{
-    Alert, Clipboard, KeyCode, KeyModifiers, Line, MouseEvent, StableRowIndex,
-    TerminalConfiguration, TerminalSize,
+    Alert, Clipboard, CommandHistoryEntry, KeyCode, KeyModifiers, Line, MouseEvent, PointerShape,
+    Progress, StableRowIndex, TerminalConfiguration, TerminalSize,
 };
 
 pub struct ClientPane {
@@ -44,10 +44,13 @@ pub struct ClientPane {
     mouse: Arc<Mutex<MouseState>>,
     clipboard: Mutex<Option<Arc<dyn Clipboard>>>,
     mouse_grabbed: Mutex<bool>,
+    alt_screen_active: Mutex<bool>,
     ignore_next_kill: Mutex<bool>,
     user_vars: Mutex<HashMap<String, String>>,
     config: Mutex<Option<Arc<dyn TerminalConfiguration>>>,
     unseen_output: Mutex<bool>,
+    progress: Mutex<Progress>,
+    pointer_shape: Mutex<Option<PointerShape>>,
 }
 
 impl ClientPane {
@@ -125,8 +128,11 @@ impl ClientPane {
             palette: Mutex::new(palette),
             clipboard: Mutex::new(None),
             mouse_grabbed: Mutex::new(false),
+            alt_screen_active: Mutex::new(false),
             ignore_next_kill: Mutex::new(false),
             unseen_output: Mutex::new(false),
+            progress: Mutex::new(Progress::None),
+            pointer_shape: Mutex::new(None),
             user_vars: Mutex::new(HashMap::new()),
             config: Mutex::new(None),
         }
@@ -136,6 +142,7 @@ impl ClientPane {
         match pdu {
             Pdu::GetPaneRenderChangesResponse(mut delta) => {
                 *self.mouse_grabbed.lock() = delta.mouse_grabbed;
+                *self.alt_screen_active.lock() = delta.alt_screen_active;
 
                 let bonus_lines = std::mem::take(&mut delta.bonus_lines);
                 let client = { Arc::clone(&self.renderable.lock().inner.borrow().client) };
@@ -183,6 +190,12 @@ impl ClientPane {
                     Alert::SetUserVar { name, value } => {
                         self.user_vars.lock().insert(name.clone(), value.clone());
                     }
+                    Alert::Progress(progress) => {
+                        *self.progress.lock() = *progress;
+                    }
+                    Alert::PointerShapeChanged(shape) => {
+                        *self.pointer_shape.lock() = *shape;
+                    }
                     Alert::OutputSinceFocusLost => {
                         *self.unseen_output.lock() = true;
                         mux.notify(MuxNotification::Alert {
@@ -262,6 +275,10 @@ impl Pane for ClientPane {
             Value::String("since_last_response_ms".to_string()),
             Value::U64(inner.last_recv_time.elapsed().as_millis() as u64),
         );
+        map.insert(
+            Value::String("progress".to_string()),
+            self.progress.lock().to_dynamic(),
+        );
 
         Value::Object(map.into())
     }
@@ -533,14 +550,21 @@ impl Pane for ClientPane {
     }
 
     fn is_alt_screen_active(&self) -> bool {
-        // FIXME: retrieve this from the remote
-        false
+        *self.alt_screen_active.lock()
     }
 
     fn get_current_working_dir(&self, _policy: CachePolicy) -> Option<Url> {
         self.renderable.lock().inner.borrow().working_dir.clone()
     }
 
+    fn get_command_history(&self) -> anyhow::Result<Vec<CommandHistoryEntry>> {
+        let response =
+            promise::spawn::block_on(self.client.client.get_command_history(GetCommandHistory {
+                pane_id: self.remote_pane_id,
+            }))?;
+        Ok(response.history)
+    }
+
     fn focus_changed(&self, focused: bool) {
         if focused {
             self.advise_focus();
@@ -585,6 +609,14 @@ impl Pane for ClientPane {
         *self.unseen_output.lock()
     }
 
+    fn get_progress(&self) -> Progress {
+        *self.progress.lock()
+    }
+
+    fn get_pointer_shape(&self) -> Option<PointerShape> {
+        *self.pointer_shape.lock()
+    }
+
     fn can_close_without_prompting(&self, reason: CloseReason) -> bool {
         match reason {
             CloseReason::Window => true,
@@ -0,0 +1,667 @@
+//! Speculative local echo for panes whose terminal state lives on a
+//! remote mux server, following the approach taken by mosh.
+//!
+//! Keypresses that we can reason about produce predictions of what the
+//! cells on the cursor row, and the cursor itself, will look like once
+//! the server has processed them.  The predictions are drawn as an
+//! underlined overlay on top of the lines received from the server,
+//! and are retired once the server output confirms them.
+//!
+//! Predictions are grouped into epochs.  A new epoch begins whenever
+//! something happens that we can't predict, such as pressing Enter or
+//! the server output diverging from a prediction.  The predictions from
+//! an epoch are not shown until one of them has been confirmed by the
+//! server, so that typing at a prompt that doesn't echo doesn't reveal
+//! what was typed.
+use codec::{GetPaneRenderChangesResponse, InputSerial};
+use mux::renderable::StableCursorPosition;
+use std::time::{Duration, Instant};
+use termwiz::cell::{Cell, CellAttributes, Underline};
+use termwiz::surface::SEQ_ZERO;
+use wezterm_term::{KeyCode, KeyModifiers, Line, StableRowIndex};
+
+/// How long to wait for the echo of a keypress to arrive after the
+/// server has acknowledged receiving it, before deciding that the
+/// prediction was wrong
+const ECHO_GRACE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
+struct CellPrediction {
+    row: StableRowIndex,
+    col: usize,
+    text: String,
+    attrs: CellAttributes,
+    epoch: usize,
+    serial: InputSerial,
+    /// When the server acknowledged the input that produced this prediction
+    acked: Option<Instant>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CursorPrediction {
+    x: usize,
+    y: StableRowIndex,
+    epoch: usize,
+    serial: InputSerial,
+    acked: Option<Instant>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Validity {
+    Pending,
+    Correct,
+    Incorrect,
+}
+
+fn validity(serial_acked: Option<Instant>, matches: bool, now: Instant) -> Validity {
+    match serial_acked {
+        None => Validity::Pending,
+        Some(_) if matches => Validity::Correct,
+        Some(acked) if now.saturating_duration_since(acked) >= ECHO_GRACE => Validity::Incorrect,
+        Some(_) => Validity::Pending,
+    }
+}
+
+pub struct Predictor {
+    /// The epoch in which new predictions are made
+    epoch: usize,
+    /// The most recent epoch that had a prediction confirmed by
+    /// the server.  Predictions from later epochs are not shown.
+    confirmed_epoch: usize,
+    cells: Vec<CellPrediction>,
+    cursor: Option<CursorPrediction>,
+    server_cursor: StableCursorPosition,
+    /// The most recent input that the server has acknowledged
+    acked_serial: InputSerial,
+    /// The most recent input that we have seen
+    last_serial: InputSerial,
+    /// No predictions are made until the server has acknowledged
+    /// this input, as we don't know where the cursor will be until then
+    suspended_until: Option<InputSerial>,
+    alt_screen_active: bool,
+}
+
+impl Predictor {
+    pub fn new() -> Self {
+        Self {
+            epoch: 1,
+            confirmed_epoch: 0,
+            cells: vec![],
+            cursor: None,
+            server_cursor: StableCursorPosition::default(),
+            acked_serial: InputSerial::empty(),
+            last_serial: InputSerial::empty(),
+            suspended_until: None,
+            alt_screen_active: false,
+        }
+    }
+
+    fn is_visible(&self, epoch: usize) -> bool {
+        epoch <= self.confirmed_epoch
+    }
+
+    /// Discard all predictions
+    pub fn reset(&mut self) {
+        self.cells.clear();
+        self.cursor = None;
+        self.epoch += 1;
+    }
+
+    /// Something happened that we can't predict; stop predicting until
+    /// the server has caught up with our input
+    fn suspend(&mut self) {
+        self.cursor = None;
+        self.epoch += 1;
+        if self.last_serial > self.acked_serial {
+            self.suspended_until = Some(self.last_serial);
+        }
+    }
+
+    fn mispredicted(&mut self, epoch: usize) {
+        if epoch > self.confirmed_epoch {
+            // Nothing from this epoch has been shown yet, so we only
+            // need to forget about it and anything that followed it
+            self.cells.retain(|p| p.epoch < epoch);
+            if self.cursor.map(|c| c.epoch >= epoch).unwrap_or(false) {
+                self.cursor = None;
+            }
+        } else {
+            self.cells.clear();
+            self.cursor = None;
+        }
+        self.suspend();
+    }
+
+    /// Returns the row on which the next keypress will be predicted
+    pub fn cursor_row(&self) -> StableRowIndex {
+        self.cursor.map(|c| c.y).unwrap_or(self.server_cursor.y)
+    }
+
+    /// Returns the cursor position that should be displayed
+    pub fn cursor_position(&self, server: StableCursorPosition) -> StableCursorPosition {
+        match self.cursor {
+            Some(c) if self.is_visible(c.epoch) => StableCursorPosition {
+                x: c.x,
+                y: c.y,
+                ..server
+            },
+            _ => server,
+        }
+    }
+
+    /// Returns the cells of the row as they would be displayed,
+    /// including any predictions for that row
+    fn displayed_row(
+        &self,
+        row: StableRowIndex,
+        line: &Line,
+        cols: usize,
+    ) -> Vec<(String, CellAttributes)> {
+        let mut cells = vec![(" ".to_string(), CellAttributes::default()); cols];
+        for cell in line.visible_cells() {
+            if let Some(slot) = cells.get_mut(cell.cell_index()) {
+                *slot = (cell.str().to_string(), cell.attrs().clone());
+            }
+        }
+        for p in self.cells.iter().filter(|p| p.row == row) {
+            if let Some(slot) = cells.get_mut(p.col) {
+                *slot = (p.text.clone(), p.attrs.clone());
+            }
+        }
+        cells
+    }
+
+    /// Predicts the effect of a keypress.
+    /// `line` is the most recent version of the line at `cursor_row()`
+    /// that was received from the server.
+    pub fn predict_key(
+        &mut self,
+        key: KeyCode,
+        mods: KeyModifiers,
+        serial: InputSerial,
+        line: Option<&Line>,
+        cols: usize,
+    ) {
+        self.last_serial = self.last_serial.max(serial);
+        self.predict(key, mods, serial, line, cols, None);
+    }
+
+    /// Predicts the effect of pasting text.
+    /// The server doesn't acknowledge pastes, so the predictions are
+    /// treated as though they were acknowledged at `expected_ack`.
+    pub fn predict_paste(
+        &mut self,
+        text: &str,
+        line: Option<&Line>,
+        cols: usize,
+        expected_ack: Instant,
+    ) {
+        if text.contains(['\n', '\r']) {
+            // We can't know where the cursor will end up
+            return self.reset();
+        }
+        for c in text.chars() {
+            self.predict(
+                KeyCode::Char(c),
+                KeyModifiers::NONE,
+                self.last_serial,
+                line,
+                cols,
+                Some(expected_ack),
+            );
+        }
+    }
+
+    fn predict(
+        &mut self,
+        key: KeyCode,
+        mods: KeyModifiers,
+        serial: InputSerial,
+        line: Option<&Line>,
+        cols: usize,
+        acked: Option<Instant>,
+    ) {
+        if self.alt_screen_active {
+            return;
+        }
+        if let Some(until) = self.suspended_until {
+            if self.acked_serial < until {
+                self.suspended_until = Some(self.last_serial);
+                return;
+            }
+            self.suspended_until.take();
+        }
+
+        let line = match line {
+            Some(line) if mods == KeyModifiers::NONE || mods == KeyModifiers::SHIFT => line,
+            _ => return self.suspend(),
+        };
+        if line_may_be_password_prompt(line) {
+            // Don't force on local echo here, as we don't want
+            // to reveal their password
+            return self.suspend();
+        }
+        if line.visible_cells().any(|cell| cell.width() != 1) {
+            // Keep things simple and avoid predicting around wide
+            // characters
+            return self.suspend();
+        }
+
+        let y = self.cursor_row();
+        let x = self.cursor.map(|c| c.x).unwrap_or(self.server_cursor.x);
+        let row = self.displayed_row(y, line, cols);
+        let end = row
+            .iter()
+            .rposition(|(text, _)| text != " ")
+            .map(|idx| idx + 1)
+            .unwrap_or(0);
+
+        let mut new_row = row.clone();
+        let new_x = match key {
+            KeyCode::Char(c) if !c.is_control() => {
+                let cell = Cell::new(c, CellAttributes::default());
+                if cell.width() != 1 || x + 1 >= cols || end + 1 >= cols {
+                    return self.suspend();
+                }
+                if x < end {
+                    new_row.remove(end);
+                    new_row.insert(x, (cell.str().to_string(), CellAttributes::default()));
+                } else {
+                    new_row[x] = (cell.str().to_string(), CellAttributes::default());
+                }
+                x + 1
+            }
+            KeyCode::Backspace if x > 0 && x <= end => {
+                new_row.remove(x - 1);
+                new_row.insert(end - 1, (" ".to_string(), CellAttributes::default()));
+                x - 1
+            }
+            KeyCode::Delete if x < end => {
+                new_row.remove(x);
+                new_row.insert(end - 1, (" ".to_string(), CellAttributes::default()));
+                x
+            }
+            KeyCode::LeftArrow if x > 0 && x <= end => x - 1,
+            KeyCode::RightArrow if x < end => x + 1,
+            _ => return self.suspend(),
+        };
+
+        for (col, (old, new)) in row.into_iter().zip(new_row).enumerate() {
+            if old.0 == new.0 {
+                continue;
+            }
+            self.cells.retain(|p| p.row != y || p.col != col);
+            self.cells.push(CellPrediction {
+                row: y,
+                col,
+                text: new.0,
+                attrs: new.1,
+                epoch: self.epoch,
+                serial,
+                acked,
+            });
+        }
+        self.cursor.replace(CursorPrediction {
+            x: new_x,
+            y,
+            epoch: self.epoch,
+            serial,
+            acked,
+        });
+    }
+
+    /// Updates the state of the predictions from a render changes
+    /// response that was received from the server.
+    /// `bonus_lines` are the lines that accompanied the response.
+    pub fn apply_changes(
+        &mut self,
+        delta: &GetPaneRenderChangesResponse,
+        bonus_lines: &[(StableRowIndex, Line)],
+        now: Instant,
+    ) {
+        self.server_cursor = delta.cursor_position;
+        self.alt_screen_active = delta.alt_screen_active;
+        if self.alt_screen_active {
+            // Full screen applications can do anything with our input
+            self.reset();
+            return;
+        }
+
+        if let Some(serial) = delta.input_serial {
+            self.acked_serial = self.acked_serial.max(serial);
+        }
+        let acked_serial = self.acked_serial;
+        for p in &mut self.cells {
+            if p.acked.is_none() && p.serial <= acked_serial {
+                p.acked.replace(now);
+            }
+        }
+        if let Some(c) = self.cursor.as_mut() {
+            if c.acked.is_none() && c.serial <= acked_serial {
+                c.acked.replace(now);
+            }
+        }
+
+        for (row, line) in bonus_lines {
+            self.validate_row(*row, line, now);
+        }
+        self.validate_cursor(now);
+    }
+
+    /// Compares the predictions for the cursor position against the
+    /// most recent cursor position received from the server
+    pub fn validate_cursor(&mut self, now: Instant) {
+        if let Some(c) = self.cursor {
+            let matches = c.x == self.server_cursor.x && c.y == self.server_cursor.y;
+            match validity(c.acked, matches, now) {
+                Validity::Pending => {}
+                Validity::Correct => {
+                    self.confirmed_epoch = self.confirmed_epoch.max(c.epoch);
+                    self.cursor = None;
+                }
+                Validity::Incorrect => self.mispredicted(c.epoch),
+            }
+        }
+    }
+
+    /// Compares the predictions for a row against a line that
+    /// was received from the server
+    pub fn validate_row(&mut self, row: StableRowIndex, line: &Line, now: Instant) {
+        let mut confirmed_epoch = self.confirmed_epoch;
+        let mut incorrect_epoch: Option<usize> = None;
+        self.cells.retain(|p| {
+            if p.row != row {
+                return true;
+            }
+            let matches = line
+                .get_cell(p.col)
+                .map(|cell| cell.str() == p.text)
+                .unwrap_or(p.text == " ");
+            match validity(p.acked, matches, now) {
+                Validity::Pending => true,
+                Validity::Correct => {
+                    confirmed_epoch = confirmed_epoch.max(p.epoch);
+                    false
+                }
+                Validity::Incorrect => {
+                    incorrect_epoch = Some(incorrect_epoch.unwrap_or(p.epoch).min(p.epoch));
+                    true
+                }
+            }
+        });
+        self.confirmed_epoch = confirmed_epoch;
+        if let Some(epoch) = incorrect_epoch {
+            self.mispredicted(epoch);
+        }
+    }
+
+    /// Draws the visible predictions for a row on top of the line
+    pub fn overlay(&self, row: StableRowIndex, line: &mut Line) {
+        let mut changed = false;
+        for p in &self.cells {
+            if p.row != row || !self.is_visible(p.epoch) {
+                continue;
+            }
+            let attrs = p.attrs.clone().set_underline(Underline::Double).clone();
+            line.set_cell(p.col, Cell::new_grapheme(&p.text, attrs, None), SEQ_ZERO);
+            changed = true;
+        }
+        if changed {
+            line.clear_appdata();
+        }
+    }
+}
+
+/// A line that asks for a password will usually not echo what is typed,
+/// and we don't want to reveal it by predicting that it will
+fn line_may_be_password_prompt(line: &Line) -> bool {
+    let text = line.as_str().to_lowercase();
+    text.contains("sword") || text.contains("passphrase") || text.contains("pin:")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mux::renderable::RenderableDimensions;
+    use std::time::UNIX_EPOCH;
+
+    const COLS: usize = 20;
+
+    struct Harness {
+        predictor: Predictor,
+        server_line: Line,
+        alt_screen_active: bool,
+        now: Instant,
+        next_serial: u64,
+    }
+
+    impl Harness {
+        fn new(text: &str) -> Self {
+            let mut harness = Self {
+                predictor: Predictor::new(),
+                server_line: Line::from_text(text, &CellAttributes::default(), SEQ_ZERO, None),
+                alt_screen_active: false,
+                now: Instant::now(),
+                next_serial: 1,
+            };
+            harness.server(None, text, text.len());
+            harness
+        }
+
+        fn key(&mut self, key: KeyCode) -> InputSerial {
+            let serial = InputSerial::from(UNIX_EPOCH + Duration::from_millis(self.next_serial));
+            self.next_serial += 1;
+            let line = self.server_line.clone();
+            self.predictor
+                .predict_key(key, KeyModifiers::NONE, serial, Some(&line), COLS);
+            serial
+        }
+
+        fn typed(&mut self, text: &str) -> InputSerial {
+            let mut serial = InputSerial::empty();
+            for c in text.chars() {
+                serial = self.key(KeyCode::Char(c));
+            }
+            serial
+        }
+
+        /// Simulate a render changes response from the server
+        fn server(&mut self, input_serial: Option<InputSerial>, text: &str, cursor_x: usize) {
+            self.server_line = Line::from_text(text, &CellAttributes::default(), SEQ_ZERO, None);
+            let mut delta = GetPaneRenderChangesResponse {
+                pane_id: 0,
+                mouse_grabbed: false,
+                alt_screen_active: self.alt_screen_active,
+                cursor_position: StableCursorPosition {
+                    x: cursor_x,
+                    y: 0,
+                    ..Default::default()
+                },
+                dimensions: RenderableDimensions {
+                    cols: COLS,
+                    viewport_rows: 1,
+                    scrollback_rows: 1,
+                    ..Default::default()
+                },
+                dirty_lines: vec![],
+                title: String::new(),
+                working_dir: None,
+                bonus_lines: vec![(0, self.server_line.clone())].into(),
+                input_serial,
+                seqno: 1,
+            };
+            let (bonus_lines, _) = std::mem::take(&mut delta.bonus_lines).extract_data();
+            self.predictor.apply_changes(&delta, &bonus_lines, self.now);
+        }
+
+        fn advance(&mut self, duration: Duration) {
+            self.now += duration;
+            let line = self.server_line.clone();
+            self.predictor.validate_row(0, &line, self.now);
+            self.predictor.validate_cursor(self.now);
+        }
+
+        /// Returns the text that would be displayed, along with the
+        /// indices of the cells that are underlined as predictions
+        fn displayed(&self) -> (String, Vec<usize>) {
+            let mut line = self.server_line.clone();
+            line.resize(COLS, SEQ_ZERO);
+            self.predictor.overlay(0, &mut line);
+            let underlined = line
+                .visible_cells()
+                .filter(|cell| cell.attrs().underline() == Underline::Double)
+                .map(|cell| cell.cell_index())
+                .collect();
+            (line.as_str().trim_end().to_string(), underlined)
+        }
+
+        fn cursor_x(&self) -> usize {
+            self.predictor
+                .cursor_position(self.predictor.server_cursor)
+                .x
+        }
+    }
+
+    /// Confirm the first epoch by having the server echo a keypress
+    fn confirmed(text: &str) -> Harness {
+        let mut h = Harness::new(text);
+        let serial = h.typed("x");
+        let echoed = format!("{}x", text);
+        h.server(Some(serial), &echoed, echoed.len());
+        h
+    }
+
+    #[test]
+    fn tentative_until_confirmed() {
+        let mut h = Harness::new("$ ");
+
+        // Nothing has been confirmed yet, so nothing is shown
+        let serial = h.typed("l");
+        assert_eq!(h.displayed(), ("$".to_string(), vec![]));
+        assert_eq!(h.cursor_x(), 2);
+
+        // The server acknowledges the key, but hasn't echoed it yet
+        h.server(Some(serial), "$ ", 2);
+        assert_eq!(h.displayed(), ("$".to_string(), vec![]));
+
+        // The echo confirms the epoch
+        h.server(None, "$ l", 3);
+        assert_eq!(h.displayed(), ("$ l".to_string(), vec![]));
+
+        // so further typing is shown straight away
+        let serial = h.typed("s-l");
+        assert_eq!(h.displayed(), ("$ ls-l".to_string(), vec![3, 4, 5]));
+        assert_eq!(h.cursor_x(), 6);
+
+        // and the underline is removed as the echo arrives
+        h.server(Some(serial), "$ ls", 4);
+        assert_eq!(h.displayed(), ("$ ls-l".to_string(), vec![4, 5]));
+        h.server(None, "$ ls-l", 6);
+        assert_eq!(h.displayed(), ("$ ls-l".to_string(), vec![]));
+        assert_eq!(h.cursor_x(), 6);
+    }
+
+    #[test]
+    fn editing_keys() {
+        let mut h = confirmed("$ ");
+        h.typed("helo");
+        assert_eq!(h.displayed().0, "$ xhelo");
+
+        h.key(KeyCode::LeftArrow);
+        h.typed("l");
+        assert_eq!(h.displayed().0, "$ xhello");
+        assert_eq!(h.cursor_x(), 7);
+
+        for _ in 0..4 {
+            h.key(KeyCode::LeftArrow);
+        }
+        h.key(KeyCode::Backspace);
+        assert_eq!(h.displayed().0, "$ hello");
+        assert_eq!(h.cursor_x(), 2);
+
+        h.key(KeyCode::Delete);
+        assert_eq!(h.displayed().0, "$ ello");
+        assert_eq!(h.cursor_x(), 2);
+
+        h.key(KeyCode::RightArrow);
+        assert_eq!(h.cursor_x(), 3);
+    }
+
+    #[test]
+    fn rollback_when_server_diverges() {
+        let mut h = confirmed("$ ");
+        let serial = h.typed("ab");
+        assert_eq!(h.displayed().0, "$ xab");
+
+        // The application decides to do something else entirely
+        h.server(Some(serial), "$ xAB", 5);
+        assert_eq!(h.displayed().0, "$ xab");
+        h.advance(ECHO_GRACE);
+        assert_eq!(h.displayed(), ("$ xAB".to_string(), vec![]));
+        assert_eq!(h.cursor_x(), 5);
+
+        // The predictions start over in a new epoch, which is not
+        // shown until it has been confirmed
+        h.typed("c");
+        assert_eq!(h.displayed().0, "$ xAB");
+    }
+
+    #[test]
+    fn paste() {
+        let mut h = confirmed("$ ");
+        let line = h.server_line.clone();
+        h.predictor.predict_paste("echo", Some(&line), COLS, h.now);
+        assert_eq!(h.displayed(), ("$ xecho".to_string(), vec![3, 4, 5, 6]));
+        assert_eq!(h.cursor_x(), 7);
+
+        h.server(None, "$ xec", 5);
+        assert_eq!(h.displayed(), ("$ xecho".to_string(), vec![5, 6]));
+        h.advance(ECHO_GRACE);
+        assert_eq!(h.displayed(), ("$ xec".to_string(), vec![]));
+
+        let line = h.server_line.clone();
+        h.predictor
+            .predict_paste("ho\nls", Some(&line), COLS, h.now);
+        assert!(h.predictor.cells.is_empty());
+    }
+
+    #[test]
+    fn suspended_until_unpredictable_key_is_acknowledged() {
+        let mut h = confirmed("$ ");
+        h.key(KeyCode::Enter);
+        let serial = h.typed("ls");
+        assert_eq!(h.displayed().0, "$ x");
+        assert!(h.predictor.cells.is_empty());
+
+        h.server(Some(serial), "$ x", 3);
+        h.typed("ls");
+        // Predicting again, but in an epoch that is yet to be confirmed
+        assert_eq!(h.displayed().0, "$ x");
+        assert_eq!(h.predictor.cells.len(), 2);
+    }
+
+    #[test]
+    fn no_prediction_at_password_prompt() {
+        let mut h = Harness::new("Password: ");
+        let serial = h.typed("hunter2");
+        h.server(Some(serial), "Password: ", 10);
+        h.typed("hunter2");
+        assert_eq!(h.displayed().0, "Password:");
+        assert!(h.predictor.cells.is_empty());
+    }
+
+    #[test]
+    fn no_prediction_in_alt_screen() {
+        let mut h = confirmed("$ ");
+        h.typed("vi");
+        assert_eq!(h.displayed().0, "$ xvi");
+
+        h.alt_screen_active = true;
+        h.server(None, "$ x", 3);
+        assert_eq!(h.displayed().0, "$ x");
+
+        h.typed("i");
+        assert_eq!(h.displayed().0, "$ x");
+        assert!(h.predictor.cells.is_empty());
+    }
+}
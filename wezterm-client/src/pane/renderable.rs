@@ -1,5 +1,6 @@
 use crate::domain::ClientInner;
 use crate::pane::clientpane::ClientPane;
+use crate::pane::prediction::Predictor;
 use anyhow::anyhow;
 use codec::*;
 use config::{configuration, ConfigHandle};
@@ -16,7 +17,7 @@ use std::ops::Range;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use termwiz::cell::{Cell, CellAttributes, Underline};
+use termwiz::cell::CellAttributes;
 use termwiz::color::AnsiColor;
 use termwiz::image::{ImageCell, ImageData};
 use termwiz::surface::{SequenceNo, SEQ_ZERO};
@@ -77,6 +78,7 @@ pub struct RenderableInner {
     last_input_rtt: u64,
 
     pub input_serial: InputSerial,
+    predictor: Predictor,
 }
 
 pub struct RenderableState {
@@ -113,6 +115,7 @@ impl RenderableInner {
             last_late_dirty: now,
             last_input_rtt: 0,
             input_serial: InputSerial::empty(),
+            predictor: Predictor::new(),
             seqno: SEQ_ZERO,
         }
     }
@@ -139,159 +142,50 @@ impl RenderableInner {
             .unwrap_or(false)
     }
 
-    /// Compute a "prediction" and apply it to the line data that we
-    /// have available, marking it as dirty so that it gets rendered.
-    /// The prediction is basically just local echo.
-    /// Open questions:
-    /// how do we tell if the intent is to suppress local echo during eg:
-    ///  * password prompt?  One option is to look back and see if the line
-    ///                      looks like a password prompt.
-    ///  * normal mode in vim: letter presses are typically movement or
-    ///                        other editor commands
-    /// There are bound to be a number of other edge cases that we should
-    /// handle.
-    fn apply_prediction(&mut self, c: KeyCode, line: &mut Line) {
-        let text = line.as_str();
-        if text.contains("sword") {
-            // This line might be a password prompt.  Don't force
-            // on local echo here, as we don't want to reveal content
-            // from their password
-            return;
-        }
-
-        match c {
-            KeyCode::Enter => {
-                self.cursor_position.x = 0;
-                self.cursor_position.y += 1;
-            }
-            KeyCode::UpArrow => {
-                self.cursor_position.y = self.cursor_position.y.saturating_sub(1);
-            }
-            KeyCode::DownArrow => {
-                self.cursor_position.y += 1;
-            }
-            KeyCode::RightArrow => {
-                self.cursor_position.x += 1;
-            }
-            KeyCode::LeftArrow => {
-                self.cursor_position.x = self.cursor_position.x.saturating_sub(1);
-            }
-            KeyCode::Delete => {
-                line.erase_cell(self.cursor_position.x, SEQ_ZERO);
-            }
-            KeyCode::Backspace => {
-                if self.cursor_position.x > 0 {
-                    line.erase_cell(self.cursor_position.x - 1, SEQ_ZERO);
-                    self.cursor_position.x -= 1;
-                }
-            }
-            KeyCode::Char(c) => {
-                let cell = Cell::new(
-                    c,
-                    CellAttributes::default()
-                        .set_underline(Underline::Double)
-                        .clone(),
-                );
-
-                let width = cell.width();
-                line.set_cell(self.cursor_position.x, cell, SEQ_ZERO);
-                // Adjust the cursor to reflect the width of this new cell
-                self.cursor_position.x += width;
-            }
-            _ => {}
+    /// Returns the line we most recently received from the server
+    /// for the row that the prediction engine considers to be the
+    /// cursor row
+    fn predicted_cursor_line(&self) -> Option<&Line> {
+        match self.lines.peek(&self.predictor.cursor_row()) {
+            Some(LineEntry::Line(line))
+            | Some(LineEntry::LineAndFetching(line, _))
+            | Some(LineEntry::Stale(line)) => Some(line),
+            Some(LineEntry::Fetching(_)) | None => None,
         }
     }
 
-    /// Based on a keypress, apply a "prediction" of what the terminal
-    /// content will look like once we receive the response from the
-    /// remote system.  The prediction helps to reduce perceived latency
-    /// when a user is typing at any reasonable velocity.
+    /// Based on a keypress, predict what the terminal content will look
+    /// like once we receive the response from the remote system.
+    /// The prediction helps to reduce perceived latency when a user is
+    /// typing at any reasonable velocity.
+    /// See the `prediction` module for how the predictions are confirmed
+    /// or rolled back as the server output arrives.
     pub fn predict_from_key_event(&mut self, key: KeyCode, mods: KeyModifiers) {
         if !self.should_predict() {
+            self.predictor.reset();
             return;
         }
 
-        let c = match key {
-            KeyCode::LeftArrow
-            | KeyCode::RightArrow
-            | KeyCode::UpArrow
-            | KeyCode::DownArrow
-            | KeyCode::Delete
-            | KeyCode::Backspace
-            | KeyCode::Enter
-            | KeyCode::Char(_) => key,
-            _ => return,
-        };
-        if mods != KeyModifiers::NONE && mods != KeyModifiers::SHIFT {
-            return;
-        }
-
-        let row = self.cursor_position.y;
-        match self.lines.pop(&row) {
-            Some(LineEntry::Stale(mut line)) | Some(LineEntry::Line(mut line)) => {
-                self.apply_prediction(c, &mut line);
-                self.lines.put(row, LineEntry::Line(line));
-            }
-            Some(LineEntry::LineAndFetching(mut line, instant)) => {
-                self.apply_prediction(c, &mut line);
-                self.lines
-                    .put(row, LineEntry::LineAndFetching(line, instant));
-            }
-            Some(entry) => {
-                self.lines.put(row, entry);
-            }
-            None => {}
-        }
-    }
-
-    fn apply_paste_prediction(&mut self, row: usize, text: &str, line: &mut Line) {
-        let attrs = CellAttributes::default()
-            .set_underline(Underline::Double)
-            .clone();
-
-        let text_line = Line::from_text(text, &attrs, SEQ_ZERO, None);
-
-        if row == 0 {
-            for cell in text_line.visible_cells() {
-                line.set_cell(self.cursor_position.x, cell.as_cell(), SEQ_ZERO);
-                self.cursor_position.x += cell.width();
-            }
-        } else {
-            // The pasted line replaces the data for the existing line
-            line.resize_and_clear(0, SEQ_ZERO, CellAttributes::default());
-            line.append_line(text_line, SEQ_ZERO);
-            self.cursor_position.x = line.len();
-        }
+        let line = self.predicted_cursor_line().cloned();
+        self.predictor.predict_key(
+            key,
+            mods,
+            self.input_serial,
+            line.as_ref(),
+            self.dimensions.cols,
+        );
     }
 
     pub fn predict_from_paste(&mut self, text: &str) {
         if !self.should_predict() {
+            self.predictor.reset();
             return;
         }
 
-        let text = textwrap::fill(text, self.dimensions.cols);
-        let lines: Vec<&str> = text.split("\n").collect();
-
-        for (idx, paste_line) in lines.iter().enumerate() {
-            let row = self.cursor_position.y + idx as StableRowIndex;
-
-            match self.lines.pop(&row) {
-                Some(LineEntry::Stale(mut line)) | Some(LineEntry::Line(mut line)) => {
-                    self.apply_paste_prediction(idx, paste_line, &mut line);
-                    self.lines.put(row, LineEntry::Line(line));
-                }
-                Some(LineEntry::LineAndFetching(mut line, instant)) => {
-                    self.apply_paste_prediction(idx, paste_line, &mut line);
-                    self.lines
-                        .put(row, LineEntry::LineAndFetching(line, instant));
-                }
-                Some(entry) => {
-                    self.lines.put(row, entry);
-                }
-                None => {}
-            }
-        }
-        self.cursor_position.y += lines.len().saturating_sub(1) as StableRowIndex;
+        let line = self.predicted_cursor_line().cloned();
+        let expected_ack = Instant::now() + Duration::from_millis(self.last_input_rtt);
+        self.predictor
+            .predict_paste(text, line.as_ref(), self.dimensions.cols, expected_ack);
     }
 
     pub fn update_last_send(&mut self) {
@@ -312,6 +206,7 @@ impl RenderableInner {
         let now = Instant::now();
         self.poll_interval = BASE_POLL_INTERVAL;
         self.last_recv_time = now;
+        self.predictor.apply_changes(&delta, &bonus_lines, now);
 
         let mut dirty = RangeSet::new();
         for r in delta.dirty_lines {
@@ -420,6 +315,7 @@ impl RenderableInner {
     }
 
     pub fn make_all_stale(&mut self) {
+        self.predictor.reset();
         let mut lines = LruCache::unbounded();
         while let Some((stable_row, entry)) = self.lines.pop_lru() {
             let entry = match entry {
@@ -468,6 +364,8 @@ impl RenderableInner {
                         self.seqno
                     );
                     line.update_last_change_seqno(self.seqno);
+                    self.predictor
+                        .validate_row(stable_row, &line, Instant::now());
                     LineEntry::Line(line)
                 }
                 Some(e) => {
@@ -718,7 +616,9 @@ pub(crate) async fn hydrate_lines(
 
 impl RenderableState {
     pub fn get_cursor_position(&self) -> StableCursorPosition {
-        self.inner.borrow().cursor_position
+        let mut inner = self.inner.borrow_mut();
+        inner.predictor.validate_cursor(Instant::now());
+        inner.predictor.cursor_position(inner.cursor_position)
     }
 
     pub fn get_lines(&self, lines: Range<StableRowIndex>) -> (StableRowIndex, Vec<Line>) {
@@ -730,6 +630,7 @@ impl RenderableState {
         for idx in lines.clone() {
             let entry = match inner.lines.pop(&idx) {
                 Some(LineEntry::Line(line)) => {
+                    inner.predictor.validate_row(idx, &line, now);
                     result.push(line.clone());
                     if line.changed_since(inner.seqno) {
                         to_fetch.add(idx);
@@ -758,12 +659,20 @@ impl RenderableState {
                 }
             };
 
-            if inner.client.overlay_lag_indicator && idx == inner.dimensions.physical_top {
-                if inner.is_tardy() {
-                    let status = format!(
+            inner.predictor.overlay(idx, result.last_mut().unwrap());
+
+            if idx == inner.dimensions.physical_top {
+                let status = if inner.client.client.is_reconnecting() {
+                    Some("wezterm: ⏳reconnecting".to_string())
+                } else if inner.client.overlay_lag_indicator && inner.is_tardy() {
+                    Some(format!(
                         "wezterm: {:.0?}⏳since last response",
                         inner.last_recv_time.elapsed()
-                    );
+                    ))
+                } else {
+                    None
+                };
+                if let Some(status) = status {
                     // Right align it in the tab
                     let col = inner
                         .dimensions
@@ -835,7 +744,7 @@ impl RenderableState {
 
         // If we're behind receiving an update, invalidate the top row so
         // that the indicator will update in a more timely fashion
-        if inner.is_tardy() {
+        if inner.is_tardy() || inner.client.client.is_reconnecting() {
             // ... but take care to avoid always reporting it as dirty, so
             // that we don't end up busy looping just to repaint it
             if inner.last_late_dirty.elapsed() >= Duration::from_secs(1) {
@@ -1,7 +1,7 @@
 use crate::error::Error;
 use crate::value::Value;
 use ordered_float::OrderedFloat;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryInto;
 use std::hash::Hash;
 
@@ -124,6 +124,24 @@ impl<K: FromDynamic + Eq + Hash, T: FromDynamic> FromDynamic for HashMap<K, T> {
     }
 }
 
+impl<K: FromDynamic + Ord, T: FromDynamic> FromDynamic for BTreeMap<K, T> {
+    fn from_dynamic(value: &Value, options: FromDynamicOptions) -> Result<Self, Error> {
+        match value {
+            Value::Object(obj) => {
+                let mut map = BTreeMap::new();
+                for (k, v) in obj.iter() {
+                    map.insert(K::from_dynamic(k, options)?, T::from_dynamic(v, options)?);
+                }
+                Ok(map)
+            }
+            other => Err(Error::NoConversion {
+                source_type: other.variant_name().to_string(),
+                dest_type: "BTreeMap",
+            }),
+        }
+    }
+}
+
 impl<T: FromDynamic> FromDynamic for Vec<T> {
     fn from_dynamic(value: &Value, options: FromDynamicOptions) -> Result<Self, Error> {
         match value {
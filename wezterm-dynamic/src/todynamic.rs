@@ -50,6 +50,17 @@ impl<K: ToDynamic + ToString + 'static, T: ToDynamic> ToDynamic for HashMap<K, T
     }
 }
 
+impl<K: ToDynamic + ToString + 'static, T: ToDynamic> ToDynamic for BTreeMap<K, T> {
+    fn to_dynamic(&self) -> Value {
+        Value::Object(
+            self.iter()
+                .map(|(k, v)| (k.to_dynamic(), v.to_dynamic()))
+                .collect::<BTreeMap<_, _>>()
+                .into(),
+        )
+    }
+}
+
 impl<T: ToDynamic> ToDynamic for std::sync::Arc<T> {
     fn to_dynamic(&self) -> Value {
         self.as_ref().to_dynamic()
@@ -288,3 +288,28 @@ fn enum_into() {
         EnumInto::from_dynamic(&Value::String("age:42".to_string()), Default::default()).unwrap()
     );
 }
+
+#[test]
+fn btreemap() {
+    let value = Value::Object(
+        btreemap!(
+            "wght".to_dynamic() => Value::U64(450),
+            "CASL".to_dynamic() => Value::F64(OrderedFloat(0.5)))
+        .into(),
+    );
+    let map = std::collections::BTreeMap::<String, f64>::from_dynamic(&value, Default::default())
+        .unwrap();
+    assert_eq!(
+        map,
+        btreemap!("CASL".to_string() => 0.5, "wght".to_string() => 450.0)
+    );
+    assert_eq!(
+        map.to_dynamic(),
+        Value::Object(
+            btreemap!(
+                "wght".to_dynamic() => Value::F64(OrderedFloat(450.0)),
+                "CASL".to_dynamic() => Value::F64(OrderedFloat(0.5)))
+            .into(),
+        )
+    );
+}
@@ -1,7 +1,7 @@
 //! Higher level freetype bindings
 
 use crate::locator::{FontDataHandle, FontDataSource};
-use crate::parser::ParsedFont;
+use crate::parser::{best_name, FontVariationInfo, NamedInstance, ParsedFont, VariationAxis};
 use crate::rasterizer::colr::DrawOp;
 use anyhow::{anyhow, Context};
 use config::{configuration, FreeTypeLoadFlags, FreeTypeLoadTarget};
@@ -208,6 +208,144 @@ impl Face {
         }
     }
 
+    pub fn is_variable(&self) -> bool {
+        unsafe { ((*self.face).face_flags as u32) & FT_FACE_FLAG_MULTIPLE_MASTERS != 0 }
+    }
+
+    /// Returns the axes and named instances of a variable font
+    pub fn variation_info(&self) -> anyhow::Result<FontVariationInfo> {
+        let mut mm = std::ptr::null_mut();
+
+        unsafe {
+            ft_result(FT_Get_MM_Var(self.face, &mut mm), ()).context("FT_Get_MM_Var")?;
+            let info = {
+                let mm = &*mm;
+                let axes = std::slice::from_raw_parts(mm.axis, mm.num_axis as usize);
+                let styles = std::slice::from_raw_parts(mm.namedstyle, mm.num_namedstyles as usize);
+
+                let strids: Vec<u32> = axes
+                    .iter()
+                    .map(|axis| axis.strid)
+                    .chain(styles.iter().map(|style| style.strid))
+                    .collect();
+                let names = self.get_sfnt_names_matching(|id| strids.contains(&id));
+                let name_of = |strid: u32| names.get(&strid).map(|records| best_name(records));
+
+                FontVariationInfo {
+                    axes: axes
+                        .iter()
+                        .map(|axis| VariationAxis {
+                            tag: tag_to_string(axis.tag),
+                            name: name_of(axis.strid).unwrap_or_else(|| {
+                                if axis.name.is_null() {
+                                    String::new()
+                                } else {
+                                    CStr::from_ptr(axis.name).to_string_lossy().to_string()
+                                }
+                            }),
+                            minimum: axis.minimum.to_num(),
+                            default: axis.def.to_num(),
+                            maximum: axis.maximum.to_num(),
+                        })
+                        .collect(),
+                    named_instances: styles
+                        .iter()
+                        .map(|style| NamedInstance {
+                            name: name_of(style.strid).unwrap_or_default(),
+                            coords: std::slice::from_raw_parts(style.coords, axes.len())
+                                .iter()
+                                .map(|c| c.to_num())
+                                .collect(),
+                        })
+                        .collect(),
+                }
+            };
+            FT_Done_MM_Var(self.lib, mm);
+
+            Ok(info)
+        }
+    }
+
+    /// Set the design coordinates of the variation axes named by
+    /// tag in `variations`; the other axes retain their current
+    /// value, which is that of the named instance, if any, that
+    /// was selected when the face was loaded.
+    pub fn set_variations(&mut self, variations: &[(String, f64)]) -> anyhow::Result<()> {
+        if !self.is_variable() {
+            log::warn!(
+                "{}: ignoring variations because it is not a variable font",
+                self.source.diagnostic_string()
+            );
+            return Ok(());
+        }
+
+        let mut mm = std::ptr::null_mut();
+
+        unsafe {
+            ft_result(FT_Get_MM_Var(self.face, &mut mm), ()).context("FT_Get_MM_Var")?;
+            let axes: Vec<FT_Var_Axis> =
+                std::slice::from_raw_parts((*mm).axis, (*mm).num_axis as usize).to_vec();
+            FT_Done_MM_Var(self.lib, mm);
+
+            let mut coords = vec![FT_Fixed::from_num(0); axes.len()];
+            ft_result(
+                FT_Get_Var_Design_Coordinates(self.face, coords.len() as _, coords.as_mut_ptr()),
+                (),
+            )
+            .context("FT_Get_Var_Design_Coordinates")?;
+
+            for (tag, value) in variations {
+                let idx = tag_from_str(tag).and_then(|tag| axes.iter().position(|a| a.tag == tag));
+                match idx {
+                    Some(idx) => {
+                        let axis = &axes[idx];
+                        coords[idx] =
+                            FT_Fixed::saturating_from_num(*value).clamp(axis.minimum, axis.maximum);
+                    }
+                    None => {
+                        log::warn!(
+                            "{}: font has no variation axis `{}`",
+                            self.source.diagnostic_string(),
+                            tag
+                        );
+                    }
+                }
+            }
+
+            ft_result(
+                FT_Set_Var_Design_Coordinates(self.face, coords.len() as _, coords.as_mut_ptr()),
+                (),
+            )
+            .context("FT_Set_Var_Design_Coordinates")
+        }
+    }
+
+    /// Returns the current design coordinates of the variation
+    /// axes, in the order in which the font defines them.
+    /// The result is empty for fonts that are not variable.
+    pub fn design_coordinates(&self) -> anyhow::Result<Vec<f32>> {
+        if !self.is_variable() {
+            return Ok(vec![]);
+        }
+
+        let mut mm = std::ptr::null_mut();
+
+        unsafe {
+            ft_result(FT_Get_MM_Var(self.face, &mut mm), ()).context("FT_Get_MM_Var")?;
+            let num_axis = (*mm).num_axis as usize;
+            FT_Done_MM_Var(self.lib, mm);
+
+            let mut coords = vec![FT_Fixed::from_num(0); num_axis];
+            ft_result(
+                FT_Get_Var_Design_Coordinates(self.face, coords.len() as _, coords.as_mut_ptr()),
+                (),
+            )
+            .context("FT_Get_Var_Design_Coordinates")?;
+
+            Ok(coords.iter().map(|c| c.to_num()).collect())
+        }
+    }
+
     pub fn get_glyph_name(&self, glyph_index: u32) -> Option<String> {
         let mut buf = [0u8; 128];
         let res = unsafe {
@@ -226,6 +364,24 @@ impl Face {
     }
 
     pub fn get_sfnt_names(&self) -> HashMap<u32, Vec<NameRecord>> {
+        self.get_sfnt_names_matching(|name_id| {
+            matches!(
+                name_id,
+                TT_NAME_ID_TYPOGRAPHIC_FAMILY
+                    | TT_NAME_ID_TYPOGRAPHIC_SUBFAMILY
+                    | TT_NAME_ID_FONT_FAMILY
+                    | TT_NAME_ID_FONT_SUBFAMILY
+                    | TT_NAME_ID_PS_NAME
+            )
+        })
+    }
+
+    /// Returns the entries from the name table with ids
+    /// for which `wanted` returns true
+    fn get_sfnt_names_matching(
+        &self,
+        wanted: impl Fn(u32) -> bool,
+    ) -> HashMap<u32, Vec<NameRecord>> {
         let num_names = unsafe { FT_Get_Sfnt_Name_Count(self.face) };
 
         let mut names = HashMap::new();
@@ -248,14 +404,7 @@ impl Face {
                 continue;
             }
 
-            if !matches!(
-                sfnt_name.name_id as u32,
-                TT_NAME_ID_TYPOGRAPHIC_FAMILY
-                    | TT_NAME_ID_TYPOGRAPHIC_SUBFAMILY
-                    | TT_NAME_ID_FONT_FAMILY
-                    | TT_NAME_ID_FONT_SUBFAMILY
-                    | TT_NAME_ID_PS_NAME
-            ) {
+            if !wanted(sfnt_name.name_id as u32) {
                 continue;
             }
 
@@ -436,6 +585,10 @@ impl Face {
     pub fn pixel_sizes(&self) -> Vec<u16> {
         let sizes = unsafe {
             let rec = &(*self.face);
+            // Scalable fonts have no strikes, and available_sizes is NULL
+            if rec.available_sizes.is_null() {
+                return vec![];
+            }
             std::slice::from_raw_parts(rec.available_sizes, rec.num_fixed_sizes as usize)
         };
         sizes
@@ -1539,3 +1692,19 @@ pub fn composite_mode_to_operator(mode: FT_Composite_Mode) -> cairo::Operator {
 fn ft_make_tag(a: u8, b: u8, c: u8, d: u8) -> FT_ULong {
     (a as FT_ULong) << 24 | (b as FT_ULong) << 16 | (c as FT_ULong) << 8 | (d as FT_ULong)
 }
+
+/// Convert an OpenType tag such as `wght` to its numeric form.
+/// Tags shorter than four characters are padded with spaces.
+pub fn tag_from_str(tag: &str) -> Option<FT_ULong> {
+    if tag.is_empty() || tag.len() > 4 || !tag.is_ascii() {
+        return None;
+    }
+    let mut bytes = [b' '; 4];
+    bytes[..tag.len()].copy_from_slice(tag.as_bytes());
+    Some(ft_make_tag(bytes[0], bytes[1], bytes[2], bytes[3]))
+}
+
+fn tag_to_string(tag: FT_ULong) -> String {
+    let bytes = (tag as u32).to_be_bytes();
+    String::from_utf8_lossy(&bytes).trim_end().to_string()
+}
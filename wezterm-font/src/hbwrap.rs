@@ -310,6 +310,14 @@ impl Font {
         }
     }
 
+    /// Set the design coordinates of all of the variation axes,
+    /// in the order in which the font defines them
+    pub fn set_var_coords_design(&mut self, coords: &[f32]) {
+        unsafe {
+            hb_font_set_var_coords_design(self.font, coords.as_ptr(), coords.len() as c_uint);
+        }
+    }
+
     pub fn set_synthetic_slant(&mut self, slant: f32) {
         unsafe {
             hb_font_set_synthetic_slant(self.font, slant);
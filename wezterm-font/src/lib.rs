@@ -23,6 +23,7 @@ use wezterm_term::{CellAttributes, Intensity};
 use wezterm_toast_notification::ToastNotification;
 
 mod hbwrap;
+mod ttfwrap;
 
 pub mod db;
 pub mod ftwrap;
@@ -440,6 +441,7 @@ impl FallbackResolveInfo {
                     ),
                     url: Some(url.to_string()),
                     timeout: Some(Duration::from_secs(15)),
+                    ..Default::default()
                 }
                 .show();
             } else {
@@ -20,6 +20,29 @@ pub struct FontPaletteInfo {
     pub usable_with_dark_bg: bool,
 }
 
+#[derive(Debug, Clone)]
+pub struct VariationAxis {
+    pub tag: String,
+    pub name: String,
+    pub minimum: f64,
+    pub default: f64,
+    pub maximum: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct NamedInstance {
+    pub name: String,
+    /// The design coordinates, in the same order as the axes
+    pub coords: Vec<f64>,
+}
+
+/// Describes the axes of a variable font
+#[derive(Debug, Clone, Default)]
+pub struct FontVariationInfo {
+    pub axes: Vec<VariationAxis>,
+    pub named_instances: Vec<NamedInstance>,
+}
+
 /// Represents a parsed font
 pub struct ParsedFont {
     names: Names,
@@ -36,12 +59,16 @@ pub struct ParsedFont {
     pub pixel_sizes: Vec<u16>,
     pub is_built_in_fallback: bool,
     pub palettes: Vec<FontPaletteInfo>,
+    pub variation_info: FontVariationInfo,
 
     pub harfbuzz_features: Option<Vec<String>>,
     pub freetype_load_target: Option<FreeTypeLoadTarget>,
     pub freetype_render_target: Option<FreeTypeLoadTarget>,
     pub freetype_load_flags: Option<FreeTypeLoadFlags>,
     pub scale: Option<f64>,
+    /// Design coordinates to apply to the variation axes,
+    /// keyed by axis tag
+    pub variations: Vec<(String, f64)>,
 }
 
 impl std::fmt::Debug for ParsedFont {
@@ -63,6 +90,7 @@ impl std::fmt::Debug for ParsedFont {
             .field("freetype_render_target", &self.freetype_render_target)
             .field("freetype_load_flags", &self.freetype_load_flags)
             .field("scale", &self.scale)
+            .field("variations", &self.variations)
             .finish()
     }
 }
@@ -88,7 +116,9 @@ impl Clone for ParsedFont {
             freetype_load_flags: self.freetype_load_flags,
             is_built_in_fallback: self.is_built_in_fallback,
             scale: self.scale,
+            variations: self.variations.clone(),
             palettes: self.palettes.clone(),
+            variation_info: self.variation_info.clone(),
         }
     }
 }
@@ -141,7 +171,7 @@ pub struct Names {
 /// Best is English from a MS entry if available, as freetype's
 /// source claims that a number of Mac entries have somewhat
 /// broken encodings.
-fn best_name(records: &[crate::ftwrap::NameRecord]) -> String {
+pub(crate) fn best_name(records: &[crate::ftwrap::NameRecord]) -> String {
     let mut win = None;
     let mut uni = None;
     let mut apple = None;
@@ -325,6 +355,26 @@ impl ParsedFont {
                     code.push_str(&info);
                 }
             }
+            for axis in &p.variation_info.axes {
+                code.push_str(&format!(
+                    "  -- Variation axis: {} \"{}\" {}..={}, default {}\n",
+                    axis.tag, axis.name, axis.minimum, axis.maximum, axis.default
+                ));
+            }
+            for instance in &p.variation_info.named_instances {
+                let coords: Vec<String> = p
+                    .variation_info
+                    .axes
+                    .iter()
+                    .zip(&instance.coords)
+                    .map(|(axis, value)| format!("{}={}", axis.tag, value))
+                    .collect();
+                code.push_str(&format!(
+                    "  -- Named instance: \"{}\" {{{}}}\n",
+                    instance.name,
+                    coords.join(", ")
+                ));
+            }
             for aka in &p.names.aliases {
                 code.push_str(&format!("  -- AKA: \"{}\"\n", aka));
             }
@@ -337,6 +387,7 @@ impl ParsedFont {
                 && p.freetype_load_flags.is_none()
                 && p.harfbuzz_features.is_none()
                 && p.scale.is_none()
+                && p.variations.is_empty()
             {
                 code.push_str(&format!("  \"{}\",\n", p.names.family));
             } else {
@@ -374,6 +425,14 @@ impl ParsedFont {
                     }
                     code.push('}');
                 }
+                if !p.variations.is_empty() {
+                    let variations: Vec<String> = p
+                        .variations
+                        .iter()
+                        .map(|(tag, value)| format!("{}={}", tag, value))
+                        .collect();
+                    code.push_str(&format!(", variations={{{}}}", variations.join(", ")));
+                }
                 code.push_str("},\n")
             }
             code.push_str("\n");
@@ -412,6 +471,12 @@ impl ParsedFont {
             Err(_) => vec![],
         };
 
+        let variation_info = if face.is_variable() {
+            face.variation_info().unwrap_or_default()
+        } else {
+            FontVariationInfo::default()
+        };
+
         let has_svg = unsafe {
             (((*face.face).face_flags as u32) & (crate::ftwrap::FT_FACE_FLAG_SVG as u32)) != 0
         };
@@ -529,7 +594,9 @@ impl ParsedFont {
             freetype_load_target: None,
             freetype_load_flags: None,
             scale: None,
+            variations: vec![],
             palettes,
+            variation_info,
         })
     }
 
@@ -781,6 +848,12 @@ impl ParsedFont {
         self.freetype_load_target = attr.freetype_load_target;
         self.freetype_load_flags = attr.freetype_load_flags;
         self.scale = attr.scale.map(|f| *f);
+        self.variations = attr
+            .variations
+            .iter()
+            .flatten()
+            .map(|(tag, value)| (tag.clone(), **value))
+            .collect();
 
         self.synthesize_italic = self.style == FontStyle::Normal && attr.style != FontStyle::Normal;
         self.synthesize_bold = attr.weight >= FontWeight::DEMIBOLD
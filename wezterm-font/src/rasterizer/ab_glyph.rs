@@ -0,0 +1,386 @@
+use crate::parser::ParsedFont;
+use crate::rasterizer::{FontRasterizer, FAKE_ITALIC_SKEW};
+use crate::ttfwrap;
+use crate::units::*;
+use crate::RasterizedGlyph;
+use ab_glyph_rasterizer::{point, Point, Rasterizer};
+use anyhow::Context;
+use rustybuzz::ttf_parser::{GlyphId, OutlineBuilder, RasterGlyphImage, RasterImageFormat};
+use wezterm_color_types::linear_u8_to_srgb8;
+
+/// A rasterizer implemented in pure Rust that renders the glyph
+/// outlines using ab_glyph_rasterizer, and that decodes the bitmap
+/// strikes of fonts that have them.
+/// Glyphs are not hinted, and COLR and SVG color glyphs are not
+/// supported: they render as their monochrome outline, if any.
+pub struct AbGlyphRasterizer {
+    face: ttfwrap::Face,
+    synthesize_italic: bool,
+    synthesize_bold: bool,
+    scale: f64,
+}
+
+impl AbGlyphRasterizer {
+    pub fn from_locator(parsed: &ParsedFont) -> anyhow::Result<Self> {
+        log::trace!("Rasterizier wants {:?}", parsed);
+        Ok(Self {
+            face: ttfwrap::Face::from_parsed(parsed)?,
+            synthesize_italic: parsed.synthesize_italic,
+            synthesize_bold: parsed.synthesize_bold,
+            scale: parsed.scale.unwrap_or(1.),
+        })
+    }
+
+    fn rasterize_image(
+        &self,
+        image: RasterGlyphImage,
+        is_scaled: bool,
+    ) -> anyhow::Result<RasterizedGlyph> {
+        let mut width = image.width as usize;
+        let mut height = image.height as usize;
+
+        let (data, has_color) = match image.format {
+            RasterImageFormat::PNG => {
+                let decoded = image::load_from_memory(image.data)
+                    .context("decoding PNG bitmap strike")?
+                    .into_rgba8();
+                // The dimensions in the image itself are authoritative
+                width = decoded.width() as usize;
+                height = decoded.height() as usize;
+                let mut data = decoded.into_vec();
+                // RasterizedGlyph is premultiplied, but PNG is not
+                for pixel in data.chunks_exact_mut(4) {
+                    let alpha = u16::from(pixel[3]);
+                    for c in &mut pixel[0..3] {
+                        *c = ((u16::from(*c) * alpha) / 255) as u8;
+                    }
+                }
+                (data, true)
+            }
+            RasterImageFormat::BitmapPremulBgra32 => {
+                let mut data = image.data.to_vec();
+                for pixel in data.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                }
+                (data, true)
+            }
+            RasterImageFormat::BitmapGray8 => (coverage_to_rgba(image.data), false),
+            RasterImageFormat::BitmapMono | RasterImageFormat::BitmapMonoPacked => {
+                let padded = image.format == RasterImageFormat::BitmapMono;
+                let stride = if padded { width.div_ceil(8) * 8 } else { width };
+                let mut coverage = vec![0u8; width * height];
+                for y in 0..height {
+                    for x in 0..width {
+                        let bit = y * stride + x;
+                        if let Some(byte) = image.data.get(bit / 8) {
+                            if byte & (0x80 >> (bit % 8)) != 0 {
+                                coverage[y * width + x] = 0xff;
+                            }
+                        }
+                    }
+                }
+                (coverage_to_rgba(&coverage), false)
+            }
+            format => anyhow::bail!("unsupported bitmap strike format {format:?}"),
+        };
+
+        if data.len() != width * height * 4 {
+            anyhow::bail!(
+                "bitmap strike has {} bytes of data but is {width}x{height}",
+                data.len()
+            );
+        }
+
+        Ok(RasterizedGlyph {
+            data,
+            height,
+            width,
+            bearing_x: PixelLength::new(f64::from(image.x)),
+            // `y` is the bottom of the image, relative to the baseline
+            bearing_y: PixelLength::new(f64::from(image.y) + height as f64),
+            has_color,
+            is_scaled,
+        })
+    }
+
+    fn rasterize_outline(&self, glyph: GlyphId, pixel_size: f64) -> RasterizedGlyph {
+        let mut outline = Outline {
+            scale: self.face.units_to_pixels(pixel_size) as f32,
+            skew: if self.synthesize_italic {
+                FAKE_ITALIC_SKEW as f32
+            } else {
+                0.
+            },
+            segments: vec![],
+            start: point(0., 0.),
+            last: point(0., 0.),
+        };
+        self.face.face().outline_glyph(glyph, &mut outline);
+
+        let empty = RasterizedGlyph {
+            data: vec![],
+            height: 0,
+            width: 0,
+            bearing_x: PixelLength::new(0.),
+            bearing_y: PixelLength::new(0.),
+            has_color: false,
+            is_scaled: true,
+        };
+
+        let Some((min, max)) = outline.bounds() else {
+            return empty;
+        };
+
+        let left = min.x.floor();
+        let top = max.y.ceil();
+        let mut width = (max.x.ceil() - left) as usize;
+        let height = (top - min.y.floor()) as usize;
+        if width == 0 || height == 0 {
+            return empty;
+        }
+
+        // Translate so that the top left of the bounds is at the
+        // origin, with y increasing downwards
+        let map = |p: Point| point(p.x - left, top - p.y);
+        let mut raster = Rasterizer::new(width, height);
+        for segment in &outline.segments {
+            match *segment {
+                Segment::Line(p0, p1) => raster.draw_line(map(p0), map(p1)),
+                Segment::Quad(p0, p1, p2) => raster.draw_quad(map(p0), map(p1), map(p2)),
+                Segment::Cubic(p0, p1, p2, p3) => {
+                    raster.draw_cubic(map(p0), map(p1), map(p2), map(p3))
+                }
+            }
+        }
+
+        let mut coverage = vec![0u8; width * height];
+        raster.for_each_pixel(|idx, alpha| {
+            coverage[idx] = (alpha.clamp(0., 1.) * 255.).round() as u8;
+        });
+
+        if self.synthesize_bold {
+            // Approximate FreeType's emboldening, which widens the
+            // glyph by 1/24th of the em size
+            let strength = (pixel_size / 24.).round().max(1.) as usize;
+            let bold_width = width + strength;
+            let mut bold = vec![0u8; bold_width * height];
+            for y in 0..height {
+                let row = &coverage[y * width..(y + 1) * width];
+                for x in 0..bold_width {
+                    let first = x.saturating_sub(strength);
+                    let last = x.min(width - 1);
+                    bold[y * bold_width + x] = row[first..=last].iter().copied().max().unwrap_or(0);
+                }
+            }
+            coverage = bold;
+            width = bold_width;
+        }
+
+        RasterizedGlyph {
+            data: coverage_to_rgba(&coverage),
+            height,
+            width,
+            bearing_x: PixelLength::new(left as f64),
+            bearing_y: PixelLength::new(top as f64),
+            has_color: false,
+            is_scaled: true,
+        }
+    }
+}
+
+impl FontRasterizer for AbGlyphRasterizer {
+    fn rasterize_glyph(
+        &self,
+        glyph_pos: u32,
+        size: f64,
+        dpi: u32,
+    ) -> anyhow::Result<RasterizedGlyph> {
+        let (pixel_size, is_scaled) = self.face.pixel_size(size * self.scale, dpi);
+        let glyph = GlyphId(
+            glyph_pos
+                .try_into()
+                .with_context(|| format!("glyph index {glyph_pos} is out of range"))?,
+        );
+
+        // Prefer bitmap strikes for fonts without outlines, and for
+        // color glyphs, which are typically emoji
+        let ppem = pixel_size.round().clamp(1., u16::MAX as f64) as u16;
+        if let Some(image) = self.face.face().glyph_raster_image(glyph, ppem) {
+            let is_color = matches!(
+                image.format,
+                RasterImageFormat::PNG | RasterImageFormat::BitmapPremulBgra32
+            );
+            if is_color || !self.face.has_outlines() {
+                return self.rasterize_image(image, is_scaled);
+            }
+        }
+
+        Ok(self.rasterize_outline(glyph, pixel_size))
+    }
+}
+
+/// Converts linear coverage values into the gamma adjusted, premultiplied
+/// RGBA representation used by the FreeType rasterizer
+fn coverage_to_rgba(coverage: &[u8]) -> Vec<u8> {
+    let mut rgba = Vec::with_capacity(coverage.len() * 4);
+    for &linear_gray in coverage {
+        let gray = linear_u8_to_srgb8(linear_gray);
+        rgba.extend_from_slice(&[gray, gray, gray, linear_gray]);
+    }
+    rgba
+}
+
+enum Segment {
+    Line(Point, Point),
+    Quad(Point, Point, Point),
+    Cubic(Point, Point, Point, Point),
+}
+
+/// Collects the outline of a glyph, scaled to pixels and with any
+/// synthetic italic skew applied.  The y axis points up.
+struct Outline {
+    scale: f32,
+    skew: f32,
+    segments: Vec<Segment>,
+    start: Point,
+    last: Point,
+}
+
+impl Outline {
+    fn point(&self, x: f32, y: f32) -> Point {
+        point((x + y * self.skew) * self.scale, y * self.scale)
+    }
+
+    /// Returns the bounding box of the points that comprise the outline
+    fn bounds(&self) -> Option<(Point, Point)> {
+        let mut points = self.segments.iter().flat_map(|s| match *s {
+            Segment::Line(a, b) => vec![a, b],
+            Segment::Quad(a, b, c) => vec![a, b, c],
+            Segment::Cubic(a, b, c, d) => vec![a, b, c, d],
+        });
+        let first = points.next()?;
+        Some(points.fold((first, first), |(min, max), p| {
+            (
+                point(min.x.min(p.x), min.y.min(p.y)),
+                point(max.x.max(p.x), max.y.max(p.y)),
+            )
+        }))
+    }
+}
+
+impl OutlineBuilder for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = self.point(x, y);
+        self.last = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.point(x, y);
+        self.segments.push(Segment::Line(self.last, p));
+        self.last = p;
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let p1 = self.point(x1, y1);
+        let p = self.point(x, y);
+        self.segments.push(Segment::Quad(self.last, p1, p));
+        self.last = p;
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let p1 = self.point(x1, y1);
+        let p2 = self.point(x2, y2);
+        let p = self.point(x, y);
+        self.segments.push(Segment::Cubic(self.last, p1, p2, p));
+        self.last = p;
+    }
+
+    fn close(&mut self) {
+        if self.last != self.start {
+            self.segments.push(Segment::Line(self.last, self.start));
+        }
+        self.last = self.start;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rasterizer::freetype::FreeTypeRasterizer;
+    use crate::FontDatabase;
+    use config::{DisplayPixelGeometry, FontAttributes};
+
+    fn coverage(glyph: &RasterizedGlyph) -> f64 {
+        glyph
+            .data
+            .chunks_exact(4)
+            .map(|pixel| f64::from(pixel[3]))
+            .sum()
+    }
+
+    #[test]
+    fn parity_with_freetype() {
+        let db = FontDatabase::with_built_in().unwrap();
+        let parsed = db
+            .resolve(
+                &FontAttributes {
+                    family: "JetBrains Mono".into(),
+                    stretch: Default::default(),
+                    weight: Default::default(),
+                    is_fallback: false,
+                    is_synthetic: false,
+                    style: Default::default(),
+                    freetype_load_flags: None,
+                    freetype_load_target: None,
+                    freetype_render_target: None,
+                    harfbuzz_features: None,
+                    scale: None,
+                    assume_emoji_presentation: None,
+                    variations: None,
+                },
+                14,
+            )
+            .unwrap()
+            .clone();
+
+        let ab = AbGlyphRasterizer::from_locator(&parsed).unwrap();
+        let ft = FreeTypeRasterizer::from_locator(&parsed, DisplayPixelGeometry::RGB).unwrap();
+        let face = ttfwrap::Face::from_parsed(&parsed).unwrap();
+
+        for c in ['a', 'M', 'g', '@', '\u{e9}'] {
+            let glyph_pos = face.face().glyph_index(c).unwrap().0 as u32;
+            let actual = ab.rasterize_glyph(glyph_pos, 12., 96).unwrap();
+            let expected = ft.rasterize_glyph(glyph_pos, 12., 96).unwrap();
+            let context = format!(
+                "{c:?}: {}x{} at {:?},{:?} vs freetype {}x{} at {:?},{:?}",
+                actual.width,
+                actual.height,
+                actual.bearing_x,
+                actual.bearing_y,
+                expected.width,
+                expected.height,
+                expected.bearing_x,
+                expected.bearing_y,
+            );
+
+            // FreeType hints the outlines, so allow them to differ by a pixel
+            assert!(actual.width.abs_diff(expected.width) <= 1, "{context}");
+            assert!(actual.height.abs_diff(expected.height) <= 1, "{context}");
+            assert!(
+                (actual.bearing_x.get() - expected.bearing_x.get()).abs() <= 1.,
+                "{context}"
+            );
+            assert!(
+                (actual.bearing_y.get() - expected.bearing_y.get()).abs() <= 1.,
+                "{context}"
+            );
+
+            let ratio = coverage(&actual) / coverage(&expected);
+            assert!((0.9..1.1).contains(&ratio), "{context}: coverage {ratio}");
+        }
+
+        let space = face.face().glyph_index(' ').unwrap().0 as u32;
+        let glyph = ab.rasterize_glyph(space, 12., 96).unwrap();
+        assert_eq!((glyph.width, glyph.height), (0, 0));
+    }
+}
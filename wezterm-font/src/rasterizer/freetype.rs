@@ -78,7 +78,8 @@ impl FontRasterizer for FreeTypeRasterizer {
                                 load_flags | FT_LOAD_NO_HINTING as i32,
                             );
                         }
-                        FontRasterizerSelection::Harfbuzz => {
+                        // AbGlyph cannot render color layers
+                        FontRasterizerSelection::Harfbuzz | FontRasterizerSelection::AbGlyph => {
                             return self.hb_raster.rasterize_glyph(glyph_pos, size, dpi);
                         }
                     }
@@ -92,11 +93,16 @@ impl FontRasterizer for FreeTypeRasterizer {
 
         // pitch is the number of bytes per source row
         let pitch = ft_glyph.bitmap.pitch.abs() as usize;
-        let data = unsafe {
-            slice::from_raw_parts_mut(
-                ft_glyph.bitmap.buffer,
-                ft_glyph.bitmap.rows as usize * pitch,
-            )
+        // The buffer is NULL for glyphs with an empty bitmap
+        let data: &'static mut [u8] = if ft_glyph.bitmap.buffer.is_null() {
+            &mut []
+        } else {
+            unsafe {
+                slice::from_raw_parts_mut(
+                    ft_glyph.bitmap.buffer,
+                    ft_glyph.bitmap.rows as usize * pitch,
+                )
+            }
         };
 
         let glyph = match mode {
@@ -384,6 +390,9 @@ impl FreeTypeRasterizer {
         log::trace!("Rasterizier wants {:?}", parsed);
         let lib = ftwrap::Library::new()?;
         let mut face = lib.face_from_locator(&parsed.handle)?;
+        if !parsed.variations.is_empty() {
+            face.set_variations(&parsed.variations)?;
+        }
         let has_color = unsafe {
             (((*face.face).face_flags as u32) & (ftwrap::FT_FACE_FLAG_COLOR as u32)) != 0
         };
@@ -1,3 +1,4 @@
+use crate::ftwrap;
 use crate::hbwrap::{
     hb_color, hb_color_get_alpha, hb_color_get_blue, hb_color_get_green, hb_color_get_red,
     hb_color_t, hb_paint_composite_mode_t, hb_tag_to_string, Font, PaintOp, IS_PNG,
@@ -21,6 +22,17 @@ impl HarfbuzzRasterizer {
         let mut font = Font::from_locator(&parsed.handle)?;
         font.set_ot_funcs();
 
+        if !parsed.variations.is_empty() {
+            // Let freetype resolve the coordinates of the axes that
+            // were not specified from the named instance, if any
+            let lib = ftwrap::Library::new()?;
+            let mut face = lib.face_from_locator(&parsed.handle)?;
+            if face.is_variable() {
+                face.set_variations(&parsed.variations)?;
+                font.set_var_coords_design(&face.design_coordinates()?);
+            }
+        }
+
         if parsed.synthesize_italic {
             font.set_synthetic_slant(FAKE_ITALIC_SKEW as f32);
         }
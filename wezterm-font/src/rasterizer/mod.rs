@@ -7,6 +7,7 @@ use image::{ImageBuffer, Rgba};
 /// italics
 pub(crate) const FAKE_ITALIC_SKEW: f64 = 0.2;
 
+pub mod ab_glyph;
 pub mod colr;
 pub mod freetype;
 pub mod harfbuzz;
@@ -49,6 +50,9 @@ pub fn new_rasterizer(
         FontRasterizerSelection::Harfbuzz => Ok(Box::new(
             harfbuzz::HarfbuzzRasterizer::from_locator(handle)?,
         )),
+        FontRasterizerSelection::AbGlyph => {
+            Ok(Box::new(ab_glyph::AbGlyphRasterizer::from_locator(handle)?))
+        }
     }
 }
 
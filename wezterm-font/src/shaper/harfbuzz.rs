@@ -1,16 +1,15 @@
 use crate::parser::ParsedFont;
+use crate::shaper::cluster::{assign_clusters, ShapedGlyph};
 use crate::shaper::{FallbackIdx, FontMetrics, FontShaper, GlyphInfo, PresentationWidth};
 use crate::units::*;
 use crate::{ftwrap, hbwrap as harfbuzz};
 use anyhow::{anyhow, Context};
 use config::ConfigHandle;
-use finl_unicode::grapheme_clusters::Graphemes;
-use log::error;
 use ordered_float::NotNan;
 use std::cell::{RefCell, RefMut};
 use std::collections::HashMap;
 use std::ops::Range;
-use termwiz::cell::{unicode_column_width, Presentation};
+use termwiz::cell::Presentation;
 use wezterm_bidi::Direction;
 
 // Changing these will switch to using harfbuzz's opentype functions.
@@ -23,46 +22,6 @@ use wezterm_bidi::Direction;
 const USE_OT_FUNCS: bool = false;
 const USE_OT_FACE: bool = false;
 
-#[derive(Clone, Debug)]
-struct Info {
-    cluster: usize,
-    len: usize,
-    codepoint: harfbuzz::hb_codepoint_t,
-    x_advance: harfbuzz::hb_position_t,
-    y_advance: harfbuzz::hb_position_t,
-    x_offset: harfbuzz::hb_position_t,
-    y_offset: harfbuzz::hb_position_t,
-}
-
-fn get_only_char(s: &str) -> Option<char> {
-    let mut chars = s.chars();
-    let first_char = chars.next()?;
-    if chars.next().is_some() {
-        None
-    } else {
-        Some(first_char)
-    }
-}
-
-fn make_glyphinfo(text: &str, num_cells: u8, font_idx: usize, info: &Info) -> GlyphInfo {
-    let is_space = text == " ";
-    let only_char = get_only_char(text);
-    GlyphInfo {
-        #[cfg(any(debug_assertions, test))]
-        text: text.into(),
-        only_char,
-        is_space,
-        num_cells,
-        font_idx,
-        glyph_pos: info.codepoint,
-        cluster: info.cluster as u32,
-        x_advance: PixelLength::new(f64::from(info.x_advance) / 64.0),
-        y_advance: PixelLength::new(f64::from(info.y_advance) / 64.0),
-        x_offset: PixelLength::new(f64::from(info.x_offset) / 64.0),
-        y_offset: PixelLength::new(f64::from(info.y_offset) / 64.0),
-    }
-}
-
 struct FontPair {
     face: ftwrap::Face,
     font: RefCell<harfbuzz::Font>,
@@ -88,33 +47,6 @@ pub struct HarfbuzzShaper {
     lang: harfbuzz::hb_language_t,
 }
 
-/// Make a string holding a set of unicode replacement
-/// characters equal to the number of graphemes in the
-/// original string.  That isn't perfect, but it should
-/// be good enough to indicate that something isn't right.
-fn make_question_string(s: &str) -> String {
-    let len = Graphemes::new(s).count();
-    let mut result = String::new();
-    let c = if !is_question_string(s) {
-        std::char::REPLACEMENT_CHARACTER
-    } else {
-        '?'
-    };
-    for _ in 0..len {
-        result.push(c);
-    }
-    result
-}
-
-fn is_question_string(s: &str) -> bool {
-    for c in s.chars() {
-        if c != std::char::REPLACEMENT_CHARACTER {
-            return false;
-        }
-    }
-    true
-}
-
 impl HarfbuzzShaper {
     pub fn new(config: &ConfigHandle, handles: &[ParsedFont]) -> anyhow::Result<Self> {
         let lib = ftwrap::Library::new()?;
@@ -157,9 +89,12 @@ impl HarfbuzzShaper {
                 if opt_pair.is_none() {
                     let handle = &self.handles[font_idx];
                     log::trace!("shaper wants {} {:?}", font_idx, handle);
-                    let face = self.lib.face_from_locator(&handle.handle)?;
+                    let mut face = self.lib.face_from_locator(&handle.handle)?;
+                    if !handle.variations.is_empty() {
+                        face.set_variations(&handle.variations)?;
+                    }
 
-                    let font = if USE_OT_FACE {
+                    let mut font = if USE_OT_FACE {
                         harfbuzz::Font::from_locator(&handle.handle)?
                     } else {
                         let (load_flags, _) = ftwrap::compute_load_flags_from_config(
@@ -172,6 +107,9 @@ impl HarfbuzzShaper {
                         font.set_load_flags(load_flags);
                         font
                     };
+                    if !handle.variations.is_empty() && face.is_variable() {
+                        font.set_var_coords_design(&face.design_coordinates()?);
+                    }
 
                     let features = match &handle.harfbuzz_features {
                         Some(features) => features
@@ -340,205 +278,41 @@ impl HarfbuzzShaper {
             }
         }
 
-        let hb_infos = buf.glyph_infos();
-        let positions = buf.glyph_positions();
-
-        let mut cluster = Vec::with_capacity(s.len());
-        let mut info_clusters: Vec<Vec<Info>> = Vec::with_capacity(s.len());
-
-        // At this point we have a list of glyphs from the shaper.
-        // Each glyph will have `info.cluster` set to the byte index
-        // into `s`.  Multiple byte positions can be coalesced into
-        // the same `info.cluster` value, representing text that combines
-        // into a ligature.
-        // It is important for the terminal to understand this relationship
-        // because the cell width of that range of text depends on the unicode
-        // version at the time that the text was added to the terminal.
-        // To calculate the width per glyph:
-        // * Make a pass over the clusters to identify the `info.cluster` starting
-        //   positions of all of the glyphs
-        // * Sort by info.cluster
-        // * Dedup
-        // * We can now get the byte length of each cluster by looking at the difference
-        //   between the `info.cluster` values.
-        // * `presentation_width` can be used to resolve the cell width of those
-        //   byte ranges.
-        // * Then distribute the glyphs across that cell width when assigning them
-        //   to a GlyphInfo.
-        //
-        // A further complication is that the shaper may cluster in surprising ways.
-        // For example, the sequence "\u{28}\u{ff9f}" is a single grapheme with
-        // width of 2 cells, but harfbuzz returns that as two different clusters.
-        //
-        // In situations where we know better (eg: when we have presentation_width)
-        // we can fixup the cluster information to be based on the cell index for
-        // the harfbuzz byte start.
-        // <https://github.com/wez/wezterm/issues/2572>
-
-        let mut cluster_resolver = ClusterResolver {
-            presentation_width,
-            ..Default::default()
-        };
-
-        cluster_resolver.build(hb_infos, s, &range);
-        log::debug!("cluster_resolver: {cluster_resolver:#?}");
-
-        let info_iter = hb_infos.iter().zip(positions.iter()).peekable();
-        for (info, pos) in info_iter {
-            let cluster_info = match cluster_resolver.get_mut(info.cluster as usize) {
-                Some(i) => i,
-                None => panic!(
-                    "expected cluster info.cluster {} to be in cluster_resolver",
-                    info.cluster
-                ),
-            };
-            let len = cluster_info.byte_len;
-
-            let mut info = Info {
-                // Take care to use our adjusted cluster_info.start rather
-                // than the info.cluster value, otherwise the combination
-                // of info.cluster + info.len will be out of bounds later
-                // in this code
-                cluster: cluster_info.start,
-                len,
+        let shaped: Vec<ShapedGlyph> = buf
+            .glyph_infos()
+            .iter()
+            .zip(buf.glyph_positions().iter())
+            .map(|(info, pos)| ShapedGlyph {
+                cluster: info.cluster as usize,
                 codepoint: info.codepoint,
                 x_advance: pos.x_advance,
                 y_advance: pos.y_advance,
                 x_offset: pos.x_offset,
                 y_offset: pos.y_offset,
-            };
-            log::debug!("hb info.cluster {} -> {info:?}", info.cluster);
-
-            if info.codepoint == 0 && !no_more_fallbacks {
-                cluster_info.incomplete = true;
-            }
-
-            if let Some(ref mut cluster) = info_clusters.last_mut() {
-                // Don't fragment runs of unresolved codepoints; they could be a sequence
-                // that shapes together in a fallback font.
-                if info.codepoint == 0 && !no_more_fallbacks {
-                    let prior = cluster.last_mut().unwrap();
-                    // This logic essentially merges `info` into `prior` by
-                    // extending the length of prior by `info`.
-                    // We can only do that if they are contiguous.
-                    // Take care, as the shaper may have re-ordered things!
-                    if prior.codepoint == 0 || prior.cluster == info.cluster {
-                        if prior.cluster + prior.len == info.cluster {
-                            // Coalesce with prior
-                            prior.len += info.len;
-                            continue;
-                        } else if info.cluster + info.len == prior.cluster {
-                            // We actually precede prior; we must have been
-                            // re-ordered by the shaper. Re-arrange and
-                            // coalesce
-                            std::mem::swap(&mut info, prior);
-                            prior.len += info.len;
-                            continue;
-                        } else if info.cluster + info.len == prior.cluster + prior.len {
-                            // Overlaps and coincide with the end of prior; this one folds away.
-                            // This can happen with NFD rather than NFC text.
-                            // <https://github.com/wez/wezterm/issues/2032>
-                            continue;
-                        }
-                        // log::info!("prior={:#?}, info={:#?}", prior, info);
-                    }
-                }
-
-                // It is important that this bit happens after we've had the
-                // opportunity to coalesce runs of unresolved codepoints,
-                // otherwise we can produce incorrect shaping
-                // <https://github.com/wez/wezterm/issues/2482>
-                if cluster.last().unwrap().cluster == info.cluster {
-                    cluster.push(info);
-                    continue;
-                }
-            }
-            info_clusters.push(vec![info]);
-        }
-        //  log::error!("do_shape: font_idx={} {:?} {:#?}", font_idx, &s[range.clone()], info_clusters);
-        log::debug!("font_idx={font_idx} info_clusters: {:#?}", info_clusters);
-
-        let mut direct_clusters = 0;
-
-        for infos in &info_clusters {
-            let cluster_info = cluster_resolver
-                .get(infos[0].cluster)
-                .expect("assigned above");
-            let sub_range = cluster_info.start..cluster_info.start + cluster_info.byte_len;
-            let substr = &s[sub_range.clone()];
-
-            if cluster_info.incomplete {
-                // One or more entries didn't have a corresponding glyph,
-                // so try a fallback
-
-                /*
-                if font_idx == 0 {
-                    log::error!("incomplete cluster for text={:?} {:?}", s, info_clusters);
-                }
-                */
-
-                let first_info = &infos[0];
+            })
+            .collect();
 
-                let mut shape = match self.do_shape(
-                    font_idx + 1,
+        let (cluster, direct_clusters) = assign_clusters(
+            &shaped,
+            s,
+            &range,
+            font_idx,
+            no_more_fallbacks,
+            presentation_width,
+            |font_idx, s, range| {
+                self.do_shape(
+                    font_idx,
                     s,
                     font_size,
                     dpi,
                     no_glyphs,
                     presentation,
                     direction,
-                    // NOT! substr; this is a coalesced sequence of incomplete clusters!
-                    first_info.cluster..first_info.cluster + first_info.len,
+                    range,
                     presentation_width,
-                ) {
-                    Ok(shape) => Ok(shape),
-                    Err(e) => {
-                        error!("{:?} for {:?}", e, substr);
-                        self.do_shape(
-                            0,
-                            &make_question_string(substr),
-                            font_size,
-                            dpi,
-                            no_glyphs,
-                            presentation,
-                            direction,
-                            sub_range,
-                            presentation_width,
-                        )
-                    }
-                }?;
-
-                cluster.append(&mut shape);
-                continue;
-            }
-
-            let total_width: f64 = infos.iter().map(|info| info.x_advance as f64).sum();
-            let mut remaining_cells = cluster_info.cell_width;
-
-            for info in infos.iter() {
-                // Proportional width based on relative pixel dimensions vs. other glyphs in
-                // this same cluster
-                // Note that weighted_cell_width can legitimately compute as zero here
-                // for the case where a combining mark composes over another glyph
-                // However, some symbol fonts have broken advance metrics and we don't
-                // want those glyphs to end up with zero width, so if this run is zero
-                // width then we round up to 1 cell.
-                // <https://github.com/wez/wezterm/issues/1787>
-                let weighted_cell_width = if total_width == 0. {
-                    1
-                } else {
-                    (cluster_info.cell_width as f64 * info.x_advance as f64 / total_width).ceil()
-                        as u8
-                };
-                let weighted_cell_width = weighted_cell_width.min(remaining_cells);
-                remaining_cells = remaining_cells.saturating_sub(weighted_cell_width);
-
-                let glyph = make_glyphinfo(substr, weighted_cell_width, font_idx, info);
-
-                cluster.push(glyph);
-                direct_clusters += 1;
-            }
-        }
+                )
+            },
+        )?;
 
         if !shaped_any {
             if let Some(opt_pair) = self.fonts.get(font_idx) {
@@ -735,104 +509,6 @@ impl FontShaper for HarfbuzzShaper {
     }
 }
 
-#[derive(Debug)]
-struct ClusterInfo {
-    start: usize,
-    byte_len: usize,
-    cell_width: u8,
-    incomplete: bool,
-}
-
-#[derive(Default, Debug)]
-struct ClusterResolver<'a> {
-    map: HashMap<usize, ClusterInfo>,
-    presentation_width: Option<&'a PresentationWidth<'a>>,
-    start_by_cell_idx: HashMap<usize, usize>,
-}
-
-impl<'a> ClusterResolver<'a> {
-    pub fn build(&mut self, hb_infos: &[harfbuzz::hb_glyph_info_t], s: &str, range: &Range<usize>) {
-        #[derive(PartialOrd, Ord, Eq, PartialEq, Copy, Clone)]
-        struct Item {
-            cell_idx: Option<usize>,
-            start: usize,
-        }
-
-        let mut map = HashMap::new();
-
-        for info in hb_infos.iter() {
-            let start = info.cluster as usize;
-
-            // Collect the cell index, which is the "true cluster"
-            // position from our perspective: the start of the grapheme
-            let cell_idx = match self.presentation_width {
-                Some(pw) => {
-                    let cell_idx = pw.byte_to_cell_idx(start);
-
-                    let entry = self.start_by_cell_idx.entry(cell_idx).or_insert(start);
-                    *entry = (*entry).min(start);
-
-                    Some(cell_idx)
-                }
-                None => None,
-            };
-
-            map.entry(start).or_insert_with(|| Item { start, cell_idx });
-        }
-
-        let mut cluster_starts: Vec<Item> = map.into_values().collect();
-        cluster_starts.sort();
-
-        // If we have multiple entries with the same starting cell index,
-        // remove the duplicates.  Don't do this for `None` as that will
-        // falsely remove valid cluster locations in the case where
-        // we have no presentation_width information.
-        cluster_starts.dedup_by(|a, b| match (a.cell_idx, b.cell_idx) {
-            (Some(a), Some(b)) => a == b,
-            _ => false,
-        });
-
-        let mut iter = cluster_starts.iter().peekable();
-        while let Some(item) = iter.next().copied() {
-            let start = item.start;
-            let next_start = iter.peek().map(|&&s| s.start).unwrap_or(range.end);
-            let byte_len = next_start - start;
-            let cell_width = match self.presentation_width {
-                Some(p) => p.num_cells(start..next_start),
-                None => unicode_column_width(&s[start..next_start], None) as u8,
-            };
-            self.map.entry(start).or_insert_with(|| ClusterInfo {
-                start,
-                byte_len,
-                cell_width,
-                incomplete: false,
-            });
-        }
-    }
-
-    pub fn get_mut(&mut self, start: usize) -> Option<&mut ClusterInfo> {
-        match self.presentation_width {
-            Some(pw) => {
-                let cell_idx = pw.byte_to_cell_idx(start);
-                let actual_start = self.start_by_cell_idx.get(&cell_idx)?;
-                self.map.get_mut(&actual_start)
-            }
-            None => self.map.get_mut(&start),
-        }
-    }
-
-    pub fn get(&self, start: usize) -> Option<&ClusterInfo> {
-        match self.presentation_width {
-            Some(pw) => {
-                let cell_idx = pw.byte_to_cell_idx(start);
-                let actual_start = self.start_by_cell_idx.get(&cell_idx)?;
-                self.map.get(&actual_start)
-            }
-            None => self.map.get(&start),
-        }
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -862,6 +538,7 @@ mod test {
                     harfbuzz_features: None,
                     scale: None,
                     assume_emoji_presentation: None,
+                    variations: None,
                 },
                 14,
             )
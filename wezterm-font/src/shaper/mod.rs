@@ -4,7 +4,9 @@ use std::ops::Range;
 use termwiz::cell::Presentation;
 use termwiz::cellcluster::CellCluster;
 
+mod cluster;
 pub mod harfbuzz;
+pub mod rustybuzz;
 pub use wezterm_bidi::Direction;
 
 /// Holds information about a shaped glyph
@@ -145,6 +147,9 @@ pub fn new_shaper(
         FontShaperSelection::Harfbuzz => {
             Ok(Box::new(harfbuzz::HarfbuzzShaper::new(config, handles)?))
         }
+        FontShaperSelection::Rustybuzz => {
+            Ok(Box::new(rustybuzz::RustybuzzShaper::new(config, handles)?))
+        }
         FontShaperSelection::Allsorts => {
             anyhow::bail!("The incomplete Allsorts shaper has been removed");
         }
@@ -0,0 +1,583 @@
+use crate::parser::ParsedFont;
+use crate::shaper::cluster::{assign_clusters, ShapedGlyph};
+use crate::shaper::{FallbackIdx, FontMetrics, FontShaper, GlyphInfo, PresentationWidth};
+use crate::ttfwrap;
+use crate::units::*;
+use anyhow::{anyhow, Context};
+use config::ConfigHandle;
+use ordered_float::NotNan;
+use std::cell::{RefCell, RefMut};
+use std::collections::HashMap;
+use std::ops::Range;
+use termwiz::cell::Presentation;
+use wezterm_bidi::Direction;
+
+struct FontInfo {
+    face: ttfwrap::Face,
+    shaped_any: bool,
+    presentation: Presentation,
+    features: Vec<::rustybuzz::Feature>,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+struct MetricsKey {
+    font_idx: usize,
+    size: NotNan<f64>,
+    dpi: u32,
+}
+
+/// A shaper implemented in pure Rust on top of rustybuzz, a port
+/// of harfbuzz.  It produces the same results as `HarfbuzzShaper`,
+/// except that the glyph advances are not hinted.
+pub struct RustybuzzShaper {
+    handles: Vec<ParsedFont>,
+    fonts: Vec<RefCell<Option<FontInfo>>>,
+    metrics: RefCell<HashMap<MetricsKey, FontMetrics>>,
+    features: Vec<::rustybuzz::Feature>,
+    lang: ::rustybuzz::Language,
+}
+
+fn parse_features(features: &[String]) -> Vec<::rustybuzz::Feature> {
+    features.iter().filter_map(|s| s.parse().ok()).collect()
+}
+
+impl RustybuzzShaper {
+    pub fn new(config: &ConfigHandle, handles: &[ParsedFont]) -> anyhow::Result<Self> {
+        let handles = handles.to_vec();
+        let mut fonts = vec![];
+        for _ in 0..handles.len() {
+            fonts.push(RefCell::new(None));
+        }
+
+        let lang = "en"
+            .parse()
+            .map_err(|err| anyhow!("invalid language: {err}"))?;
+
+        Ok(Self {
+            fonts,
+            handles,
+            metrics: RefCell::new(HashMap::new()),
+            features: parse_features(&config.harfbuzz_features),
+            lang,
+        })
+    }
+
+    fn load_fallback(&self, font_idx: FallbackIdx) -> anyhow::Result<Option<RefMut<FontInfo>>> {
+        if font_idx >= self.handles.len() {
+            return Ok(None);
+        }
+        match self.fonts.get(font_idx) {
+            None => Ok(None),
+            Some(opt_info) => {
+                let mut opt_info = opt_info.borrow_mut();
+                if opt_info.is_none() {
+                    let handle = &self.handles[font_idx];
+                    log::trace!("shaper wants {} {:?}", font_idx, handle);
+                    let face = ttfwrap::Face::from_parsed(handle)?;
+
+                    let features = match &handle.harfbuzz_features {
+                        Some(features) => parse_features(features),
+                        None => self.features.clone(),
+                    };
+
+                    *opt_info = Some(FontInfo {
+                        face,
+                        shaped_any: false,
+                        presentation: if handle.assume_emoji_presentation {
+                            Presentation::Emoji
+                        } else {
+                            Presentation::Text
+                        },
+                        features,
+                    });
+                }
+
+                Ok(Some(RefMut::map(opt_info, |opt_info| {
+                    opt_info.as_mut().unwrap()
+                })))
+            }
+        }
+    }
+
+    fn make_buffer(
+        &self,
+        s: &str,
+        direction: Direction,
+        range: &Range<usize>,
+    ) -> ::rustybuzz::UnicodeBuffer {
+        let mut buf = ::rustybuzz::UnicodeBuffer::new();
+        // As with the harfbuzz shaper, we leave the script to be
+        // inferred from the buffer contents.
+        buf.set_direction(match direction {
+            Direction::LeftToRight => ::rustybuzz::Direction::LeftToRight,
+            Direction::RightToLeft => ::rustybuzz::Direction::RightToLeft,
+        });
+        buf.set_language(self.lang.clone());
+
+        // The cluster values are byte offsets into `s` so that they
+        // have the same meaning as those produced by the harfbuzz shaper
+        buf.set_pre_context(&s[..range.start]);
+        for (idx, c) in s[range.clone()].char_indices() {
+            buf.add(c, (range.start + idx) as u32);
+        }
+        buf.set_post_context(&s[range.end..]);
+
+        buf.guess_segment_properties();
+        buf.set_cluster_level(::rustybuzz::BufferClusterLevel::MonotoneGraphemes);
+        buf
+    }
+
+    fn do_shape(
+        &self,
+        mut font_idx: FallbackIdx,
+        s: &str,
+        font_size: f64,
+        dpi: u32,
+        no_glyphs: &mut Vec<char>,
+        presentation: Option<Presentation>,
+        direction: Direction,
+        range: Range<usize>,
+        presentation_width: Option<&PresentationWidth>,
+    ) -> anyhow::Result<Vec<GlyphInfo>> {
+        let shaped_any;
+
+        // We set this to true when we've run out of fallback fonts to try.
+        // In that case, we accept shaper info with codepoint==0 and
+        // will use the notdef glyph from the base font.
+        let mut no_more_fallbacks = false;
+
+        let shaped = loop {
+            match self.load_fallback(font_idx).context("load_fallback")? {
+                Some(info) => {
+                    if let Some(p) = presentation {
+                        if info.presentation != p {
+                            log::trace!(
+                                "wanted presentation is {p:?} != font \
+                                     presentation {:?} so skip \
+                                     font_idx={font_idx}",
+                                info.presentation
+                            );
+                            font_idx += 1;
+                            continue;
+                        }
+                    }
+                    let point_size = font_size * self.handles[font_idx].scale.unwrap_or(1.);
+                    let (pixel_size, _) = info.face.pixel_size(point_size, dpi);
+                    // Positions are reported in font units; we want
+                    // 26.6 fixed point pixels
+                    let scale = info.face.units_to_pixels(pixel_size) * 64.;
+                    let to_26_6 = |v: i32| (f64::from(v) * scale).round() as i32;
+
+                    shaped_any = info.shaped_any;
+                    let buf = self.make_buffer(s, direction, &range);
+                    let glyphs = ::rustybuzz::shape(info.face.face(), &info.features, buf);
+                    log::trace!(
+                        "shaped font_idx={} {:?} presentation={presentation:?} as: {}",
+                        font_idx,
+                        &s[range.start..range.end],
+                        glyphs.serialize(info.face.face(), ::rustybuzz::SerializeFlags::default())
+                    );
+
+                    break glyphs
+                        .glyph_infos()
+                        .iter()
+                        .zip(glyphs.glyph_positions().iter())
+                        .map(|(info, pos)| ShapedGlyph {
+                            cluster: info.cluster as usize,
+                            codepoint: info.glyph_id,
+                            x_advance: to_26_6(pos.x_advance),
+                            y_advance: to_26_6(pos.y_advance),
+                            x_offset: to_26_6(pos.x_offset),
+                            y_offset: to_26_6(pos.y_offset),
+                        })
+                        .collect::<Vec<_>>();
+                }
+                None => {
+                    for c in s.chars() {
+                        no_glyphs.push(c);
+                    }
+
+                    if presentation.is_some() {
+                        log::debug!(
+                            "Ran out of fallback options, retry shape with no presentation"
+                        );
+                        // See the comments in the harfbuzz shaper for why
+                        // we restart without a specific presentation
+                        return self.do_shape(
+                            0,
+                            s,
+                            font_size,
+                            dpi,
+                            no_glyphs,
+                            None,
+                            direction,
+                            range,
+                            presentation_width,
+                        );
+                    }
+
+                    // One more go around to pick up the base font and
+                    // accept using the notdef glyph from that.
+                    no_more_fallbacks = true;
+                    font_idx = 0;
+                    continue;
+                }
+            }
+        };
+
+        let (cluster, direct_clusters) = assign_clusters(
+            &shaped,
+            s,
+            &range,
+            font_idx,
+            no_more_fallbacks,
+            presentation_width,
+            |font_idx, s, range| {
+                self.do_shape(
+                    font_idx,
+                    s,
+                    font_size,
+                    dpi,
+                    no_glyphs,
+                    presentation,
+                    direction,
+                    range,
+                    presentation_width,
+                )
+            },
+        )?;
+
+        if !shaped_any {
+            if let Some(opt_info) = self.fonts.get(font_idx) {
+                if direct_clusters == 0 {
+                    // If we've never shaped anything from this font, and we didn't
+                    // shape it just now, then we're probably a fallback font from
+                    // the system and unlikely to be useful to keep around, so we
+                    // unload it.
+                    log::trace!(
+                        "Shaper didn't resolve glyphs from {:?}, so unload it",
+                        self.handles[font_idx]
+                    );
+                    opt_info.borrow_mut().take();
+                } else if let Some(info) = &mut *opt_info.borrow_mut() {
+                    // We shaped something: mark this font up so that it sticks around
+                    info.shaped_any = true;
+                }
+            }
+        }
+
+        Ok(cluster)
+    }
+}
+
+impl FontShaper for RustybuzzShaper {
+    fn shape(
+        &self,
+        text: &str,
+        size: f64,
+        dpi: u32,
+        no_glyphs: &mut Vec<char>,
+        presentation: Option<Presentation>,
+        direction: Direction,
+        range: Option<Range<usize>>,
+        presentation_width: Option<&PresentationWidth>,
+    ) -> anyhow::Result<Vec<GlyphInfo>> {
+        let range = range.unwrap_or_else(|| 0..text.len());
+
+        log::trace!(
+            "shape {range:?} `{}` with presentation={presentation:?}",
+            text.escape_debug()
+        );
+        let start = std::time::Instant::now();
+        let result = self.do_shape(
+            0,
+            text,
+            size,
+            dpi,
+            no_glyphs,
+            presentation,
+            direction,
+            range,
+            presentation_width,
+        );
+        metrics::histogram!("shape.rustybuzz", start.elapsed());
+        result
+    }
+
+    fn metrics_for_idx(&self, font_idx: usize, size: f64, dpi: u32) -> anyhow::Result<FontMetrics> {
+        let info = self
+            .load_fallback(font_idx)?
+            .ok_or_else(|| anyhow!("metrics_for_idx: there is no font with idx={font_idx}!?"))?;
+
+        let key = MetricsKey {
+            font_idx,
+            size: NotNan::new(size).unwrap(),
+            dpi,
+        };
+        if let Some(metrics) = self.metrics.borrow().get(&key) {
+            return Ok(*metrics);
+        }
+
+        let scale = self.handles[font_idx].scale.unwrap_or(1.);
+
+        let face = &info.face;
+        let (pixel_size, is_scaled) = face.pixel_size(size * scale, dpi);
+        let (cell_width, cell_height) = face.cell_metrics(pixel_size);
+        let units = face.units_to_pixels(pixel_size);
+        let cap_height = face.cap_height(pixel_size);
+        let (_, descender, _) = face.vertical_metrics();
+
+        let underline = face.face().underline_metrics();
+        let underline_thickness = underline.map(|u| f64::from(u.thickness)).unwrap_or(0.);
+        // This matches the adjustment that FreeType makes to the
+        // position so that it refers to the top of the underline
+        let underline_position = underline
+            .map(|u| f64::from(u.position) - (underline_thickness / 2.).trunc())
+            .unwrap_or(0.);
+
+        let mut metrics = FontMetrics {
+            cell_height: PixelLength::new(cell_height),
+            cell_width: PixelLength::new(cell_width),
+            // FreeType scales to 26.6 fixed point and then floors
+            // to a whole pixel
+            descender: PixelLength::new(
+                ((f64::from(descender) * units * 64.).round() / 64.).floor(),
+            ),
+            underline_thickness: PixelLength::new(underline_thickness * units),
+            underline_position: PixelLength::new(underline_position * units),
+            cap_height_ratio: cap_height.map(|h| h / cell_height),
+            cap_height: cap_height.map(PixelLength::new),
+            is_scaled,
+            presentation: info.presentation,
+            force_y_adjust: PixelLength::new(0.),
+        };
+
+        // When the user has overridden the scale, we need to stash a y-adjustment
+        // so that the glyphs are better vertically aligned
+        // <https://github.com/wez/wezterm/issues/1803>
+        if scale != 1.0 && metrics.is_scaled {
+            let diff = metrics.descender - (metrics.descender / scale);
+            metrics.force_y_adjust = diff;
+        }
+
+        self.metrics.borrow_mut().insert(key, metrics);
+
+        log::trace!(
+            "metrics_for_idx={}, size={}, dpi={} -> {:?}",
+            font_idx,
+            size,
+            dpi,
+            metrics
+        );
+
+        Ok(metrics)
+    }
+
+    fn metrics(&self, size: f64, dpi: u32) -> anyhow::Result<FontMetrics> {
+        // As in the harfbuzz shaper, skip over fallback slots whose
+        // size is implausible, such as bitmap emoji fonts
+        let theoretical_height = size * dpi as f64 / 72.0;
+        let mut metrics_idx = 0;
+        while let Ok(Some(info)) = self.load_fallback(metrics_idx) {
+            let (pixel_size, _) = info
+                .face
+                .pixel_size(size * self.handles[metrics_idx].scale.unwrap_or(1.), dpi);
+            let (_, height) = info.face.cell_metrics(pixel_size);
+            let diff = (theoretical_height - height).abs();
+            let factor = diff / theoretical_height;
+            if factor < 2.0 {
+                break;
+            }
+            log::trace!(
+                "skip idx {} because diff={} factor={} theoretical_height={} cell_height={}",
+                metrics_idx,
+                diff,
+                factor,
+                theoretical_height,
+                height
+            );
+
+            if metrics_idx + 1 >= self.handles.len() {
+                log::warn!(
+                    "metrics: I wanted to skip idx {} because diff={} factor={} \
+                    theoretical_height={} cell_height={}, but there are no more \
+                    fallback fonts. Metrics will likely be crazy.",
+                    metrics_idx,
+                    diff,
+                    factor,
+                    theoretical_height,
+                    height
+                );
+                break;
+            }
+
+            metrics_idx += 1;
+        }
+
+        self.metrics_for_idx(metrics_idx, size, dpi)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::shaper::harfbuzz::HarfbuzzShaper;
+    use crate::FontDatabase;
+    use config::FontAttributes;
+
+    fn resolve(db: &FontDatabase, family: &str) -> ParsedFont {
+        db.resolve(
+            &FontAttributes {
+                family: family.into(),
+                stretch: Default::default(),
+                weight: Default::default(),
+                is_fallback: false,
+                is_synthetic: false,
+                style: Default::default(),
+                freetype_load_flags: None,
+                freetype_load_target: None,
+                freetype_render_target: None,
+                harfbuzz_features: None,
+                scale: None,
+                assume_emoji_presentation: None,
+                variations: None,
+            },
+            14,
+        )
+        .unwrap()
+        .clone()
+    }
+
+    fn shapers(families: &[&str]) -> (HarfbuzzShaper, RustybuzzShaper) {
+        let db = FontDatabase::with_built_in().unwrap();
+        let handles: Vec<ParsedFont> = families.iter().map(|f| resolve(&db, f)).collect();
+        let config = config::configuration();
+        (
+            HarfbuzzShaper::new(&config, &handles).unwrap(),
+            RustybuzzShaper::new(&config, &handles).unwrap(),
+        )
+    }
+
+    /// Shapes `text` with both shapers and asserts that they agree on
+    /// the glyphs and the cells they occupy.
+    /// The advances and offsets are permitted to differ by a fraction
+    /// of a pixel, as harfbuzz uses the hinted metrics from FreeType.
+    fn assert_parity(
+        hb: &HarfbuzzShaper,
+        rb: &RustybuzzShaper,
+        text: &str,
+        size: f64,
+        dpi: u32,
+        direction: Direction,
+    ) {
+        let shape = |shaper: &dyn FontShaper| {
+            let mut no_glyphs = vec![];
+            let info = shaper
+                .shape(text, size, dpi, &mut no_glyphs, None, direction, None, None)
+                .unwrap();
+            (info, no_glyphs)
+        };
+        let (expected, expected_no_glyphs) = shape(hb);
+        let (actual, actual_no_glyphs) = shape(rb);
+        assert_eq!(actual_no_glyphs, expected_no_glyphs, "{text:?}");
+
+        let strip = |info: &[GlyphInfo]| -> Vec<GlyphInfo> {
+            info.iter()
+                .map(|g| GlyphInfo {
+                    x_advance: PixelLength::new(0.),
+                    y_advance: PixelLength::new(0.),
+                    x_offset: PixelLength::new(0.),
+                    y_offset: PixelLength::new(0.),
+                    ..g.clone()
+                })
+                .collect()
+        };
+        assert_eq!(strip(&actual), strip(&expected), "{text:?}");
+
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            for (what, a, e) in [
+                ("x_advance", a.x_advance, e.x_advance),
+                ("y_advance", a.y_advance, e.y_advance),
+                ("x_offset", a.x_offset, e.x_offset),
+                ("y_offset", a.y_offset, e.y_offset),
+            ] {
+                assert!(
+                    (a.get() - e.get()).abs() < 1.0,
+                    "{text:?} {what} of {a:?} differs from harfbuzz {e:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parity_monospace() {
+        let (hb, rb) = shapers(&["JetBrains Mono"]);
+        for (size, dpi) in [(10., 72), (12., 96), (13.5, 144)] {
+            for text in [
+                "abc",
+                "Hello, World!",
+                // Ligatures
+                "<-",
+                "a != b && c >= d",
+                "www -> ===",
+                // Greek and Cyrillic
+                "Καλημέρα κόσμε",
+                "Съешь же ещё этих мягких французских булок",
+                // Combining marks, both precomposed and decomposed
+                "caf\u{e9} cafe\u{301}",
+                "a\u{301}\u{302}\u{303}",
+                // Characters that the font doesn't have
+                "\u{4f60}\u{597d}",
+            ] {
+                assert_parity(&hb, &rb, text, size, dpi, Direction::LeftToRight);
+            }
+            assert_parity(&hb, &rb, "abc def", size, dpi, Direction::RightToLeft);
+        }
+    }
+
+    #[test]
+    fn parity_proportional() {
+        // Roboto has kerning and a wider variety of advances
+        let (hb, rb) = shapers(&["Roboto"]);
+        for text in ["AVATAR To Wa", "office ffi", "Ünïcödé façade"] {
+            assert_parity(&hb, &rb, text, 12., 96, Direction::LeftToRight);
+        }
+    }
+
+    #[test]
+    fn parity_fallback() {
+        let (hb, rb) = shapers(&["JetBrains Mono", "Symbols Nerd Font Mono"]);
+        for text in ["a\u{e0b0}b", "\u{f015} home", "\u{e0b0}\u{e0b2}"] {
+            assert_parity(&hb, &rb, text, 10., 72, Direction::LeftToRight);
+        }
+    }
+
+    #[test]
+    fn parity_metrics() {
+        for family in ["JetBrains Mono", "Roboto", "Symbols Nerd Font Mono"] {
+            let (hb, rb) = shapers(&[family]);
+            for (size, dpi) in [(10., 72), (12., 96), (13.5, 144)] {
+                let expected = hb.metrics(size, dpi).unwrap();
+                let actual = rb.metrics(size, dpi).unwrap();
+                let context = format!("{family} {size} {dpi}: {actual:?} vs {expected:?}");
+
+                assert_eq!(actual.cell_width, expected.cell_width, "{context}");
+                assert_eq!(actual.descender, expected.descender, "{context}");
+                assert_eq!(actual.is_scaled, expected.is_scaled, "{context}");
+                for (a, e) in [
+                    (actual.cell_height, expected.cell_height),
+                    (actual.underline_thickness, expected.underline_thickness),
+                    (actual.underline_position, expected.underline_position),
+                ] {
+                    assert!((a.get() - e.get()).abs() < 0.01, "{context}");
+                }
+                // FreeType measures the cap height from the hinted
+                // rendering of the `I` glyph, so it can be a pixel shorter
+                match (actual.cap_height, expected.cap_height) {
+                    (Some(a), Some(e)) => assert!((a.get() - e.get()).abs() <= 1.0, "{context}"),
+                    (a, e) => assert_eq!(a, e, "{context}"),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Higher level wrappers around ttf-parser and rustybuzz, which are
+//! used by the pure Rust shaper and rasterizer implementations.
+use crate::ftwrap::tag_from_str;
+use crate::locator::FontDataSource;
+use crate::parser::ParsedFont;
+use anyhow::Context;
+use memmap2::{Mmap, MmapOptions};
+use rustybuzz::ttf_parser::{GlyphId, Tag};
+use rustybuzz::Variation;
+use std::io::Read;
+use std::sync::Arc;
+
+/// Holds the bytes of a font for the lifetime of the `Face`
+/// that was parsed from them.
+enum FontData {
+    Static(&'static [u8]),
+    Shared(Arc<Box<[u8]>>),
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl FontData {
+    fn load(source: &FontDataSource) -> anyhow::Result<Self> {
+        match source {
+            FontDataSource::OnDisk(p) => {
+                let mut file = std::fs::File::open(p)
+                    .with_context(|| format!("opening file {}", p.display()))?;
+                match unsafe { MmapOptions::new().map(&file) } {
+                    Ok(map) => Ok(Self::Mapped(map)),
+                    Err(err) => {
+                        log::warn!(
+                            "Unable to memory map {}: {}, will use regular file IO instead",
+                            p.display(),
+                            err
+                        );
+                        let mut data = vec![];
+                        file.read_to_end(&mut data)
+                            .with_context(|| format!("reading font file {}", p.display()))?;
+                        Ok(Self::Owned(data))
+                    }
+                }
+            }
+            FontDataSource::BuiltIn { data, .. } => Ok(Self::Static(data)),
+            FontDataSource::Memory { data, .. } => Ok(Self::Shared(Arc::clone(data))),
+        }
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Self::Static(data) => data,
+            Self::Shared(data) => data,
+            Self::Mapped(map) => map,
+            Self::Owned(data) => data,
+        }
+    }
+}
+
+pub struct Face {
+    // Note that `face` borrows from `_data`, so it must be declared
+    // first in order for it to be dropped first.
+    face: rustybuzz::Face<'static>,
+    _data: FontData,
+}
+
+impl Face {
+    /// Parses the font referenced by `parsed`, selecting its named
+    /// instance and applying its variations, if any.
+    pub fn from_parsed(parsed: &ParsedFont) -> anyhow::Result<Self> {
+        let handle = &parsed.handle;
+        let data = FontData::load(&handle.source)?;
+        // Safety: the slice refers to memory that is owned by `data`,
+        // whose address doesn't change when `data` is moved: it is
+        // static, heap allocated or memory mapped.  `data` is kept
+        // alive alongside the face and is dropped after it.
+        let slice: &'static [u8] = unsafe { std::mem::transmute(data.as_slice()) };
+        let mut face = rustybuzz::Face::from_slice(slice, handle.index).ok_or_else(|| {
+            anyhow::anyhow!("{}: failed to parse font", handle.diagnostic_string())
+        })?;
+
+        let mut variations = vec![];
+        if handle.variation != 0 {
+            let info = &parsed.variation_info;
+            if let Some(instance) = info.named_instances.get(handle.variation as usize - 1) {
+                for (axis, value) in info.axes.iter().zip(instance.coords.iter()) {
+                    if let Some(tag) = tag_from_str(&axis.tag) {
+                        variations.push(Variation {
+                            tag: Tag(tag as u32),
+                            value: *value as f32,
+                        });
+                    }
+                }
+            }
+        }
+
+        if !parsed.variations.is_empty() {
+            if face.is_variable() {
+                for (name, value) in &parsed.variations {
+                    match tag_from_str(name).map(|tag| Tag(tag as u32)).filter(|tag| {
+                        face.variation_axes()
+                            .into_iter()
+                            .any(|axis| axis.tag == *tag)
+                    }) {
+                        Some(tag) => variations.push(Variation {
+                            tag,
+                            value: *value as f32,
+                        }),
+                        None => log::warn!(
+                            "{}: font has no variation axis `{}`",
+                            handle.diagnostic_string(),
+                            name
+                        ),
+                    }
+                }
+            } else {
+                log::warn!(
+                    "{}: ignoring variations because it is not a variable font",
+                    handle.diagnostic_string()
+                );
+            }
+        }
+
+        if !variations.is_empty() {
+            face.set_variations(&variations);
+        }
+
+        Ok(Self { face, _data: data })
+    }
+
+    pub fn face(&self) -> &rustybuzz::Face<'_> {
+        &self.face
+    }
+
+    /// Returns true if the font has scalable glyph outlines
+    pub fn has_outlines(&self) -> bool {
+        let tables = self.face.tables();
+        tables.glyf.is_some() || tables.cff.is_some() || tables.cff2.is_some()
+    }
+
+    /// Returns the number of font units in one pixel at the specified
+    /// pixel size
+    pub fn units_to_pixels(&self, pixel_size: f64) -> f64 {
+        pixel_size / f64::from(self.face.units_per_em())
+    }
+
+    /// Computes the pixel size to use for the specified point size.
+    /// Fonts without outlines can only be rendered at the sizes of
+    /// their bitmap strikes, so for those the size of the closest
+    /// strike is returned instead.
+    /// The second element of the tuple is true if the size was
+    /// scaled to match the requested point size.
+    pub fn pixel_size(&self, point_size: f64, dpi: u32) -> (f64, bool) {
+        let pixel_size = point_size * dpi as f64 / 72.0;
+        if self.has_outlines() {
+            return (pixel_size, true);
+        }
+
+        // There isn't a direct way to enumerate the strikes, so probe
+        // the first few glyphs to discover the best match
+        let wanted = pixel_size.round().clamp(1., u16::MAX as f64) as u16;
+        let num_glyphs = self.face.number_of_glyphs().min(64);
+        for glyph in 0..num_glyphs {
+            if let Some(image) = self.face.glyph_raster_image(GlyphId(glyph), wanted) {
+                return (f64::from(image.pixels_per_em), false);
+            }
+        }
+        (pixel_size, true)
+    }
+
+    /// Returns the ascender, descender and line gap in font units.
+    /// These are taken from the `hhea` table in the same way as
+    /// FreeType, which doesn't honor the `USE_TYPO_METRICS` flag.
+    pub fn vertical_metrics(&self) -> (i16, i16, i16) {
+        let hhea = &self.face.tables().hhea;
+        if hhea.ascender == 0 && hhea.descender == 0 {
+            (
+                self.face.ascender(),
+                self.face.descender(),
+                self.face.line_gap(),
+            )
+        } else {
+            (hhea.ascender, hhea.descender, hhea.line_gap)
+        }
+    }
+
+    /// Computes the metrics for the nominal monospace cell at the
+    /// specified pixel size, in the same way as
+    /// `ftwrap::Face::cell_metrics`.
+    pub fn cell_metrics(&self, pixel_size: f64) -> (f64, f64) {
+        let face = &self.face;
+        let scale = self.units_to_pixels(pixel_size);
+        let (ascender, descender, line_gap) = self.vertical_metrics();
+        let height = f64::from(ascender - descender + line_gap) * scale;
+
+        let advance = |glyph| face.glyph_hor_advance(glyph).map(f64::from);
+
+        let mut width = (32..128u8)
+            .filter_map(|c| face.glyph_index(c as char))
+            .filter_map(advance)
+            .fold(0., f64::max);
+        if width == 0.0 {
+            // Most likely we're looking at a symbol font with no latin
+            // glyphs at all. Let's just pick a selection of glyphs
+            width = (1..8u16)
+                .filter_map(|g| advance(GlyphId(g)))
+                .fold(0., f64::max);
+        }
+
+        if width == 0.0 {
+            log::error!(
+                "Couldn't find usable advance metrics in the font, \
+                 so guessing width == height"
+            );
+            (height, height)
+        } else {
+            ((width * scale).round(), height)
+        }
+    }
+
+    /// Returns the distance between the first and last rows of pixels
+    /// of the rendered `I` glyph, which is how `ftwrap::Face` determines
+    /// the cap height
+    pub fn cap_height(&self, pixel_size: f64) -> Option<f64> {
+        let scale = self.units_to_pixels(pixel_size);
+        let glyph = self.face.glyph_index('I')?;
+        let bbox = self.face.glyph_bounding_box(glyph)?;
+        let rows = (f64::from(bbox.y_max) * scale).ceil() - (f64::from(bbox.y_min) * scale).floor();
+        Some(rows - 1.)
+    }
+}
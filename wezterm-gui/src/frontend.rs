@@ -7,14 +7,18 @@ use anyhow::{Context, Error};
 use config::keyassignment::{KeyAssignment, SpawnCommand};
 use config::{ConfigSubscription, NotificationHandling};
 use mux::client::ClientId;
+use mux::pane::PaneId;
 use mux::window::WindowId as MuxWindowId;
 use mux::{Mux, MuxNotification};
 use promise::{Future, Promise};
 use std::cell::RefCell;
 use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
 use std::rc::Rc;
 use std::sync::Arc;
-use wezterm_term::{Alert, ClipboardSelection};
+use termwiz::escape::osc::DesktopNotification;
+use termwiz::escape::OperatingSystemCommand;
+use wezterm_term::{Alert, ClipboardSelection, NotificationUrgency};
 use wezterm_toast_notification::*;
 
 pub struct GuiFrontEnd {
@@ -94,43 +98,67 @@ impl GuiFrontEnd {
                 MuxNotification::WindowInvalidated(_) => {}
                 MuxNotification::PaneOutput(_) => {}
                 MuxNotification::PaneAdded(_) => {}
+                MuxNotification::ListeningSocketsChanged { .. } => {}
+                MuxNotification::TriggerMatched { .. } => {}
                 MuxNotification::Alert {
                     pane_id,
                     alert:
                         Alert::ToastNotification {
                             title,
                             body,
-                            focus: _,
+                            focus,
+                            id,
+                            urgency,
+                            report_activation,
+                            report_close,
+                            timeout,
                         },
                 } => {
-                    let mux = Mux::get();
+                    let on_event: Option<ToastEventHandler> =
+                        if focus || report_activation || report_close {
+                            let id = id.clone();
+                            Some(Arc::new(move |event: ToastEvent| {
+                                let id = id.clone();
+                                promise::spawn::spawn_into_main_thread(async move {
+                                    handle_toast_event(
+                                        pane_id,
+                                        id.as_deref(),
+                                        event,
+                                        focus,
+                                        report_activation,
+                                        report_close,
+                                    );
+                                })
+                                .detach();
+                            }))
+                        } else {
+                            None
+                        };
 
-                    if let Some((_domain, window_id, tab_id)) = mux.resolve_pane_id(pane_id) {
-                        let config = config::configuration();
-
-                        if let Some((_fdomain, f_window, f_tab, f_pane)) =
-                            mux.resolve_focused_pane(&client_id)
-                        {
-                            let show = match config.notification_handling {
-                                NotificationHandling::NeverShow => false,
-                                NotificationHandling::AlwaysShow => true,
-                                NotificationHandling::SuppressFromFocusedPane => f_pane != pane_id,
-                                NotificationHandling::SuppressFromFocusedTab => f_tab != tab_id,
-                                NotificationHandling::SuppressFromFocusedWindow => {
-                                    f_window != window_id
-                                }
-                            };
-
-                            if show {
-                                let message = if title.is_none() { "" } else { &body };
-                                let title = title.as_ref().unwrap_or(&body);
-                                // FIXME: if notification.focus is true, we should do
-                                // something here to arrange to focus pane_id when the
-                                // notification is clicked
-                                persistent_toast_notification(title, message);
-                            }
-                        }
-                    }
+                    show_pane_notification(
+                        &client_id,
+                        pane_id,
+                        ToastNotification {
+                            // Qualify the id with the pane, so that a pane
+                            // cannot replace or close the notifications
+                            // of another pane
+                            id: id.map(|id| format!("{pane_id}:{id}")),
+                            urgency: urgency.map(|urgency| match urgency {
+                                NotificationUrgency::Low => Urgency::Low,
+                                NotificationUrgency::Normal => Urgency::Normal,
+                                NotificationUrgency::Critical => Urgency::Critical,
+                            }),
+                            timeout,
+                            on_event,
+                            ..pane_toast(title.as_deref(), &body)
+                        },
+                    );
+                }
+                MuxNotification::Alert {
+                    pane_id,
+                    alert: Alert::CloseToastNotification { id },
+                } => {
+                    close_notification(&format!("{pane_id}:{id}"));
                 }
                 MuxNotification::Alert {
                     pane_id: _,
@@ -147,8 +175,17 @@ impl GuiFrontEnd {
                         | Alert::WindowTitleChanged(_)
                         | Alert::TabTitleChanged(_)
                         | Alert::IconTitleChanged(_)
-                        | Alert::SetUserVar { .. },
+                        | Alert::SetUserVar { .. }
+                        | Alert::Progress(_)
+                        | Alert::PointerShapeChanged(_),
                 } => {}
+                MuxNotification::Alert {
+                    pane_id: _,
+                    alert: Alert::CommandFinished { .. },
+                } => {
+                    // Handled via TermWindowNotif, so that the
+                    // command-finished event can be emitted first
+                }
                 MuxNotification::Empty => {
                     if config::configuration().quit_when_all_windows_are_closed {
                         promise::spawn::spawn_into_main_thread(async move {
@@ -468,6 +505,12 @@ impl GuiFrontEnd {
         }
     }
 
+    /// Shows a toast notification on behalf of `pane_id`, subject
+    /// to the `notification_handling` configuration
+    pub fn show_pane_notification(&self, pane_id: PaneId, title: Option<&str>, body: &str) {
+        show_pane_notification(&self.client_id, pane_id, pane_toast(title, body));
+    }
+
     pub fn is_switching_workspace(&self) -> bool {
         *self.switching_workspaces.borrow()
     }
@@ -490,6 +533,89 @@ thread_local! {
     static FRONT_END: RefCell<Option<Rc<GuiFrontEnd>>> = RefCell::new(None);
 }
 
+/// Builds a notification with the specified title and body; if there
+/// is no title then the body is used as the title instead
+fn pane_toast(title: Option<&str>, body: &str) -> ToastNotification {
+    let message = if title.is_none() { "" } else { body };
+    ToastNotification {
+        title: title.unwrap_or(body).to_string(),
+        message: message.to_string(),
+        ..Default::default()
+    }
+}
+
+fn show_pane_notification(client_id: &ClientId, pane_id: PaneId, notif: ToastNotification) {
+    let mux = Mux::get();
+
+    if let Some((_domain, window_id, tab_id)) = mux.resolve_pane_id(pane_id) {
+        let config = config::configuration();
+
+        if let Some((_fdomain, f_window, f_tab, f_pane)) = mux.resolve_focused_pane(client_id) {
+            let show = match config.notification_handling {
+                NotificationHandling::NeverShow => false,
+                NotificationHandling::AlwaysShow => true,
+                NotificationHandling::SuppressFromFocusedPane => f_pane != pane_id,
+                NotificationHandling::SuppressFromFocusedTab => f_tab != tab_id,
+                NotificationHandling::SuppressFromFocusedWindow => f_window != window_id,
+            };
+
+            if show {
+                notif.show();
+            }
+        }
+    }
+}
+
+/// Responds to the user interacting with a notification that was
+/// requested by the application running in pane_id
+fn handle_toast_event(
+    pane_id: PaneId,
+    id: Option<&str>,
+    event: ToastEvent,
+    focus: bool,
+    report_activation: bool,
+    report_close: bool,
+) {
+    let mux = Mux::get();
+    let report = match event {
+        ToastEvent::Activated => {
+            if focus {
+                if let Err(err) = mux.focus_pane_and_containing_tab(pane_id) {
+                    log::error!("Error focusing pane for notification: {err:#}");
+                }
+                if let Some((_domain, window_id, _tab_id)) = mux.resolve_pane_id(pane_id) {
+                    if let Some(gui_win) = front_end().gui_window_for_mux_window(window_id) {
+                        gui_win.window.focus();
+                    }
+                }
+            }
+            report_activation
+        }
+        ToastEvent::Closed => report_close,
+    };
+
+    // The application can only make sense of reports for
+    // notifications that it assigned an id to
+    let id = match id {
+        Some(id) if report => id,
+        _ => return,
+    };
+    let report = match event {
+        ToastEvent::Activated => DesktopNotification::activation_report(id),
+        ToastEvent::Closed => DesktopNotification::close_report(id),
+    };
+    if let Some(pane) = mux.get_pane(pane_id) {
+        let mut writer = pane.writer();
+        write!(
+            writer,
+            "{}",
+            OperatingSystemCommand::DesktopNotification(report)
+        )
+        .ok();
+        writer.flush().ok();
+    }
+}
+
 pub fn try_front_end() -> Option<Rc<GuiFrontEnd>> {
     FRONT_END.with(|f| f.borrow().as_ref().map(Rc::clone))
 }
@@ -527,14 +653,29 @@ pub fn shutdown() {
     FRONT_END.with(|f| drop(f.borrow_mut().take()));
 }
 
+/// Panes only watch for changes to the sockets that their processes
+/// are listening on when the config handles the event that reports them
+fn update_listening_sockets_wanted() {
+    let wanted = config::run_immediate_with_lua_config(|lua| {
+        Ok(match lua {
+            Some(lua) => config::lua::has_event_handler(&lua, "listening-sockets-changed")?,
+            None => false,
+        })
+    })
+    .unwrap_or(false);
+    Mux::get().set_listening_sockets_wanted(wanted);
+}
+
 pub fn try_new() -> Result<Rc<GuiFrontEnd>, Error> {
     let front_end = GuiFrontEnd::try_new()?;
     FRONT_END.with(|f| *f.borrow_mut() = Some(Rc::clone(&front_end)));
+    update_listening_sockets_wanted();
 
     let config_subscription = config::subscribe_to_config_reload({
         move || {
             promise::spawn::spawn_into_main_thread(async {
                 crate::commands::CommandDef::recreate_menubar(&config::configuration());
+                update_listening_sockets_wanted();
             })
             .detach();
             true
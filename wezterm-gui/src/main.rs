@@ -572,7 +572,7 @@ impl Publish {
 
                         let window_id = if new_tab || config.prefer_to_spawn_tabs {
                             if let Ok(pane_id) = client.resolve_pane_id(None).await {
-                                let panes = client.list_panes().await?;
+                                let panes = client.list_panes(codec::ListPanes::default()).await?;
 
                                 let mut window_id = None;
                                 'outer: for tabroot in panes.tabs {
@@ -62,11 +62,11 @@ use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use termwiz::hyperlink::Hyperlink;
 use termwiz::surface::SequenceNo;
-use wezterm_dynamic::Value;
+use wezterm_dynamic::{ToDynamic, Value};
 use wezterm_font::FontConfiguration;
 use wezterm_term::color::ColorPalette;
 use wezterm_term::input::LastMouseClick;
-use wezterm_term::{Alert, StableRowIndex, TerminalConfiguration, TerminalSize};
+use wezterm_term::{Alert, Progress, StableRowIndex, TerminalConfiguration, TerminalSize};
 
 pub mod background;
 pub mod box_model;
@@ -269,6 +269,7 @@ pub struct PaneInformation {
     pub pixel_height: usize,
     pub title: String,
     pub user_vars: HashMap<String, String>,
+    pub progress: Progress,
 }
 
 impl UserData for PaneInformation {
@@ -286,6 +287,9 @@ impl UserData for PaneInformation {
         fields.add_field_method_get("pixel_height", |_, this| Ok(this.pixel_width));
         fields.add_field_method_get("title", |_, this| Ok(this.title.clone()));
         fields.add_field_method_get("user_vars", |_, this| Ok(this.user_vars.clone()));
+        fields.add_field_method_get("progress", |lua, this| {
+            luahelper::dynamic_to_lua_value(lua, this.progress.to_dynamic())
+        });
         fields.add_field_method_get("foreground_process_name", |_, this| {
             let mut name = None;
             if let Some(mux) = Mux::try_get() {
@@ -1181,7 +1185,8 @@ impl TermWindow {
                         | Alert::CurrentWorkingDirectoryChanged
                         | Alert::WindowTitleChanged(_)
                         | Alert::TabTitleChanged(_)
-                        | Alert::IconTitleChanged(_),
+                        | Alert::IconTitleChanged(_)
+                        | Alert::Progress(_),
                     ..
                 } => {
                     self.update_title();
@@ -1215,9 +1220,33 @@ impl TermWindow {
                     window.invalidate();
                 }
                 MuxNotification::Alert {
-                    alert: Alert::ToastNotification { .. },
+                    alert:
+                        Alert::ToastNotification { .. }
+                        | Alert::CloseToastNotification { .. }
+                        | Alert::PointerShapeChanged(_),
                     ..
                 } => {}
+                MuxNotification::Alert {
+                    alert:
+                        Alert::CommandFinished {
+                            command,
+                            exit_status,
+                            duration,
+                        },
+                    pane_id,
+                } => {
+                    self.emit_command_finished_event(pane_id, command, exit_status, duration);
+                }
+                MuxNotification::ListeningSocketsChanged { pane_id, sockets } => {
+                    self.emit_listening_sockets_event(pane_id, sockets);
+                }
+                MuxNotification::TriggerMatched {
+                    pane_id,
+                    action,
+                    captures,
+                } => {
+                    self.perform_trigger_action(pane_id, action, captures);
+                }
                 MuxNotification::TabAddedToWindow {
                     window_id: _,
                     tab_id,
@@ -1429,8 +1458,12 @@ impl TermWindow {
                     | Alert::TabTitleChanged(_)
                     | Alert::IconTitleChanged(_)
                     | Alert::SetUserVar { .. }
+                    | Alert::CommandFinished { .. }
+                    | Alert::Progress(_)
                     | Alert::Bell,
             }
+            | MuxNotification::ListeningSocketsChanged { pane_id, .. }
+            | MuxNotification::TriggerMatched { pane_id, .. }
             | MuxNotification::PaneFocused(pane_id)
             | MuxNotification::PaneRemoved(pane_id)
             | MuxNotification::PaneOutput(pane_id) => {
@@ -1476,7 +1509,10 @@ impl TermWindow {
                 }
             }
             MuxNotification::Alert {
-                alert: Alert::ToastNotification { .. },
+                alert:
+                    Alert::ToastNotification { .. }
+                    | Alert::CloseToastNotification { .. }
+                    | Alert::PointerShapeChanged(_),
                 ..
             }
             | MuxNotification::AssignClipboard { .. }
@@ -1763,6 +1799,7 @@ impl TermWindow {
             for tab in window.iter() {
                 for pane in tab.iter_panes_ignoring_zoom() {
                     pane.pane.set_config(Arc::clone(&term_config));
+                    pane.pane.clear_highlights();
                 }
             }
             for state in self.pane_state.borrow().values() {
@@ -1906,6 +1943,201 @@ impl TermWindow {
         .detach();
     }
 
+    fn emit_command_finished_event(
+        &mut self,
+        pane_id: PaneId,
+        command: String,
+        exit_status: i32,
+        duration: Duration,
+    ) {
+        let mux = Mux::get();
+
+        let (_domain, window_id, _tab_id) = match mux.resolve_pane_id(pane_id) {
+            Some(tuple) => tuple,
+            None => return,
+        };
+
+        // We only want to emit the event for the window which contains
+        // this pane.
+        if window_id != self.mux_window_id {
+            return;
+        }
+
+        let window = GuiWin::new(self);
+        let pane = match mux.get_pane(pane_id) {
+            Some(pane) => mux_lua::MuxPane(pane.pane_id()),
+            None => return,
+        };
+
+        async fn do_event(
+            lua: Option<Rc<mlua::Lua>>,
+            command: String,
+            exit_status: i32,
+            duration: Duration,
+            window: GuiWin,
+            pane: MuxPane,
+        ) -> anyhow::Result<()> {
+            let default_action = match lua {
+                Some(lua) => {
+                    let info = lua.create_table()?;
+                    info.set("command", command.clone())?;
+                    info.set("exit_status", exit_status)?;
+                    info.set("duration", duration.as_secs_f64())?;
+                    let args = lua.pack_multi((window, pane, info))?;
+                    match config::lua::emit_event(&lua, ("command-finished".to_string(), args))
+                        .await
+                    {
+                        Ok(default_action) => default_action,
+                        Err(err) => {
+                            log::error!("while processing command-finished event: {:#}", err);
+                            true
+                        }
+                    }
+                }
+                None => true,
+            };
+
+            if default_action {
+                let title = if exit_status == 0 {
+                    "Command finished".to_string()
+                } else {
+                    format!("Command failed with exit status {}", exit_status)
+                };
+                let secs = duration.as_secs();
+                let took = if secs >= 3600 {
+                    format!("{}h{:02}m{:02}s", secs / 3600, (secs / 60) % 60, secs % 60)
+                } else if secs >= 60 {
+                    format!("{}m{:02}s", secs / 60, secs % 60)
+                } else {
+                    format!("{}s", secs)
+                };
+                let body = format!("{} (took {})", command, took);
+                front_end().show_pane_notification(pane.0, Some(&title), &body);
+            }
+
+            Ok(())
+        }
+
+        promise::spawn::spawn(config::with_lua_config_on_main_thread(move |lua| {
+            do_event(lua, command, exit_status, duration, window, pane)
+        }))
+        .detach();
+    }
+
+    fn perform_trigger_action(
+        &mut self,
+        pane_id: PaneId,
+        action: config::TriggerAction,
+        captures: Vec<String>,
+    ) {
+        let mux = Mux::get();
+
+        let (_domain, window_id, _tab_id) = match mux.resolve_pane_id(pane_id) {
+            Some(tuple) => tuple,
+            None => return,
+        };
+
+        // We only want to perform the action in the window which
+        // contains this pane.
+        if window_id != self.mux_window_id {
+            return;
+        }
+
+        let pane = match mux.get_pane(pane_id) {
+            Some(pane) => pane,
+            None => return,
+        };
+
+        match action {
+            config::TriggerAction::PerformAction(assignment) => {
+                if let Err(err) = self.perform_key_assignment(&pane, &assignment) {
+                    log::error!("while performing trigger action: {:#}", err);
+                }
+            }
+            config::TriggerAction::EmitEvent(name) => {
+                let window = GuiWin::new(self);
+                let pane = mux_lua::MuxPane(pane_id);
+
+                async fn do_event(
+                    lua: Option<Rc<mlua::Lua>>,
+                    name: String,
+                    captures: Vec<String>,
+                    window: GuiWin,
+                    pane: MuxPane,
+                ) -> anyhow::Result<()> {
+                    if let Some(lua) = lua {
+                        let args = lua.pack_multi((window, pane, captures))?;
+                        if let Err(err) = config::lua::emit_event(&lua, (name.clone(), args)).await
+                        {
+                            log::error!("while processing {} event: {:#}", name, err);
+                        }
+                    }
+                    Ok(())
+                }
+
+                promise::spawn::spawn(config::with_lua_config_on_main_thread(move |lua| {
+                    do_event(lua, name, captures, window, pane)
+                }))
+                .detach();
+            }
+            // These are performed by the mux
+            config::TriggerAction::Highlight { .. }
+            | config::TriggerAction::Notify { .. }
+            | config::TriggerAction::SetUserVar { .. } => {}
+        }
+    }
+
+    fn emit_listening_sockets_event(
+        &mut self,
+        pane_id: PaneId,
+        sockets: Vec<procinfo::LocalListeningSocket>,
+    ) {
+        let mux = Mux::get();
+
+        let (_domain, window_id, _tab_id) = match mux.resolve_pane_id(pane_id) {
+            Some(tuple) => tuple,
+            None => return,
+        };
+
+        // We only want to emit the event for the window which contains
+        // this pane.
+        if window_id != self.mux_window_id {
+            return;
+        }
+
+        let window = GuiWin::new(self);
+        let pane = match mux.get_pane(pane_id) {
+            Some(pane) => mux_lua::MuxPane(pane.pane_id()),
+            None => return,
+        };
+
+        async fn do_event(
+            lua: Option<Rc<mlua::Lua>>,
+            sockets: Vec<procinfo::LocalListeningSocket>,
+            window: GuiWin,
+            pane: MuxPane,
+        ) -> anyhow::Result<()> {
+            if let Some(lua) = lua {
+                let args = lua.pack_multi((window, pane, sockets))?;
+                if let Err(err) =
+                    config::lua::emit_event(&lua, ("listening-sockets-changed".to_string(), args))
+                        .await
+                {
+                    log::error!(
+                        "while processing listening-sockets-changed event: {:#}",
+                        err
+                    );
+                }
+            }
+            Ok(())
+        }
+
+        promise::spawn::spawn(config::with_lua_config_on_main_thread(move |lua| {
+            do_event(lua, sockets, window, pane)
+        }))
+        .detach();
+    }
+
     /// Called by window:set_right_status after the status has
     /// been updated; let's update the bar
     pub fn update_title_post_status(&mut self) {
@@ -3336,6 +3568,7 @@ impl TermWindow {
             pixel_height: pos.pixel_height,
             title: pos.pane.get_title(),
             user_vars: pos.pane.copy_user_vars(),
+            progress: pos.pane.get_progress(),
         }
     }
 
@@ -21,7 +21,7 @@ use termwiz::hyperlink::Hyperlink;
 use termwiz::surface::Line;
 use wezterm_dynamic::ToDynamic;
 use wezterm_term::input::{MouseButton, MouseEventKind as TMEK};
-use wezterm_term::{ClickPosition, LastMouseClick, StableRowIndex};
+use wezterm_term::{ClickPosition, LastMouseClick, PointerShape, StableRowIndex};
 
 impl super::TermWindow {
     fn resolve_ui_item(&self, event: &MouseEvent) -> Option<UIItem> {
@@ -837,7 +837,18 @@ impl super::TermWindow {
             // When hovering over a hyperlink, show an appropriate
             // mouse cursor to give the cue that it is clickable
             MouseCursor::Hand
-        } else if pane.is_mouse_grabbed() || outside_window {
+        } else if outside_window {
+            MouseCursor::Arrow
+        } else if let Some(shape) = pane.get_pointer_shape() {
+            // The application asked for a specific shape via OSC 22
+            match shape {
+                PointerShape::Default => MouseCursor::Arrow,
+                PointerShape::Text => MouseCursor::Text,
+                PointerShape::Pointer => MouseCursor::Hand,
+                PointerShape::EwResize => MouseCursor::SizeLeftRight,
+                PointerShape::NsResize => MouseCursor::SizeUpDown,
+            }
+        } else if pane.is_mouse_grabbed() {
             MouseCursor::Arrow
         } else {
             MouseCursor::Text
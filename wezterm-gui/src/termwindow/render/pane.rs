@@ -310,6 +310,8 @@ impl crate::TermWindow {
 
             pos.pane
                 .apply_hyperlinks(stable_range.clone(), &self.config.hyperlink_rules);
+            pos.pane
+                .apply_highlights(stable_range.clone(), &self.config.highlight_rules);
 
             struct LineRender<'a, 'b> {
                 term_window: &'a mut crate::TermWindow,
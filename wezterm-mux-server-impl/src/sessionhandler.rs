@@ -42,6 +42,7 @@ pub(crate) struct PerPane {
     working_dir: Option<Url>,
     dimensions: RenderableDimensions,
     mouse_grabbed: bool,
+    alt_screen_active: bool,
     sent_initial_palette: bool,
     seqno: SequenceNo,
     config_generation: usize,
@@ -60,6 +61,11 @@ impl PerPane {
             changed = true;
         }
 
+        let alt_screen_active = pane.is_alt_screen_active();
+        if alt_screen_active != self.alt_screen_active {
+            changed = true;
+        }
+
         let dims = pane.get_dimensions();
         if dims != self.dimensions {
             changed = true;
@@ -124,12 +130,14 @@ impl PerPane {
         self.working_dir = working_dir.clone();
         self.dimensions = dims;
         self.mouse_grabbed = mouse_grabbed;
+        self.alt_screen_active = alt_screen_active;
         self.seqno = pane.get_current_seqno();
 
         let bonus_lines = bonus_lines.into();
         Some(GetPaneRenderChangesResponse {
             pane_id: pane.pane_id(),
             mouse_grabbed,
+            alt_screen_active,
             dirty_lines: all_dirty_lines.iter().cloned().collect(),
             dimensions: dims,
             cursor_position,
@@ -199,6 +207,7 @@ pub struct SessionHandler {
     to_write_tx: PduSender,
     per_pane: HashMap<TabId, Arc<Mutex<PerPane>>>,
     client_id: Option<Arc<ClientId>>,
+    mux_events: Option<SubscribeToMuxEvents>,
 }
 
 impl Drop for SessionHandler {
@@ -216,9 +225,106 @@ impl SessionHandler {
             to_write_tx,
             per_pane: HashMap::new(),
             client_id: None,
+            mux_events: None,
         }
     }
 
+    /// If the client has subscribed to mux events, returns the
+    /// event that should be sent to it for the given notification
+    pub(crate) fn mux_event_for_notification(
+        &self,
+        notification: &MuxNotification,
+    ) -> Option<MuxEvent> {
+        let subscription = self.mux_events.as_ref()?;
+        let mux = Mux::get();
+
+        let resolve = |pane_id: PaneId| match mux.resolve_pane_id(pane_id) {
+            Some((_domain_id, window_id, tab_id)) => (Some(tab_id), Some(window_id)),
+            None => (None, None),
+        };
+
+        Some(match notification {
+            MuxNotification::PaneOutput(pane_id) => {
+                if !subscription.include_pane_output {
+                    return None;
+                }
+                let (tab_id, window_id) = resolve(*pane_id);
+                MuxEvent::PaneOutput {
+                    pane_id: *pane_id,
+                    tab_id,
+                    window_id,
+                }
+            }
+            MuxNotification::PaneAdded(pane_id) => {
+                let (tab_id, window_id) = resolve(*pane_id);
+                MuxEvent::PaneAdded {
+                    pane_id: *pane_id,
+                    tab_id,
+                    window_id,
+                }
+            }
+            MuxNotification::PaneRemoved(pane_id) => MuxEvent::PaneRemoved { pane_id: *pane_id },
+            MuxNotification::PaneFocused(pane_id) => {
+                let (tab_id, window_id) = resolve(*pane_id);
+                MuxEvent::PaneFocused {
+                    pane_id: *pane_id,
+                    tab_id,
+                    window_id,
+                }
+            }
+            MuxNotification::Alert { pane_id, alert } => {
+                let (tab_id, window_id) = resolve(*pane_id);
+                MuxEvent::Alert {
+                    pane_id: *pane_id,
+                    tab_id,
+                    window_id,
+                    alert: alert.clone(),
+                }
+            }
+            MuxNotification::WindowCreated(window_id) => MuxEvent::WindowCreated {
+                window_id: *window_id,
+            },
+            MuxNotification::WindowRemoved(window_id) => MuxEvent::WindowRemoved {
+                window_id: *window_id,
+            },
+            MuxNotification::WindowWorkspaceChanged(window_id) => {
+                MuxEvent::WindowWorkspaceChanged {
+                    window_id: *window_id,
+                    workspace: mux.get_window(*window_id)?.get_workspace().to_string(),
+                }
+            }
+            MuxNotification::WindowTitleChanged { window_id, title } => {
+                MuxEvent::WindowTitleChanged {
+                    window_id: *window_id,
+                    title: title.clone(),
+                }
+            }
+            MuxNotification::TabAddedToWindow { tab_id, window_id } => MuxEvent::TabAddedToWindow {
+                tab_id: *tab_id,
+                window_id: *window_id,
+            },
+            MuxNotification::TabResized(tab_id) => MuxEvent::TabResized { tab_id: *tab_id },
+            MuxNotification::TabTitleChanged { tab_id, title } => MuxEvent::TabTitleChanged {
+                tab_id: *tab_id,
+                title: title.clone(),
+            },
+            MuxNotification::WorkspaceRenamed {
+                old_workspace,
+                new_workspace,
+            } => MuxEvent::WorkspaceRenamed {
+                old_workspace: old_workspace.clone(),
+                new_workspace: new_workspace.clone(),
+            },
+            MuxNotification::WindowInvalidated(_)
+            | MuxNotification::ActiveWorkspaceChanged(_)
+            | MuxNotification::AssignClipboard { .. }
+            | MuxNotification::SaveToDownloads { .. }
+            | MuxNotification::ListeningSocketsChanged { .. }
+            | MuxNotification::TriggerMatched { .. }
+            | MuxNotification::Empty => return None,
+        })
+    }
+
     pub(crate) fn per_pane(&mut self, pane_id: PaneId) -> Arc<Mutex<PerPane>> {
         Arc::clone(
             self.per_pane
@@ -302,6 +408,10 @@ impl SessionHandler {
                 .detach();
                 send_response(Ok(Pdu::UnitResponse(UnitResponse {})))
             }
+            Pdu::SubscribeToMuxEvents(subscription) => {
+                self.mux_events.replace(subscription);
+                send_response(Ok(Pdu::UnitResponse(UnitResponse {})))
+            }
             Pdu::SetFocusedPane(SetFocusedPane { pane_id }) => {
                 let client_id = self.client_id.clone();
                 spawn_into_main_thread(async move {
@@ -359,7 +469,7 @@ impl SessionHandler {
                 })
                 .detach();
             }
-            Pdu::ListPanes(ListPanes {}) => {
+            Pdu::ListPanes(ListPanes { include_resources }) => {
                 spawn_into_main_thread(async move {
                     catch(
                         move || {
@@ -371,7 +481,7 @@ impl SessionHandler {
                                 let window = mux.get_window(window_id).unwrap();
                                 window_titles.insert(window_id, window.get_title().to_string());
                                 for tab in window.iter() {
-                                    tabs.push(tab.codec_pane_tree());
+                                    tabs.push(tab.codec_pane_tree(include_resources));
                                     tab_titles.push(tab.get_title());
                                 }
                             }
@@ -584,6 +694,63 @@ impl SessionHandler {
                 .detach();
             }
 
+            Pdu::GetSemanticZones(GetSemanticZones { pane_id }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            let zones = pane.get_semantic_zones()?;
+                            let exit_statuses = pane.get_command_exit_statuses()?;
+                            Ok(Pdu::GetSemanticZonesResponse(GetSemanticZonesResponse {
+                                zones,
+                                exit_statuses,
+                            }))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::GetCommandHistory(GetCommandHistory { pane_id }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            let history = pane.get_command_history()?;
+                            Ok(Pdu::GetCommandHistoryResponse(GetCommandHistoryResponse {
+                                history,
+                            }))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::GetPalette(GetPalette { pane_id }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            let palette = pane.palette();
+                            Ok(Pdu::GetPaletteResponse(GetPaletteResponse { palette }))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
             Pdu::ActivatePaneDirection(ActivatePaneDirection { pane_id, direction }) => {
                 spawn_into_main_thread(async move {
                     catch(
@@ -971,6 +1138,9 @@ impl SessionHandler {
             | Pdu::UnitResponse { .. }
             | Pdu::LivenessResponse { .. }
             | Pdu::GetPaneDirectionResponse { .. }
+            | Pdu::GetSemanticZonesResponse { .. }
+            | Pdu::GetPaletteResponse { .. }
+            | Pdu::GetCommandHistoryResponse { .. }
             | Pdu::SearchScrollbackResponse { .. }
             | Pdu::GetLinesResponse { .. }
             | Pdu::GetCodecVersionResponse { .. }
@@ -983,6 +1153,7 @@ impl SessionHandler {
             | Pdu::GetImageCellResponse { .. }
             | Pdu::MovePaneToNewTabResponse { .. }
             | Pdu::TabAddedToWindow { .. }
+            | Pdu::MuxEventNotification { .. }
             | Pdu::GetPaneRenderableDimensionsResponse { .. }
             | Pdu::ErrorResponse { .. } => {
                 send_response(Err(anyhow!("expected a request, got {:?}", decoded.pdu)))
@@ -1,10 +1,12 @@
 #![cfg(all(not(target_os = "macos"), not(windows)))]
 //! See <https://developer.gnome.org/notification-spec/>
 
-use crate::ToastNotification;
-use futures_util::stream::{abortable, StreamExt};
+use crate::{ToastEvent, ToastNotification, Urgency};
+use futures_util::stream::{abortable, AbortHandle, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 use zbus::dbus_proxy;
 use zvariant::{Type, Value};
 
@@ -86,50 +88,129 @@ impl Reason {
     }
 }
 
+/// Maps the ids that were assigned to notifications by the application
+/// to the ids that were assigned by the notification server, so that
+/// they can be replaced or closed later
+static NOTIFICATION_IDS: Mutex<BTreeMap<String, u32>> = Mutex::new(BTreeMap::new());
+
+/// Listens for the signals about a notification on behalf of
+/// the most recent call to show it
+struct Listener {
+    generation: u64,
+    abort_invoked: AbortHandle,
+    abort_closed: AbortHandle,
+}
+
+impl Listener {
+    fn abort(&self) {
+        self.abort_invoked.abort();
+        self.abort_closed.abort();
+    }
+}
+
+/// Maps the ids that were assigned by the notification server to
+/// the listener for that notification.  When a notification is
+/// replaced, the listener for the notification that it replaces is
+/// cancelled so that each signal is only reported once.
+static LISTENERS: Mutex<BTreeMap<u32, Listener>> = Mutex::new(BTreeMap::new());
+static NEXT_LISTENER_GENERATION: AtomicU64 = AtomicU64::new(0);
+
 async fn show_notif_impl(notif: ToastNotification) -> Result<(), Box<dyn std::error::Error>> {
     let connection = zbus::ConnectionBuilder::session()?.build().await?;
+    show_notif_on(&connection, notif).await
+}
 
-    let proxy = NotificationsProxy::new(&connection).await?;
+async fn show_notif_on(
+    connection: &zbus::Connection,
+    notif: ToastNotification,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let proxy = NotificationsProxy::new(connection).await?;
     let caps = proxy.get_capabilities().await?;
+    let has_actions = caps.iter().any(|cap| cap == "actions");
 
-    if notif.url.is_some() && !caps.iter().any(|cap| cap == "actions") {
+    if notif.url.is_some() && !has_actions {
         // Server doesn't support actions, so skip showing this notification
         // because it might have text that says "click to see more"
         // and that just wouldn't work.
         return Ok(());
     }
 
+    // Subscribe before showing the notification, so that we cannot
+    // miss a signal that is sent before we start listening
+    let (mut invoked_stream, abort_invoked) = abortable(proxy.receive_action_invoked().await?);
+    let (mut closed_stream, abort_closed) = abortable(proxy.receive_notification_closed().await?);
+
+    let mut actions = vec![];
+    if notif.url.is_some() {
+        actions.extend_from_slice(&["show", "Show"]);
+    }
+    if notif.on_event.is_some() && has_actions {
+        // The "default" action is invoked by clicking on the notification
+        actions.extend_from_slice(&["default", "Activate"]);
+    }
+
+    let replaces_id = notif
+        .id
+        .as_ref()
+        .and_then(|id| NOTIFICATION_IDS.lock().unwrap().get(id).copied())
+        .unwrap_or(0);
+
     let mut hints = HashMap::new();
-    hints.insert("urgency", Value::U8(2 /* Critical */));
+    hints.insert(
+        "urgency",
+        Value::U8(match notif.urgency.unwrap_or(Urgency::Critical) {
+            Urgency::Low => 0,
+            Urgency::Normal => 1,
+            Urgency::Critical => 2,
+        }),
+    );
     let notification = proxy
         .notify(
             "wezterm",
-            0,
+            replaces_id,
             "org.wezfurlong.wezterm",
             &notif.title,
             &notif.message,
-            if notif.url.is_some() {
-                &["show", "Show"]
-            } else {
-                &[]
-            },
+            &actions,
             &hints,
             notif.timeout.map(|d| d.as_millis() as _).unwrap_or(0),
         )
         .await?;
 
-    let (mut invoked_stream, abort_invoked) = abortable(proxy.receive_action_invoked().await?);
-    let (mut closed_stream, abort_closed) = abortable(proxy.receive_notification_closed().await?);
+    if let Some(id) = &notif.id {
+        NOTIFICATION_IDS
+            .lock()
+            .unwrap()
+            .insert(id.clone(), notification);
+    }
+
+    let generation = NEXT_LISTENER_GENERATION.fetch_add(1, Ordering::Relaxed);
+    let listener = Listener {
+        generation,
+        abort_invoked: abort_invoked.clone(),
+        abort_closed: abort_closed.clone(),
+    };
+    if let Some(replaced) = LISTENERS.lock().unwrap().insert(notification, listener) {
+        replaced.abort();
+    }
 
-    futures_util::try_join!(
+    let result = futures_util::try_join!(
         async {
             while let Some(signal) = invoked_stream.next().await {
                 let args = signal.args()?;
                 if args.nid == notification {
                     if let Some(url) = notif.url.as_ref() {
                         wezterm_open_url::open_url(url);
-                        abort_closed.abort();
-                        break;
+                    }
+                    match &notif.on_event {
+                        // Keep listening so that we can report
+                        // when the notification is closed
+                        Some(on_event) => on_event(ToastEvent::Activated),
+                        None if notif.url.is_some() => {
+                            abort_closed.abort();
+                            break;
+                        }
+                        None => {}
                     }
                 }
             }
@@ -140,14 +221,30 @@ async fn show_notif_impl(notif: ToastNotification) -> Result<(), Box<dyn std::er
                 let args = signal.args()?;
                 let _reason = Reason::new(args.reason);
                 if args.nid == notification {
+                    if let Some(id) = &notif.id {
+                        let mut ids = NOTIFICATION_IDS.lock().unwrap();
+                        if ids.get(id) == Some(&notification) {
+                            ids.remove(id);
+                        }
+                    }
+                    if let Some(on_event) = &notif.on_event {
+                        on_event(ToastEvent::Closed);
+                    }
                     abort_invoked.abort();
                     break;
                 }
             }
             Ok(())
         }
-    )?;
+    );
 
+    let mut listeners = LISTENERS.lock().unwrap();
+    if listeners.get(&notification).map(|l| l.generation) == Some(generation) {
+        listeners.remove(&notification);
+    }
+    drop(listeners);
+
+    result?;
     Ok(())
 }
 
@@ -162,3 +259,196 @@ pub fn show_notif(notif: ToastNotification) -> Result<(), Box<dyn std::error::Er
     });
     Ok(())
 }
+
+pub fn close_notif(id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let nid = match NOTIFICATION_IDS.lock().unwrap().get(id).copied() {
+        Some(nid) => nid,
+        None => return Ok(()),
+    };
+    std::thread::spawn(move || {
+        let res = async_io::block_on(async move {
+            let connection = zbus::ConnectionBuilder::session()?.build().await?;
+            let proxy = NotificationsProxy::new(&connection).await?;
+            proxy.close_notification(nid).await
+        });
+        if let Err(err) = res {
+            log::error!("while closing notification: {:#}", err);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Command, Stdio};
+    use std::sync::mpsc::{sync_channel, SyncSender};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use zbus::dbus_interface;
+    use zvariant::OwnedValue;
+
+    #[derive(Debug, PartialEq)]
+    struct Notified {
+        replaces_id: u32,
+        summary: String,
+        actions: Vec<String>,
+        urgency: Option<u8>,
+    }
+
+    /// Stands in for the notification server
+    struct FakeServer {
+        notified: SyncSender<Notified>,
+    }
+
+    #[dbus_interface(name = "org.freedesktop.Notifications")]
+    impl FakeServer {
+        fn get_capabilities(&self) -> Vec<String> {
+            vec!["actions".to_string(), "body".to_string()]
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            _app_name: String,
+            replaces_id: u32,
+            _app_icon: String,
+            summary: String,
+            _body: String,
+            actions: Vec<String>,
+            hints: HashMap<String, OwnedValue>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            let urgency = hints
+                .get("urgency")
+                .and_then(|value| u8::try_from(value.clone()).ok());
+            self.notified
+                .send(Notified {
+                    replaces_id,
+                    summary,
+                    actions,
+                    urgency,
+                })
+                .unwrap();
+            if replaces_id == 0 {
+                42
+            } else {
+                replaces_id
+            }
+        }
+
+        fn close_notification(&self, _nid: u32) {}
+    }
+
+    /// Runs a private bus, which is shut down when this is dropped
+    struct Bus(std::process::Child);
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            self.0.kill().ok();
+            self.0.wait().ok();
+        }
+    }
+
+    fn emit(connection: &zbus::Connection, signal: &str, body: &(u32, impl Serialize + Type)) {
+        async_io::block_on(connection.emit_signal(
+            None::<()>,
+            "/org/freedesktop/Notifications",
+            "org.freedesktop.Notifications",
+            signal,
+            body,
+        ))
+        .unwrap();
+    }
+
+    #[test]
+    fn events() {
+        let mut bus = match Command::new("dbus-daemon")
+            .args([
+                "--session",
+                "--nofork",
+                "--print-address=1",
+                "--address=unix:tmpdir=/tmp",
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => Bus(child),
+            Err(err) => {
+                eprintln!("skipping test because dbus-daemon is not available: {err}");
+                return;
+            }
+        };
+        let mut address = String::new();
+        BufReader::new(bus.0.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        let address = address.trim();
+
+        let (notified_tx, notified_rx) = sync_channel(8);
+        let server = async_io::block_on(async {
+            zbus::ConnectionBuilder::address(address)?
+                .name("org.freedesktop.Notifications")?
+                .serve_at(
+                    "/org/freedesktop/Notifications",
+                    FakeServer {
+                        notified: notified_tx,
+                    },
+                )?
+                .build()
+                .await
+        })
+        .unwrap();
+        let client =
+            async_io::block_on(zbus::ConnectionBuilder::address(address).unwrap().build()).unwrap();
+
+        let (event_tx, event_rx) = sync_channel(8);
+        let notif = ToastNotification {
+            title: "hello".to_string(),
+            id: Some("1:build".to_string()),
+            urgency: Some(Urgency::Low),
+            on_event: Some(Arc::new(move |event| event_tx.send(event).unwrap())),
+            ..Default::default()
+        };
+        let show = |notif: ToastNotification| {
+            let client = client.clone();
+            std::thread::spawn(move || {
+                async_io::block_on(show_notif_on(&client, notif)).map_err(|err| err.to_string())
+            })
+        };
+        let timeout = Duration::from_secs(10);
+
+        let first = show(notif.clone());
+        assert_eq!(
+            notified_rx.recv_timeout(timeout).unwrap(),
+            Notified {
+                replaces_id: 0,
+                summary: "hello".to_string(),
+                actions: vec!["default".to_string(), "Activate".to_string()],
+                urgency: Some(0),
+            }
+        );
+
+        emit(&server, "ActionInvoked", &(42, "default"));
+        assert_eq!(event_rx.recv_timeout(timeout), Ok(ToastEvent::Activated));
+
+        // Showing a notification with the same id replaces it
+        let second = show(ToastNotification {
+            title: "updated".to_string(),
+            ..notif
+        });
+        assert_eq!(notified_rx.recv_timeout(timeout).unwrap().replaces_id, 42);
+        // which cancels the listener for the notification it replaced
+        first.join().unwrap().unwrap();
+
+        emit(&server, "NotificationClosed", &(42, REASON_USER_DISMISSED));
+        assert_eq!(event_rx.recv_timeout(timeout), Ok(ToastEvent::Closed));
+        second.join().unwrap().unwrap();
+        assert!(event_rx.try_recv().is_err());
+        assert!(NOTIFICATION_IDS.lock().unwrap().is_empty());
+        assert!(LISTENERS.lock().unwrap().is_empty());
+    }
+}
@@ -2,12 +2,56 @@ mod dbus;
 mod macos;
 mod windows;
 
-#[derive(Debug, Clone)]
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+/// Events that are reported to the `on_event` handler
+/// of a `ToastNotification`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastEvent {
+    /// The user clicked on the notification
+    Activated,
+    /// The notification was dismissed, expired or closed
+    Closed,
+}
+
+pub type ToastEventHandler = Arc<dyn Fn(ToastEvent) + Send + Sync>;
+
+#[derive(Clone, Default)]
 pub struct ToastNotification {
     pub title: String,
     pub message: String,
     pub url: Option<String>,
     pub timeout: Option<std::time::Duration>,
+    /// Identifies the notification, so that a subsequent notification
+    /// with the same id replaces it, and so that it can be closed
+    /// via `close_notification`
+    pub id: Option<String>,
+    /// Defaults to `Urgency::Critical`
+    pub urgency: Option<Urgency>,
+    /// Called when the notification is activated or closed.
+    /// This is currently only supported by the dbus backend.
+    pub on_event: Option<ToastEventHandler>,
+}
+
+impl std::fmt::Debug for ToastNotification {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("ToastNotification")
+            .field("title", &self.title)
+            .field("message", &self.message)
+            .field("url", &self.url)
+            .field("timeout", &self.timeout)
+            .field("id", &self.id)
+            .field("urgency", &self.urgency)
+            .field("on_event", &self.on_event.is_some())
+            .finish()
+    }
 }
 
 impl ToastNotification {
@@ -30,6 +74,11 @@ mod nop {
     pub fn show_notif(_: ToastNotification) -> Result<(), Box<dyn std::error::Error>> {
         Ok(())
     }
+
+    #[allow(dead_code)]
+    pub fn close_notif(_: &str) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
 }
 
 pub fn show(notif: ToastNotification) {
@@ -38,12 +87,20 @@ pub fn show(notif: ToastNotification) {
     }
 }
 
+/// Closes the notification that was shown with the specified id
+pub fn close_notification(id: &str) {
+    if let Err(err) = backend::close_notif(id) {
+        log::error!("Failed to close notification: {}", err);
+    }
+}
+
 pub fn persistent_toast_notification_with_click_to_open_url(title: &str, message: &str, url: &str) {
     show(ToastNotification {
         title: title.to_string(),
         message: message.to_string(),
         url: Some(url.to_string()),
         timeout: None,
+        ..Default::default()
     });
 }
 
@@ -53,5 +110,6 @@ pub fn persistent_toast_notification(title: &str, message: &str) {
         message: message.to_string(),
         url: None,
         timeout: None,
+        ..Default::default()
     });
 }
@@ -1,5 +1,6 @@
 #![cfg(target_os = "macos")]
 
+pub use crate::nop::close_notif;
 use crate::ToastNotification;
 use cocoa::base::*;
 use cocoa::foundation::{NSDictionary, NSString};
@@ -1,5 +1,6 @@
 #![cfg(windows)]
 
+pub use crate::nop::close_notif;
 use crate::ToastNotification as TN;
 use xml::escape::escape_str_pcdata;
 
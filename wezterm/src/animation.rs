@@ -0,0 +1,95 @@
+//! Encoding of rendered frames into animated images
+use anyhow::Context;
+use clap::ValueEnum;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::time::Duration;
+
+/// Trades off the quality of the GIF color quantization against
+/// encoding time; 1 is the slowest and best, and 30 is the fastest
+const GIF_ENCODING_SPEED: i32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+}
+
+impl AnimationFormat {
+    /// Determine the format from the extension of the file name
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "gif" => Some(Self::Gif),
+            "png" | "apng" => Some(Self::Apng),
+            _ => None,
+        }
+    }
+}
+
+/// Writes a sequence of equally sized frames to an animated image
+/// that loops forever
+pub enum AnimationWriter {
+    Gif(GifEncoder<BufWriter<File>>),
+    Apng(png::Writer<BufWriter<File>>),
+}
+
+impl AnimationWriter {
+    /// Create the file at `path`.  `num_frames` is the total number
+    /// of frames that will be added, which APNG requires up front.
+    pub fn create(
+        path: &Path,
+        format: AnimationFormat,
+        width: u32,
+        height: u32,
+        num_frames: usize,
+    ) -> anyhow::Result<Self> {
+        let file = BufWriter::new(
+            File::create(path).with_context(|| format!("creating {}", path.display()))?,
+        );
+        match format {
+            AnimationFormat::Gif => {
+                let mut encoder = GifEncoder::new_with_speed(file, GIF_ENCODING_SPEED);
+                encoder.set_repeat(Repeat::Infinite)?;
+                Ok(Self::Gif(encoder))
+            }
+            AnimationFormat::Apng => {
+                let mut encoder = png::Encoder::new(file, width, height);
+                encoder.set_color(png::ColorType::Rgba);
+                encoder.set_depth(png::BitDepth::Eight);
+                encoder.set_animated(num_frames.max(1) as u32, 0)?;
+                Ok(Self::Apng(encoder.write_header()?))
+            }
+        }
+    }
+
+    /// Append a frame that is displayed for `duration`
+    pub fn add_frame(&mut self, image: RgbaImage, duration: Duration) -> anyhow::Result<()> {
+        match self {
+            Self::Gif(encoder) => {
+                let delay = Delay::from_saturating_duration(duration);
+                encoder.encode_frame(Frame::from_parts(image, 0, 0, delay))?;
+            }
+            Self::Apng(writer) => {
+                // The delay is a u16 fraction; centiseconds allow for
+                // frames lasting more than ten minutes
+                let centis = (duration.as_millis() / 10).clamp(1, u16::MAX as u128) as u16;
+                writer.set_frame_delay(centis, 100)?;
+                writer.write_image_data(&image)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> anyhow::Result<()> {
+        match self {
+            // The GIF trailer is written when the encoder is dropped
+            Self::Gif(encoder) => drop(encoder),
+            Self::Apng(writer) => writer.finish()?,
+        }
+        Ok(())
+    }
+}
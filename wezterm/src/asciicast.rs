@@ -1,7 +1,7 @@
 use anyhow::Context;
 use chrono::serde::ts_seconds_option;
 use chrono::{DateTime, Utc};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use config::ConfigHandle;
 use filedescriptor::FileDescriptor;
 use portable_pty::{native_pty_system, PtySize};
@@ -10,7 +10,7 @@ use std::collections::HashMap;
 use std::ffi::{OsStr, OsString};
 use std::io::{BufRead, BufReader, BufWriter, Read, Write};
 use std::path::PathBuf;
-use std::sync::mpsc::channel;
+use std::sync::mpsc::{channel, RecvTimeoutError};
 use std::time::{Duration, Instant};
 use termwiz::escape::parser::Parser as TWParser;
 use termwiz::escape::Action;
@@ -20,11 +20,23 @@ use wezterm_term::color::ColorPalette;
 #[cfg(windows)]
 use win::WinTty as Tty;
 
+/// How often the recorder checks whether the terminal has been resized
+const RESIZE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The version of the asciicast format to produce when recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CastVersion {
+    V2,
+    V3,
+}
+
 /// See <https://github.com/asciinema/asciinema/blob/develop/doc/asciicast-v2.md>
-/// for file format specification
+/// for file format specification.
+/// Version 3 headers are converted to this representation when read;
+/// see [HeaderV3].
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct Header {
-    /// Must be 2 or higher
+    /// Either 2 or 3
     pub version: u32,
     /// Initial terminal width (number of columns)
     pub width: u32,
@@ -110,6 +122,115 @@ impl Header {
             ..Default::default()
         }
     }
+
+    /// Parse the header line of either a version 2 or a version 3 asciicast
+    fn parse(line: &str) -> anyhow::Result<Self> {
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+
+        let Version { version } = serde_json::from_str(line)?;
+        match version {
+            2 => Ok(serde_json::from_str(line)?),
+            3 => Ok(serde_json::from_str::<HeaderV3>(line)?.into()),
+            version => anyhow::bail!("unsupported asciicast version {version}"),
+        }
+    }
+}
+
+/// See <https://docs.asciinema.org/manual/asciicast/v3/>
+/// for file format specification
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct HeaderV3 {
+    /// Must be 3
+    pub version: u32,
+    /// Information about the recorded terminal
+    pub term: TermInfo,
+    /// Unix timestamp of starting time of session
+    #[serde(
+        default,
+        with = "ts_seconds_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Used to reduce terminal inactivity (delays between frames)
+    /// to a maximum of this amount.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_time_limit: Option<f32>,
+    /// Command that was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Title of the asciicast
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Map of captured environment variables
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct TermInfo {
+    /// Initial terminal width (number of columns)
+    pub cols: u32,
+    /// Initial terminal height (number of rows)
+    pub rows: u32,
+    /// The value of the TERM environment variable
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub term_type: Option<String>,
+    /// The version of the terminal emulator
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Color theme of the recorded terminal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
+}
+
+impl From<HeaderV3> for Header {
+    fn from(header: HeaderV3) -> Self {
+        let mut env = header.env;
+        if let Some(term) = header.term.term_type {
+            env.entry("TERM".to_string()).or_insert(term);
+        }
+        Header {
+            version: header.version,
+            width: header.term.cols,
+            height: header.term.rows,
+            timestamp: header.timestamp,
+            duration: None,
+            idle_time_limit: header.idle_time_limit,
+            command: header.command,
+            title: header.title,
+            env,
+            theme: header.term.theme,
+        }
+    }
+}
+
+impl From<Header> for HeaderV3 {
+    fn from(header: Header) -> Self {
+        // Version 3 records the terminal type alongside the
+        // other information about the terminal, rather than
+        // in the environment
+        let mut env = header.env;
+        let term_type = env.remove("TERM");
+        let version = env.get("WEZTERM_VERSION").cloned();
+        HeaderV3 {
+            version: 3,
+            term: TermInfo {
+                cols: header.width,
+                rows: header.height,
+                term_type,
+                version,
+                theme: header.theme,
+            },
+            timestamp: header.timestamp,
+            idle_time_limit: header.idle_time_limit,
+            command: header.command,
+            title: header.title,
+            env,
+        }
+    }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -122,13 +243,95 @@ pub struct Theme {
     pub palette: String,
 }
 
+/// An event in the recording: the time at which it occurred, the
+/// type of event, and its data.
+/// The types are `o` for output, `i` for input, `r` for a resize
+/// to `COLSxROWS`, `m` for a marker with an optional label and,
+/// in version 3, `x` for the exit status of the recorded program.
+/// The time is always measured in seconds from the start of the
+/// recording; version 3 files, which store the interval since the
+/// prior event instead, are converted by [CastReader] and [CastWriter].
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct Event(pub f32, pub String, pub String);
 
-impl Event {
-    fn log_output<W: Write>(mut w: W, elapsed: f32, output: &str) -> std::io::Result<()> {
-        let event = Event(elapsed, "o".to_string(), output.to_string());
-        writeln!(w, "{}", serde_json::to_string(&event)?)
+/// Reads the events that follow the header of a cast file
+struct CastReader<R: BufRead> {
+    lines: std::io::Lines<R>,
+    version: u32,
+    elapsed: f32,
+}
+
+impl<R: BufRead> CastReader<R> {
+    fn new(reader: R, header: &Header) -> Self {
+        Self {
+            lines: reader.lines(),
+            version: header.version,
+            elapsed: 0.,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for CastReader<R> {
+    type Item = anyhow::Result<Event>;
+
+    fn next(&mut self) -> Option<anyhow::Result<Event>> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err.into())),
+            };
+            // Version 3 permits blank lines and comments
+            if self.version >= 3 && (line.trim().is_empty() || line.starts_with('#')) {
+                continue;
+            }
+            let mut event: Event = match serde_json::from_str(&line) {
+                Ok(event) => event,
+                Err(err) => return Some(Err(err.into())),
+            };
+            if self.version >= 3 {
+                self.elapsed += event.0;
+                event.0 = self.elapsed;
+            }
+            return Some(Ok(event));
+        }
+    }
+}
+
+/// Writes a header followed by events to a cast file
+struct CastWriter<W: Write> {
+    w: W,
+    version: CastVersion,
+    prior: f32,
+}
+
+impl<W: Write> CastWriter<W> {
+    fn new(mut w: W, header: &Header, version: CastVersion) -> anyhow::Result<Self> {
+        let header = match version {
+            CastVersion::V2 => serde_json::to_string(header)?,
+            CastVersion::V3 => serde_json::to_string(&HeaderV3::from(header.clone()))?,
+        };
+        writeln!(w, "{}", header)?;
+        Ok(Self {
+            w,
+            version,
+            prior: 0.,
+        })
+    }
+
+    /// Write an event that occurred `elapsed` seconds into the recording
+    fn log(&mut self, elapsed: f32, code: &str, data: &str) -> anyhow::Result<()> {
+        let time = match self.version {
+            CastVersion::V2 => elapsed,
+            CastVersion::V3 => (elapsed - self.prior).max(0.),
+        };
+        self.prior = elapsed;
+        let event = Event(time, code.to_string(), data.to_string());
+        writeln!(self.w, "{}", serde_json::to_string(&event)?)?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.w.flush()
     }
 }
 
@@ -342,6 +545,22 @@ pub struct RecordCommand {
     #[arg(long)]
     cwd: Option<std::path::PathBuf>,
 
+    /// The version of the asciicast format to write
+    #[arg(long, value_enum, default_value = "v2")]
+    format: CastVersion,
+
+    /// Record keyboard input in addition to the output.
+    /// Note that this includes anything typed at a password prompt!
+    #[arg(long)]
+    capture_input: bool,
+
+    /// When this key is pressed, add a marker to the recording
+    /// rather than passing the key through to the program.
+    /// Specify either a single character or a control character
+    /// in caret notation, such as `^]` for CTRL-].
+    #[arg(long, value_parser = parse_marker_key)]
+    marker_key: Option<u8>,
+
     /// Start prog instead of the default_prog defined by your
     /// wezterm configuration
     #[arg(value_parser)]
@@ -355,7 +574,11 @@ impl RecordCommand {
         let mut tty = Tty::new()?;
         let size = tty.get_size()?;
 
-        let header = Header::new(&config, size, &prog);
+        let mut header = Header::new(&config, size, &prog);
+        header.version = match self.format {
+            CastVersion::V2 => 2,
+            CastVersion::V3 => 3,
+        };
 
         let (cast_file, cast_file_name) = tempfile::Builder::new()
             .prefix("wezterm-recording-")
@@ -363,8 +586,7 @@ impl RecordCommand {
             .suffix(".cast.txt")
             .tempfile()?
             .keep()?;
-        let mut cast_file = BufWriter::new(cast_file);
-        writeln!(cast_file, "{}", serde_json::to_string(&header)?)?;
+        let mut cast_file = CastWriter::new(BufWriter::new(cast_file), &header, self.format)?;
 
         let pty_system = native_pty_system();
         let pair = pty_system.openpty(size)?;
@@ -431,13 +653,43 @@ impl RecordCommand {
         let first_output = Instant::now();
         let mut buffer = vec![];
         let mut writer = pair.master.take_writer()?;
+        let mut size = size;
+
+        loop {
+            let msg = match rx.recv_timeout(RESIZE_POLL_INTERVAL) {
+                Ok(msg) => Some(msg),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            let new_size = tty.get_size()?;
+            if (new_size.cols, new_size.rows) != (size.cols, size.rows) {
+                size = new_size;
+                pair.master.resize(size)?;
+                let elapsed = first_output.elapsed().as_secs_f32();
+                cast_file.log(elapsed, "r", &format!("{}x{}", size.cols, size.rows))?;
+            }
 
-        for msg in rx {
             match msg {
-                Message::Stdin(data) => {
+                None => {}
+                Some(Message::Stdin(mut data)) => {
+                    let elapsed = first_output.elapsed().as_secs_f32();
+                    if let Some(key) = self.marker_key {
+                        let len = data.len();
+                        data.retain(|&b| b != key);
+                        for _ in data.len()..len {
+                            cast_file.log(elapsed, "m", "")?;
+                        }
+                    }
+                    if data.is_empty() {
+                        continue;
+                    }
+                    if self.capture_input {
+                        cast_file.log(elapsed, "i", &String::from_utf8_lossy(&data))?;
+                    }
                     writer.write_all(&data)?;
                 }
-                Message::Stdout(mut data) => {
+                Some(Message::Stdout(mut data)) => {
                     let elapsed = first_output.elapsed().as_secs_f32();
                     tty.write_all(&data)?;
 
@@ -448,12 +700,12 @@ impl RecordCommand {
                     buffer.append(&mut data);
                     match std::str::from_utf8(&buffer) {
                         Ok(valid) => {
-                            Event::log_output(&mut cast_file, elapsed, valid)?;
+                            cast_file.log(elapsed, "o", valid)?;
                             buffer.clear();
                         }
                         Err(error) => {
                             let valid_len = error.valid_up_to();
-                            Event::log_output(&mut cast_file, elapsed, unsafe {
+                            cast_file.log(elapsed, "o", unsafe {
                                 std::str::from_utf8_unchecked(&buffer[0..valid_len])
                             })?;
 
@@ -466,7 +718,11 @@ impl RecordCommand {
                         }
                     }
                 }
-                Message::Terminated(status) => {
+                Some(Message::Terminated(status)) => {
+                    if self.format == CastVersion::V3 {
+                        let elapsed = first_output.elapsed().as_secs_f32();
+                        cast_file.log(elapsed, "x", &status.exit_code().to_string())?;
+                    }
                     child_status.replace(status);
                     break;
                 }
@@ -482,6 +738,19 @@ impl RecordCommand {
     }
 }
 
+/// Parse the `--marker-key` argument of the record command
+fn parse_marker_key(key: &str) -> anyhow::Result<u8> {
+    match key.as_bytes() {
+        [b'^', c] if (b'@'..=b'_').contains(&c.to_ascii_uppercase()) => {
+            Ok(c.to_ascii_uppercase() ^ 0x40)
+        }
+        [c] if c.is_ascii() => Ok(*c),
+        _ => anyhow::bail!(
+            "expected a single character or a control character such as ^] but got {key}"
+        ),
+    }
+}
+
 #[derive(Debug, Parser, Clone)]
 pub struct PlayCommand {
     /// Explain what is being sent/received
@@ -496,11 +765,52 @@ pub struct PlayCommand {
     #[arg(long, conflicts_with = "explain")]
     cat: bool,
 
+    /// Instead of replaying into the terminal, render the screen
+    /// as it appears at the end of the recording to an image.
+    /// The format is determined by the extension of the file name,
+    /// which may be either `.png` or `.svg`.
+    #[cfg(feature = "screenshot")]
+    #[arg(long, conflicts_with_all = ["explain", "explain_only", "cat", "pause_on_markers"])]
+    screenshot: Option<PathBuf>,
+
+    /// Used together with `--screenshot` to capture the screen as it
+    /// appears at the specified number of seconds into the recording,
+    /// rather than at the end.
+    #[cfg(feature = "screenshot")]
+    #[arg(long, requires = "screenshot")]
+    at: Option<f32>,
+
+    /// Instead of replaying into the terminal, render the recording
+    /// to an animated image.
+    /// The format is determined by the extension of the file name:
+    /// `.gif` produces a GIF, while `.png` and `.apng` produce an
+    /// animated PNG.
+    #[cfg(feature = "screenshot")]
+    #[arg(long, conflicts_with_all = ["explain", "explain_only", "cat", "screenshot", "pause_on_markers"])]
+    render: Option<PathBuf>,
+
+    /// The number of frames per second to sample from the recording
+    /// when using `--render`.
+    /// Consecutive frames in which nothing changed are combined.
+    #[cfg(feature = "screenshot")]
+    #[arg(long, default_value = "10")]
+    fps: f32,
+
+    /// Playback speed; 2 plays the recording twice as fast
+    /// as it was recorded, while 0.5 plays it at half speed.
+    /// Also applies to the timing of `--render`.
+    #[arg(long, default_value = "1")]
+    speed: f32,
+
+    /// Pause playback when a marker is reached
+    #[arg(long, conflicts_with_all = ["explain_only", "cat"])]
+    pause_on_markers: bool,
+
     cast_file: PathBuf,
 }
 
 impl PlayCommand {
-    pub fn run(&self) -> anyhow::Result<()> {
+    pub fn run(&self, #[cfg(feature = "screenshot")] config: ConfigHandle) -> anyhow::Result<()> {
         let mut cast_file = BufReader::new(
             std::fs::File::open(&self.cast_file)
                 .with_context(|| format!("reading cast file {}", self.cast_file.display()))?,
@@ -510,12 +820,17 @@ impl PlayCommand {
             .read_line(&mut header_line)
             .context("reading Header line")?;
 
-        let header: Header = serde_json::from_str(&header_line).context("parsing Header")?;
+        let header = Header::parse(&header_line).context("parsing Header")?;
+        let events = CastReader::new(cast_file, &header);
+
+        #[cfg(feature = "screenshot")]
+        if self.screenshot.is_some() || self.render.is_some() {
+            return self.render_to_file(&config, &header, events);
+        }
 
         if self.cat {
-            for line in cast_file.lines() {
-                let line = line?;
-                let event: Event = serde_json::from_str(&line)?;
+            for event in events {
+                let event = event?;
                 if event.1 != "o" {
                     continue;
                 }
@@ -528,11 +843,11 @@ impl PlayCommand {
         let (tx, rx) = channel();
         let mut sent_parser = TWParser::new();
         let mut sent_actions = vec![];
+        let mut received = vec![];
 
         if self.explain_only {
-            for line in cast_file.lines() {
-                let line = line?;
-                let event: Event = serde_json::from_str(&line)?;
+            for event in events {
+                let event = event?;
                 if event.1 != "o" {
                     continue;
                 }
@@ -540,6 +855,10 @@ impl PlayCommand {
             }
             drop(tx);
         } else {
+            if !self.speed.is_finite() || self.speed <= 0. {
+                anyhow::bail!("--speed must be greater than zero");
+            }
+
             let mut tty = Tty::new()?;
             let size = tty.get_size()?;
             if u32::from(size.cols) < header.width || u32::from(size.rows) < header.height {
@@ -573,17 +892,57 @@ impl PlayCommand {
                 });
             }
 
-            let start = Instant::now();
+            let mut clock = PlaybackClock::new(self.speed);
+            let mut seeking = false;
 
-            for line in cast_file.lines() {
-                let line = line?;
-                let event: Event = serde_json::from_str(&line)?;
-                if event.1 != "o" {
+            'events: for event in events {
+                let event = event?;
+                if event.1 != "o" && event.1 != "m" {
+                    continue;
+                }
+
+                // Wait for the event to become due, responding to
+                // the playback controls in the meantime
+                while !seeking {
+                    let timeout = match clock.time_until(event.0) {
+                        Some(timeout) if timeout.is_zero() => break,
+                        Some(timeout) => timeout,
+                        None => Duration::from_secs(1),
+                    };
+                    let data = match rx.recv_timeout(timeout) {
+                        Ok(Message::Stdin(data)) => data,
+                        Ok(_) => unreachable!(),
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        Err(RecvTimeoutError::Disconnected) => {
+                            std::thread::sleep(timeout);
+                            continue;
+                        }
+                    };
+                    match PlaybackControl::from_input(&data) {
+                        Some(PlaybackControl::Quit) => break 'events,
+                        Some(PlaybackControl::TogglePause) => clock.set_paused(!clock.paused),
+                        Some(PlaybackControl::Step) if clock.paused => {
+                            clock.seek(event.0);
+                            break;
+                        }
+                        Some(PlaybackControl::Step) => {}
+                        Some(PlaybackControl::NextMarker) => seeking = true,
+                        Some(PlaybackControl::Faster) => clock.set_speed(clock.speed * 2.),
+                        Some(PlaybackControl::Slower) => clock.set_speed(clock.speed / 2.),
+                        // Anything else is most likely the terminal responding
+                        // to a query in the recording
+                        None => received.push(data),
+                    }
+                }
+
+                if event.1 == "m" {
+                    if seeking || self.pause_on_markers {
+                        seeking = false;
+                        clock.seek(event.0);
+                        clock.set_paused(true);
+                    }
                     continue;
                 }
-                let target = start + Duration::from_secs_f32(event.0);
-                let duration = target.saturating_duration_since(Instant::now());
-                std::thread::sleep(duration);
 
                 tty.write_all(&event.2.as_bytes())?;
                 sent_parser.parse(&event.2.as_bytes(), |act| sent_actions.push(act));
@@ -608,22 +967,464 @@ impl PlayCommand {
             let mut parser = TWParser::new();
             while let Ok(msg) = rx.try_recv() {
                 match msg {
-                    Message::Stdin(data) => {
-                        if self.explain {
-                            let answer_back = String::from_utf8_lossy(&data);
-                            println!("\t{:?}", answer_back);
-                            parser.parse(&data, |action| {
-                                println!("\t{:?}", action);
-                            });
-                        }
-                    }
+                    Message::Stdin(data) => received.push(data),
                     _ => unreachable!(),
                 }
             }
+            if self.explain {
+                for data in received {
+                    let answer_back = String::from_utf8_lossy(&data);
+                    println!("\t{:?}", answer_back);
+                    parser.parse(&data, |action| {
+                        println!("\t{:?}", action);
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Keys that control the playback of a recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlaybackControl {
+    /// `q` or CTRL-C stops playback
+    Quit,
+    /// Space pauses or resumes playback
+    TogglePause,
+    /// `.` outputs the next event while paused
+    Step,
+    /// `]` skips ahead to the next marker and pauses there
+    NextMarker,
+    /// `+` doubles the playback speed
+    Faster,
+    /// `-` halves the playback speed
+    Slower,
+}
+
+impl PlaybackControl {
+    /// Keys are only recognized when they arrive on their own;
+    /// longer input is assumed to be a response from the terminal
+    fn from_input(data: &[u8]) -> Option<Self> {
+        match data {
+            b"q" | b"\x03" => Some(Self::Quit),
+            b" " => Some(Self::TogglePause),
+            b"." => Some(Self::Step),
+            b"]" => Some(Self::NextMarker),
+            b"+" | b"=" => Some(Self::Faster),
+            b"-" => Some(Self::Slower),
+            _ => None,
+        }
+    }
+}
+
+/// Tracks the position within a recording while it is being
+/// played back, accounting for pauses and changes in speed
+struct PlaybackClock {
+    speed: f32,
+    paused: bool,
+    /// The position, in seconds from the start of the recording,
+    /// as of `since`
+    position: f32,
+    since: Instant,
+}
+
+impl PlaybackClock {
+    fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            paused: false,
+            position: 0.,
+            since: Instant::now(),
+        }
+    }
+
+    fn position(&self) -> f32 {
+        if self.paused {
+            self.position
+        } else {
+            self.position + self.since.elapsed().as_secs_f32() * self.speed
+        }
+    }
+
+    fn seek(&mut self, position: f32) {
+        self.position = position;
+        self.since = Instant::now();
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        self.seek(self.position());
+        self.paused = paused;
+    }
+
+    fn set_speed(&mut self, speed: f32) {
+        self.seek(self.position());
+        self.speed = speed.clamp(1. / 16., 16.);
+    }
+
+    /// How long until the recording reaches `position`, or None
+    /// if playback is paused
+    fn time_until(&self, position: f32) -> Option<Duration> {
+        if self.paused {
+            return None;
+        }
+        let remaining = (position - self.position()).max(0.) / self.speed;
+        Some(Duration::from_secs_f32(remaining))
+    }
+}
+
+/// Rendering of recordings to images, for `--screenshot` and `--render`
+#[cfg(feature = "screenshot")]
+mod render {
+    use super::*;
+    use crate::animation::{AnimationFormat, AnimationWriter};
+    use crate::screenshot::{ImageFormat, Screen, ScreenRenderer};
+    use config::TermConfig;
+    use image::{Rgba, RgbaImage};
+    use std::path::Path;
+    use std::sync::Arc;
+    use termwiz::surface::CursorVisibility;
+    use wezterm_term::{Terminal, TerminalSize};
+
+    /// How long the last frame of a rendered animation is displayed
+    /// before it loops back to the start
+    const FINAL_FRAME_DURATION: Duration = Duration::from_secs(2);
+
+    impl Theme {
+        /// Replace the default and ANSI colors in the palette with
+        /// those from the theme.  Colors that fail to parse are ignored.
+        fn apply_to_palette(&self, palette: &mut ColorPalette) {
+            if let Ok(fg) = self.fg.parse() {
+                palette.foreground = fg;
+            }
+            if let Ok(bg) = self.bg.parse() {
+                palette.background = bg;
+            }
+            for (idx, color) in self.palette.split(':').take(16).enumerate() {
+                if let Ok(color) = color.parse() {
+                    palette.colors.0[idx] = color;
+                }
+            }
+        }
+    }
+
+    impl PlayCommand {
+        /// Handle `--screenshot` and `--render` by applying the events to
+        /// a headless terminal and rendering it to an image
+        pub(super) fn render_to_file(
+            &self,
+            config: &ConfigHandle,
+            header: &Header,
+            events: impl Iterator<Item = anyhow::Result<Event>>,
+        ) -> anyhow::Result<()> {
+            if let Some(path) = &self.screenshot {
+                let format = ImageFormat::from_path(path).ok_or_else(|| {
+                    anyhow::anyhow!("cannot determine the image format from {}", path.display())
+                })?;
+
+                let mut term = headless_terminal(config, header);
+                for event in events {
+                    let event = event?;
+                    if matches!(self.at, Some(at) if event.0 > at) {
+                        break;
+                    }
+                    apply_event(&mut term, &event)?;
+                }
+
+                let renderer = ScreenRenderer::new(config)?;
+                return render_terminal(&term, |screen| renderer.save(screen, format, path));
+            }
+
+            let path = match &self.render {
+                Some(path) => path,
+                None => return Ok(()),
+            };
+            let format = AnimationFormat::from_path(path).ok_or_else(|| {
+                anyhow::anyhow!("cannot determine the image format from {}", path.display())
+            })?;
+            if !self.fps.is_finite() || self.fps <= 0. {
+                anyhow::bail!("--fps must be greater than zero");
+            }
+            if !self.speed.is_finite() || self.speed <= 0. {
+                anyhow::bail!("--speed must be greater than zero");
+            }
+
+            let mut rendered = vec![];
+            for event in events {
+                let event = event?;
+                if event.1 == "o" || event.1 == "r" {
+                    rendered.push(event);
+                }
+            }
+            self.render_animation(config, header, &rendered, format, path)
         }
 
+        fn render_animation(
+            &self,
+            config: &ConfigHandle,
+            header: &Header,
+            events: &[Event],
+            format: AnimationFormat,
+            path: &Path,
+        ) -> anyhow::Result<()> {
+            let frames = frame_schedule(events, self.fps, self.speed, header.idle_time_limit);
+
+            // Every frame must have the same dimensions, so size the
+            // animation to fit the largest screen in the recording
+            let (mut cols, mut rows) = (header.width as usize, header.height as usize);
+            for event in events {
+                if let Some((c, r)) = parse_resize(event) {
+                    cols = cols.max(c);
+                    rows = rows.max(r);
+                }
+            }
+            let renderer = ScreenRenderer::new(config)?;
+            let (width, height) = renderer.image_size(cols, rows);
+
+            let mut writer = AnimationWriter::create(path, format, width, height, frames.len())?;
+            let mut term = headless_terminal(config, header);
+            let mut applied = 0;
+
+            for (num_events, duration) in frames {
+                for event in &events[applied..num_events] {
+                    apply_event(&mut term, event)?;
+                }
+                applied = num_events;
+
+                let image = render_terminal(&term, |screen| {
+                    let image = renderer.render_image(screen)?;
+                    if image.dimensions() == (width, height) {
+                        return Ok(image);
+                    }
+                    let (r, g, b, a) = screen.palette.background.to_srgb_u8();
+                    let mut canvas = RgbaImage::from_pixel(width, height, Rgba([r, g, b, a]));
+                    image::imageops::replace(&mut canvas, &image, 0, 0);
+                    Ok(canvas)
+                })?;
+                writer.add_frame(image, duration)?;
+            }
+
+            writer.finish()
+        }
+    }
+
+    /// Compute the frames needed to animate the events, sampling at `fps`
+    /// frames per second.  Returns the number of events that must have
+    /// been applied before rendering each frame, along with how long the
+    /// frame is displayed.
+    /// When `idle_time_limit` is set, pauses between events are shortened
+    /// to at most that many seconds, as is done by asciinema, before the
+    /// timing is scaled by `speed`.
+    fn frame_schedule(
+        events: &[Event],
+        fps: f32,
+        speed: f32,
+        idle_time_limit: Option<f32>,
+    ) -> Vec<(usize, Duration)> {
+        let mut ticks: Vec<u64> = vec![];
+        let mut skipped = 0.;
+        let mut prior = 0.;
+        for event in events {
+            let gap = event.0 - prior;
+            prior = event.0;
+            if let Some(limit) = idle_time_limit {
+                if gap > limit {
+                    skipped += gap - limit;
+                }
+            }
+            ticks.push(((event.0 - skipped).max(0.) / speed * fps).ceil() as u64);
+        }
+
+        // Start with the empty screen, then add a frame for each tick
+        // in which at least one event occurred
+        let mut frames: Vec<(usize, u64)> = vec![(0, 0)];
+        for (idx, &tick) in ticks.iter().enumerate() {
+            match frames.last_mut() {
+                Some((num_events, last_tick)) if *last_tick >= tick => *num_events = idx + 1,
+                _ => frames.push((idx + 1, tick)),
+            }
+        }
+
+        let tick_duration = Duration::from_secs_f32(1. / fps);
+        let mut schedule = vec![];
+        for (idx, &(num_events, tick)) in frames.iter().enumerate() {
+            let duration = match frames.get(idx + 1) {
+                Some((_, next_tick)) => tick_duration * (next_tick - tick) as u32,
+                None => FINAL_FRAME_DURATION,
+            };
+            schedule.push((num_events, duration));
+        }
+        schedule
+    }
+
+    /// Create a terminal model that can have the recording applied
+    /// to it without displaying it, using the color scheme from the
+    /// recording if it has one
+    fn headless_terminal(config: &ConfigHandle, header: &Header) -> Terminal {
+        let term_config = TermConfig::with_config(config.clone());
+        if let Some(theme) = &header.theme {
+            let mut palette: ColorPalette = config.resolved_palette.clone().into();
+            theme.apply_to_palette(&mut palette);
+            term_config.set_client_palette(palette);
+        }
+
+        Terminal::new(
+            TerminalSize {
+                rows: header.height as usize,
+                cols: header.width as usize,
+                ..Default::default()
+            },
+            Arc::new(term_config),
+            "WezTerm",
+            config::wezterm_version(),
+            Box::new(std::io::sink()),
+        )
+    }
+
+    /// Apply a recorded event to a headless terminal
+    fn apply_event(term: &mut Terminal, event: &Event) -> anyhow::Result<()> {
+        match event.1.as_str() {
+            "o" => term.advance_bytes(&event.2),
+            "r" => {
+                let (cols, rows) = parse_resize(event)
+                    .ok_or_else(|| anyhow::anyhow!("invalid resize event {:?}", event.2))?;
+                term.resize(TerminalSize {
+                    rows,
+                    cols,
+                    ..Default::default()
+                });
+            }
+            _ => {}
+        }
         Ok(())
     }
+
+    /// Parse the `COLSxROWS` data of a resize event
+    fn parse_resize(event: &Event) -> Option<(usize, usize)> {
+        if event.1 != "r" {
+            return None;
+        }
+        let (cols, rows) = event.2.split_once('x')?;
+        Some((cols.parse().ok()?, rows.parse().ok()?))
+    }
+
+    /// Pass the visible portion of a headless terminal to `func`
+    /// in a form that can be rendered to an image
+    fn render_terminal<T>(
+        term: &Terminal,
+        func: impl FnOnce(&Screen) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let screen = term.screen();
+        let lines = screen.lines_in_phys_range(screen.phys_range(&(0..screen.physical_rows as _)));
+        let palette = term.palette();
+        let cursor = term.cursor_pos();
+        let cursor = if cursor.visibility == CursorVisibility::Visible {
+            Some((cursor.x, cursor.y as usize))
+        } else {
+            None
+        };
+
+        func(&Screen {
+            lines: &lines,
+            cols: screen.physical_cols,
+            palette: &palette,
+            cursor,
+        })
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        fn output_at(times: &[f32]) -> Vec<Event> {
+            times
+                .iter()
+                .map(|&t| Event(t, "o".to_string(), "x".to_string()))
+                .collect()
+        }
+
+        fn secs(n: u64) -> Duration {
+            Duration::from_secs(n)
+        }
+
+        #[test]
+        fn schedule() {
+            assert_eq!(
+                frame_schedule(&output_at(&[1., 3.]), 1., 1., None),
+                vec![(0, secs(1)), (1, secs(2)), (2, FINAL_FRAME_DURATION)]
+            );
+            assert_eq!(
+                frame_schedule(&[], 1., 1., None),
+                vec![(0, FINAL_FRAME_DURATION)]
+            );
+        }
+
+        #[test]
+        fn idle_time_limit() {
+            // The 10 second pause is shortened to 2 seconds
+            assert_eq!(
+                frame_schedule(&output_at(&[1., 11., 12.]), 1., 1., Some(2.)),
+                vec![
+                    (0, secs(1)),
+                    (1, secs(2)),
+                    (2, secs(1)),
+                    (3, FINAL_FRAME_DURATION)
+                ]
+            );
+            // Pauses within the limit are left alone
+            assert_eq!(
+                frame_schedule(&output_at(&[1., 3.]), 1., 1., Some(2.)),
+                frame_schedule(&output_at(&[1., 3.]), 1., 1., None),
+            );
+        }
+
+        #[test]
+        fn speed() {
+            assert_eq!(
+                frame_schedule(&output_at(&[2., 4.]), 1., 2., None),
+                vec![(0, secs(1)), (1, secs(1)), (2, FINAL_FRAME_DURATION)]
+            );
+            assert_eq!(
+                frame_schedule(&output_at(&[1., 2.]), 1., 0.5, None),
+                vec![(0, secs(2)), (1, secs(2)), (2, FINAL_FRAME_DURATION)]
+            );
+            // The idle limit applies to the recorded time, before scaling
+            assert_eq!(
+                frame_schedule(&output_at(&[2., 12.]), 1., 2., Some(2.)),
+                vec![(0, secs(1)), (1, secs(1)), (2, FINAL_FRAME_DURATION)]
+            );
+        }
+
+        #[test]
+        fn zero_gap() {
+            // Events that happen at the same time share a frame
+            assert_eq!(
+                frame_schedule(&output_at(&[1., 1., 2.]), 1., 1., None),
+                vec![(0, secs(1)), (2, secs(1)), (3, FINAL_FRAME_DURATION)]
+            );
+            // as do events at the very start and the initial empty screen
+            assert_eq!(
+                frame_schedule(&output_at(&[0., 1.]), 1., 1., None),
+                vec![(1, secs(1)), (2, FINAL_FRAME_DURATION)]
+            );
+        }
+
+        #[test]
+        fn negative_gap() {
+            // An event that is earlier than its predecessor is shown
+            // in the same frame rather than going back in time
+            assert_eq!(
+                frame_schedule(&output_at(&[2., 1., 3.]), 1., 1., None),
+                vec![(0, secs(2)), (2, secs(1)), (3, FINAL_FRAME_DURATION)]
+            );
+            // and events before the start are shown on the first frame
+            assert_eq!(
+                frame_schedule(&output_at(&[-1., 1.]), 1., 1., None),
+                vec![(1, secs(1)), (2, FINAL_FRAME_DURATION)]
+            );
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -653,3 +1454,176 @@ fn summarize(actions: Vec<Action>) -> Vec<Summarized> {
     }
     res
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read_cast(cast: &str) -> (Header, Vec<Event>) {
+        let mut reader = BufReader::new(cast.as_bytes());
+        let mut header_line = String::new();
+        reader.read_line(&mut header_line).unwrap();
+        let header = Header::parse(&header_line).unwrap();
+        let events = CastReader::new(reader, &header)
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+        (header, events)
+    }
+
+    fn times(events: &[Event]) -> Vec<f32> {
+        events.iter().map(|event| event.0).collect()
+    }
+
+    #[test]
+    fn parse_header_v2() {
+        let header = Header::parse(
+            r#"{"version": 2, "width": 80, "height": 24, "idle_time_limit": 2.5, "env": {"TERM": "xterm"}}"#,
+        )
+        .unwrap();
+        assert_eq!(header.version, 2);
+        assert_eq!((header.width, header.height), (80, 24));
+        assert_eq!(header.idle_time_limit, Some(2.5));
+        assert_eq!(header.env.get("TERM").map(String::as_str), Some("xterm"));
+        assert!(header.theme.is_none());
+    }
+
+    #[test]
+    fn parse_header_v3() {
+        let header = Header::parse(
+            r##"{"version": 3, "term": {"cols": 100, "rows": 30, "type": "wezterm",
+                "theme": {"fg": "#ffffff", "bg": "#000000", "palette": "#000000:#ff0000"}},
+                "title": "demo"}"##,
+        )
+        .unwrap();
+        assert_eq!(header.version, 3);
+        assert_eq!((header.width, header.height), (100, 30));
+        assert_eq!(header.title.as_deref(), Some("demo"));
+        assert_eq!(header.env.get("TERM").map(String::as_str), Some("wezterm"));
+        assert_eq!(header.theme.unwrap().bg, "#000000");
+    }
+
+    #[test]
+    fn parse_header_unsupported() {
+        assert!(Header::parse(r#"{"version": 1, "width": 80, "height": 24}"#).is_err());
+        assert!(Header::parse("not json").is_err());
+    }
+
+    #[test]
+    fn read_v2_events() {
+        let (_, events) = read_cast(
+            r#"{"version": 2, "width": 80, "height": 24}
+[0.5, "o", "a"]
+[1.25, "o", "b"]
+"#,
+        );
+        assert_eq!(times(&events), vec![0.5, 1.25]);
+    }
+
+    #[test]
+    fn read_v3_relative_times() {
+        let (_, events) = read_cast(
+            r#"{"version": 3, "term": {"cols": 80, "rows": 24}}
+[0.5, "o", "a"]
+# a comment
+
+[0.25, "m", "marker"]
+[0.0, "o", "b"]
+[1.5, "x", "0"]
+"#,
+        );
+        assert_eq!(times(&events), vec![0.5, 0.75, 0.75, 2.25]);
+        let codes: Vec<&str> = events.iter().map(|event| event.1.as_str()).collect();
+        assert_eq!(codes, vec!["o", "m", "o", "x"]);
+    }
+
+    #[test]
+    fn writer_round_trip() {
+        for &version in &[CastVersion::V2, CastVersion::V3] {
+            let header = Header {
+                version: 2,
+                width: 80,
+                height: 24,
+                idle_time_limit: Some(1.5),
+                env: vec![("TERM".to_string(), "wezterm".to_string())]
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            };
+            let mut writer = CastWriter::new(vec![], &header, version).unwrap();
+            writer.log(0.5, "o", "hello\r\n").unwrap();
+            writer.log(1.25, "r", "100x30").unwrap();
+            writer.log(1.25, "m", "").unwrap();
+            writer.log(3., "o", "\x1b[0m").unwrap();
+            writer.flush().unwrap();
+            let cast = writer.w;
+
+            let (read, events) = read_cast(std::str::from_utf8(&cast).unwrap());
+            let expected_version = match version {
+                CastVersion::V2 => 2,
+                CastVersion::V3 => 3,
+            };
+            assert_eq!(read.version, expected_version);
+            assert_eq!((read.width, read.height), (80, 24));
+            assert_eq!(read.idle_time_limit, Some(1.5));
+            assert_eq!(read.env.get("TERM").map(String::as_str), Some("wezterm"));
+            assert_eq!(times(&events), vec![0.5, 1.25, 1.25, 3.]);
+            let data: Vec<(&str, &str)> = events
+                .iter()
+                .map(|event| (event.1.as_str(), event.2.as_str()))
+                .collect();
+            assert_eq!(
+                data,
+                vec![
+                    ("o", "hello\r\n"),
+                    ("r", "100x30"),
+                    ("m", ""),
+                    ("o", "\x1b[0m")
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn marker_key() {
+        assert_eq!(parse_marker_key("m").unwrap(), b'm');
+        assert_eq!(parse_marker_key("^]").unwrap(), 0x1d);
+        assert_eq!(parse_marker_key("^a").unwrap(), 0x01);
+        assert_eq!(parse_marker_key("^@").unwrap(), 0x00);
+        assert_eq!(parse_marker_key("^").unwrap(), b'^');
+        assert!(parse_marker_key("^1").is_err());
+        assert!(parse_marker_key("ab").is_err());
+        assert!(parse_marker_key("").is_err());
+        assert!(parse_marker_key("é").is_err());
+    }
+
+    #[test]
+    fn clock_paused() {
+        let mut clock = PlaybackClock::new(1.);
+        clock.set_paused(true);
+        clock.seek(5.);
+        assert_eq!(clock.position(), 5.);
+        assert_eq!(clock.time_until(10.), None);
+
+        // Changing the speed while paused keeps the position
+        clock.set_speed(4.);
+        assert_eq!(clock.position(), 5.);
+        assert_eq!(clock.speed, 4.);
+    }
+
+    #[test]
+    fn clock_speed() {
+        let mut clock = PlaybackClock::new(2.);
+        clock.seek(1.);
+        let until = clock.time_until(11.).unwrap();
+        assert!(until <= Duration::from_secs(5), "{:?}", until);
+        assert!(until > Duration::from_secs(4), "{:?}", until);
+
+        // Positions that have already been reached are due immediately
+        assert_eq!(clock.time_until(0.), Some(Duration::ZERO));
+
+        clock.set_speed(100.);
+        assert_eq!(clock.speed, 16.);
+        clock.set_speed(0.);
+        assert_eq!(clock.speed, 1. / 16.);
+    }
+}
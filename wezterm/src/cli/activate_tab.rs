@@ -46,7 +46,7 @@ pub struct ActivateTab {
 
 impl ActivateTab {
     pub async fn run(&self, client: Client) -> anyhow::Result<()> {
-        let panes = client.list_panes().await?;
+        let panes = client.list_panes(codec::ListPanes::default()).await?;
 
         let mut pane_id_to_tab_id = HashMap::new();
         let mut tab_id_to_active_pane_id = HashMap::new();
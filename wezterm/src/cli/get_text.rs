@@ -1,8 +1,18 @@
-use clap::Parser;
+use crate::cli::resolve_line_arg;
+use clap::{Parser, ValueEnum};
 use mux::pane::PaneId;
+use std::io::Write;
+use std::time::Duration;
+use termwiz::cell::SemanticType;
+use termwiz::surface::SEQ_ZERO;
 use termwiz_funcs::lines_to_escapes;
 use wezterm_client::client::Client;
-use wezterm_term::{ScrollbackOrVisibleRowIndex, StableRowIndex};
+use wezterm_term::{
+    CommandExitStatus, Line, ScrollbackOrVisibleRowIndex, SemanticZone, StableRowIndex,
+};
+
+/// How often to check the pane for new output when following
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 #[derive(Debug, Parser, Clone)]
 pub struct GetText {
@@ -32,41 +42,97 @@ pub struct GetText {
     /// If omitted, unattributed text will be returned.
     #[arg(long)]
     escapes: bool,
+
+    /// Keep running and print each line as the pane completes it,
+    /// in the manner of `tail -f`.
+    /// Lines are printed from the starting line onwards; lines that
+    /// were wrapped to fit the width of the pane are joined together.
+    #[arg(long, conflicts_with = "end_line")]
+    follow: bool,
+
+    /// Retrieve part of a command that was run in the pane, rather
+    /// than a range of lines.
+    /// This requires the shell to mark its prompts, input and output
+    /// using OSC 133 escape sequences, as is done by the wezterm
+    /// shell integration.
+    #[arg(
+        long,
+        value_enum,
+        conflicts_with_all = ["start_line", "end_line", "follow", "escapes"]
+    )]
+    zone: Option<ZoneKind>,
+
+    /// Which command to use with `--zone`.
+    /// 0 is the oldest command in the scrollback, and negative
+    /// numbers count backwards from the most recent command, which
+    /// is -1.
+    /// The default is -1.
+    #[arg(long, allow_hyphen_values = true, requires = "zone")]
+    index: Option<isize>,
+
+    /// Instead of printing the text of the zone, print the exit
+    /// status that the shell reported for the command.
+    /// Fails if the shell did not report the status.
+    #[arg(long, requires = "zone")]
+    exit_status: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ZoneKind {
+    /// The prompt displayed by the shell
+    Prompt,
+    /// The command line entered at the prompt
+    Input,
+    /// The output produced by the command
+    Output,
+}
+
+/// The zones and exit status of a command run by the shell
+#[derive(Debug, Default)]
+struct Command {
+    prompt: Option<SemanticZone>,
+    input: Option<SemanticZone>,
+    output: Option<SemanticZone>,
+    exit_status: Option<i32>,
+}
+
+impl Command {
+    fn start(&self) -> Option<(StableRowIndex, usize)> {
+        [self.prompt, self.input, self.output]
+            .iter()
+            .flatten()
+            .map(|zone| (zone.start_y, zone.start_x))
+            .next()
+    }
 }
 
 impl GetText {
     pub async fn run(self, client: Client) -> anyhow::Result<()> {
         let pane_id = client.resolve_pane_id(self.pane_id).await?;
 
+        if let Some(kind) = self.zone {
+            return self.zone(&client, pane_id, kind).await;
+        }
+
         let info = client
             .get_dimensions(codec::GetPaneRenderableDimensions { pane_id })
             .await?;
 
-        let start_line = match self.start_line {
-            None => info.dimensions.physical_top,
-            Some(n) if n >= 0 => info.dimensions.physical_top + n as StableRowIndex,
-            Some(n) => {
-                let line = info.dimensions.physical_top as isize + n as isize;
-                if line < info.dimensions.scrollback_top as isize {
-                    info.dimensions.scrollback_top
-                } else {
-                    line as StableRowIndex
-                }
-            }
-        };
+        let start_line = self
+            .start_line
+            .map(|line| resolve_line_arg(&info.dimensions, line))
+            .unwrap_or(info.dimensions.physical_top);
 
-        let end_line = match self.end_line {
-            None => info.dimensions.physical_top + info.dimensions.viewport_rows as StableRowIndex,
-            Some(n) if n >= 0 => info.dimensions.physical_top + n as StableRowIndex,
-            Some(n) => {
-                let line = info.dimensions.physical_top as isize + n as isize;
-                if line < info.dimensions.scrollback_top as isize {
-                    info.dimensions.scrollback_top
-                } else {
-                    line as StableRowIndex
-                }
-            }
-        };
+        if self.follow {
+            return self.follow(&client, pane_id, start_line).await;
+        }
+
+        let end_line = self
+            .end_line
+            .map(|line| resolve_line_arg(&info.dimensions, line))
+            .unwrap_or(
+                info.dimensions.physical_top + info.dimensions.viewport_rows as StableRowIndex,
+            );
 
         let lines = client
             .get_lines(codec::GetLines {
@@ -90,4 +156,363 @@ impl GetText {
         }
         Ok(())
     }
+
+    async fn follow(
+        &self,
+        client: &Client,
+        pane_id: PaneId,
+        mut next_row: StableRowIndex,
+    ) -> anyhow::Result<()> {
+        loop {
+            let info = client
+                .get_dimensions(codec::GetPaneRenderableDimensions { pane_id })
+                .await?;
+
+            // If the pane produced output faster than we consumed it,
+            // rows may have been trimmed from the scrollback already;
+            // resume from the oldest row that is still available.
+            next_row = next_row.max(info.dimensions.scrollback_top);
+
+            // The row holding the cursor may still be written to,
+            // so only the rows above it are complete.
+            let cursor_row = info.cursor_position.y;
+            if cursor_row > next_row {
+                let lines = client
+                    .get_lines(codec::GetLines {
+                        pane_id,
+                        lines: vec![next_row..cursor_row],
+                    })
+                    .await?;
+
+                let mut completed = vec![];
+                let mut partial: Option<Line> = None;
+                for (row, line) in lines.lines.extract_data().0 {
+                    let wrapped = line.last_cell_was_wrapped();
+                    let line = match partial.take() {
+                        Some(mut prior) => {
+                            prior.set_last_cell_was_wrapped(false, SEQ_ZERO);
+                            prior.append_line(line, SEQ_ZERO);
+                            prior
+                        }
+                        None => line,
+                    };
+                    if wrapped {
+                        // Continues onto the next row; if that is the
+                        // cursor row then we'll pick it up next time
+                        partial = Some(line);
+                    } else {
+                        completed.push(line);
+                        next_row = row + 1;
+                    }
+                }
+
+                if !completed.is_empty() && !self.print_lines(completed)? {
+                    return Ok(());
+                }
+            }
+
+            smol::Timer::after(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Writes lines to stdout, returning false if the reader has
+    /// gone away, eg: because we are piped into `head`.
+    fn print_lines(&self, lines: Vec<Line>) -> anyhow::Result<bool> {
+        let mut stdout = std::io::stdout().lock();
+        let result = if self.escapes {
+            stdout
+                .write_all(lines_to_escapes(lines)?.as_bytes())
+                .and_then(|_| stdout.flush())
+        } else {
+            lines
+                .iter()
+                .try_for_each(|line| writeln!(stdout, "{}", line.as_str()))
+                .and_then(|_| stdout.flush())
+        };
+        match result {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn zone(&self, client: &Client, pane_id: PaneId, kind: ZoneKind) -> anyhow::Result<()> {
+        let response = client
+            .get_semantic_zones(codec::GetSemanticZones { pane_id })
+            .await?;
+        let commands = group_into_commands(response.zones, &response.exit_statuses);
+
+        let index = self.index.unwrap_or(-1);
+        let resolved = if index < 0 {
+            commands.len().checked_sub(index.unsigned_abs())
+        } else {
+            Some(index as usize).filter(|&idx| idx < commands.len())
+        };
+        let command = match resolved {
+            Some(idx) => &commands[idx],
+            None if commands.is_empty() => anyhow::bail!(
+                "pane {} has no commands in its scrollback. \
+                 Is shell integration enabled?",
+                pane_id
+            ),
+            None => anyhow::bail!(
+                "there is no command at index {}; \
+                 pane {} has {} commands in its scrollback",
+                index,
+                pane_id,
+                commands.len()
+            ),
+        };
+
+        if self.exit_status {
+            match command.exit_status {
+                Some(status) => println!("{}", status),
+                None => anyhow::bail!("the shell did not report an exit status for that command"),
+            }
+            return Ok(());
+        }
+
+        let zone = match kind {
+            ZoneKind::Prompt => command.prompt,
+            ZoneKind::Input => command.input,
+            ZoneKind::Output => command.output,
+        };
+        if let Some(zone) = zone {
+            println!("{}", zone_text(client, pane_id, &zone).await?);
+        }
+        Ok(())
+    }
+}
+
+/// Group the zones of a pane into the commands that produced them.
+/// A command begins with a prompt or, for shells that don't mark
+/// their prompts, with the input that follows some output.
+/// Any output that precedes the first prompt isn't part of a command.
+/// Exit statuses are matched to commands by position, because
+/// the statuses are recorded separately from the zones.
+fn group_into_commands(
+    zones: Vec<SemanticZone>,
+    exit_statuses: &[CommandExitStatus],
+) -> Vec<Command> {
+    let mut commands: Vec<Command> = vec![];
+    for zone in zones {
+        let need_new = match (commands.last(), zone.semantic_type) {
+            (None, SemanticType::Output) => continue,
+            (None, _) => true,
+            (Some(_), SemanticType::Prompt) => true,
+            (Some(cmd), SemanticType::Input) => cmd.input.is_some() || cmd.output.is_some(),
+            (Some(cmd), SemanticType::Output) => cmd.output.is_some(),
+        };
+        if need_new {
+            commands.push(Command::default());
+        }
+        let cmd = commands.last_mut().expect("pushed a command above");
+        match zone.semantic_type {
+            SemanticType::Prompt => cmd.prompt = Some(zone),
+            SemanticType::Input => cmd.input = Some(zone),
+            SemanticType::Output => cmd.output = Some(zone),
+        }
+    }
+
+    // A prompt on its own, such as the one that is currently
+    // waiting for input, is not a command
+    commands.retain(|cmd| cmd.input.is_some() || cmd.output.is_some());
+
+    // The output of a command starts after the start of its prompt
+    // or input, and no later than the start of the next command;
+    // if the command produced no output then it will be the same
+    // position as the next prompt.
+    for idx in 0..commands.len() {
+        let start = commands[idx].start();
+        let end = commands.get(idx + 1).and_then(Command::start);
+        commands[idx].exit_status = exit_statuses
+            .iter()
+            .rev()
+            .find(|status| {
+                let pos = (status.output_start_y, status.output_start_x);
+                Some(pos) > start && end.map(|end| pos <= end).unwrap_or(true)
+            })
+            .map(|status| status.status);
+    }
+
+    commands
+}
+
+async fn zone_text(
+    client: &Client,
+    pane_id: PaneId,
+    zone: &SemanticZone,
+) -> anyhow::Result<String> {
+    let lines = client
+        .get_lines(codec::GetLines {
+            pane_id,
+            lines: vec![zone.start_y..zone.end_y + 1],
+        })
+        .await?;
+
+    let mut text = String::new();
+    for (row, line) in lines.lines.extract_data().0 {
+        let start = if row == zone.start_y { zone.start_x } else { 0 };
+        let end = if row == zone.end_y {
+            zone.end_x + 1
+        } else {
+            line.len()
+        };
+        text.push_str(&line.columns_as_str(start..end));
+        if row != zone.end_y && !line.last_cell_was_wrapped() {
+            text.truncate(text.trim_end().len());
+            text.push('\n');
+        }
+    }
+    text.truncate(text.trim_end().len());
+    Ok(text)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn zone(
+        semantic_type: SemanticType,
+        start: (StableRowIndex, usize),
+        end: (StableRowIndex, usize),
+    ) -> SemanticZone {
+        SemanticZone {
+            start_y: start.0,
+            start_x: start.1,
+            end_y: end.0,
+            end_x: end.1,
+            semantic_type,
+        }
+    }
+
+    fn status(output_start: (StableRowIndex, usize), status: i32) -> CommandExitStatus {
+        CommandExitStatus {
+            output_start_y: output_start.0,
+            output_start_x: output_start.1,
+            status,
+        }
+    }
+
+    /// Summarizes each command as the start of its input, the start of
+    /// its output and its exit status
+    fn summarize(
+        commands: &[Command],
+    ) -> Vec<(
+        Option<(StableRowIndex, usize)>,
+        Option<(StableRowIndex, usize)>,
+        Option<i32>,
+    )> {
+        commands
+            .iter()
+            .map(|cmd| {
+                (
+                    cmd.input.map(|z| (z.start_y, z.start_x)),
+                    cmd.output.map(|z| (z.start_y, z.start_x)),
+                    cmd.exit_status,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn commands_with_output() {
+        let zones = vec![
+            zone(SemanticType::Prompt, (0, 0), (0, 1)),
+            zone(SemanticType::Input, (0, 2), (0, 5)),
+            zone(SemanticType::Output, (1, 0), (2, 9)),
+            zone(SemanticType::Prompt, (3, 0), (3, 1)),
+            zone(SemanticType::Input, (3, 2), (3, 5)),
+            zone(SemanticType::Output, (4, 0), (4, 9)),
+        ];
+        let statuses = vec![status((1, 0), 0), status((4, 0), 1)];
+        assert_eq!(
+            summarize(&group_into_commands(zones, &statuses)),
+            vec![
+                (Some((0, 2)), Some((1, 0)), Some(0)),
+                (Some((3, 2)), Some((4, 0)), Some(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn command_without_output() {
+        // The output of a command that printed nothing starts at the
+        // position of the following prompt
+        let zones = vec![
+            zone(SemanticType::Prompt, (0, 0), (0, 1)),
+            zone(SemanticType::Input, (0, 2), (0, 5)),
+            zone(SemanticType::Prompt, (1, 0), (1, 1)),
+            zone(SemanticType::Input, (1, 2), (1, 5)),
+            zone(SemanticType::Output, (2, 0), (2, 9)),
+        ];
+        let statuses = vec![status((1, 0), 2), status((2, 0), 0)];
+        assert_eq!(
+            summarize(&group_into_commands(zones, &statuses)),
+            vec![
+                (Some((0, 2)), None, Some(2)),
+                (Some((1, 2)), Some((2, 0)), Some(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn trailing_prompt_is_not_a_command() {
+        let zones = vec![
+            zone(SemanticType::Prompt, (0, 0), (0, 1)),
+            zone(SemanticType::Input, (0, 2), (0, 5)),
+            zone(SemanticType::Output, (1, 0), (1, 9)),
+            zone(SemanticType::Prompt, (2, 0), (2, 1)),
+        ];
+        let statuses = vec![status((1, 0), 0)];
+        assert_eq!(
+            summarize(&group_into_commands(zones, &statuses)),
+            vec![(Some((0, 2)), Some((1, 0)), Some(0))]
+        );
+
+        let zones = vec![zone(SemanticType::Prompt, (0, 0), (0, 1))];
+        assert!(group_into_commands(zones, &[]).is_empty());
+    }
+
+    #[test]
+    fn missing_status() {
+        // Only the second command reported a status; the first must
+        // not pick it up
+        let zones = vec![
+            zone(SemanticType::Prompt, (0, 0), (0, 1)),
+            zone(SemanticType::Input, (0, 2), (0, 5)),
+            zone(SemanticType::Output, (1, 0), (1, 9)),
+            zone(SemanticType::Prompt, (2, 0), (2, 1)),
+            zone(SemanticType::Input, (2, 2), (2, 5)),
+            zone(SemanticType::Output, (3, 0), (3, 9)),
+        ];
+        let statuses = vec![status((3, 0), 127)];
+        assert_eq!(
+            summarize(&group_into_commands(zones, &statuses)),
+            vec![
+                (Some((0, 2)), Some((1, 0)), None),
+                (Some((2, 2)), Some((3, 0)), Some(127)),
+            ]
+        );
+    }
+
+    #[test]
+    fn unmarked_prompts() {
+        // Output before the first command is ignored, and shells that
+        // don't mark their prompts start a command with each input
+        let zones = vec![
+            zone(SemanticType::Output, (0, 0), (0, 9)),
+            zone(SemanticType::Input, (1, 2), (1, 5)),
+            zone(SemanticType::Output, (2, 0), (2, 9)),
+            zone(SemanticType::Input, (3, 2), (3, 5)),
+            zone(SemanticType::Output, (4, 0), (4, 9)),
+        ];
+        assert_eq!(
+            summarize(&group_into_commands(zones, &[])),
+            vec![
+                (Some((1, 2)), Some((2, 0)), None),
+                (Some((3, 2)), Some((4, 0)), None),
+            ]
+        );
+    }
 }
@@ -18,7 +18,12 @@ impl ListCommand {
         let out = std::io::stdout();
 
         let mut output_items = vec![];
-        let panes = client.list_panes().await?;
+        let panes = client
+            .list_panes(codec::ListPanes {
+                // Only the json output includes the resources
+                include_resources: matches!(self.format, CliOutputFormatKind::Json),
+            })
+            .await?;
 
         for (tabroot, tab_title) in panes.tabs.into_iter().zip(panes.tab_titles.iter()) {
             let mut cursor = tabroot.into_tree().cursor();
@@ -139,6 +144,9 @@ struct CliListResultItem {
     is_active: bool,
     is_zoomed: bool,
     tty_name: Option<String>,
+    /// The resources used by the processes running in the pane,
+    /// if known
+    process_resources: Option<procinfo::LocalProcessResources>,
 }
 
 impl CliListResultItem {
@@ -157,6 +165,7 @@ impl CliListResultItem {
             is_active_pane,
             is_zoomed_pane,
             tty_name,
+            process_resources,
             size:
                 TerminalSize {
                     rows,
@@ -197,6 +206,7 @@ impl CliListResultItem {
             is_active: is_active_pane,
             is_zoomed: is_zoomed_pane,
             tty_name,
+            process_resources,
         }
     }
 }
@@ -1,7 +1,9 @@
 use anyhow::anyhow;
 use clap::Parser;
+use mux::renderable::RenderableDimensions;
 use std::ffi::OsString;
 use wezterm_client::client::Client;
+use wezterm_term::{ScrollbackOrVisibleRowIndex, StableRowIndex};
 
 mod activate_pane;
 mod activate_pane_direction;
@@ -15,14 +17,21 @@ mod list_clients;
 mod move_pane_to_new_tab;
 mod proxy;
 mod rename_workspace;
+#[cfg(feature = "screenshot")]
+mod screenshot;
+mod send_key;
 mod send_text;
 mod set_tab_title;
 mod set_window_title;
 mod spawn_command;
 mod split_pane;
+mod subscribe;
 mod tls_creds;
+mod wait_for;
 mod zoom_pane;
 
+pub use wait_for::{WaitForTimedOut, TIMEOUT_EXIT_STATUS};
+
 #[derive(Debug, Parser, Clone, Copy)]
 enum CliOutputFormatKind {
     #[command(name = "table", about = "multi line space separated table")]
@@ -117,10 +126,40 @@ Outputs the pane-id for the newly created pane on success"
     #[command(name = "send-text", rename_all = "kebab")]
     SendText(send_text::SendText),
 
+    /// Send key presses to a pane, as though they were typed.
+    ///
+    /// Unlike send-text, the keys are encoded according to the
+    /// keyboard protocol that the application in the pane has
+    /// enabled, so modifiers are preserved.
+    #[command(name = "send-key", rename_all = "kebab")]
+    SendKey(send_key::SendKey),
+
     /// Retrieves the textual content of a pane and output it to stdout
     #[command(name = "get-text", rename_all = "kebab")]
     GetText(get_text::GetText),
 
+    /// Render the content of a pane to a PNG or SVG image.
+    ///
+    /// The image is rendered in software using the fonts and colors
+    /// from your configuration, so this works without a GPU or display.
+    #[cfg(feature = "screenshot")]
+    #[command(name = "screenshot", rename_all = "kebab")]
+    Screenshot(screenshot::Screenshot),
+
+    /// Print a stream of mux events, one JSON object per line.
+    ///
+    /// Events describe changes such as panes, tabs and windows being
+    /// added or removed, focus and title changes, and alerts.
+    #[command(name = "subscribe", rename_all = "kebab")]
+    Subscribe(subscribe::Subscribe),
+
+    /// Wait until a pane outputs text that matches a regular expression.
+    ///
+    /// Prints the matching text and exits successfully once a match
+    /// is found, or exits with status 124 if the timeout elapses first.
+    #[command(name = "wait-for", rename_all = "kebab")]
+    WaitFor(wait_for::WaitFor),
+
     /// Activate an adjacent pane in the specified direction.
     #[command(name = "activate-pane-direction", rename_all = "kebab")]
     ActivatePaneDirection(activate_pane_direction::ActivatePaneDirection),
@@ -185,7 +224,12 @@ async fn run_cli_async(opts: &crate::Opt, cli: CliCommand) -> anyhow::Result<()>
         CliSubCommand::MovePaneToNewTab(cmd) => cmd.run(client).await,
         CliSubCommand::SplitPane(cmd) => cmd.run(client).await,
         CliSubCommand::SendText(cmd) => cmd.run(client).await,
+        CliSubCommand::SendKey(cmd) => cmd.run(client).await,
         CliSubCommand::GetText(cmd) => cmd.run(client).await,
+        #[cfg(feature = "screenshot")]
+        CliSubCommand::Screenshot(cmd) => cmd.run(client, &crate::init_config(opts)?).await,
+        CliSubCommand::WaitFor(cmd) => cmd.run(client).await,
+        CliSubCommand::Subscribe(cmd) => cmd.run(client).await,
         CliSubCommand::SpawnCommand(cmd) => cmd.run(client, &crate::init_config(opts)?).await,
         CliSubCommand::Proxy(cmd) => cmd.run(client, &crate::init_config(opts)?).await,
         CliSubCommand::TlsCreds(cmd) => cmd.run(client).await,
@@ -204,10 +248,18 @@ async fn run_cli_async(opts: &crate::Opt, cli: CliCommand) -> anyhow::Result<()>
 
 pub fn run_cli(opts: &crate::Opt, cli: CliCommand) -> anyhow::Result<()> {
     let executor = promise::spawn::ScopedExecutor::new();
-    match promise::spawn::block_on(executor.run(async move { run_cli_async(opts, cli).await })) {
-        Ok(_) => Ok(()),
-        Err(err) => crate::terminate_with_error(err),
-    }
+    promise::spawn::block_on(executor.run(async move { run_cli_async(opts, cli).await }))
+}
+
+/// Resolves a `--start-line` or `--end-line` argument to a stable row
+/// index.  The argument is relative to the top of the screen, with
+/// negative values reaching back into the scrollback; lines before the
+/// start of the scrollback are clamped to it.
+pub fn resolve_line_arg(
+    dims: &RenderableDimensions,
+    line: ScrollbackOrVisibleRowIndex,
+) -> StableRowIndex {
+    (dims.physical_top + line as StableRowIndex).max(dims.scrollback_top)
 }
 
 pub fn resolve_relative_cwd(cwd: Option<OsString>) -> anyhow::Result<Option<String>> {
@@ -38,7 +38,7 @@ impl MovePaneToNewTab {
             match self.window_id {
                 Some(w) => Some(w),
                 None => {
-                    let panes = client.list_panes().await?;
+                    let panes = client.list_panes(codec::ListPanes::default()).await?;
                     let mut window_id = None;
                     'outer_move: for tabroot in panes.tabs {
                         let mut cursor = tabroot.into_tree().cursor();
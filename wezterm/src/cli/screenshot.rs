@@ -0,0 +1,116 @@
+use crate::cli::resolve_line_arg;
+use crate::screenshot::{ImageFormat, Screen, ScreenRenderer};
+use clap::Parser;
+use config::ConfigHandle;
+use mux::pane::PaneId;
+use std::path::PathBuf;
+use wezterm_client::client::Client;
+use wezterm_term::{ScrollbackOrVisibleRowIndex, StableRowIndex};
+
+#[derive(Debug, Parser, Clone)]
+pub struct Screenshot {
+    /// Specify the target pane.
+    /// The default is to use the current pane based on the
+    /// environment variable WEZTERM_PANE.
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+
+    /// The starting line number.
+    /// 0 is the first line of terminal screen.
+    /// Negative numbers proceed backwards into the scrollback.
+    /// The default value is unspecified is 0, the first line of
+    /// the terminal screen.
+    #[arg(long, allow_hyphen_values = true)]
+    start_line: Option<ScrollbackOrVisibleRowIndex>,
+
+    /// The ending line number.
+    /// 0 is the first line of terminal screen.
+    /// Negative numbers proceed backwards into the scrollback.
+    /// The default value if unspecified is the bottom of the
+    /// the terminal screen.
+    #[arg(long, allow_hyphen_values = true)]
+    end_line: Option<ScrollbackOrVisibleRowIndex>,
+
+    /// The image format to produce.
+    /// The default is to use the extension of the output file name.
+    #[arg(long, value_enum)]
+    format: Option<ImageFormat>,
+
+    /// Don't draw the cursor
+    #[arg(long)]
+    no_cursor: bool,
+
+    /// The file name to which the image will be written
+    output: PathBuf,
+}
+
+impl Screenshot {
+    pub async fn run(self, client: Client, config: &ConfigHandle) -> anyhow::Result<()> {
+        let format = match self.format {
+            Some(format) => format,
+            None => ImageFormat::from_path(&self.output).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "cannot determine the image format from {}; use --format",
+                    self.output.display()
+                )
+            })?,
+        };
+
+        let pane_id = client.resolve_pane_id(self.pane_id).await?;
+        let info = client
+            .get_dimensions(codec::GetPaneRenderableDimensions { pane_id })
+            .await?;
+
+        let start_line = self
+            .start_line
+            .map(|line| resolve_line_arg(&info.dimensions, line))
+            .unwrap_or(info.dimensions.physical_top);
+        let end_line = self
+            .end_line
+            .map(|line| resolve_line_arg(&info.dimensions, line))
+            .unwrap_or(
+                info.dimensions.physical_top + info.dimensions.viewport_rows as StableRowIndex - 1,
+            );
+        if end_line < start_line {
+            anyhow::bail!("--end-line must not be before --start-line");
+        }
+
+        let lines = client
+            .get_lines(codec::GetLines {
+                pane_id,
+                lines: vec![start_line..end_line + 1],
+            })
+            .await?;
+        let lines: Vec<_> = lines
+            .lines
+            .extract_data()
+            .0
+            .into_iter()
+            .map(|(_idx, line)| line)
+            .collect();
+
+        let palette = client
+            .get_palette(codec::GetPalette { pane_id })
+            .await?
+            .palette;
+
+        let cursor_y = info.cursor_position.y;
+        let cursor = if self.no_cursor
+            || info.cursor_position.visibility != termwiz::surface::CursorVisibility::Visible
+            || cursor_y < start_line
+            || cursor_y > end_line
+        {
+            None
+        } else {
+            Some((info.cursor_position.x, (cursor_y - start_line) as usize))
+        };
+
+        let screen = Screen {
+            lines: &lines,
+            cols: info.dimensions.cols,
+            palette: &palette,
+            cursor,
+        };
+        ScreenRenderer::new(config)?.save(&screen, format, &self.output)
+    }
+}
@@ -0,0 +1,136 @@
+use clap::Parser;
+use config::{key_code_to_termwiz, DeferredKeyCode, KeyMapPreference};
+use mux::pane::PaneId;
+use std::convert::TryFrom;
+use termwiz::input::{KeyEvent, Modifiers};
+use wezterm_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct SendKey {
+    /// Specify the target pane.
+    /// The default is to use the current pane based on the
+    /// environment variable WEZTERM_PANE.
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+
+    /// The keys to send, in order.
+    ///
+    /// Each key is written as `MODS+KEY`, where `KEY` uses the same
+    /// names as the `key` field of a key assignment in your config,
+    /// and `MODS` is an optional list of modifiers separated by `|`,
+    /// such as `CTRL|SHIFT`.
+    /// For example: `Enter`, `CTRL+c`, `CTRL|SHIFT+F5`, `phys:A`.
+    #[arg(required = true, value_parser = parse_key_combo)]
+    keys: Vec<KeyEvent>,
+}
+
+impl SendKey {
+    pub async fn run(self, client: Client) -> anyhow::Result<()> {
+        let pane_id = client.resolve_pane_id(self.pane_id).await?;
+
+        for event in self.keys {
+            client
+                .key_down(codec::SendKeyDown {
+                    pane_id,
+                    event,
+                    input_serial: codec::InputSerial::now(),
+                })
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a key combination such as `CTRL|SHIFT+F5` into a KeyEvent
+fn parse_key_combo(s: &str) -> anyhow::Result<KeyEvent> {
+    // The key itself may be `+`, so only consider separators that
+    // appear before the final character
+    let last_char = s
+        .char_indices()
+        .last()
+        .map(|(idx, _)| idx)
+        .ok_or_else(|| anyhow::anyhow!("empty key"))?;
+    let (mods, key) = match s[..last_char].rfind('+') {
+        Some(idx) => (&s[..idx], &s[idx + 1..]),
+        None => ("", s),
+    };
+
+    // Report the underlying problem in the message itself, as clap
+    // only displays the outermost context of the error
+    let modifiers =
+        Modifiers::try_from(mods.to_uppercase()).map_err(|err| anyhow::anyhow!("{}", err))?;
+
+    let key = DeferredKeyCode::try_from(key)
+        .map_err(|err| anyhow::anyhow!("{:#}", err))?
+        .resolve(KeyMapPreference::Mapped);
+    let key = key_code_to_termwiz(&key, false)
+        .ok_or_else(|| anyhow::anyhow!("{} cannot be sent as a key event", s))?;
+
+    Ok(KeyEvent { key, modifiers })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use termwiz::input::KeyCode;
+
+    fn key(key: KeyCode, modifiers: Modifiers) -> KeyEvent {
+        KeyEvent { key, modifiers }
+    }
+
+    #[test]
+    fn named_keys() {
+        assert_eq!(
+            parse_key_combo("Enter").unwrap(),
+            key(KeyCode::Enter, Modifiers::NONE)
+        );
+        assert_eq!(
+            parse_key_combo("F5").unwrap(),
+            key(KeyCode::Function(5), Modifiers::NONE)
+        );
+        assert_eq!(
+            parse_key_combo("a").unwrap(),
+            key(KeyCode::Char('a'), Modifiers::NONE)
+        );
+        assert_eq!(
+            parse_key_combo("phys:A").unwrap(),
+            key(KeyCode::Char('a'), Modifiers::NONE)
+        );
+    }
+
+    #[test]
+    fn modifiers() {
+        assert_eq!(
+            parse_key_combo("CTRL+c").unwrap(),
+            key(KeyCode::Char('c'), Modifiers::CTRL)
+        );
+        assert_eq!(
+            parse_key_combo("ctrl|shift+F5").unwrap(),
+            key(KeyCode::Function(5), Modifiers::CTRL | Modifiers::SHIFT)
+        );
+        assert_eq!(
+            parse_key_combo("ALT+LeftArrow").unwrap(),
+            key(KeyCode::LeftArrow, Modifiers::ALT)
+        );
+    }
+
+    #[test]
+    fn plus_key() {
+        assert_eq!(
+            parse_key_combo("+").unwrap(),
+            key(KeyCode::Char('+'), Modifiers::NONE)
+        );
+        assert_eq!(
+            parse_key_combo("CTRL++").unwrap(),
+            key(KeyCode::Char('+'), Modifiers::CTRL)
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse_key_combo("").is_err());
+        assert!(parse_key_combo("NotAKey").is_err());
+        assert!(parse_key_combo("HYPERDRIVE+a").is_err());
+        assert!(parse_key_combo("raw:42").is_err());
+    }
+}
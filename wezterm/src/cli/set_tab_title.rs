@@ -23,7 +23,7 @@ pub struct SetTabTitle {
 
 impl SetTabTitle {
     pub async fn run(self, client: Client) -> anyhow::Result<()> {
-        let panes = client.list_panes().await?;
+        let panes = client.list_panes(codec::ListPanes::default()).await?;
 
         let mut pane_id_to_tab_id = HashMap::new();
 
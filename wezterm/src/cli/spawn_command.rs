@@ -60,7 +60,7 @@ impl SpawnCommand {
                 None => {
                     let pane_id = client.resolve_pane_id(self.pane_id).await?;
 
-                    let panes = client.list_panes().await?;
+                    let panes = client.list_panes(codec::ListPanes::default()).await?;
                     let mut window_id = None;
                     'outer: for tabroot in panes.tabs {
                         let mut cursor = tabroot.into_tree().cursor();
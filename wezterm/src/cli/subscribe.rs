@@ -0,0 +1,27 @@
+use clap::Parser;
+use wezterm_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct Subscribe {
+    /// Also report an event each time a pane produces output.
+    /// These events are very frequent for busy panes, so they
+    /// are omitted by default.
+    #[arg(long)]
+    include_pane_output: bool,
+}
+
+impl Subscribe {
+    pub async fn run(self, client: Client) -> anyhow::Result<()> {
+        let events = client
+            .subscribe_to_mux_events(codec::SubscribeToMuxEvents {
+                include_pane_output: self.include_pane_output,
+            })
+            .await?;
+
+        while let Ok(event) = events.recv().await {
+            println!("{}", serde_json::to_string(&event)?);
+        }
+
+        anyhow::bail!("lost connection to the mux server");
+    }
+}
@@ -0,0 +1,157 @@
+use clap::Parser;
+use mux::pane::{PaneId, Pattern, SearchResult};
+use std::time::{Duration, Instant};
+use wezterm_client::client::Client;
+use wezterm_term::StableRowIndex;
+
+/// How often to check the pane for new output
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The exit status used when the timeout elapses without a match.
+/// This is the same value used by timeout(1).
+pub const TIMEOUT_EXIT_STATUS: i32 = 124;
+
+/// The error returned when the timeout elapses without a match.
+/// `main` maps it to `TIMEOUT_EXIT_STATUS`.
+#[derive(Debug)]
+pub struct WaitForTimedOut {
+    pattern: String,
+}
+
+impl std::fmt::Display for WaitForTimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "timed out waiting for {:?}", self.pattern)
+    }
+}
+
+impl std::error::Error for WaitForTimedOut {}
+
+#[derive(Debug, Parser, Clone)]
+pub struct WaitFor {
+    /// Specify the target pane.
+    /// The default is to use the current pane based on the
+    /// environment variable WEZTERM_PANE.
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+
+    /// Give up after this many seconds.
+    /// If the timeout elapses before a match is found, wezterm
+    /// exits with status 124.
+    /// The default is to wait indefinitely.
+    #[arg(long)]
+    timeout: Option<f64>,
+
+    /// The regular expression to match against the output.
+    /// Only output produced after this command starts is considered.
+    /// If the expression contains capture groups, the text of the
+    /// last group that participated in the match is printed instead
+    /// of the entire match.
+    pattern: String,
+}
+
+impl WaitFor {
+    pub async fn run(self, client: Client) -> anyhow::Result<()> {
+        let pane_id = client.resolve_pane_id(self.pane_id).await?;
+        let deadline = match self.timeout {
+            Some(secs) if secs >= 0.0 => Some(Instant::now() + Duration::from_secs_f64(secs)),
+            Some(_) => anyhow::bail!("--timeout must not be negative"),
+            None => None,
+        };
+
+        // Matches must start at or after the cursor position at the
+        // time we started waiting; anything before that was already
+        // present in the pane.
+        let info = client
+            .get_dimensions(codec::GetPaneRenderableDimensions { pane_id })
+            .await?;
+        let origin = (info.cursor_position.y, info.cursor_position.x);
+        let mut search_start = origin.0;
+
+        loop {
+            let info = client
+                .get_dimensions(codec::GetPaneRenderableDimensions { pane_id })
+                .await?;
+            let bottom =
+                info.dimensions.physical_top + info.dimensions.viewport_rows as StableRowIndex;
+            let search_start_row = search_start.max(info.dimensions.scrollback_top);
+
+            let results = client
+                .search_scrollback(codec::SearchScrollbackRequest {
+                    pane_id,
+                    pattern: Pattern::Regex(self.pattern.clone()),
+                    range: search_start_row..bottom,
+                    limit: None,
+                })
+                .await?
+                .results;
+
+            if let Some(result) = first_match_after(results, origin) {
+                let text = self.matched_text(&client, pane_id, &result).await?;
+                println!("{}", text);
+                return Ok(());
+            }
+
+            // Everything above the cursor row has been searched;
+            // the search itself extends backwards to cover any
+            // logical line that wraps into this row.
+            search_start = info.cursor_position.y.max(search_start);
+
+            if let Some(deadline) = deadline {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(WaitForTimedOut {
+                        pattern: self.pattern,
+                    }
+                    .into());
+                }
+                smol::Timer::after(POLL_INTERVAL.min(deadline - now)).await;
+            } else {
+                smol::Timer::after(POLL_INTERVAL).await;
+            }
+        }
+    }
+
+    async fn matched_text(
+        &self,
+        client: &Client,
+        pane_id: PaneId,
+        result: &SearchResult,
+    ) -> anyhow::Result<String> {
+        let lines = client
+            .get_lines(codec::GetLines {
+                pane_id,
+                lines: vec![result.start_y..result.end_y + 1],
+            })
+            .await?;
+
+        let mut text = String::new();
+        for (row, line) in lines.lines.extract_data().0 {
+            let start = if row == result.start_y {
+                result.start_x
+            } else {
+                0
+            };
+            let end = if row == result.end_y {
+                result.end_x
+            } else {
+                line.len()
+            };
+            text.push_str(&line.columns_as_str(start..end));
+            if row != result.end_y && !line.last_cell_was_wrapped() {
+                text.push('\n');
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// Returns the earliest match that starts at or after `origin`
+fn first_match_after(
+    results: Vec<SearchResult>,
+    origin: (StableRowIndex, usize),
+) -> Option<SearchResult> {
+    results
+        .into_iter()
+        .filter(|r| (r.start_y, r.start_x) >= origin)
+        .min_by_key(|r| (r.start_y, r.start_x))
+}
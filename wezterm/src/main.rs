@@ -16,8 +16,12 @@ use termwiz::terminal::{ScreenSize, Terminal};
 use umask::UmaskSaver;
 use wezterm_gui_subcommands::*;
 
+#[cfg(feature = "screenshot")]
+mod animation;
 mod asciicast;
 mod cli;
+#[cfg(feature = "screenshot")]
+mod screenshot;
 
 //    let message = "; ❤ 😍🤢\n\x1b[91;mw00t\n\x1b[37;104;m bleet\x1b[0;m.";
 
@@ -703,6 +707,11 @@ fn main() {
     config::designate_this_as_the_main_thread();
     config::assign_error_callback(mux::connui::show_configuration_error_message);
     if let Err(e) = run() {
+        if e.is::<cli::WaitForTimedOut>() {
+            eprintln!("{:#}", e);
+            Mux::shutdown();
+            std::process::exit(cli::TIMEOUT_EXIT_STATUS);
+        }
         terminate_with_error(e);
     }
     Mux::shutdown();
@@ -744,6 +753,9 @@ fn run() -> anyhow::Result<()> {
         SubCommand::SetCwd(cmd) => cmd.run(),
         SubCommand::Cli(cli) => cli::run_cli(&opts, cli),
         SubCommand::Record(cmd) => cmd.run(init_config(&opts)?),
+        #[cfg(feature = "screenshot")]
+        SubCommand::Replay(cmd) => cmd.run(init_config(&opts)?),
+        #[cfg(not(feature = "screenshot"))]
         SubCommand::Replay(cmd) => cmd.run(),
         SubCommand::ShellCompletion { shell } => {
             use clap::CommandFactory;
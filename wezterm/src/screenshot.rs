@@ -0,0 +1,757 @@
+//! Software rendering of terminal lines to PNG and SVG images.
+//! This uses the same font stack as the GUI, but composes the glyphs
+//! on the CPU so that screenshots can be produced on machines that
+//! have no GPU or display.
+use anyhow::Context;
+use clap::ValueEnum;
+use config::{BoldBrightening, ConfigHandle, TextStyle};
+use image::imageops::FilterType;
+use image::{Rgba, RgbaImage};
+use std::fmt::Write as _;
+use std::path::Path;
+use termwiz::cell::{CellAttributes, Intensity, Underline};
+use termwiz::cellcluster::CellCluster;
+use termwiz::color::{ColorAttribute, LinearRgba, SrgbaTuple};
+use wezterm_font::shaper::PresentationWidth;
+use wezterm_font::{FontConfiguration, RasterizedGlyph};
+use wezterm_term::color::ColorPalette;
+use wezterm_term::Line;
+
+/// The DPI to use when the configuration doesn't specify one
+const DEFAULT_DPI: f64 = 96.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImageFormat {
+    Png,
+    Svg,
+}
+
+impl ImageFormat {
+    /// Determine the format from the extension of the file name
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "png" => Some(Self::Png),
+            "svg" => Some(Self::Svg),
+            _ => None,
+        }
+    }
+}
+
+/// The content to be rendered
+pub struct Screen<'a> {
+    pub lines: &'a [Line],
+    /// The width of the screen, in cells
+    pub cols: usize,
+    pub palette: &'a ColorPalette,
+    /// The cursor position, as (column, index into lines),
+    /// if it should be drawn
+    pub cursor: Option<(usize, usize)>,
+}
+
+impl<'a> Screen<'a> {
+    fn is_cursor(&self, x: usize, y: usize) -> bool {
+        self.cursor == Some((x, y))
+    }
+}
+
+/// A rectangle in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rect {
+    left: usize,
+    top: usize,
+    width: usize,
+    height: usize,
+}
+
+pub struct ScreenRenderer {
+    config: ConfigHandle,
+    fonts: FontConfiguration,
+    cell_width: usize,
+    cell_height: usize,
+    /// Distance from the top of the cell to the baseline
+    baseline: isize,
+    underline_height: usize,
+    /// Distance from the top of the cell to the top of the underline
+    underline_row: isize,
+    strike_row: isize,
+}
+
+impl ScreenRenderer {
+    pub fn new(config: &ConfigHandle) -> anyhow::Result<Self> {
+        let dpi = config.dpi.unwrap_or(DEFAULT_DPI) as usize;
+        let fonts = FontConfiguration::new(Some(config.clone()), dpi)?;
+        let metrics = fonts
+            .default_font_metrics()
+            .context("resolving default font metrics")?;
+
+        let natural_height = metrics.cell_height.get().ceil();
+        let cell_height = (metrics.cell_height.get() * config.line_height).ceil() as usize;
+        let cell_width = (metrics.cell_width.get() * config.cell_width).ceil() as usize;
+        // When line_height is adjusted, the text is centered vertically
+        let y_adjust = ((cell_height as f64 - natural_height) / 2.).round();
+
+        let baseline = (natural_height + metrics.descender.get() + y_adjust) as isize;
+        let underline_height = metrics.underline_thickness.get().round().max(1.) as usize;
+        let underline_row = baseline - metrics.underline_position.get().round() as isize;
+        let strike_row = (y_adjust + (natural_height + metrics.descender.get()) / 2.) as isize;
+
+        Ok(Self {
+            config: config.clone(),
+            fonts,
+            cell_width,
+            cell_height,
+            baseline,
+            underline_height,
+            underline_row,
+            strike_row,
+        })
+    }
+
+    /// Returns the dimensions in pixels of an image of a screen
+    /// with the specified number of columns and rows
+    pub fn image_size(&self, cols: usize, rows: usize) -> (u32, u32) {
+        (
+            (cols * self.cell_width) as u32,
+            (rows * self.cell_height) as u32,
+        )
+    }
+
+    /// Returns the area covered by `num_cells` cells starting at
+    /// column `first_cell` of the line with index `y`
+    fn cell_rect(&self, y: usize, first_cell: usize, num_cells: usize) -> Rect {
+        Rect {
+            left: first_cell * self.cell_width,
+            top: y * self.cell_height,
+            width: num_cells * self.cell_width,
+            height: self.cell_height,
+        }
+    }
+
+    /// Returns the column of the cursor and the area that it covers,
+    /// if it should be drawn on the line with index `y`.
+    /// The cursor may be beyond the end of the text on the line,
+    /// but not beyond the edge of the screen.
+    fn cursor_rect(&self, screen: &Screen, y: usize) -> Option<(usize, Rect)> {
+        match screen.cursor {
+            Some((x, cursor_y)) if cursor_y == y && x < screen.cols => {
+                Some((x, self.cell_rect(y, x, 1)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Render the screen and write it to the specified file
+    pub fn save(&self, screen: &Screen, format: ImageFormat, path: &Path) -> anyhow::Result<()> {
+        match format {
+            ImageFormat::Png => self
+                .render_image(screen)?
+                .save_with_format(path, image::ImageFormat::Png)
+                .with_context(|| format!("writing {}", path.display())),
+            ImageFormat::Svg => std::fs::write(path, self.render_svg(screen))
+                .with_context(|| format!("writing {}", path.display())),
+        }
+    }
+
+    /// Rasterize the screen to an image
+    pub fn render_image(&self, screen: &Screen) -> anyhow::Result<RgbaImage> {
+        let (width, height) = self.image_size(screen.cols, screen.lines.len());
+        let (r, g, b, a) = screen.palette.background.to_srgb_u8();
+        let mut image = RgbaImage::from_pixel(width, height, Rgba([r, g, b, a]));
+
+        for (y, line) in screen.lines.iter().enumerate() {
+            let clusters: Vec<_> = line
+                .cluster(None)
+                .into_iter()
+                .map(|cluster| {
+                    let style = self.fonts.match_style(&self.config, &cluster.attrs);
+                    let (fg, bg) = self.resolve_colors(&cluster.attrs, style, screen.palette);
+                    (cluster, style, fg, bg)
+                })
+                .collect();
+
+            // Fill in all of the backgrounds before drawing any glyphs,
+            // so that glyphs that overhang their cells are not clipped
+            for (cluster, _, _, bg) in &clusters {
+                if *bg != screen.palette.background {
+                    let rect = self.cell_rect(y, cluster.first_cell_idx, cluster.width);
+                    self.fill_cells(&mut image, rect, *bg);
+                }
+            }
+            if let Some((_, rect)) = self.cursor_rect(screen, y) {
+                self.fill_cells(&mut image, rect, screen.palette.cursor_bg);
+            }
+
+            for (cluster, style, fg, _) in &clusters {
+                self.draw_cluster(&mut image, screen, y, cluster, style, *fg)?;
+                self.draw_decorations(&mut image, y, cluster, *fg, screen.palette);
+            }
+        }
+
+        Ok(image)
+    }
+
+    fn draw_cluster(
+        &self,
+        image: &mut RgbaImage,
+        screen: &Screen,
+        y: usize,
+        cluster: &CellCluster,
+        style: &TextStyle,
+        fg: SrgbaTuple,
+    ) -> anyhow::Result<()> {
+        if cluster.text.chars().all(|c| c == ' ') {
+            return Ok(());
+        }
+
+        let font = self.fonts.resolve_font(style)?;
+        let presentation_width = PresentationWidth::with_cluster(cluster);
+        let infos = font.blocking_shape(
+            &cluster.text,
+            Some(cluster.presentation),
+            cluster.direction,
+            None,
+            Some(&presentation_width),
+        )?;
+
+        let top = (y * self.cell_height) as isize;
+        for info in infos {
+            if info.is_space {
+                continue;
+            }
+            let glyph = font.rasterize_glyph(info.glyph_pos, info.font_idx)?;
+            if glyph.width == 0 || glyph.height == 0 {
+                continue;
+            }
+
+            let cell_idx = cluster.byte_to_cell_idx(info.cluster as usize);
+            let max_width = self.cell_width * (info.num_cells.max(1) as usize);
+            let (glyph, scale) = self.fit_glyph(glyph, max_width);
+
+            let left = (cell_idx * self.cell_width) as f64
+                + (info.x_offset.get() * scale)
+                + glyph.bearing_x.get();
+            let glyph_top = top as f64 + self.baseline as f64
+                - (info.y_offset.get() * scale)
+                - glyph.bearing_y.get();
+
+            let color = if screen.is_cursor(cell_idx, y) {
+                screen.palette.cursor_fg
+            } else {
+                fg
+            };
+            blit_glyph(
+                image,
+                &glyph,
+                left.round() as i64,
+                glyph_top.round() as i64,
+                color,
+            );
+        }
+        Ok(())
+    }
+
+    /// Color glyphs, such as emoji, are often larger than the cell
+    /// and need to be scaled down to fit.
+    /// Returns the glyph along with the scale that was applied.
+    fn fit_glyph(&self, glyph: RasterizedGlyph, max_width: usize) -> (RasterizedGlyph, f64) {
+        if !glyph.has_color || (glyph.width <= max_width && glyph.height <= self.cell_height) {
+            return (glyph, 1.0);
+        }
+        let scale = (max_width as f64 / glyph.width as f64)
+            .min(self.cell_height as f64 / glyph.height as f64);
+        let width = ((glyph.width as f64 * scale).round() as u32).max(1);
+        let height = ((glyph.height as f64 * scale).round() as u32).max(1);
+
+        let source = match RgbaImage::from_raw(glyph.width as u32, glyph.height as u32, glyph.data)
+        {
+            Some(source) => source,
+            None => {
+                return (
+                    RasterizedGlyph {
+                        data: vec![],
+                        width: 0,
+                        height: 0,
+                        ..glyph
+                    },
+                    scale,
+                )
+            }
+        };
+        let scaled = image::imageops::resize(&source, width, height, FilterType::Triangle);
+
+        (
+            RasterizedGlyph {
+                data: scaled.into_vec(),
+                width: width as usize,
+                height: height as usize,
+                bearing_x: glyph.bearing_x * scale,
+                bearing_y: glyph.bearing_y * scale,
+                ..glyph
+            },
+            scale,
+        )
+    }
+
+    fn draw_decorations(
+        &self,
+        image: &mut RgbaImage,
+        y: usize,
+        cluster: &CellCluster,
+        fg: SrgbaTuple,
+        palette: &ColorPalette,
+    ) {
+        let attrs = &cluster.attrs;
+        let Rect {
+            left, top, width, ..
+        } = self.cell_rect(y, cluster.first_cell_idx, cluster.width);
+        let top = top as isize;
+
+        let underline_color = match attrs.underline_color() {
+            ColorAttribute::Default => fg,
+            color => palette.resolve_fg(color),
+        };
+        let underline_top = top + self.underline_row;
+        match attrs.underline() {
+            Underline::None => {}
+            Underline::Double => {
+                let gap = 2 * self.underline_height as isize;
+                self.fill_rect(
+                    image,
+                    left,
+                    underline_top - gap,
+                    width,
+                    self.underline_height,
+                    underline_color,
+                );
+                self.fill_rect(
+                    image,
+                    left,
+                    underline_top,
+                    width,
+                    self.underline_height,
+                    underline_color,
+                );
+            }
+            // Curly, dotted and dashed underlines are approximated
+            // by a single underline
+            _ => self.fill_rect(
+                image,
+                left,
+                underline_top,
+                width,
+                self.underline_height,
+                underline_color,
+            ),
+        }
+
+        if attrs.strikethrough() {
+            self.fill_rect(
+                image,
+                left,
+                top + self.strike_row,
+                width,
+                self.underline_height,
+                fg,
+            );
+        }
+        if attrs.overline() {
+            self.fill_rect(image, left, top, width, self.underline_height, fg);
+        }
+    }
+
+    fn fill_cells(&self, image: &mut RgbaImage, rect: Rect, color: SrgbaTuple) {
+        self.fill_rect(
+            image,
+            rect.left,
+            rect.top as isize,
+            rect.width,
+            rect.height,
+            color,
+        );
+    }
+
+    fn fill_rect(
+        &self,
+        image: &mut RgbaImage,
+        left: usize,
+        top: isize,
+        width: usize,
+        height: usize,
+        color: SrgbaTuple,
+    ) {
+        let (r, g, b, a) = color.to_srgb_u8();
+        let right = (left + width).min(image.width() as usize);
+        let bottom = (top + height as isize).min(image.height() as isize);
+        for y in top.max(0)..bottom {
+            for x in left..right {
+                image.put_pixel(x as u32, y as u32, Rgba([r, g, b, a]));
+            }
+        }
+    }
+
+    /// Compute the foreground and background colors for a cell,
+    /// following the same rules as the GUI
+    fn resolve_colors(
+        &self,
+        attrs: &CellAttributes,
+        style: &TextStyle,
+        palette: &ColorPalette,
+    ) -> (SrgbaTuple, SrgbaTuple) {
+        let fg = match attrs.foreground() {
+            ColorAttribute::Default => match style.foreground {
+                Some(fg) => fg.into(),
+                None => palette.resolve_fg(ColorAttribute::Default),
+            },
+            ColorAttribute::PaletteIndex(idx)
+                if idx < 8 && self.config.bold_brightens_ansi_colors != BoldBrightening::No =>
+            {
+                let idx = if attrs.intensity() == Intensity::Bold {
+                    idx + 8
+                } else {
+                    idx
+                };
+                palette.resolve_fg(ColorAttribute::PaletteIndex(idx))
+            }
+            fg => palette.resolve_fg(fg),
+        };
+        let bg = palette.resolve_bg(attrs.background());
+
+        let (fg, bg) = if attrs.reverse() { (bg, fg) } else { (fg, bg) };
+        let fg = if attrs.invisible() {
+            bg
+        } else if attrs.intensity() == Intensity::Half {
+            fg.interpolate(bg, 0.5)
+        } else {
+            fg
+        };
+        (fg, bg)
+    }
+
+    /// Produce an SVG document that describes the screen using text
+    /// elements, so that the text remains selectable and scalable.
+    pub fn render_svg(&self, screen: &Screen) -> String {
+        let (width, height) = self.image_size(screen.cols, screen.lines.len());
+        let font_size = self.config.font_size * self.config.dpi.unwrap_or(DEFAULT_DPI) / 72.;
+        let mut families: Vec<String> = self
+            .config
+            .font
+            .font
+            .iter()
+            .map(|attr| format!("'{}'", xml_escape(&attr.family)))
+            .collect();
+        families.push("monospace".to_string());
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        );
+        let _ = writeln!(
+            svg,
+            r#"<rect width="100%" height="100%" fill="{}"/>"#,
+            screen.palette.background.to_rgb_string()
+        );
+        let _ = writeln!(
+            svg,
+            r#"<g font-family="{}" font-size="{:.2}px" xml:space="preserve">"#,
+            families.join(", "),
+            font_size
+        );
+
+        for (y, line) in screen.lines.iter().enumerate() {
+            for cluster in line.cluster(None) {
+                let style = self.fonts.match_style(&self.config, &cluster.attrs);
+                let (fg, bg) = self.resolve_colors(&cluster.attrs, style, screen.palette);
+                let rect = self.cell_rect(y, cluster.first_cell_idx, cluster.width);
+
+                if bg != screen.palette.background {
+                    svg_rect(&mut svg, rect, bg);
+                }
+
+                if cluster.text.chars().any(|c| c != ' ') {
+                    let attrs = &cluster.attrs;
+                    let mut extra = String::new();
+                    if attrs.intensity() == Intensity::Bold {
+                        extra.push_str(r#" font-weight="bold""#);
+                    }
+                    if attrs.italic() {
+                        extra.push_str(r#" font-style="italic""#);
+                    }
+                    let mut decorations = vec![];
+                    if attrs.underline() != Underline::None {
+                        decorations.push("underline");
+                    }
+                    if attrs.strikethrough() {
+                        decorations.push("line-through");
+                    }
+                    if attrs.overline() {
+                        decorations.push("overline");
+                    }
+                    if !decorations.is_empty() {
+                        let _ = write!(extra, r#" text-decoration="{}""#, decorations.join(" "));
+                    }
+
+                    // Stretch the text to the width of the cells so that
+                    // the grid stays aligned regardless of the font that
+                    // the viewer chooses
+                    let _ = writeln!(
+                        svg,
+                        r#"<text x="{}" y="{}" fill="{}" textLength="{}" lengthAdjust="spacingAndGlyphs"{extra}>{}</text>"#,
+                        rect.left,
+                        rect.top as isize + self.baseline,
+                        fg.to_rgb_string(),
+                        rect.width,
+                        xml_escape(&cluster.text)
+                    );
+                }
+            }
+
+            if let Some((x, rect)) = self.cursor_rect(screen, y) {
+                svg_rect(&mut svg, rect, screen.palette.cursor_bg);
+                if let Some(cell) = line.get_cell(x) {
+                    if !cell.str().trim().is_empty() {
+                        let _ = writeln!(
+                            svg,
+                            r#"<text x="{}" y="{}" fill="{}">{}</text>"#,
+                            rect.left,
+                            rect.top as isize + self.baseline,
+                            screen.palette.cursor_fg.to_rgb_string(),
+                            xml_escape(cell.str())
+                        );
+                    }
+                }
+            }
+        }
+
+        svg.push_str("</g>\n</svg>\n");
+        svg
+    }
+}
+
+fn svg_rect(svg: &mut String, rect: Rect, color: SrgbaTuple) {
+    let _ = writeln!(
+        svg,
+        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+        rect.left,
+        rect.top,
+        rect.width,
+        rect.height,
+        color.to_rgb_string()
+    );
+}
+
+/// Composite a rasterized glyph onto the image with its top left
+/// corner at the specified position
+fn blit_glyph(image: &mut RgbaImage, glyph: &RasterizedGlyph, left: i64, top: i64, fg: SrgbaTuple) {
+    let fg = fg.to_linear();
+    for gy in 0..glyph.height {
+        let y = top + gy as i64;
+        if y < 0 || y >= image.height() as i64 {
+            continue;
+        }
+        for gx in 0..glyph.width {
+            let x = left + gx as i64;
+            if x < 0 || x >= image.width() as i64 {
+                continue;
+            }
+            let offset = (gy * glyph.width + gx) * 4;
+            let src = &glyph.data[offset..offset + 4];
+            if src[3] == 0 {
+                continue;
+            }
+            let pixel = image.get_pixel_mut(x as u32, y as u32);
+
+            if glyph.has_color {
+                // Color glyphs hold premultiplied sRGB data
+                let inv_alpha = 255 - src[3] as u16;
+                for i in 0..3 {
+                    pixel[i] = (src[i] as u16 + (pixel[i] as u16 * inv_alpha) / 255).min(255) as u8;
+                }
+            } else {
+                // The alpha channel holds the linear coverage of the
+                // glyph; blend in linear space so that antialiased
+                // edges have the correct weight
+                let coverage = src[3] as f32 / 255.;
+                let dest = LinearRgba::with_srgba(pixel[0], pixel[1], pixel[2], 255);
+                let blended = LinearRgba(
+                    fg.0 * coverage + dest.0 * (1. - coverage),
+                    fg.1 * coverage + dest.1 * (1. - coverage),
+                    fg.2 * coverage + dest.2 * (1. - coverage),
+                    1.,
+                );
+                let (r, g, b, _) = blended.to_srgb().to_srgb_u8();
+                pixel[0] = r;
+                pixel[1] = g;
+                pixel[2] = b;
+            }
+        }
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            c if c.is_control() => {}
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use termwiz::surface::SEQ_ZERO;
+
+    fn renderer() -> ScreenRenderer {
+        config::use_test_configuration();
+        ScreenRenderer::new(&config::configuration()).unwrap()
+    }
+
+    fn line(text: &str, attrs: &CellAttributes) -> Line {
+        Line::from_text(text, attrs, SEQ_ZERO, None)
+    }
+
+    /// A two line screen: some plain text with the cursor on the `<`,
+    /// then two reverse video spaces
+    fn lines() -> Vec<Line> {
+        let mut reverse = CellAttributes::default();
+        reverse.set_reverse(true);
+        vec![
+            line("a<b", &CellAttributes::default()),
+            line("  ", &reverse),
+        ]
+    }
+
+    #[test]
+    fn geometry() {
+        let renderer = renderer();
+        let (w, h) = (renderer.cell_width, renderer.cell_height);
+        assert!(w > 0 && h > 0);
+
+        assert_eq!(
+            renderer.image_size(80, 24),
+            ((80 * w) as u32, (24 * h) as u32)
+        );
+        assert_eq!(
+            renderer.cell_rect(2, 3, 4),
+            Rect {
+                left: 3 * w,
+                top: 2 * h,
+                width: 4 * w,
+                height: h,
+            }
+        );
+
+        let lines = lines();
+        let palette = ColorPalette::default();
+        let mut screen = Screen {
+            lines: &lines,
+            cols: 4,
+            palette: &palette,
+            cursor: Some((3, 1)),
+        };
+        assert_eq!(renderer.cursor_rect(&screen, 0), None);
+        // Beyond the end of the text on the line
+        assert_eq!(
+            renderer.cursor_rect(&screen, 1),
+            Some((3, renderer.cell_rect(1, 3, 1)))
+        );
+        // Beyond the edge of the screen
+        screen.cursor = Some((4, 1));
+        assert_eq!(renderer.cursor_rect(&screen, 1), None);
+    }
+
+    #[test]
+    fn image() {
+        let renderer = renderer();
+        let (w, h) = (renderer.cell_width as u32, renderer.cell_height as u32);
+        let lines = lines();
+        let palette = ColorPalette::default();
+        let screen = Screen {
+            lines: &lines,
+            cols: 4,
+            palette: &palette,
+            cursor: Some((3, 1)),
+        };
+        let image = renderer.render_image(&screen).unwrap();
+        assert_eq!(image.dimensions(), (4 * w, 2 * h));
+
+        let color = |c: SrgbaTuple| {
+            let (r, g, b, a) = c.to_srgb_u8();
+            Rgba([r, g, b, a])
+        };
+        // The center of a cell in each of the interesting areas
+        let pixel = |x: u32, y: u32| *image.get_pixel(x * w + w / 2, y * h + h / 2);
+        assert_eq!(pixel(3, 0), color(palette.background));
+        assert_eq!(pixel(0, 1), color(palette.foreground));
+        assert_eq!(pixel(1, 1), color(palette.foreground));
+        assert_eq!(pixel(2, 1), color(palette.background));
+        assert_eq!(pixel(3, 1), color(palette.cursor_bg));
+    }
+
+    #[test]
+    fn svg() {
+        let renderer = renderer();
+        let (w, h) = (renderer.cell_width, renderer.cell_height);
+        let lines = lines();
+        let palette = ColorPalette::default();
+        let screen = Screen {
+            lines: &lines,
+            cols: 4,
+            palette: &palette,
+            cursor: Some((1, 0)),
+        };
+        let svg = renderer.render_svg(&screen);
+
+        assert!(svg.starts_with(&format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+            4 * w,
+            2 * h,
+            4 * w,
+            2 * h
+        )));
+        assert!(svg.ends_with("</g>\n</svg>\n"));
+
+        let baseline = renderer.baseline;
+        assert!(svg.contains(&format!(
+            r#"<text x="0" y="{baseline}" fill="{}" textLength="{}" lengthAdjust="spacingAndGlyphs">a&lt;b</text>"#,
+            palette.foreground.to_rgb_string(),
+            3 * w,
+        )));
+
+        // The cursor is drawn over the `<`, which is repeated in the
+        // cursor color
+        assert!(svg.contains(&format!(
+            r#"<rect x="{w}" y="0" width="{w}" height="{h}" fill="{}"/>"#,
+            palette.cursor_bg.to_rgb_string()
+        )));
+        assert!(svg.contains(&format!(
+            r#"<text x="{w}" y="{baseline}" fill="{}">&lt;</text>"#,
+            palette.cursor_fg.to_rgb_string()
+        )));
+
+        // The reverse video spaces only produce a background
+        assert!(svg.contains(&format!(
+            r#"<rect x="0" y="{h}" width="{}" height="{h}" fill="{}"/>"#,
+            2 * w,
+            palette.foreground.to_rgb_string()
+        )));
+        assert_eq!(svg.matches("<text").count(), 2);
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(
+            xml_escape("<a href=\"x\">'&'</a>\x07"),
+            "&lt;a href=&quot;x&quot;&gt;&apos;&amp;&apos;&lt;/a&gt;"
+        );
+    }
+}